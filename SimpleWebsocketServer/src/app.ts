@@ -1,69 +1,1085 @@
 import WebSocket, { WebSocketServer } from 'ws';
+import http from 'http';
+import readline from 'readline';
+import { decode, encode } from './msgpack';
+import {
+    Assertion,
+    NewDevice,
+    SUPPORTED_ALGORITHMS,
+    checkAssertion,
+    deviceList,
+    devicesOf,
+    enroll,
+    newChallenge,
+    newSession,
+    removeDevice,
+    sessionValid,
+    userHandle,
+} from './devices';
+import { handlePoll, pollClients } from './poll';
+import { authProblem, handleLogin } from './auth';
 
 const PORT = process.env.PORT ? parseInt(process.env.PORT) : 8080;
 interface User {
     ws: WebSocket;
     nick: String;
     isAlive: boolean;
+    status: String;
 }
 
 interface Message {
     messageType: String;
     data: String;
     dataArray: String[];
+    room?: String;
+    clientId?: String;
+    id?: number;
+    version?: number;
+    // Proof of an earlier device verification, sent with `register`.
+    token?: String;
+    // The token from POST /login, sent with `register`.
+    auth?: String;
+    // Makes a `message` private to this nick.
+    to?: String;
+    // Files a `message` in the thread under this message.
+    parentId?: number;
+    // Quotes this message in a `message`.
+    replyTo?: number;
 }
 
+// Bump PROTOCOL_VERSION on any change to frame shapes; raise
+// MIN_CLIENT_VERSION when older clients can no longer cope.
+const PROTOCOL_VERSION = 1;
+const MIN_CLIENT_VERSION = 1;
+// Optional parts of the protocol this server supports, listed in `hello`.
+// Clients hide whatever is missing.
+const FEATURES = ['threads', 'reactions', 'pins', 'announcements'];
+
+const MAX_MESSAGE_LENGTH = 2000;
+// Messages kept for history requests, across all rooms.
+const HISTORY_LIMIT = 1000;
+const HISTORY_PAGE_MAX = 100;
+const PIN_LIMIT = process.env.PIN_LIMIT ? parseInt(process.env.PIN_LIMIT) : 5;
+// Nicks that may delete anyone's messages and remove members, comma-separated.
+const MODERATORS = (process.env.MODERATORS || '').split(',').map((nick) => nick.trim()).filter(Boolean);
+// Nicks that may do all that and remove moderators too.
+const OWNERS = (process.env.OWNERS || '').split(',').map((nick) => nick.trim()).filter(Boolean);
+// Files are relayed chunk by chunk, never stored. A chunk is 16 KiB before
+// base64; anything much bigger isn't from our client.
+const MAX_FILE_BYTES = 2 * 1024 * 1024;
+const MAX_CHUNK_LENGTH = 32 * 1024;
+// Pictures can carry a description for screen readers.
+const MAX_ALT_LENGTH = 500;
+// A transfer whose sender went quiet is forgotten after this long.
+const TRANSFER_TTL_MS = 10 * 60 * 1000;
+
 let users: User[] = [];
+// Clients that asked for MessagePack framing.
+const msgpackClients = new WeakSet<WebSocket>();
+// The WebAuthn challenge each socket is expected to answer next.
+const challenges = new WeakMap<WebSocket, { purpose: 'verify' | 'enroll'; nick: string; challenge: Buffer }>();
+// File transfer ID -> who is sending it, so nobody else can add to it.
+const transfers = new Map<string, { ws: WebSocket; started: number }>();
+// Banned nick -> when the ban ends (ms since epoch), or null for never.
+const bans = new Map<string, number | null>();
+// Nick -> picture set by the operator, in place of the generated one.
+const avatars = new Map<string, string>();
+let nextMessageId = 1;
+
+interface ChatMessage {
+    id: number;
+    from: String;
+    message: String;
+    room: String;
+    clientId?: String;
+    // Set on direct messages, which only the two people involved ever see.
+    to?: String;
+    // Set on replies in a thread: the ID of the message that started it.
+    parentId?: number;
+    // The message this one quotes.
+    replyTo?: number;
+    time: number;
+    edited?: boolean;
+    deleted?: boolean;
+    // Sent by a moderator to the whole room, with `announce`.
+    announcement?: boolean;
+    // An announcement clients keep at the top until it's dismissed.
+    sticky?: boolean;
+}
+const history: ChatMessage[] = [];
+// Room -> nick -> last message ID they have read.
+const readCursors = new Map<string, Map<string, number>>();
+// Room -> pinned message IDs, in display order.
+const pins = new Map<string, number[]>();
+// "nick:thread ID" -> when they last pinged that thread's participants.
+const threadNotified = new Map<string, number>();
+const NOTIFY_COOLDOWN_MS = 60 * 1000;
+// Rooms made with `createroom`, with who made them. Any other room still
+// springs into being when first used. Archived rooms take no new messages.
+const rooms = new Map<string, { description: string; createdBy: String; archived?: boolean }>([
+    ['general', { description: '', createdBy: '' }],
+]);
+const ROOM_NAME = /^[a-z0-9_-]{1,32}$/;
+
+// Whether `room` was made with `createroom` or has been talked in.
+function roomExists(room: string): boolean {
+    return ROOM_NAME.test(room) && (rooms.has(room) || history.some((m) => m.room === room));
+}
+// Room -> nick -> when they first opened it, in join order.
+const roomMembers = new Map<string, Map<string, number>>();
+const MEMBERS_PAGE_SIZE = 50;
+const MAX_DESCRIPTION_LENGTH = 200;
+// What a moderator can say about why they removed someone.
+const MAX_REASON_LENGTH = 200;
 
-console.log(`Listening on port ${PORT}`);
-const wss = new WebSocketServer({ port: PORT });
+interface Health {
+    status: 'ok' | 'degraded' | 'maintenance';
+    message?: string;
+    until?: number;
+}
+let health: Health = { status: 'ok' };
+
+// Plain HTTP on the same port serves the health check, sign-in and
+// long-polling; everything else is upgraded to a WebSocket.
+const server = http.createServer((req, res) => {
+    if (handlePoll(req, res, handleConnection)) {
+        return;
+    }
+    if (handleLogin(req, res, (nick) => activeBan(nick)?.reason)) {
+        return;
+    }
+    if (req.url === '/health') {
+        res.writeHead(200, { 'Content-Type': 'application/json', 'Access-Control-Allow-Origin': '*' });
+        res.end(JSON.stringify(health));
+    } else {
+        res.writeHead(404);
+        res.end();
+    }
+});
+const wss = new WebSocketServer({ server });
+server.listen(PORT, () => console.log(`Listening on port ${PORT}`));
 
-wss.on('connection', (ws: WebSocket) => {
+// Long-polling clients come through here too, with a stand-in socket.
+const handleConnection = (ws: WebSocket) => {
     console.log('ws connected');
 
-    ws.on('message', (data) => {
-        const raw_data = data.toString();
+    ws.on('message', (data, isBinary) => {
         try {
+            // Binary frames are MessagePack; ws hands them over as a Buffer.
+            const raw_data = isBinary ? JSON.stringify(decode(data as Buffer)) : data.toString();
             const parsed_data: Message = JSON.parse(raw_data);
             switch (parsed_data.messageType) {
+                case 'codec':
+                    // The answer goes out as JSON; everything after it uses the agreed codec.
+                    ws.send(JSON.stringify({ messageType: 'codec', data: parsed_data.data === 'msgpack' ? 'msgpack' : 'json' }));
+                    if (parsed_data.data === 'msgpack') {
+                        msgpackClients.add(ws);
+                    }
+                    break;
+                case 'ping':
+                    sendFrame(ws, JSON.stringify({ messageType: 'pong' }));
+                    break;
                 case 'register':
-                    users.push({ ws, nick: parsed_data.data, isAlive: true });
-                    broadcast(JSON.stringify({ messageType: 'users', dataArray: users.map((u) => u.nick) }));
+                    sendFrame(
+                        ws,
+                        JSON.stringify({
+                            messageType: 'hello',
+                            data: JSON.stringify({
+                                version: PROTOCOL_VERSION,
+                                minClientVersion: MIN_CLIENT_VERSION,
+                                moderator: moderates(String(parsed_data.data)),
+                                role: roleOf(String(parsed_data.data)),
+                                features: FEATURES,
+                            }),
+                        })
+                    );
+                    if ((parsed_data.version || 0) < MIN_CLIENT_VERSION) {
+                        break;
+                    }
+                    // A stale entry from our own dropped connection doesn't count.
+                    if (users.some((u) => u.nick === parsed_data.data && u.ws !== ws && u.ws.readyState === WebSocket.OPEN)) {
+                        sendError(ws, 'name_taken', `The name "${parsed_data.data}" is already in use.`);
+                        break;
+                    }
+                    const ban = activeBan(String(parsed_data.data));
+                    if (ban) {
+                        removeSocket(ws, 'ban', ban.reason, ban.seconds);
+                        break;
+                    }
+                    const authFailure = authProblem(parsed_data.auth, String(parsed_data.data));
+                    if (authFailure) {
+                        sendFrame(ws, JSON.stringify({ messageType: 'authfailed', data: authFailure }));
+                        ws.close(1008, 'auth');
+                        break;
+                    }
+                    if (devicesOf(String(parsed_data.data)).length > 0 && !sessionValid(parsed_data.token, String(parsed_data.data))) {
+                        askToVerify(ws, String(parsed_data.data));
+                        break;
+                    }
+                    join(ws, String(parsed_data.data));
                     break;
-                case 'message':
+                case 'verify': {
+                    const pending = challenges.get(ws);
+                    challenges.delete(ws);
+                    if (!pending || pending.purpose !== 'verify') {
+                        break;
+                    }
+                    let assertion: Assertion | undefined;
+                    try {
+                        assertion = JSON.parse(String(parsed_data.data));
+                    } catch (e) {
+                        assertion = undefined;
+                    }
+                    if (!assertion || !checkAssertion(pending.nick, pending.challenge, assertion)) {
+                        sendError(ws, 'verification_failed', 'That device could not be verified. Please try again.');
+                        askToVerify(ws, pending.nick);
+                        break;
+                    }
+                    sendFrame(ws, JSON.stringify({ messageType: 'verified', data: newSession(pending.nick) }));
+                    join(ws, pending.nick);
+                    break;
+                }
+                case 'devices': {
+                    const owner = users.find((u) => u.ws === ws);
+                    if (owner) {
+                        sendFrame(ws, devicesFrame(String(owner.nick)));
+                    }
+                    break;
+                }
+                case 'enroll': {
+                    const owner = users.find((u) => u.ws === ws);
+                    if (!owner) {
+                        sendError(ws, 'not_registered', 'Register before adding devices.');
+                        break;
+                    }
+                    const nick = String(owner.nick);
+                    // Without data this asks for a challenge; with data it answers one.
+                    if (!parsed_data.data) {
+                        const challenge = newChallenge();
+                        challenges.set(ws, { purpose: 'enroll', nick, challenge });
+                        sendFrame(
+                            ws,
+                            JSON.stringify({
+                                messageType: 'enroll',
+                                data: JSON.stringify({
+                                    challenge: Array.from(challenge),
+                                    userId: Array.from(userHandle(nick)),
+                                    algorithms: SUPPORTED_ALGORITHMS,
+                                    exclude: devicesOf(nick).map((d) => Array.from(Buffer.from(d.id, 'base64url'))),
+                                }),
+                            })
+                        );
+                        break;
+                    }
+                    const pending = challenges.get(ws);
+                    challenges.delete(ws);
+                    let device: NewDevice | undefined;
+                    try {
+                        device = JSON.parse(String(parsed_data.data));
+                    } catch (e) {
+                        device = undefined;
+                    }
+                    const problem =
+                        !pending || pending.purpose !== 'enroll' || pending.nick !== nick || !device
+                            ? 'The device registration expired. Please try again.'
+                            : enroll(nick, pending.challenge, device);
+                    if (problem) {
+                        sendError(ws, 'enroll_failed', problem);
+                    }
+                    sendFrame(ws, devicesFrame(nick));
+                    break;
+                }
+                case 'unenroll': {
+                    const owner = users.find((u) => u.ws === ws);
+                    if (owner) {
+                        removeDevice(String(owner.nick), String(parsed_data.data));
+                        sendFrame(ws, devicesFrame(String(owner.nick)));
+                    }
+                    break;
+                }
+                case 'typing': {
+                    const typist = users.find((u) => u.ws === ws);
+                    if (typist && parsed_data.to) {
+                        // In a direct conversation only the other person hears of it.
+                        const frame = JSON.stringify({
+                            messageType: 'typing',
+                            data: JSON.stringify({ from: typist.nick, room: `@${typist.nick}` }),
+                        });
+                        users.filter((u) => u.nick === parsed_data.to).forEach((u) => sendFrame(u.ws, frame));
+                    } else if (typist) {
+                        broadcast(
+                            JSON.stringify({
+                                messageType: 'typing',
+                                data: JSON.stringify({ from: typist.nick, room: parsed_data.room || 'general' }),
+                            }),
+                            ws
+                        );
+                    }
+                    break;
+                }
+                case 'filestart': {
                     const sender = users.find((u) => u.ws === ws);
-                    if (sender) {
+                    let meta: { id?: unknown; size?: unknown; alt?: unknown; from?: String } | undefined;
+                    try {
+                        meta = JSON.parse(String(parsed_data.data));
+                    } catch (e) {
+                        meta = undefined;
+                    }
+                    if (!sender || !meta || typeof meta.id !== 'string' || transfers.has(meta.id)) {
+                        break;
+                    }
+                    if (rooms.get(String(parsed_data.room || 'general'))?.archived) {
+                        sendError(ws, 'room_archived', 'This room is archived, so it takes no new messages.');
+                        break;
+                    }
+                    if (typeof meta.size !== 'number' || meta.size > MAX_FILE_BYTES) {
+                        sendError(ws, 'file_rejected', `Files can be at most ${MAX_FILE_BYTES / (1024 * 1024)} MB.`);
+                        break;
+                    }
+                    if (meta.alt !== undefined && (typeof meta.alt !== 'string' || meta.alt.length > MAX_ALT_LENGTH)) {
+                        sendError(ws, 'file_rejected', `Image descriptions can be at most ${MAX_ALT_LENGTH} characters.`);
+                        break;
+                    }
+                    const now = Date.now();
+                    transfers.forEach((t, id) => t.started + TRANSFER_TTL_MS < now && transfers.delete(id));
+                    transfers.set(meta.id, { ws, started: now });
+                    meta.from = sender.nick;
+                    broadcast(
+                        JSON.stringify({ messageType: 'filestart', room: parsed_data.room || 'general', data: JSON.stringify(meta) }),
+                        ws
+                    );
+                    break;
+                }
+                case 'filechunk':
+                case 'fileend': {
+                    const id = String(parsed_data.clientId);
+                    if (transfers.get(id)?.ws !== ws) {
+                        break;
+                    }
+                    if (parsed_data.messageType === 'fileend') {
+                        transfers.delete(id);
+                        broadcast(JSON.stringify({ messageType: 'fileend', clientId: id }), ws);
+                    } else if (String(parsed_data.data || '').length <= MAX_CHUNK_LENGTH) {
+                        broadcast(
+                            JSON.stringify({ messageType: 'filechunk', clientId: id, id: parsed_data.id, data: parsed_data.data }),
+                            ws
+                        );
+                    }
+                    break;
+                }
+                case 'presence': {
+                    const member = users.find((u) => u.ws === ws);
+                    if (member && ['online', 'away', 'offline'].includes(String(parsed_data.data))) {
+                        member.status = parsed_data.data;
                         broadcast(
                             JSON.stringify({
-                                messageType: 'message',
-                                data: JSON.stringify({
-                                    from: sender.nick,
-                                    message: parsed_data.data,
-                                    time: Date.now(),
+                                messageType: 'presence',
+                                data: JSON.stringify({ from: member.nick, status: member.status }),
+                            })
+                        );
+                    }
+                    break;
+                }
+                case 'history': {
+                    const room = String(parsed_data.room || 'general');
+                    const limit = Math.min(parseInt(String(parsed_data.data)) || HISTORY_PAGE_MAX, HISTORY_PAGE_MAX);
+                    // `id`, when given, asks for the page before that message.
+                    const before = parsed_data.id;
+                    const reader = users.find((u) => u.ws === ws);
+                    // `@nick` asks for our direct messages with nick.
+                    const peer = room.startsWith('@') ? room.slice(1) : undefined;
+                    if (peer !== undefined && !reader) {
+                        break;
+                    }
+                    const page = history
+                        .filter((m) =>
+                            peer === undefined
+                                ? m.room === room && !m.to
+                                : !!m.to && isBetween(m, String(reader!.nick), peer)
+                        )
+                        .filter((m) => typeof before !== 'number' || m.id < before)
+                        .slice(-limit);
+                    sendFrame(
+                        ws,
+                        JSON.stringify({
+                            messageType: 'history',
+                            room,
+                            id: before,
+                            clientId: parsed_data.clientId,
+                            dataArray: page.map((m) => JSON.stringify(peer === undefined ? m : { ...m, room })),
+                        })
+                    );
+                    if (peer !== undefined) {
+                        break;
+                    }
+                    if (reader) {
+                        addMember(room, String(reader.nick));
+                    }
+                    sendFrame(ws, pinsFrame(room));
+                    sendFrame(ws, roomUpdateFrame(room));
+                    (readCursors.get(room) || new Map<string, number>()).forEach((id, from) => {
+                        sendFrame(ws, JSON.stringify({ messageType: 'read', data: JSON.stringify({ from, room, id }) }));
+                    });
+                    break;
+                }
+                case 'read': {
+                    const reader = users.find((u) => u.ws === ws);
+                    const room = String(parsed_data.room || 'general');
+                    if (reader && typeof parsed_data.id === 'number') {
+                        const cursors = readCursors.get(room) || new Map<string, number>();
+                        if ((cursors.get(String(reader.nick)) || 0) < parsed_data.id) {
+                            cursors.set(String(reader.nick), parsed_data.id);
+                            readCursors.set(room, cursors);
+                            broadcast(
+                                JSON.stringify({
+                                    messageType: 'read',
+                                    data: JSON.stringify({ from: reader.nick, room, id: parsed_data.id }),
                                 }),
+                                ws
+                            );
+                        }
+                    }
+                    break;
+                }
+                case 'createroom': {
+                    const creator = users.find((u) => u.ws === ws);
+                    const room = String(parsed_data.room || '');
+                    if (!creator) {
+                        break;
+                    }
+                    if (!ROOM_NAME.test(room)) {
+                        sendError(
+                            ws,
+                            'room_invalid',
+                            'Room names are 1-32 lowercase letters, digits, dashes or underscores.',
+                            parsed_data.clientId
+                        );
+                        break;
+                    }
+                    // A room someone is already talking in counts as taken too.
+                    if (rooms.has(room) || history.some((m) => m.room === room)) {
+                        sendError(ws, 'room_exists', `#${room} already exists. Pick another name.`, parsed_data.clientId);
+                        break;
+                    }
+                    const description = String(parsed_data.data || '').slice(0, MAX_DESCRIPTION_LENGTH);
+                    rooms.set(room, { description, createdBy: creator.nick });
+                    sendFrame(ws, JSON.stringify({ messageType: 'createroom', room, data: description, clientId: parsed_data.clientId }));
+                    broadcast(JSON.stringify({ messageType: 'createroom', room, data: description }), ws);
+                    break;
+                }
+                case 'members': {
+                    const room = String(parsed_data.room || 'general');
+                    // `id` is how many the client already has.
+                    const offset = Math.max(0, Number(parsed_data.id) || 0);
+                    const all = Array.from((roomMembers.get(room) || new Map<string, number>()).entries());
+                    sendFrame(
+                        ws,
+                        JSON.stringify({
+                            messageType: 'members',
+                            room,
+                            id: offset,
+                            data: String(all.length),
+                            dataArray: all
+                                .slice(offset, offset + MEMBERS_PAGE_SIZE)
+                                .map(([name, joinedAt]) => JSON.stringify({ name, role: roleIn(room, name), joinedAt })),
+                        })
+                    );
+                    break;
+                }
+                case 'roomupdate': {
+                    const editor = users.find((u) => u.ws === ws);
+                    const room = String(parsed_data.room || 'general');
+                    if (!editor) {
+                        break;
+                    }
+                    if (!roomExists(room)) {
+                        sendError(ws, 'room_unknown', `There's no #${room} to change.`);
+                        break;
+                    }
+                    // A room that just sprang up has no creator, so only moderators can.
+                    const known = rooms.get(room);
+                    if (!moderates(String(editor.nick)) && known?.createdBy !== editor.nick) {
+                        sendError(ws, 'room_forbidden', `Only moderators and whoever made #${room} can change its topic.`);
+                        break;
+                    }
+                    const description = String(parsed_data.data || '').trim().slice(0, MAX_DESCRIPTION_LENGTH);
+                    rooms.set(room, { ...known, description, createdBy: known ? known.createdBy : '' });
+                    broadcast(roomUpdateFrame(room, String(editor.nick)));
+                    break;
+                }
+                case 'archive':
+                case 'unarchive': {
+                    const editor = users.find((u) => u.ws === ws);
+                    const room = String(parsed_data.room || 'general');
+                    if (!editor) {
+                        break;
+                    }
+                    if (!roomExists(room)) {
+                        sendError(ws, 'room_unknown', `There's no #${room} to archive.`);
+                        break;
+                    }
+                    const known = rooms.get(room);
+                    if (!moderates(String(editor.nick)) && known?.createdBy !== editor.nick) {
+                        sendError(ws, 'room_forbidden', `Only moderators and whoever made #${room} can archive it.`);
+                        break;
+                    }
+                    // Everyone starts out there, so it stays open.
+                    if (room === 'general') {
+                        sendError(ws, 'room_forbidden', `#general can't be archived.`);
+                        break;
+                    }
+                    rooms.set(room, {
+                        description: known ? known.description : '',
+                        createdBy: known ? known.createdBy : '',
+                        archived: parsed_data.messageType === 'archive',
+                    });
+                    broadcast(roomUpdateFrame(room, String(editor.nick)));
+                    break;
+                }
+                case 'pin':
+                case 'unpin':
+                case 'pins': {
+                    const room = String(parsed_data.room || 'general');
+                    const current = pins.get(room) || [];
+                    let next = current;
+                    // Anyone may ask what's pinned; changing it is for moderators and the room's creator,
+                    // or anyone where the room has no creator.
+                    const pinner = users.find((u) => u.ws === ws);
+                    const creator = rooms.get(room)?.createdBy || '';
+                    const changing = parsed_data.messageType !== 'pins' || Boolean(parsed_data.dataArray);
+                    if (changing && (!pinner || (creator && !moderates(String(pinner.nick)) && creator !== pinner.nick))) {
+                        sendError(ws, 'pin_forbidden', `Only moderators and whoever made #${room} can change its pins.`);
+                        sendFrame(ws, pinsFrame(room));
+                        break;
+                    }
+                    if (changing && rooms.get(room)?.archived) {
+                        sendError(ws, 'room_archived', 'This room is archived, so it takes no new messages.');
+                        sendFrame(ws, pinsFrame(room));
+                        break;
+                    }
+                    if (parsed_data.messageType === 'pin') {
+                        const id = parsed_data.id;
+                        const exists = history.some((m) => m.id === id && m.room === room && !m.deleted);
+                        if (typeof id !== 'number' || !exists || current.includes(id)) {
+                            sendFrame(ws, pinsFrame(room));
+                            break;
+                        }
+                        if (current.length >= PIN_LIMIT) {
+                            sendError(ws, 'pin_limit', `#${room} already has ${PIN_LIMIT} pins. Unpin one first.`);
+                            sendFrame(ws, pinsFrame(room));
+                            break;
+                        }
+                        next = [...current, id];
+                    } else if (parsed_data.messageType === 'unpin') {
+                        next = current.filter((id) => id !== parsed_data.id);
+                    } else {
+                        // A reorder must be a permutation of what is pinned now.
+                        const order = (parsed_data.dataArray || []).map((id) => parseInt(String(id)));
+                        const same = order.length === current.length && current.every((id) => order.includes(id));
+                        if (!same) {
+                            sendFrame(ws, pinsFrame(room));
+                            break;
+                        }
+                        next = order;
+                    }
+                    pins.set(room, next);
+                    broadcast(pinsFrame(room));
+                    break;
+                }
+                case 'edit': {
+                    const editor = users.find((u) => u.ws === ws);
+                    const target = history.find((m) => m.id === parsed_data.id);
+                    if (!editor || !target || target.from !== editor.nick || target.deleted) {
+                        sendError(ws, 'edit_rejected', 'You can only edit your own recent messages.', parsed_data.clientId);
+                    } else if (rooms.get(String(target.room))?.archived) {
+                        sendError(ws, 'edit_rejected', 'This room is archived, so its messages stay as they are.', parsed_data.clientId);
+                    } else if (!parsed_data.data || parsed_data.data.length > MAX_MESSAGE_LENGTH) {
+                        sendError(ws, 'edit_rejected', `Messages must be between 1 and ${MAX_MESSAGE_LENGTH} characters.`, parsed_data.clientId);
+                    } else {
+                        target.message = parsed_data.data;
+                        target.edited = true;
+                        deliver(
+                            target,
+                            JSON.stringify({
+                                messageType: 'edit',
+                                data: JSON.stringify({ id: target.id, message: target.message, from: editor.nick }),
                             })
                         );
                     }
+                    break;
+                }
+                case 'notify': {
+                    const notifier = users.find((u) => u.ws === ws);
+                    const root = history.find((m) => m.id === parsed_data.id && !m.parentId && !m.deleted);
+                    if (!notifier) {
+                        break;
+                    }
+                    if (!root || (root.to && root.from !== notifier.nick && root.to !== notifier.nick)) {
+                        sendError(ws, 'thread_missing', 'That thread is no longer available.');
+                        break;
+                    }
+                    const key = `${notifier.nick}:${root.id}`;
+                    const now = Date.now();
+                    if (now - (threadNotified.get(key) || 0) < NOTIFY_COOLDOWN_MS) {
+                        sendError(ws, 'notify_too_soon', 'You notified this thread a moment ago.');
+                        break;
+                    }
+                    threadNotified.forEach((at, k) => now - at >= NOTIFY_COOLDOWN_MS && threadNotified.delete(k));
+                    threadNotified.set(key, now);
+                    const thread = [root, ...history.filter((m) => m.parentId === root.id && !m.deleted)];
+                    const participants = new Set(thread.map((m) => m.from).filter((nick) => nick !== notifier.nick));
+                    // A direct conversation is `@` the other person to whoever gets this.
+                    const room = root.to ? `@${notifier.nick}` : root.room;
+                    const frame = JSON.stringify({
+                        messageType: 'notify',
+                        data: JSON.stringify({ from: notifier.nick, room, threadId: root.id }),
+                    });
+                    const reached = users.filter((u) => participants.has(u.nick));
+                    reached.forEach((u) => sendFrame(u.ws, frame));
+                    // Someone connected twice still counts once.
+                    const count = new Set(reached.map((u) => u.nick)).size;
+                    sendFrame(
+                        ws,
+                        JSON.stringify({
+                            messageType: 'notify',
+                            data: JSON.stringify({ from: notifier.nick, room: root.room, threadId: root.id, count }),
+                        })
+                    );
+                    break;
+                }
+                case 'announce': {
+                    const announcer = users.find((u) => u.ws === ws);
+                    const room = String(parsed_data.room || 'general');
+                    let request: { text?: unknown; sticky?: unknown } | undefined;
+                    try {
+                        request = JSON.parse(String(parsed_data.data));
+                    } catch (e) {
+                        request = undefined;
+                    }
+                    const text = String(request?.text || '').trim();
+                    if (!announcer || !moderates(String(announcer.nick))) {
+                        sendError(ws, 'announce_forbidden', 'Only moderators can make announcements.');
+                    } else if (!text || text.length > MAX_MESSAGE_LENGTH) {
+                        sendError(ws, 'announce_rejected', `Announcements must be between 1 and ${MAX_MESSAGE_LENGTH} characters.`);
+                    } else if (!ROOM_NAME.test(room)) {
+                        sendError(ws, 'room_invalid', 'Room names are 1-32 lowercase letters, digits, dashes or underscores.');
+                    } else if (!roomExists(room)) {
+                        sendError(ws, 'room_unknown', `There's no #${room} to announce to.`);
+                    } else if (rooms.get(room)?.archived) {
+                        sendError(ws, 'room_archived', 'This room is archived, so it takes no new messages.');
+                    } else {
+                        const stored: ChatMessage = {
+                            id: nextMessageId++,
+                            from: announcer.nick,
+                            message: text,
+                            room,
+                            time: Date.now(),
+                            announcement: true,
+                            sticky: request?.sticky === true || undefined,
+                        };
+                        history.push(stored);
+                        if (history.length > HISTORY_LIMIT) {
+                            history.shift();
+                        }
+                        broadcast(JSON.stringify({ messageType: 'announce', data: JSON.stringify(stored) }));
+                    }
+                    break;
+                }
+                case 'kick':
+                case 'ban': {
+                    const moderator = users.find((u) => u.ws === ws);
+                    let request: { name?: unknown; reason?: unknown; minutes?: unknown } | undefined;
+                    try {
+                        request = JSON.parse(String(parsed_data.data));
+                    } catch (e) {
+                        request = undefined;
+                    }
+                    const name = String(request?.name || '');
+                    if (!moderator || !name || !outranks(String(moderator.nick), name)) {
+                        sendError(ws, 'remove_forbidden', 'Moderators can remove members, and owners moderators too.');
+                        break;
+                    }
+                    const target = users.find((u) => u.nick === name);
+                    const reason = String(request?.reason || '').slice(0, MAX_REASON_LENGTH);
+                    const by = String(moderator.nick);
+                    if (parsed_data.messageType === 'ban') {
+                        // Names that aren't connected can be banned too, ahead of time.
+                        const minutes = Math.max(0, Math.floor(Number(request?.minutes) || 0));
+                        bans.set(name, minutes > 0 ? Date.now() + minutes * 60_000 : null);
+                        if (target) {
+                            removeSocket(target.ws, 'ban', reason, minutes > 0 ? minutes * 60 : undefined, by);
+                        }
+                    } else if (target) {
+                        removeSocket(target.ws, 'kick', reason, undefined, by);
+                    }
+                    break;
+                }
+                case 'delete': {
+                    const deleter = users.find((u) => u.ws === ws);
+                    const target = history.find((m) => m.id === parsed_data.id);
+                    if (!deleter || !target || (target.from !== deleter.nick && !moderates(String(deleter.nick)))) {
+                        sendError(ws, 'delete_rejected', 'You can only delete your own recent messages.', parsed_data.clientId);
+                    } else if (rooms.get(String(target.room))?.archived) {
+                        sendError(ws, 'delete_rejected', 'This room is archived, so its messages stay as they are.', parsed_data.clientId);
+                    } else if (!target.deleted) {
+                        target.message = '';
+                        target.deleted = true;
+                        const roomPins = pins.get(String(target.room));
+                        if (roomPins && roomPins.includes(target.id)) {
+                            pins.set(String(target.room), roomPins.filter((id) => id !== target.id));
+                            broadcast(pinsFrame(String(target.room)));
+                        }
+                        deliver(
+                            target,
+                            JSON.stringify({
+                                messageType: 'delete',
+                                data: JSON.stringify({ id: target.id, from: deleter.nick }),
+                            })
+                        );
+                    }
+                    break;
+                }
+                case 'reaction': {
+                    const reactor = users.find((u) => u.ws === ws);
+                    if (reactor && typeof parsed_data.id === 'number' && parsed_data.data) {
+                        const frame = JSON.stringify({
+                            messageType: 'reaction',
+                            data: JSON.stringify({ id: parsed_data.id, emoji: parsed_data.data, from: reactor.nick }),
+                        });
+                        // One that has fallen out of history can't be checked, so goes to everyone.
+                        const target = history.find((m) => m.id === parsed_data.id);
+                        if (target && rooms.get(String(target.room))?.archived) {
+                            sendError(ws, 'room_archived', 'This room is archived, so its messages stay as they are.');
+                        } else {
+                            target ? deliver(target, frame) : broadcast(frame);
+                        }
+                    }
+                    break;
+                }
+                case 'message':
+                    const sender = users.find((u) => u.ws === ws);
+                    if (!sender) {
+                        sendError(ws, 'not_registered', 'Register before sending messages.', parsed_data.clientId);
+                    } else if (!parsed_data.data || parsed_data.data.length > MAX_MESSAGE_LENGTH) {
+                        sendError(
+                            ws,
+                            'message_rejected',
+                            `Messages must be between 1 and ${MAX_MESSAGE_LENGTH} characters.`,
+                            parsed_data.clientId
+                        );
+                    } else if (!parsed_data.to && rooms.get(parsed_data.room || 'general')?.archived) {
+                        sendError(ws, 'room_archived', 'This room is archived, so it takes no new messages.', parsed_data.clientId);
+                    } else if (parsed_data.parentId !== undefined && !threadRoot(parsed_data)) {
+                        sendError(ws, 'thread_missing', 'That thread is no longer available.', parsed_data.clientId);
+                    } else {
+                        // The client ID is an idempotency key: a retransmission of
+                        // something we already stored is only acknowledged again.
+                        const clientId = parsed_data.clientId;
+                        const known =
+                            typeof clientId === 'string'
+                                ? history.find((m) => m.from === sender.nick && m.clientId === clientId)
+                                : undefined;
+                        if (known) {
+                            sendFrame(ws, JSON.stringify({ messageType: 'ack', clientId, id: known.id }));
+                            break;
+                        }
+                        const stored: ChatMessage = {
+                            id: nextMessageId++,
+                            from: sender.nick,
+                            message: parsed_data.data,
+                            room: parsed_data.to ? 'direct' : parsed_data.room || 'general',
+                            clientId: parsed_data.clientId,
+                            to: parsed_data.to ? String(parsed_data.to) : undefined,
+                            parentId: parsed_data.parentId,
+                            replyTo: quotable(parsed_data.replyTo, sender.nick) ? parsed_data.replyTo : undefined,
+                            time: Date.now(),
+                        };
+                        history.push(stored);
+                        if (!stored.to) {
+                            addMember(stored.room, String(sender.nick));
+                        }
+                        if (history.length > HISTORY_LIMIT) {
+                            history.shift();
+                        }
+                        if (typeof clientId === 'string') {
+                            sendFrame(ws, JSON.stringify({ messageType: 'ack', clientId, id: stored.id }));
+                        }
+                        if (stored.to) {
+                            // Each side files it under the other's name.
+                            users
+                                .filter((u) => u.nick === stored.from || u.nick === stored.to)
+                                .forEach((u) =>
+                                    sendFrame(
+                                        u.ws,
+                                        JSON.stringify({
+                                            messageType: 'message',
+                                            data: JSON.stringify({ ...stored, room: '@' + (u.nick === stored.from ? stored.to : stored.from) }),
+                                        })
+                                    )
+                                );
+                        } else {
+                            broadcast(JSON.stringify({ messageType: 'message', data: JSON.stringify(stored) }));
+                        }
+                    }
             }
         } catch (e) {
             console.log('Error in message', e);
         }
     });
-});
+};
+wss.on('connection', handleConnection);
 
 const interval = setInterval(function ping() {
-    const current_clients = Array.from(wss.clients);
+    const current_clients = [...Array.from(wss.clients), ...pollClients()];
     const updated_users = users.filter((u) => current_clients.includes(u.ws));
     if (updated_users.length !== users.length) {
         users = updated_users;
-        broadcast(JSON.stringify({ messageType: 'users', dataArray: users.map((u) => u.nick) }));
+        broadcastUsers();
     }
 }, 5000);
 
-const broadcast = (data: any) => {
-    wss.clients.forEach((client) => {
-        if (client.readyState === WebSocket.OPEN) {
-            client.send(data);
+const activeBan = (nick: string) => {
+    if (!bans.has(nick)) {
+        return undefined;
+    }
+    const until = bans.get(nick) ?? null;
+    if (until !== null && until <= Date.now()) {
+        bans.delete(nick);
+        return undefined;
+    }
+    return {
+        reason: 'You are banned from this server.',
+        seconds: until === null ? undefined : Math.ceil((until - Date.now()) / 1000),
+    };
+};
+
+// `by` is the moderator who did it from their client, if one did.
+const removeSocket = (ws: WebSocket, kind: 'kick' | 'ban', reason: string, seconds?: number, by?: string) => {
+    sendFrame(ws, JSON.stringify({ messageType: kind, data: JSON.stringify({ reason, seconds, by }) }));
+    ws.close(1008, kind);
+    users = users.filter((u) => u.ws !== ws);
+    broadcastUsers();
+};
+
+const userUpdated = (name: string, update: { newName?: string; avatar?: string }) =>
+    JSON.stringify({ messageType: 'userupdated', data: JSON.stringify({ name, ...update }) });
+
+// Moves everything kept under `from` to `to` and tells every client, so
+// past messages show the new name without a refresh.
+const renameUser = (from: string, to: string) => {
+    users.forEach((u) => {
+        if (u.nick === from) {
+            u.nick = to;
+        }
+    });
+    history.forEach((m) => {
+        if (m.from === from) {
+            m.from = to;
+        }
+        if (m.to === from) {
+            m.to = to;
+        }
+    });
+    readCursors.forEach((cursors) => {
+        const id = cursors.get(from);
+        if (id !== undefined) {
+            cursors.delete(from);
+            cursors.set(to, id);
+        }
+    });
+    roomMembers.forEach((members, room) => {
+        // Rebuilt rather than re-keyed, to keep the join order.
+        roomMembers.set(room, new Map(Array.from(members.entries()).map(([nick, joinedAt]) => [nick === from ? to : nick, joinedAt])));
+    });
+    rooms.forEach((room) => {
+        if (room.createdBy === from) {
+            room.createdBy = to;
+        }
+    });
+    const avatar = avatars.get(from);
+    if (avatar !== undefined) {
+        avatars.delete(from);
+        avatars.set(to, avatar);
+    }
+    broadcast(userUpdated(from, { newName: to }));
+    broadcastUsers();
+};
+
+// Operator commands on stdin:
+//   kick <nick> [reason]
+//   ban <nick> <minutes, 0 for permanent> [reason]
+//   status <ok|degraded|maintenance> [minutes] [message]
+//   rename <nick> <new nick>
+//   avatar <nick> [url, or nothing to go back to the generated one]
+readline.createInterface({ input: process.stdin }).on('line', (line) => {
+    const [command, nick, ...rest] = line.trim().split(/\s+/);
+    if (command === 'status' && (nick === 'ok' || nick === 'degraded' || nick === 'maintenance')) {
+        const minutes = parseInt(rest[0]);
+        if (!isNaN(minutes)) {
+            rest.shift();
+        }
+        health = {
+            status: nick,
+            message: rest.join(' ') || undefined,
+            until: minutes > 0 ? Date.now() + minutes * 60_000 : undefined,
+        };
+        return;
+    }
+    const target = users.find((u) => u.nick === nick);
+    if (command === 'kick' && target) {
+        removeSocket(target.ws, 'kick', rest.join(' '));
+    } else if (command === 'rename' && nick && rest[0]) {
+        const to = rest[0];
+        if (users.some((u) => u.nick === to) || history.some((m) => m.from === to)) {
+            console.log(`${to} is already in use`);
+        } else if (devicesOf(nick).length > 0) {
+            console.log(`${nick} has registered devices, which stay with the name; remove them first`);
+        } else {
+            renameUser(nick, to);
+        }
+    } else if (command === 'avatar' && nick) {
+        const url = rest[0] || '';
+        if (url) {
+            avatars.set(nick, url);
+        } else {
+            avatars.delete(nick);
+        }
+        broadcast(userUpdated(nick, { avatar: url }));
+    } else if (command === 'ban' && nick) {
+        const minutes = parseInt(rest.shift() || '0') || 0;
+        const reason = rest.join(' ');
+        bans.set(nick, minutes > 0 ? Date.now() + minutes * 60_000 : null);
+        if (target) {
+            removeSocket(target.ws, 'ban', reason, minutes > 0 ? minutes * 60 : undefined);
+        }
+    } else {
+        console.log(
+            'usage: kick <nick> [reason] | ban <nick> <minutes> [reason] | status <ok|degraded|maintenance> [minutes] [message]' +
+                ' | rename <nick> <new nick> | avatar <nick> [url]'
+        );
+    }
+});
+
+const join = (ws: WebSocket, nick: string) => {
+    users = users.filter((u) => u.ws !== ws && u.nick !== nick);
+    users.push({ ws, nick, isAlive: true, status: 'online' });
+    broadcastUsers();
+    avatars.forEach((avatar, name) => sendFrame(ws, userUpdated(name, { avatar })));
+};
+
+// The nick has registered devices: the socket joins once it proves it holds one.
+const askToVerify = (ws: WebSocket, nick: string) => {
+    const challenge = newChallenge();
+    challenges.set(ws, { purpose: 'verify', nick, challenge });
+    sendFrame(
+        ws,
+        JSON.stringify({
+            messageType: 'verify',
+            data: JSON.stringify({
+                challenge: Array.from(challenge),
+                credentials: devicesOf(nick).map((d) => Array.from(Buffer.from(d.id, 'base64url'))),
+            }),
+        })
+    );
+};
+
+const devicesFrame = (nick: string) => JSON.stringify({ messageType: 'devices', dataArray: deviceList(nick) });
+
+// The message a reply's thread hangs off. Threads live in rooms, not
+// direct conversations, and go one level deep.
+const threadRoot = (parsed_data: Message) =>
+    parsed_data.to
+        ? undefined
+        : history.find(
+              (m) => m.id === parsed_data.parentId && m.room === (parsed_data.room || 'general') && !m.parentId && !m.deleted
+          );
+
+// Whether `nick` may quote message `id`: it exists, and if it's a direct
+// message, it's one of theirs.
+const quotable = (id: number | undefined, nick: String) =>
+    id !== undefined && history.some((m) => m.id === id && !m.deleted && (!m.to || m.from === nick || m.to === nick));
+
+const pinsFrame = (room: string) =>
+    JSON.stringify({
+        messageType: 'pins',
+        room,
+        data: String(PIN_LIMIT),
+        dataArray: (pins.get(room) || []).map(String),
+    });
+
+const addMember = (room: string, nick: string) => {
+    const members = roomMembers.get(room) || new Map<string, number>();
+    if (!members.has(nick)) {
+        members.set(nick, Date.now());
+        roomMembers.set(room, members);
+    }
+};
+
+const roleIn = (room: string, nick: string) =>
+    rooms.get(room)?.createdBy === nick ? 'creator' : roleOf(nick);
+
+// Where someone stands on the whole server, lowest first.
+const ROLES = ['member', 'moderator', 'owner'];
+
+const roleOf = (nick: string) => (OWNERS.includes(nick) ? 'owner' : MODERATORS.includes(nick) ? 'moderator' : 'member');
+
+const moderates = (nick: string) => roleOf(nick) !== 'member';
+
+const outranks = (a: string, b: string) => ROLES.indexOf(roleOf(a)) > ROLES.indexOf(roleOf(b));
+
+// A room's topic, creator and whether it's archived; `by` says who just
+// changed one of them.
+const roomUpdateFrame = (room: string, by?: string) => {
+    const known = rooms.get(room);
+    return JSON.stringify({
+        messageType: 'roomupdate',
+        room,
+        data: JSON.stringify({
+            description: known ? known.description : '',
+            createdBy: known ? known.createdBy : '',
+            archived: Boolean(known?.archived),
+            by,
+        }),
+    });
+};
+
+const sendError = (ws: WebSocket, code: string, message: string, clientId?: String) => {
+    sendFrame(ws, JSON.stringify({ messageType: 'error', data: JSON.stringify({ code, message, clientId }) }));
+};
+
+const broadcastUsers = () => {
+    const statuses: { [nick: string]: { status: String; role: string } } = {};
+    users.forEach((u) => (statuses[String(u.nick)] = { status: u.status, role: roleOf(String(u.nick)) }));
+    broadcast(
+        JSON.stringify({
+            messageType: 'users',
+            dataArray: users.map((u) => u.nick),
+            data: JSON.stringify(statuses),
+        })
+    );
+};
+
+const sendFrame = (ws: WebSocket, data: string) => {
+    ws.send(msgpackClients.has(ws) ? encode(JSON.parse(data)) : data);
+};
+
+const isBetween = (m: ChatMessage, a: string, b: string) =>
+    (m.from === a && m.to === b) || (m.from === b && m.to === a);
+
+// Sends news about `target` to whoever can see it: everyone, or for a
+// direct message just the two people in it.
+const deliver = (target: ChatMessage, data: string) => {
+    if (!target.to) {
+        broadcast(data);
+        return;
+    }
+    users.filter((u) => u.nick === target.from || u.nick === target.to).forEach((u) => sendFrame(u.ws, data));
+};
+
+const broadcast = (data: string, except?: WebSocket) => {
+    [...Array.from(wss.clients), ...pollClients()].forEach((client) => {
+        if (client !== except && client.readyState === WebSocket.OPEN) {
+            sendFrame(client, data);
         }
     });
 };
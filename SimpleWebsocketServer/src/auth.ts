@@ -0,0 +1,116 @@
+// Sign-in tokens for `register`.
+//
+//   POST /login   the body is { username, password }, answers { token }
+//
+// The first sign-in under a name sets its password; after that only the
+// same password gets a token for it. A token is good for one name until it
+// expires. Clients that register without one are let in unless
+// REQUIRE_LOGIN is set; a token that doesn't check out is always turned
+// away. Passwords, like tokens, live in memory, so a restart frees every
+// name again.
+import { randomBytes, scryptSync, timingSafeEqual } from 'crypto';
+import http from 'http';
+
+const TOKEN_TTL_MS = 24 * 60 * 60 * 1000;
+const MAX_BODY_BYTES = 4 * 1024;
+const MIN_PASSWORD_LENGTH = 8;
+const REQUIRE_LOGIN = process.env.REQUIRE_LOGIN === '1';
+
+const tokens = new Map<string, { nick: string; expires: number }>();
+// Name -> salt and scrypt hash of its password.
+const passwords = new Map<string, { salt: Buffer; hash: Buffer }>();
+
+const hashPassword = (password: string, salt: Buffer) => scryptSync(password, salt, 32);
+
+// Why `password` doesn't get a token for `nick`, or undefined if it does.
+// An unclaimed name is claimed with it.
+const passwordProblem = (nick: string, password: string): string | undefined => {
+    const known = passwords.get(nick);
+    if (!known) {
+        if (password.length < MIN_PASSWORD_LENGTH) {
+            return `Choose a password of at least ${MIN_PASSWORD_LENGTH} characters for "${nick}".`;
+        }
+        const salt = randomBytes(16);
+        passwords.set(nick, { salt, hash: hashPassword(password, salt) });
+        return undefined;
+    }
+    return timingSafeEqual(hashPassword(password, known.salt), known.hash) ? undefined : 'Wrong name or password.';
+};
+
+const reply = (res: http.ServerResponse, status: number, contentType: string, body?: string) => {
+    res.writeHead(status, {
+        'Content-Type': contentType,
+        'Cache-Control': 'no-store',
+        'Access-Control-Allow-Origin': '*',
+        'Access-Control-Allow-Headers': 'Content-Type',
+        'Access-Control-Allow-Methods': 'POST',
+    });
+    res.end(body);
+};
+
+// Answers the request if it's for /login; returns whether it was. `refuse`
+// gets a say in who may sign in, returning the reason if not.
+export const handleLogin = (
+    req: http.IncomingMessage,
+    res: http.ServerResponse,
+    refuse: (nick: string) => string | undefined
+): boolean => {
+    if ((req.url || '').split('?')[0] !== '/login') {
+        return false;
+    }
+    if (req.method === 'OPTIONS') {
+        reply(res, 204, 'text/plain');
+        return true;
+    }
+    if (req.method !== 'POST') {
+        reply(res, 405, 'text/plain');
+        return true;
+    }
+    const chunks: Buffer[] = [];
+    let size = 0;
+    req.on('data', (chunk: Buffer) => {
+        size += chunk.length;
+        if (size <= MAX_BODY_BYTES) {
+            chunks.push(chunk);
+        }
+    });
+    req.on('end', () => {
+        let nick = '';
+        let password = '';
+        try {
+            const body = JSON.parse(Buffer.concat(chunks).toString());
+            nick = String(body.username || '').trim();
+            password = String(body.password || '');
+        } catch (e) {
+            nick = '';
+        }
+        if (size > MAX_BODY_BYTES || !nick) {
+            reply(res, 400, 'text/plain', 'Please choose a name.');
+            return;
+        }
+        const reason = refuse(nick) || passwordProblem(nick, password);
+        if (reason) {
+            reply(res, 403, 'text/plain', reason);
+            return;
+        }
+        const token = randomBytes(24).toString('base64url');
+        tokens.set(token, { nick, expires: Date.now() + TOKEN_TTL_MS });
+        reply(res, 200, 'application/json', JSON.stringify({ token }));
+    });
+    return true;
+};
+
+// Why `nick` may not register with `token`, or undefined if it may.
+export const authProblem = (token: unknown, nick: string): string | undefined => {
+    if (token === undefined || token === null) {
+        return REQUIRE_LOGIN ? 'Please sign in first.' : undefined;
+    }
+    const entry = typeof token === 'string' ? tokens.get(token) : undefined;
+    if (!entry || entry.expires <= Date.now()) {
+        if (entry) {
+            tokens.delete(String(token));
+        }
+        return 'Your sign-in has expired. Please sign in again.';
+    }
+    return entry.nick === nick ? undefined : `That sign-in was for "${entry.nick}", not "${nick}".`;
+};
@@ -0,0 +1,146 @@
+// WebAuthn device verification for nicknames. Once someone registers a
+// device for a nick, joining under that nick requires proving possession of
+// one of its devices. Like everything else here this lives in memory, so a
+// restart leaves every nick unprotected again.
+//
+// Binary values travel as arrays of byte values; public keys arrive as the
+// SPKI DER the browser's getPublicKey() returns, so no CBOR parsing is needed.
+import { createHash, randomBytes, verify } from 'crypto';
+
+export interface Device {
+    id: string; // base64url credential ID
+    publicKey: string; // base64url SPKI DER
+    algorithm: number; // COSE algorithm identifier
+    name: string;
+    added: number;
+}
+
+export interface NewDevice {
+    id: number[];
+    publicKey: number[];
+    algorithm: number;
+    name: string;
+    clientDataJSON: number[];
+}
+
+export interface Assertion {
+    id: number[];
+    clientDataJSON: number[];
+    authenticatorData: number[];
+    signature: number[];
+}
+
+// ES256 and RS256; both hash with SHA-256.
+export const SUPPORTED_ALGORITHMS = [-7, -257];
+const SESSION_TTL_MS = 12 * 60 * 60 * 1000;
+const MAX_DEVICES = 10;
+const MAX_NAME_LENGTH = 40;
+// The page the client is served from, and the relying party ID its
+// credentials are scoped to. Both must match exactly what the browser
+// reports, so a page elsewhere can't relay an assertion for us.
+const ORIGIN = process.env.WEBAUTHN_ORIGIN || 'http://localhost:8000';
+const RP_ID = process.env.WEBAUTHN_RP_ID || new URL(ORIGIN).hostname;
+
+const devices = new Map<string, Device[]>();
+// Token -> the nick it vouches for, so a reconnect doesn't need the device again.
+const sessions = new Map<string, { nick: string; expires: number }>();
+
+const bytes = (value: unknown): Buffer | undefined =>
+    Array.isArray(value) && value.every((b) => Number.isInteger(b) && b >= 0 && b < 256) ? Buffer.from(value) : undefined;
+
+export const newChallenge = () => randomBytes(32);
+
+export const devicesOf = (nick: string) => devices.get(nick) || [];
+
+// What the client gets to see: no keys.
+export const deviceList = (nick: string) =>
+    devicesOf(nick).map((d) => JSON.stringify({ id: d.id, name: d.name, added: d.added }));
+
+export const removeDevice = (nick: string, id: string) => {
+    devices.set(nick, devicesOf(nick).filter((d) => d.id !== id));
+};
+
+// A stable, opaque user handle for the authenticator.
+export const userHandle = (nick: string) => createHash('sha256').update(nick).digest().subarray(0, 16);
+
+export const newSession = (nick: string) => {
+    const token = randomBytes(24).toString('base64url');
+    sessions.set(token, { nick, expires: Date.now() + SESSION_TTL_MS });
+    return token;
+};
+
+export const sessionValid = (token: unknown, nick: string) => {
+    const session = typeof token === 'string' ? sessions.get(token) : undefined;
+    if (!session || session.expires <= Date.now()) {
+        if (session) {
+            sessions.delete(String(token));
+        }
+        return false;
+    }
+    return session.nick === nick;
+};
+
+// Parses clientDataJSON and checks it answers our challenge from our page.
+const checkClientData = (clientData: Buffer, type: string, challenge: Buffer): boolean => {
+    try {
+        const parsed = JSON.parse(clientData.toString('utf8'));
+        return parsed.type === type && parsed.challenge === challenge.toString('base64url') && parsed.origin === ORIGIN;
+    } catch (e) {
+        return false;
+    }
+};
+
+export const enroll = (nick: string, challenge: Buffer, device: NewDevice): string | undefined => {
+    const id = bytes(device.id);
+    const publicKey = bytes(device.publicKey);
+    const clientData = bytes(device.clientDataJSON);
+    if (!id || !publicKey || !clientData || !SUPPORTED_ALGORITHMS.includes(device.algorithm)) {
+        return 'The device sent an incomplete registration.';
+    }
+    if (!checkClientData(clientData, 'webauthn.create', challenge)) {
+        return 'The registration did not match the request.';
+    }
+    const current = devicesOf(nick);
+    if (current.length >= MAX_DEVICES) {
+        return `At most ${MAX_DEVICES} devices can be registered.`;
+    }
+    if (current.some((d) => d.id === id.toString('base64url'))) {
+        return 'This device is already registered.';
+    }
+    current.push({
+        id: id.toString('base64url'),
+        publicKey: publicKey.toString('base64url'),
+        algorithm: device.algorithm,
+        name: String(device.name || 'Unnamed device').slice(0, MAX_NAME_LENGTH),
+        added: Date.now(),
+    });
+    devices.set(nick, current);
+    return undefined;
+};
+
+export const checkAssertion = (nick: string, challenge: Buffer, assertion: Assertion): boolean => {
+    const id = bytes(assertion.id);
+    const clientData = bytes(assertion.clientDataJSON);
+    const authenticatorData = bytes(assertion.authenticatorData);
+    const signature = bytes(assertion.signature);
+    const device = id && devicesOf(nick).find((d) => d.id === id.toString('base64url'));
+    if (!device || !clientData || !authenticatorData || !signature) {
+        return false;
+    }
+    // authenticatorData: 32-byte RP ID hash, flags, 4-byte sign counter.
+    if (!checkClientData(clientData, 'webauthn.get', challenge) || authenticatorData.length < 37) {
+        return false;
+    }
+    const rpIdHash = createHash('sha256').update(RP_ID).digest();
+    const userPresent = (authenticatorData[32] & 0x01) !== 0;
+    if (!rpIdHash.equals(authenticatorData.subarray(0, 32)) || !userPresent) {
+        return false;
+    }
+    const signed = Buffer.concat([authenticatorData, createHash('sha256').update(clientData).digest()]);
+    try {
+        const key = { key: Buffer.from(device.publicKey, 'base64url'), format: 'der' as const, type: 'spki' as const };
+        return verify('sha256', signed, key, signature);
+    } catch (e) {
+        return false;
+    }
+};
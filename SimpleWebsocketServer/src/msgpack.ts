@@ -0,0 +1,129 @@
+// Minimal MessagePack codec for JSON-shaped values: null, booleans, numbers,
+// strings, arrays and string-keyed objects. Enough for our frames without
+// pulling in a dependency.
+
+type Json = null | boolean | number | string | Json[] | { [key: string]: Json };
+
+export const encode = (value: Json): Buffer => {
+    const parts: Buffer[] = [];
+    const header = (bytes: number[]) => parts.push(Buffer.from(bytes));
+    const sized = (small: number, codes: [number, number, number], length: number) => {
+        if (small >= 0 && length < 16) {
+            header([small | length]);
+        } else if (codes[0] && length < 0x100) {
+            header([codes[0], length]);
+        } else if (length < 0x10000) {
+            header([codes[1], length >> 8, length & 0xff]);
+        } else {
+            const b = Buffer.alloc(5);
+            b[0] = codes[2];
+            b.writeUInt32BE(length, 1);
+            parts.push(b);
+        }
+    };
+    const write = (v: Json) => {
+        if (v === null || v === undefined) {
+            header([0xc0]);
+        } else if (typeof v === 'boolean') {
+            header([v ? 0xc3 : 0xc2]);
+        } else if (typeof v === 'number') {
+            if (Number.isInteger(v) && v >= 0 && v < 0x80) {
+                header([v]);
+            } else if (Number.isInteger(v) && v < 0 && v >= -32) {
+                header([v & 0xff]);
+            } else if (Number.isInteger(v) && v >= 0 && v <= 0xffffffff) {
+                const b = Buffer.alloc(5);
+                b[0] = 0xce;
+                b.writeUInt32BE(v, 1);
+                parts.push(b);
+            } else if (Number.isInteger(v) && v >= -0x80000000 && v < 0) {
+                const b = Buffer.alloc(5);
+                b[0] = 0xd2;
+                b.writeInt32BE(v, 1);
+                parts.push(b);
+            } else {
+                const b = Buffer.alloc(9);
+                b[0] = 0xcb;
+                b.writeDoubleBE(v, 1);
+                parts.push(b);
+            }
+        } else if (typeof v === 'string') {
+            const bytes = Buffer.from(v, 'utf8');
+            // fixstr only goes up to 31 bytes.
+            if (bytes.length < 32) {
+                header([0xa0 | bytes.length]);
+            } else {
+                sized(-1, [0xd9, 0xda, 0xdb], bytes.length);
+            }
+            parts.push(bytes);
+        } else if (Array.isArray(v)) {
+            sized(0x90, [0, 0xdc, 0xdd], v.length);
+            v.forEach(write);
+        } else {
+            const entries = Object.entries(v).filter(([, x]) => x !== undefined);
+            sized(0x80, [0, 0xde, 0xdf], entries.length);
+            entries.forEach(([k, x]) => {
+                write(k);
+                write(x);
+            });
+        }
+    };
+    write(value);
+    return Buffer.concat(parts);
+};
+
+export const decode = (buffer: Buffer): Json => {
+    let pos = 0;
+    const str = (length: number) => {
+        const s = buffer.toString('utf8', pos, pos + length);
+        pos += length;
+        return s;
+    };
+    const array = (length: number): Json[] => Array.from({ length }, () => read());
+    const map = (length: number) => {
+        const out: { [key: string]: Json } = {};
+        for (let i = 0; i < length; i++) {
+            const key = String(read());
+            out[key] = read();
+        }
+        return out;
+    };
+    const read = (): Json => {
+        const b = buffer[pos++];
+        if (b === undefined) throw new Error('msgpack: unexpected end of input');
+        if (b < 0x80) return b;
+        if (b >= 0xe0) return b - 0x100;
+        if ((b & 0xf0) === 0x80) return map(b & 0x0f);
+        if ((b & 0xf0) === 0x90) return array(b & 0x0f);
+        if ((b & 0xe0) === 0xa0) return str(b & 0x1f);
+        const next = (size: number, f: (at: number) => number) => {
+            const v = f(pos);
+            pos += size;
+            return v;
+        };
+        switch (b) {
+            case 0xc0: return null;
+            case 0xc2: return false;
+            case 0xc3: return true;
+            case 0xca: return next(4, (at) => buffer.readFloatBE(at));
+            case 0xcb: return next(8, (at) => buffer.readDoubleBE(at));
+            case 0xcc: return next(1, (at) => buffer.readUInt8(at));
+            case 0xcd: return next(2, (at) => buffer.readUInt16BE(at));
+            case 0xce: return next(4, (at) => buffer.readUInt32BE(at));
+            case 0xcf: return next(8, (at) => Number(buffer.readBigUInt64BE(at)));
+            case 0xd0: return next(1, (at) => buffer.readInt8(at));
+            case 0xd1: return next(2, (at) => buffer.readInt16BE(at));
+            case 0xd2: return next(4, (at) => buffer.readInt32BE(at));
+            case 0xd3: return next(8, (at) => Number(buffer.readBigInt64BE(at)));
+            case 0xd9: return str(next(1, (at) => buffer.readUInt8(at)));
+            case 0xda: return str(next(2, (at) => buffer.readUInt16BE(at)));
+            case 0xdb: return str(next(4, (at) => buffer.readUInt32BE(at)));
+            case 0xdc: return array(next(2, (at) => buffer.readUInt16BE(at)));
+            case 0xdd: return array(next(4, (at) => buffer.readUInt32BE(at)));
+            case 0xde: return map(next(2, (at) => buffer.readUInt16BE(at)));
+            case 0xdf: return map(next(4, (at) => buffer.readUInt32BE(at)));
+            default: throw new Error(`msgpack: unsupported type 0x${b.toString(16)}`);
+        }
+    };
+    return read();
+};
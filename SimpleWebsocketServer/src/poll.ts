@@ -0,0 +1,170 @@
+// Long-polling for clients behind proxies that won't pass a WebSocket.
+//
+//   POST /poll        opens a session, answers { id }
+//   GET  /poll/<id>   held until there is something to send, answers a JSON
+//                     array of frames (empty if nothing came up in time)
+//   POST /poll/<id>   the body is one frame from the client
+//
+// Each session stands in for a WebSocket, so the rest of the server doesn't
+// know the difference.
+import { EventEmitter } from 'events';
+import { randomBytes } from 'crypto';
+import http from 'http';
+import WebSocket from 'ws';
+
+// How long a GET is held when there is nothing to send.
+const HOLD_MS = 20_000;
+// A session that hasn't polled for this long is closed.
+const EXPIRE_MS = 45_000;
+const MAX_FRAME_BYTES = 64 * 1024;
+
+const sessions = new Map<string, PollSocket>();
+
+class PollSocket extends EventEmitter {
+    readonly id: string;
+    readyState: number = WebSocket.OPEN;
+    private queue: string[] = [];
+    private waiting?: { res: http.ServerResponse; timer: NodeJS.Timeout };
+    private expiry: NodeJS.Timeout;
+
+    constructor(id: string) {
+        super();
+        this.id = id;
+        this.expiry = setTimeout(() => this.expire(), EXPIRE_MS);
+    }
+
+    send(data: string | Buffer) {
+        if (this.readyState !== WebSocket.OPEN) {
+            return;
+        }
+        this.queue.push(data.toString());
+        this.respond();
+    }
+
+    // Whatever was sent last (a kick, say) still goes out with the next poll.
+    close() {
+        if (this.readyState !== WebSocket.OPEN) {
+            return;
+        }
+        this.readyState = WebSocket.CLOSED;
+        this.emit('close');
+        if (this.queue.length === 0) {
+            this.end();
+        } else {
+            this.respond();
+        }
+    }
+
+    receive(frame: Buffer) {
+        this.emit('message', frame, false);
+    }
+
+    poll(res: http.ServerResponse) {
+        // A newer poll takes over from one still waiting.
+        this.respond(true);
+        clearTimeout(this.expiry);
+        this.expiry = setTimeout(() => this.expire(), EXPIRE_MS);
+        const timer = setTimeout(() => this.respond(true), HOLD_MS);
+        this.waiting = { res, timer };
+        res.on('close', () => {
+            if (this.waiting?.res === res) {
+                clearTimeout(timer);
+                this.waiting = undefined;
+            }
+        });
+        this.respond();
+    }
+
+    private respond(evenIfEmpty = false) {
+        if (!this.waiting || (this.queue.length === 0 && !evenIfEmpty)) {
+            return;
+        }
+        const { res, timer } = this.waiting;
+        this.waiting = undefined;
+        clearTimeout(timer);
+        reply(res, 200, JSON.stringify(this.queue));
+        this.queue = [];
+        if (this.readyState !== WebSocket.OPEN) {
+            this.end();
+        }
+    }
+
+    private expire() {
+        this.close();
+        this.end();
+    }
+
+    private end() {
+        clearTimeout(this.expiry);
+        sessions.delete(this.id);
+        if (this.waiting) {
+            this.respond(true);
+        }
+    }
+}
+
+// The server only ever uses send, close, readyState and the 'message'
+// event, all of which a PollSocket has.
+const asWebSocket = (socket: PollSocket) => socket as unknown as WebSocket;
+
+export const pollClients = () => Array.from(sessions.values()).map(asWebSocket);
+
+const reply = (res: http.ServerResponse, status: number, body?: string) => {
+    res.writeHead(status, {
+        'Content-Type': 'application/json',
+        'Cache-Control': 'no-store',
+        'Access-Control-Allow-Origin': '*',
+    });
+    res.end(body);
+};
+
+// Answers the request if it's for /poll; returns whether it was.
+export const handlePoll = (
+    req: http.IncomingMessage,
+    res: http.ServerResponse,
+    onOpen: (ws: WebSocket) => void
+): boolean => {
+    const match = /^\/poll(?:\/([\w-]+))?\/?$/.exec((req.url || '').split('?')[0]);
+    if (!match) {
+        return false;
+    }
+    const id = match[1];
+    if (!id) {
+        if (req.method === 'POST') {
+            const socket = new PollSocket(randomBytes(16).toString('base64url'));
+            sessions.set(socket.id, socket);
+            onOpen(asWebSocket(socket));
+            reply(res, 200, JSON.stringify({ id: socket.id }));
+        } else {
+            reply(res, 405);
+        }
+        return true;
+    }
+
+    const socket = sessions.get(id);
+    if (!socket) {
+        reply(res, 404);
+    } else if (req.method === 'GET') {
+        socket.poll(res);
+    } else if (req.method === 'POST' && socket.readyState === WebSocket.OPEN) {
+        const chunks: Buffer[] = [];
+        let size = 0;
+        req.on('data', (chunk: Buffer) => {
+            size += chunk.length;
+            if (size <= MAX_FRAME_BYTES) {
+                chunks.push(chunk);
+            }
+        });
+        req.on('end', () => {
+            if (size > MAX_FRAME_BYTES) {
+                reply(res, 413);
+            } else {
+                socket.receive(Buffer.concat(chunks));
+                reply(res, 204);
+            }
+        });
+    } else {
+        reply(res, socket.readyState === WebSocket.OPEN ? 405 : 404);
+    }
+    return true;
+};
@@ -0,0 +1,311 @@
+use gloo_events::EventListener;
+use gloo_timers::callback::Interval;
+use wasm_bindgen::JsCast;
+use web_sys::{Element, HtmlElement, KeyboardEvent};
+use yew::prelude::*;
+
+const RESCAN_MS: u32 = 2_000;
+const MAX_ISSUES: usize = 200;
+const OVERLAY_ATTR: &str = "data-a11y-audit";
+
+#[derive(Clone, Copy, PartialEq)]
+enum IssueKind {
+    MissingName,
+    LowContrast,
+    Unfocusable,
+}
+
+impl IssueKind {
+    fn label(&self) -> &str {
+        match self {
+            IssueKind::MissingName => "Missing accessible name",
+            IssueKind::LowContrast => "Low contrast",
+            IssueKind::Unfocusable => "Not keyboard reachable",
+        }
+    }
+
+    fn outline(&self) -> &str {
+        match self {
+            IssueKind::MissingName => "border-red-500",
+            IssueKind::LowContrast => "border-orange-400",
+            IssueKind::Unfocusable => "border-purple-500",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+struct Issue {
+    kind: IssueKind,
+    detail: String,
+    rect: (f64, f64, f64, f64),
+}
+
+fn describe(el: &Element) -> String {
+    let tag = el.tag_name().to_lowercase();
+    match el.get_attribute("class") {
+        Some(class) if !class.is_empty() => {
+            let first: Vec<&str> = class.split_whitespace().take(3).collect();
+            format!("<{} class=\"{}…\">", tag, first.join(" "))
+        }
+        _ => format!("<{}>", tag),
+    }
+}
+
+fn attr_present(el: &Element, name: &str) -> bool {
+    el.get_attribute(name).map(|v| !v.trim().is_empty()).unwrap_or(false)
+}
+
+fn has_accessible_name(el: &Element, document: &web_sys::Document) -> bool {
+    if attr_present(el, "aria-label") || attr_present(el, "aria-labelledby") || attr_present(el, "title") {
+        return true;
+    }
+    let tag = el.tag_name().to_lowercase();
+    if tag == "img" {
+        // An empty alt marks the image as decorative, which is fine.
+        return el.has_attribute("alt");
+    }
+    if matches!(tag.as_str(), "input" | "select" | "textarea") {
+        if el.closest("label").ok().flatten().is_some() {
+            return true;
+        }
+        let id = el.id();
+        return !id.is_empty()
+            && document
+                .query_selector(&format!("label[for=\"{}\"]", id))
+                .ok()
+                .flatten()
+                .is_some();
+    }
+    if el.text_content().map(|t| !t.trim().is_empty()).unwrap_or(false) {
+        return true;
+    }
+    el.query_selector("img[alt]:not([alt=\"\"])").ok().flatten().is_some()
+}
+
+fn parse_rgba(value: &str) -> Option<(f64, f64, f64, f64)> {
+    let inner = value.split_once('(')?.1.trim_end_matches(')');
+    let parts: Vec<f64> = inner
+        .split([',', '/', ' '])
+        .filter(|p| !p.is_empty())
+        .filter_map(|p| p.trim().parse().ok())
+        .collect();
+    match parts.as_slice() {
+        [r, g, b] => Some((*r, *g, *b, 1.0)),
+        [r, g, b, a] => Some((*r, *g, *b, *a)),
+        _ => None,
+    }
+}
+
+fn luminance((r, g, b, _): (f64, f64, f64, f64)) -> f64 {
+    let channel = |c: f64| {
+        let c = c / 255.0;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * channel(r) + 0.7152 * channel(g) + 0.0722 * channel(b)
+}
+
+fn contrast_ratio(fg: (f64, f64, f64, f64), bg: (f64, f64, f64, f64)) -> f64 {
+    let (a, b) = (luminance(fg), luminance(bg));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+fn effective_background(window: &web_sys::Window, el: &Element) -> (f64, f64, f64, f64) {
+    let mut current = Some(el.clone());
+    while let Some(node) = current {
+        let bg = window
+            .get_computed_style(&node)
+            .ok()
+            .flatten()
+            .and_then(|s| s.get_property_value("background-color").ok())
+            .and_then(|v| parse_rgba(&v));
+        if let Some(bg) = bg {
+            if bg.3 > 0.5 {
+                return bg;
+            }
+        }
+        current = node.parent_element();
+    }
+    (255.0, 255.0, 255.0, 1.0)
+}
+
+fn has_own_text(el: &Element) -> bool {
+    let children = el.child_nodes();
+    (0..children.length()).filter_map(|i| children.item(i)).any(|n| {
+        n.node_type() == web_sys::Node::TEXT_NODE
+            && n.text_content().map(|t| !t.trim().is_empty()).unwrap_or(false)
+    })
+}
+
+fn scan() -> Vec<Issue> {
+    let window = match web_sys::window() {
+        Some(w) => w,
+        None => return vec![],
+    };
+    let document = match window.document() {
+        Some(d) => d,
+        None => return vec![],
+    };
+    let nodes = match document.query_selector_all("body *") {
+        Ok(nodes) => nodes,
+        Err(_) => return vec![],
+    };
+
+    let mut issues = Vec::new();
+    for i in 0..nodes.length() {
+        if issues.len() >= MAX_ISSUES {
+            break;
+        }
+        let el = match nodes.item(i).and_then(|n| n.dyn_into::<Element>().ok()) {
+            Some(el) => el,
+            None => continue,
+        };
+        if el.closest(&format!("[{}]", OVERLAY_ATTR)).ok().flatten().is_some() {
+            continue;
+        }
+        let r = el.get_bounding_client_rect();
+        if r.width() == 0.0 || r.height() == 0.0 {
+            continue;
+        }
+        let rect = (r.left(), r.top(), r.width(), r.height());
+        let tag = el.tag_name().to_lowercase();
+        let role = el.get_attribute("role").unwrap_or_default();
+        let interactive = matches!(tag.as_str(), "button" | "a" | "input" | "select" | "textarea" | "img")
+            || matches!(role.as_str(), "button" | "link" | "checkbox" | "menuitem" | "tab" | "option");
+
+        if interactive && !has_accessible_name(&el, &document) {
+            issues.push(Issue { kind: IssueKind::MissingName, detail: describe(&el), rect });
+        }
+
+        let style = window.get_computed_style(&el).ok().flatten();
+        if has_own_text(&el) {
+            let fg = style
+                .as_ref()
+                .and_then(|s| s.get_property_value("color").ok())
+                .and_then(|v| parse_rgba(&v));
+            if let Some(fg) = fg {
+                let ratio = contrast_ratio(fg, effective_background(&window, &el));
+                let size: f64 = style
+                    .as_ref()
+                    .and_then(|s| s.get_property_value("font-size").ok())
+                    .and_then(|v| v.trim_end_matches("px").parse().ok())
+                    .unwrap_or(16.0);
+                let required = if size >= 24.0 { 3.0 } else { 4.5 };
+                if ratio < required {
+                    issues.push(Issue {
+                        kind: IssueKind::LowContrast,
+                        detail: format!("{} ({:.1}:1, needs {}:1)", describe(&el), ratio, required),
+                        rect,
+                    });
+                }
+            }
+        }
+
+        let focusable = el
+            .dyn_ref::<HtmlElement>()
+            .map(|h| h.tab_index() >= 0)
+            .unwrap_or(false);
+        let pointer = style
+            .as_ref()
+            .and_then(|s| s.get_property_value("cursor").ok())
+            .map(|c| c == "pointer")
+            .unwrap_or(false);
+        // Options are driven through aria-activedescendant on their owner.
+        let owned_option = role == "option";
+        let inside_control = el
+            .parent_element()
+            .and_then(|p| p.closest("button, a[href], label, input, select, textarea, [tabindex]").ok().flatten())
+            .is_some();
+        if !focusable && !owned_option && !inside_control && ((interactive && tag != "img") || pointer) {
+            issues.push(Issue { kind: IssueKind::Unfocusable, detail: describe(&el), rect });
+        }
+    }
+    issues
+}
+
+/// Developer overlay (Alt+Shift+A) that outlines elements with common
+/// accessibility problems under the current theme.
+#[function_component(A11yAudit)]
+pub fn a11y_audit() -> Html {
+    let active = use_state(|| false);
+    let issues = use_state(Vec::<Issue>::new);
+
+    {
+        let handle = active.clone();
+        use_effect_with_deps(
+            move |_| {
+                let active = handle;
+                let document = web_sys::window().and_then(|w| w.document());
+                let listener = document.map(|document| {
+                    EventListener::new(&document, "keydown", move |e| {
+                        if let Some(e) = e.dyn_ref::<KeyboardEvent>() {
+                            if e.alt_key() && e.shift_key() && e.code() == "KeyA" {
+                                active.set(!*active);
+                            }
+                        }
+                    })
+                });
+                move || drop(listener)
+            },
+            *active,
+        );
+    }
+
+    {
+        let issues = issues.clone();
+        use_effect_with_deps(
+            move |active| {
+                let interval = if *active {
+                    issues.set(scan());
+                    let issues = issues.clone();
+                    Some(Interval::new(RESCAN_MS, move || issues.set(scan())))
+                } else {
+                    None
+                };
+                move || drop(interval)
+            },
+            *active,
+        );
+    }
+
+    if !*active {
+        return html! {};
+    }
+
+    let count = |kind: IssueKind| issues.iter().filter(|i| i.kind == kind).count();
+
+    html! {
+        <div data-a11y-audit="" class="fixed inset-0 pointer-events-none z-50">
+            {
+                issues.iter().map(|issue| {
+                    let (x, y, w, h) = issue.rect;
+                    html! {
+                        <div
+                            class={format!("absolute border-2 {}", issue.kind.outline())}
+                            style={format!("left:{}px;top:{}px;width:{}px;height:{}px", x, y, w, h)}
+                            title={format!("{}: {}", issue.kind.label(), issue.detail)}
+                        />
+                    }
+                }).collect::<Html>()
+            }
+            <div class="absolute bottom-3 left-3 w-96 max-h-64 overflow-y-auto pointer-events-auto bg-white text-black text-xs rounded-lg shadow-xl p-3">
+                <div class="font-bold mb-1">{"Accessibility audit"}</div>
+                <div class="mb-2">
+                    <span class="text-red-600 mr-3">{format!("{} unnamed", count(IssueKind::MissingName))}</span>
+                    <span class="text-orange-500 mr-3">{format!("{} low contrast", count(IssueKind::LowContrast))}</span>
+                    <span class="text-purple-600">{format!("{} unreachable", count(IssueKind::Unfocusable))}</span>
+                </div>
+                <ul>
+                    {
+                        issues.iter().map(|issue| html! {
+                            <li class="truncate">{format!("{}: {}", issue.kind.label(), issue.detail)}</li>
+                        }).collect::<Html>()
+                    }
+                </ul>
+            </div>
+        </div>
+    }
+}
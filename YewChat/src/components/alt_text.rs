@@ -0,0 +1,69 @@
+use web_sys::HtmlTextAreaElement;
+use yew::prelude::*;
+
+use crate::services::file_transfer::MAX_ALT_LENGTH;
+
+#[derive(Properties, PartialEq)]
+pub struct AltTextPromptProps {
+    pub name: String,
+    /// An object URL for the picture, to describe what you're looking at.
+    pub preview: String,
+    /// Takes the description, empty if the user skipped it.
+    pub on_send: Callback<String>,
+    pub on_close: Callback<()>,
+}
+
+/// Asks for a description of a picture before it's sent, so people using
+/// a screen reader get more than its file name. Can be turned off in the
+/// settings.
+#[function_component(AltTextPrompt)]
+pub fn alt_text_prompt(props: &AltTextPromptProps) -> Html {
+    let alt = use_state(String::new);
+    let oninput = {
+        let alt = alt.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlTextAreaElement = e.target_unchecked_into();
+            alt.set(input.value());
+        })
+    };
+    let onsubmit = {
+        let alt = alt.clone();
+        props.on_send.reform(move |e: FocusEvent| {
+            e.prevent_default();
+            alt.trim().to_string()
+        })
+    };
+    let skip = props.on_send.reform(|_: MouseEvent| String::new());
+    let close = props.on_close.reform(|_: MouseEvent| ());
+
+    html! {
+        <div class="fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center z-50">
+            <form {onsubmit} role="dialog" aria-modal="true" aria-labelledby="alt-text-title" class="bg-white text-gray-900 rounded-lg shadow-xl w-96 max-w-full p-6">
+                <h2 id="alt-text-title" class="text-lg font-bold mb-3">{"Describe this image"}</h2>
+                <img src={props.preview.clone()} alt="" class="max-h-48 mx-auto rounded mb-3" />
+                <label class="block text-sm font-medium mb-1" for="alt-text-input">{"Alt text"}</label>
+                <textarea
+                    id="alt-text-input"
+                    value={(*alt).clone()}
+                    {oninput}
+                    rows="3"
+                    maxlength={MAX_ALT_LENGTH.to_string()}
+                    placeholder="What's in the picture, for people who can't see it"
+                    class="w-full px-2 py-1 rounded bg-gray-100 outline-none resize-none focus:ring-2 focus:ring-blue-600"
+                />
+                <p class="text-xs text-gray-500 mt-1 truncate" title={props.name.clone()}>{props.name.clone()}</p>
+                <div class="flex justify-end mt-4">
+                    <button type="button" onclick={close} class="px-4 py-2 rounded hover:bg-gray-100 mr-2">{"Cancel"}</button>
+                    <button type="button" onclick={skip} class="px-4 py-2 rounded hover:bg-gray-100 mr-2">{"Send without"}</button>
+                    <button
+                        type="submit"
+                        disabled={alt.trim().is_empty()}
+                        class="px-4 py-2 rounded bg-blue-600 text-white hover:bg-blue-700 disabled:opacity-50"
+                    >
+                        {"Send"}
+                    </button>
+                </div>
+            </form>
+        </div>
+    }
+}
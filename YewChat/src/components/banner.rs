@@ -0,0 +1,55 @@
+use serde::Deserialize;
+use yew::prelude::*;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BannerLevel {
+    #[default]
+    Info,
+    Warning,
+    Critical,
+}
+
+impl BannerLevel {
+    pub fn get_css_classes(&self) -> &str {
+        match self {
+            BannerLevel::Info => "bg-blue-100 text-blue-900 border-blue-300",
+            BannerLevel::Warning => "bg-yellow-100 text-yellow-900 border-yellow-300",
+            BannerLevel::Critical => "bg-red-100 text-red-900 border-red-300",
+        }
+    }
+}
+
+/// A server announcement. Banners without a room are shown everywhere.
+#[derive(Clone, PartialEq)]
+pub struct Banner {
+    pub id: usize,
+    pub room: Option<String>,
+    pub text: String,
+    pub level: BannerLevel,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct BannersProps {
+    pub banners: Vec<Banner>,
+    pub on_dismiss: Callback<usize>,
+}
+
+#[function_component(Banners)]
+pub fn banners(props: &BannersProps) -> Html {
+    props
+        .banners
+        .iter()
+        .map(|banner| {
+            let id = banner.id;
+            let onclick = props.on_dismiss.reform(move |_: MouseEvent| id);
+            let role = if banner.level == BannerLevel::Critical { "alert" } else { "status" };
+            html! {
+                <div key={banner.id} {role} class={format!("flex items-center mb-3 px-4 py-2 border rounded-lg text-sm {}", banner.level.get_css_classes())}>
+                    <span class="grow">{banner.text.clone()}</span>
+                    <button {onclick} aria-label="Dismiss announcement" class="ml-3 opacity-60 hover:opacity-100">{"✕"}</button>
+                </div>
+            }
+        })
+        .collect::<Html>()
+}
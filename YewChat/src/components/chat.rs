@@ -1,35 +1,423 @@
-use serde::{Deserialize, Serialize};
-use web_sys::HtmlInputElement;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use web_sys::{Element, File, HtmlInputElement, HtmlTextAreaElement, Url};
 use yew::prelude::*;
 use yew_agent::{Bridge, Bridged};
+use yew_router::prelude::*;
 
-use crate::{User, services::websocket::WebsocketService};
-use crate::services::event_bus::EventBus;
+use crate::{Route, User};
+use crate::services::auth;
+use crate::services::chat_transport::{ChatTransport, TransportContext};
+use crate::services::websocket::WebsocketService;
+use crate::services::event_bus::{BusEvent, EventBus};
+use crate::services::health::{Health, HealthMonitor, HealthStatus};
+use crate::services::config::Config;
+use crate::services::language;
+use crate::services::archive::{Archive, ArchivedMessage};
+use crate::services::clipboard;
+use crate::services::files;
+use crate::services::file_transfer::{self, FileMeta, Transfer, MAX_FILE_BYTES};
+use crate::services::flood::RateLimiter;
+use crate::services::last_read::LastRead;
+use crate::services::announcements::DismissedAnnouncements;
+use crate::services::recent::Recent;
+use crate::services::starred::{Starred, StarredMessage};
+use crate::services::storage::{self, Estimate, RoomUsage, KEEP_WHEN_PRUNING, MEMORY_WARN_BYTES};
+use crate::services::watchlist::Watchlist;
+use crate::services::transcript::Transcript;
+use crate::services::traffic::{LoggedFrame, TrafficStats};
+use crate::services::translate::{self, Translation};
+use crate::services::invite;
+use crate::services::logbook;
+use crate::services::shortcuts::{self, Shortcut};
+use crate::services::report::Report;
+use crate::services::onboarding::{Onboarding, Step};
+use crate::services::permissions::{Permissions, Role};
+use crate::services::capabilities::{Capabilities, Feature};
+use crate::services::presence::{PresenceStatus, PresenceTracker};
+use crate::services::websocket::ConnectionState;
+use crate::components::banner::{Banner, BannerLevel, Banners};
+use crate::components::pinned::{PinnedEntry, PinnedPanel};
+use crate::components::starred::StarredPanel;
+use crate::components::removed::{RemovalKind, Removed};
+use crate::components::room_switcher::{dm_peer, dm_room, room_label, unread_badges, Room, RoomSwitcher, DEFAULT_ROOM};
+use crate::components::room_topic::RoomTopic;
+use crate::components::composer::{insert_text, pasted_text, ComposerInput};
+use crate::components::members::{Member, MemberList, MembersPanel};
+use crate::components::invite::InviteDialog;
+use crate::components::user_list::{merge_users, RoleBadge, UserList, UserProfile};
+use crate::components::selection_bar::SelectionBar;
+use crate::components::toast::{Toast, ToastAction, Toasts, TOAST_TIMEOUT_MS};
+use crate::components::alt_text::AltTextPrompt;
+use crate::components::command_palette::{CommandPalette, PaletteItem};
+use crate::components::file_card::FileCard;
+use crate::components::incompatible::Incompatible;
+use crate::components::verify::DeviceVerification;
+use crate::components::link_warning::LinkWarning;
+use crate::components::new_room::NewRoomDialog;
+use crate::components::links;
+use crate::components::leaderboard::{Leaderboard, ReactedMessage};
+use crate::components::media::{Avatar, LazyImage};
+use crate::components::onboarding::OnboardingChecklist;
+use crate::components::settings::SettingsPanel;
+use crate::components::stats::{SentMessage, StatsDialog};
+use crate::components::traffic::TrafficDialog;
+use crate::components::gallery::{GalleryPanel, Shared, SharedItem};
+use crate::components::frame_log::FrameLogPanel;
+use crate::components::report::ReportDialog;
+use crate::components::moderation::{AnnounceDialog, RemoveDialog};
+use crate::services::settings::{ConversationPrivacy, Settings, Theme, ThemeTokens};
+use crate::services::unsent::{Unsent, UnsentMessage};
+use crate::services::webauthn::{self, Assertion, Device, EnrollRequest, NewDevice, VerifyRequest};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::spawn_local;
+use crate::plugins::{self, CommandContext, CommandOutcome, OutgoingMessage, Plugin, ReceivedMessage};
+use crate::services::notification_rules::{self, Action};
+use crate::services::notifications::{is_hidden, play_chime, Incoming, Notifier};
+use gloo_events::EventListener;
+use gloo_timers::callback::{Interval, Timeout};
 
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
 use web_sys::HtmlSelectElement;
 
+#[allow(clippy::enum_variant_names)]
 pub enum Msg {
     HandleMsg(String),
+    AttachFile(File),
+    /// Sends the picture waiting on its description, with this one.
+    DescribeImage(String),
+    CancelImage,
+    /// A file has been read, and the description it goes out with.
+    FileRead(File, String, Result<Vec<u8>, String>),
+    ConnectionChanged(ConnectionState),
+    EndpointChanged(String),
+    LatencyMeasured(f64),
     SubmitMessage,
     ChangeTheme(Theme),
     ToggleEmojiPicker,
     AddEmoji(String),
-    AddReaction(usize, String),
+    AddReaction(u64, String),
+    SwitchRoom(String),
+    JoinRoom(String),
+    FlushNotifications,
+    ShowToast(String),
+    DismissToast(usize),
+    JumpToMissed,
+    ToggleSettings,
+    ToggleStats,
+    ToggleTraffic,
+    CelebrateOnboarding,
+    DismissOnboarding,
+    RefreshTraffic,
+    ToggleFrameLog,
+    RefreshFrameLog,
+    ToggleReport,
+    /// How many of the latest frames to include, and whether to redact them.
+    DownloadReport(usize, bool),
+    UpdateSettings(Box<Settings>),
+    ToggleMute(String),
+    ToggleBlock(String),
+    /// Shows a blocked user's message after all.
+    RevealBlocked(u64),
+    /// Mutes or unmutes notifications from a room.
+    ToggleRoomMute(String),
+    SendTimedOut(String),
+    /// Gives up waiting on a resync, by its ID.
+    ResyncTimedOut(String),
+    RetryMessage(String),
+    ComposerKeyUp(KeyboardEvent),
+    ExpireTyping,
+    PresenceChanged(PresenceStatus),
+    DismissBanner(usize),
+    HealthChanged(Health),
+    MessagesScrolled,
+    /// Counts what's in view of the active room as read.
+    MarkSeen,
+    TogglePalette,
+    PalettePick(PaletteItem),
+    OpenNewRoom,
+    CloseNewRoom,
+    /// Asks the server for a room with this name and description.
+    CreateRoom(String, String),
+    /// Gives up on a room request the server never answered, by its client ID.
+    CreateRoomTimedOut(String),
+    /// Asks the server to change the active room's topic.
+    SetTopic(String),
+    /// Archives the active room, or brings it back.
+    SetArchived(bool),
+    StartEdit(u64),
+    CancelEdit,
+    SubmitEdit(u64, String),
+    DeleteMessage(u64),
+    /// Asks to confirm kicking or banning someone.
+    Moderate(String, RemovalKind),
+    /// Takes the reason and, for a ban, its length in minutes.
+    ConfirmRemoval(String, u32),
+    CancelRemoval,
+    TogglePins,
+    ToggleStarred,
+    /// The links, pictures and files shared in the active room.
+    ToggleGallery,
+    ToggleMembers,
+    ToggleInvite,
+    TogglePrivacy,
+    ToggleAnnounce,
+    /// Takes the text and whether it stays at the top until dismissed.
+    SendAnnouncement(String, bool),
+    DismissAnnouncement(u64),
+    SetPrivacy(String, ConversationPrivacy),
+    CopyInvite(String),
+    /// Asks for the next page of the active room's members.
+    LoadMoreMembers,
+    /// Stars the message, or unstars it if it already is.
+    StarMessage(u64),
+    /// The deployment's translation endpoint, if it has one.
+    TranslationEndpoint(Option<String>),
+    /// Translates a message into our language, or hides its translation.
+    Translate(u64),
+    /// The message, the language it was asked in, and the answer.
+    Translated(u64, String, Result<String, String>),
+    JumpToStarred(u64),
+    ToggleSelecting,
+    /// Answer message `id` from the composer.
+    Reply(u64),
+    /// Bring message `id` into view, opening its thread if it's in one.
+    ScrollToMessage(u64),
+    /// Bring the first message on a day, given as `YYYY-MM-DD`, into view.
+    GoToDate(String),
+    CancelReply,
+    /// A link in a message was clicked.
+    OpenLink(String),
+    /// Takes whether to trust the link's domain from now on.
+    FollowLink(bool),
+    CloseLinkWarning,
+    /// A finger lifted off message `id`; two in quick succession react.
+    MessageTapped(u64, TouchEvent),
+    OpenDirect(String),
+    ToggleSelected(u64),
+    CopySelected,
+    CopyMessage(u64, CopyFormat),
+    ForwardSelected(String),
+    DeleteSelected,
+    ToggleLeaderboard,
+    /// Show message `id` and its replies beside the room.
+    OpenThread(u64),
+    /// Opens the split view on the room we were last in, or closes it.
+    ToggleSplit,
+    /// Shows this room in the second pane.
+    OpenSplit(String),
+    SubmitSplit,
+    CloseThread,
+    /// Pings everyone else who has written in the open thread.
+    NotifyThread,
+    SubmitThreadReply,
+    PinMessage(u64),
+    UnpinMessage(u64),
+    ReorderPins(Vec<u64>),
+    SlowModeTick,
+    RestoreUnsent,
+    DiscardUnsent,
+    PluginButton(usize, &'static str),
+    VerifyDevice,
+    DeviceVerified(Result<Assertion, String>),
+    AddDevice(String),
+    DeviceEnrolled(Result<NewDevice, String>),
+    RemoveDevice(String),
+    ShowHeld,
+    StartTranscript,
+    TranscriptStarted(Result<Option<Transcript>, String>),
+    TranscriptFailed(String),
+    StopTranscript,
+    ExportHistory,
+    ImportHistory(String),
+    CheckStorage,
+    StorageEstimated(Option<Estimate>),
+    /// Drop a room's files and all but its newest messages.
+    PruneRoom(String),
+}
+
+// How long to wait for the server to acknowledge a message before sending
+// it again, and how many times it goes out before we call it failed.
+const ACK_TIMEOUT_MS: u32 = 5_000;
+const MAX_SEND_ATTEMPTS: u32 = 3;
+// Until the server tells us its own limit.
+const DEFAULT_PIN_LIMIT: usize = 5;
+// How many past messages to fetch when entering a room.
+const HISTORY_PAGE_SIZE: usize = 50;
+// How long catching up after a reconnect may take before we report what
+// we have and stop waiting for the rest.
+const RESYNC_TIMEOUT_MS: u32 = 15_000;
+// How long a room we've asked for stays listed without the server answering.
+const CREATE_ROOM_TIMEOUT_MS: u32 = 10_000;
+// During maintenance with no announced end, hold reconnects this long; the
+// next health poll extends it if the server is still down.
+const MAINTENANCE_HOLD_MS: f64 = 60_000.0;
+// A server sending garbage tends to keep doing it; tell the user at most this often.
+const PROTOCOL_ERROR_TOAST_GAP_MS: f64 = 30_000.0;
+// Incoming messages shown per second. The rest wait behind a "hidden" row
+// so a flood can't freeze the page.
+const FLOOD_MESSAGES_PER_SECOND: usize = 20;
+// Held-back messages kept at most; the oldest go first.
+const FLOOD_HELD_LIMIT: usize = 1_000;
+// Twice what the server accepts; anything longer isn't a real message.
+const MAX_RECEIVED_MESSAGE_CHARS: usize = 4_000;
+// How many messages we send in any SEND_WINDOW_MS, so that typing fast
+// doesn't trip the server's flood protection and get us disconnected.
+const SEND_LIMIT: usize = 5;
+const SEND_WINDOW_MS: f64 = 10_000.0;
+// Heartbeat round trips below these are shown as good and fair; anything
+// slower as poor.
+const LATENCY_GOOD_MS: f64 = 150.0;
+const LATENCY_FAIR_MS: f64 = 400.0;
+// Marks our away replies, so that two people away never answer each other.
+const AUTO_REPLY_PREFIX: &str = "[auto-reply]";
+// However many people mention us, send away replies at most this often.
+const AUTO_REPLY_GAP_MS: f64 = 10_000.0;
+
+/// How a single message is put on the clipboard, for pasting into an
+/// issue or another chat.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CopyFormat {
+    /// The author and time in bold over the text.
+    Markdown,
+    /// The text as a blockquote, signed with the author and time.
+    Quote,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum DeliveryStatus {
+    Sending,
+    #[default]
+    Sent,
+    Failed,
 }
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct MessageData {
+    /// Assigned by the server; `None` until our own send is echoed back.
+    #[serde(default)]
+    id: Option<u64>,
     from: String,
     message: String,
+    #[serde(default = "default_room")]
+    room: String,
+    #[serde(default)]
+    client_id: Option<String>,
+    #[serde(default)]
+    time: Option<f64>,
+    #[serde(default)]
+    edited: bool,
+    #[serde(default)]
+    deleted: bool,
+    #[serde(skip)]
+    status: DeliveryStatus,
+    /// Detected on arrival; `None` when the text is too short to tell.
+    #[serde(skip)]
+    lang: Option<&'static str>,
+    /// Set on the stand-in for a file transfer, whose `message` is the
+    /// file's name.
+    #[serde(skip)]
+    file: Option<String>,
+    /// The message whose thread this replies in.
+    #[serde(default)]
+    parent_id: Option<u64>,
+    /// The message this quotes.
+    #[serde(default)]
+    reply_to: Option<u64>,
+    /// From a moderator to the whole room, shown as a banner across it.
+    #[serde(default)]
+    announcement: bool,
+    /// Announcements only: kept above the conversation until dismissed.
+    #[serde(default)]
+    sticky: bool,
+}
+
+fn default_room() -> String {
+    DEFAULT_ROOM.to_string()
+}
+
+impl MessageData {
+    fn key(&self) -> String {
+        match (self.id, &self.client_id) {
+            (Some(id), _) => id.to_string(),
+            (None, Some(client_id)) => client_id.clone(),
+            // Servers that assign no IDs, and no echo of ours: who and when
+            // still tells siblings apart.
+            (None, None) => format!("{}@{}", self.from, self.time.unwrap_or_default()),
+        }
+    }
+
+    fn detect_language(&mut self) {
+        self.lang = language::detect(&self.message);
+    }
+
+    /// Written in a language other than the reader's.
+    fn is_foreign(&self, reader_language: &str) -> bool {
+        self.lang.is_some_and(|lang| lang != reader_language)
+    }
+
+    fn copied_as(&self, format: CopyFormat) -> String {
+        let time = self.time.unwrap_or_else(js_sys::Date::now);
+        let time = String::from(js_sys::Date::new(&time.into()).to_locale_string("default", &JsValue::UNDEFINED));
+        let text = match &self.file {
+            Some(_) => format!("📎 {}", self.message),
+            None => self.message.clone(),
+        };
+        match format {
+            CopyFormat::Markdown => format!("**{}** · {} in {}\n\n{}", self.from, time, room_label(&self.room), text),
+            CopyFormat::Quote => {
+                let quoted: Vec<String> = text.lines().map(|line| format!("> {}", line).trim_end().to_string()).collect();
+                format!("{}\n>\n> — {}, {}", quoted.join("\n"), self.from, time)
+            }
+        }
+    }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum MsgTypes {
     Users,
     Register,
     Message,
+    Typing,
+    Presence,
+    SlowMode,
+    Banner,
+    Reaction,
+    Error,
+    Kick,
+    Ban,
+    History,
+    Edit,
+    Delete,
+    Pin,
+    Unpin,
+    Pins,
+    Read,
+    Hello,
+    Verify,
+    Verified,
+    Devices,
+    Enroll,
+    Unenroll,
+    Ack,
+    AuthFailed,
+    FileStart,
+    FileChunk,
+    FileEnd,
+    CreateRoom,
+    UserUpdated,
+    RoomUpdate,
+    Members,
+    Archive,
+    Unarchive,
+    /// Pings everyone who has written in a thread.
+    Notify,
+    /// A moderator's message to a whole room, shown as a banner in it.
+    Announce,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -38,134 +426,4266 @@ struct WebSocketMessage {
     message_type: MsgTypes,
     data_array: Option<Vec<String>>,
     data: Option<String>,
+    room: Option<String>,
+    client_id: Option<String>,
+    id: Option<u64>,
+    version: Option<u32>,
+    /// Proof of an earlier device verification, sent with `Register`.
+    token: Option<String>,
+    /// The login endpoint's token, sent with `Register`.
+    auth: Option<String>,
+    /// Makes a `Message` private to this user.
+    to: Option<String>,
+    /// Files a `Message` in this message's thread.
+    parent_id: Option<u64>,
+    /// Quotes this message in a `Message`.
+    reply_to: Option<u64>,
+}
+
+impl WebSocketMessage {
+    fn new(message_type: MsgTypes) -> Self {
+        Self {
+            message_type,
+            data_array: None,
+            data: None,
+            room: None,
+            client_id: None,
+            id: None,
+            version: None,
+            token: None,
+            auth: None,
+            to: None,
+            parent_id: None,
+            reply_to: None,
+        }
+    }
+}
+
+/// The longest message the server accepts, in characters.
+const MAX_MESSAGE_LENGTH: usize = 2_000;
+
+/// The protocol this client speaks, sent with `Register`.
+const PROTOCOL_VERSION: u32 = 1;
+/// The oldest server protocol we still understand.
+const MIN_SERVER_VERSION: u32 = 1;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HelloData {
+    version: u32,
+    min_client_version: u32,
+    /// Whether the name we registered may moderate.
+    #[serde(default)]
+    moderator: bool,
+    /// Our role; older servers only say whether we moderate.
+    #[serde(default)]
+    role: Option<Role>,
+    /// Optional parts of the protocol the server supports. Older servers
+    /// don't say, and support them all.
+    #[serde(default)]
+    features: Option<Vec<String>>,
+}
+
+/// Someone's entry in a `Users` frame. Older servers send just the status.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum UserEntry {
+    Status(PresenceStatus),
+    Full {
+        status: PresenceStatus,
+        #[serde(default)]
+        role: Role,
+    },
+}
+
+impl UserEntry {
+    fn status(&self) -> PresenceStatus {
+        match self {
+            UserEntry::Status(status) | UserEntry::Full { status, .. } => *status,
+        }
+    }
+
+    fn role(&self) -> Role {
+        match self {
+            UserEntry::Status(_) => Role::Member,
+            UserEntry::Full { role, .. } => *role,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TypingData {
+    from: String,
+    #[serde(default = "default_room")]
+    room: String,
+}
+
+#[derive(Deserialize)]
+struct PresenceData {
+    from: String,
+    status: PresenceStatus,
+}
+
+/// A room's topic, who made the room and whether it's archived. `by` is
+/// set when someone just changed one of them, rather than it being sent
+/// with the room's history.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RoomUpdateData {
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    created_by: String,
+    #[serde(default)]
+    archived: bool,
+    #[serde(default)]
+    by: Option<String>,
+}
+
+/// Someone's identity changed on the server: a new name, a new avatar, or
+/// both.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UserUpdatedData {
+    name: String,
+    #[serde(default)]
+    new_name: Option<String>,
+    /// Empty to go back to the generated one.
+    #[serde(default)]
+    avatar: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ErrorData {
+    code: String,
+    message: String,
+    /// Set when the error is about one of our own messages.
+    #[serde(default)]
+    client_id: Option<String>,
+}
+
+/// Catching up after a reconnect: the rooms we're still paging back
+/// through, and what we've found so far that we hadn't seen.
+#[derive(Default)]
+struct Resync {
+    id: String,
+    /// Rooms still to catch up, by the client ID of the page asked for.
+    waiting: HashMap<String, String>,
+    /// The newest message each room had before the connection dropped;
+    /// paging back stops once a page reaches it.
+    newest: HashMap<String, u64>,
+    missed: usize,
+    first_missed: Option<u64>,
+}
+
+/// What deleting a message cleared away, kept until the server confirms.
+struct DeletedMessage {
+    id: u64,
+    message: String,
+    reactions: Option<HashMap<String, usize>>,
+    reactors: Option<HashMap<String, usize>>,
+    /// The rooms it was pinned in, and where.
+    pins: Vec<(String, usize)>,
+}
+
+/// What the new room dialog opens with.
+#[derive(Default)]
+struct NewRoomForm {
+    name: String,
+    description: String,
+    /// Why the server turned these down.
+    error: Option<String>,
+}
+
+// Where the list scrolls to when jumping to what was missed.
+const MISSED_DIVIDER_ID: &str = "missed-divider";
+
+/// Paging state for a room's history.
+#[derive(Default)]
+struct HistoryCursor {
+    loading: bool,
+    /// The server has nothing older than what we hold.
+    exhausted: bool,
+}
+
+// Load an older page once the message list is scrolled this close to the top.
+const LOAD_OLDER_THRESHOLD_PX: i32 = 64;
+
+#[derive(Deserialize)]
+struct RemovalData {
+    #[serde(default)]
+    reason: String,
+    #[serde(default)]
+    seconds: Option<u64>,
+    /// The moderator who removed us, when it wasn't done from the server.
+    #[serde(default)]
+    by: Option<String>,
+}
+
+/// What to announce, sent as the frame's data.
+#[derive(Serialize)]
+struct AnnouncementRequest {
+    text: String,
+    sticky: bool,
 }
 
-#[derive(Clone)]
-struct UserProfile {
+/// Who to kick or ban, sent as the frame's data.
+#[derive(Serialize)]
+struct RemovalRequest {
     name: String,
-    avatar: String,
+    reason: String,
+    /// For a ban; 0 bans for good.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    minutes: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct EditData {
+    id: u64,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct ReadData {
+    from: String,
+    room: String,
+    id: u64,
+}
+
+#[derive(Deserialize)]
+struct DeleteData {
+    id: u64,
+}
+
+/// Someone wants the people in a thread we wrote in to look at it. Sent
+/// back to whoever asked with `count` set instead.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NotifyData {
+    from: String,
+    room: String,
+    thread_id: u64,
+    /// How many connected participants were pinged.
+    #[serde(default)]
+    count: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct ReactionData {
+    id: u64,
+    emoji: String,
+    from: String,
+}
+
+#[derive(Deserialize)]
+struct SlowModeData {
+    seconds: u32,
+}
+
+#[derive(Deserialize)]
+struct BannerData {
+    text: String,
+    #[serde(default)]
+    level: BannerLevel,
+}
+
+/// A frame from the server, decoded and checked against the shape its
+/// message type promises.
+enum ServerEvent {
+    Users {
+        names: Vec<String>,
+        entries: HashMap<String, UserEntry>,
+    },
+    Presence(PresenceData),
+    Message(MessageData),
+    Typing(TypingData),
+    Edit(EditData),
+    Delete(DeleteData),
+    Notify(NotifyData),
+    Pins {
+        room: String,
+        ids: Vec<u64>,
+        limit: Option<usize>,
+    },
+    Hello(HelloData),
+    Read(ReadData),
+    Reaction(ReactionData),
+    SlowMode {
+        room: String,
+        seconds: u32,
+    },
+    Banner {
+        room: Option<String>,
+        banner: BannerData,
+    },
+    Error(ErrorData),
+    History {
+        room: String,
+        page: Vec<MessageData>,
+        /// Fewer entries than a full page came back.
+        exhausted: bool,
+        /// An older page asked for while scrolling, not the first one.
+        older: bool,
+        /// Echoed from the request, when it had one.
+        client_id: Option<String>,
+    },
+    Removed(RemovalKind, RemovalData),
+    /// Our login token was missing, expired or for another name.
+    AuthFailed(String),
+    /// Someone is sending a file; its chunks follow under `meta.id`.
+    FileStart {
+        room: String,
+        meta: FileMeta,
+    },
+    FileChunk {
+        id: String,
+        index: usize,
+        data: String,
+    },
+    FileEnd(String),
+    /// Our name is protected; prove we hold one of its devices.
+    Verify(VerifyRequest),
+    /// Proof accepted; the token spares us on reconnect.
+    Verified(String),
+    Devices(Vec<Device>),
+    Enroll(EnrollRequest),
+    /// The server has stored one of our messages under `id`.
+    Ack {
+        client_id: String,
+        id: u64,
+    },
+    /// A room now exists; `client_id` is set if we asked for it.
+    RoomCreated {
+        client_id: Option<String>,
+        room: String,
+        description: String,
+    },
+    UserUpdated(UserUpdatedData),
+    /// A room's topic, when it changes or its history is loaded.
+    RoomUpdate {
+        room: String,
+        update: RoomUpdateData,
+    },
+    /// A page of a room's members, starting `offset` in, of `total`.
+    Members {
+        room: String,
+        offset: usize,
+        total: usize,
+        page: Vec<Member>,
+    },
+}
+
+#[derive(Debug)]
+enum ProtocolError {
+    /// Not JSON, or not a frame we know the type of.
+    Envelope(serde_json::Error),
+    MissingData(MsgTypes),
+    BadData(MsgTypes, serde_json::Error),
+    /// A frame only clients send.
+    Unexpected(MsgTypes),
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProtocolError::Envelope(e) => write!(f, "unreadable frame: {}", e),
+            ProtocolError::MissingData(t) => write!(f, "{:?} frame without data", t),
+            ProtocolError::BadData(t, e) => write!(f, "malformed {:?} data: {}", t, e),
+            ProtocolError::Unexpected(t) => write!(f, "unexpected {:?} frame from server", t),
+        }
+    }
+}
+
+fn payload<T: DeserializeOwned>(msg: &WebSocketMessage) -> Result<T, ProtocolError> {
+    let data = msg.data.as_deref().ok_or(ProtocolError::MissingData(msg.message_type))?;
+    serde_json::from_str(data).map_err(|e| ProtocolError::BadData(msg.message_type, e))
+}
+
+impl ServerEvent {
+    fn decode(frame: &str) -> Result<Self, ProtocolError> {
+        let msg: WebSocketMessage = serde_json::from_str(frame).map_err(ProtocolError::Envelope)?;
+        Ok(match msg.message_type {
+            MsgTypes::Users => ServerEvent::Users {
+                names: msg.data_array.unwrap_or_default(),
+                // Older servers send names only; everyone is then online.
+                entries: msg.data.and_then(|d| serde_json::from_str(&d).ok()).unwrap_or_default(),
+            },
+            MsgTypes::Presence => ServerEvent::Presence(payload(&msg)?),
+            MsgTypes::Message | MsgTypes::Announce => {
+                let mut message: MessageData = payload(&msg)?;
+                // History says so itself; one arriving now is known by its type.
+                message.announcement |= matches!(msg.message_type, MsgTypes::Announce);
+                message.detect_language();
+                ServerEvent::Message(message)
+            }
+            MsgTypes::Typing => ServerEvent::Typing(payload(&msg)?),
+            MsgTypes::Edit => ServerEvent::Edit(payload(&msg)?),
+            MsgTypes::Delete => ServerEvent::Delete(payload(&msg)?),
+            MsgTypes::Notify => ServerEvent::Notify(payload(&msg)?),
+            MsgTypes::Pins => ServerEvent::Pins {
+                room: msg.room.unwrap_or_else(default_room),
+                ids: msg
+                    .data_array
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|id| id.parse().ok())
+                    .collect(),
+                limit: msg.data.and_then(|d| d.parse().ok()),
+            },
+            MsgTypes::Hello => ServerEvent::Hello(payload(&msg)?),
+            MsgTypes::Read => ServerEvent::Read(payload(&msg)?),
+            MsgTypes::Reaction => ServerEvent::Reaction(payload(&msg)?),
+            MsgTypes::SlowMode => ServerEvent::SlowMode {
+                seconds: payload::<SlowModeData>(&msg)?.seconds,
+                room: msg.room.unwrap_or_else(default_room),
+            },
+            MsgTypes::Banner => ServerEvent::Banner {
+                banner: payload(&msg)?,
+                room: msg.room,
+            },
+            MsgTypes::Error => ServerEvent::Error(payload(&msg)?),
+            MsgTypes::History => {
+                let entries = msg.data_array.unwrap_or_default();
+                // One bad entry shouldn't cost the reader the rest of the page.
+                let page = entries
+                    .iter()
+                    .filter_map(|m| match serde_json::from_str::<MessageData>(m) {
+                        Ok(mut m) => {
+                            m.detect_language();
+                            Some(m)
+                        }
+                        Err(e) => {
+                            log::warn!("skipping malformed history entry {}: {}", m, e);
+                            None
+                        }
+                    })
+                    .collect();
+                ServerEvent::History {
+                    room: msg.room.unwrap_or_else(default_room),
+                    page,
+                    exhausted: entries.len() < HISTORY_PAGE_SIZE,
+                    older: msg.id.is_some(),
+                    client_id: msg.client_id,
+                }
+            }
+            MsgTypes::Kick | MsgTypes::Ban => {
+                // A removal without a reason is still a removal.
+                let removal = payload(&msg).unwrap_or(RemovalData { reason: String::new(), seconds: None, by: None });
+                let kind = match msg.message_type {
+                    MsgTypes::Ban => RemovalKind::Ban,
+                    _ => RemovalKind::Kick,
+                };
+                ServerEvent::Removed(kind, removal)
+            }
+            MsgTypes::Verify => ServerEvent::Verify(payload(&msg)?),
+            MsgTypes::Verified => {
+                ServerEvent::Verified(msg.data.ok_or(ProtocolError::MissingData(msg.message_type))?)
+            }
+            MsgTypes::Devices => ServerEvent::Devices(
+                msg.data_array
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|d| serde_json::from_str(d))
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| ProtocolError::BadData(msg.message_type, e))?,
+            ),
+            MsgTypes::Enroll => ServerEvent::Enroll(payload(&msg)?),
+            MsgTypes::UserUpdated => ServerEvent::UserUpdated(payload(&msg)?),
+            MsgTypes::Members => {
+                let page: Vec<Member> = msg
+                    .data_array
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|m| serde_json::from_str(m))
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| ProtocolError::BadData(msg.message_type, e))?;
+                ServerEvent::Members {
+                    room: msg.room.ok_or(ProtocolError::MissingData(msg.message_type))?,
+                    offset: msg.id.unwrap_or(0) as usize,
+                    total: msg.data.and_then(|d| d.parse().ok()).unwrap_or(page.len()),
+                    page,
+                }
+            }
+            MsgTypes::RoomUpdate => ServerEvent::RoomUpdate {
+                update: payload(&msg)?,
+                room: msg.room.ok_or(ProtocolError::MissingData(msg.message_type))?,
+            },
+            MsgTypes::CreateRoom => ServerEvent::RoomCreated {
+                room: msg.room.ok_or(ProtocolError::MissingData(msg.message_type))?,
+                description: msg.data.unwrap_or_default(),
+                client_id: msg.client_id,
+            },
+            MsgTypes::Ack => ServerEvent::Ack {
+                client_id: msg.client_id.ok_or(ProtocolError::MissingData(msg.message_type))?,
+                id: msg.id.ok_or(ProtocolError::MissingData(msg.message_type))?,
+            },
+            MsgTypes::AuthFailed => ServerEvent::AuthFailed(msg.data.unwrap_or_default()),
+            MsgTypes::FileStart => ServerEvent::FileStart {
+                meta: payload(&msg)?,
+                room: msg.room.unwrap_or_else(default_room),
+            },
+            MsgTypes::FileChunk => ServerEvent::FileChunk {
+                id: msg.client_id.ok_or(ProtocolError::MissingData(msg.message_type))?,
+                index: msg.id.ok_or(ProtocolError::MissingData(msg.message_type))? as usize,
+                data: msg.data.ok_or(ProtocolError::MissingData(msg.message_type))?,
+            },
+            MsgTypes::FileEnd => ServerEvent::FileEnd(msg.client_id.ok_or(ProtocolError::MissingData(msg.message_type))?),
+            MsgTypes::Register
+            | MsgTypes::Pin
+            | MsgTypes::Unpin
+            | MsgTypes::Unenroll
+            | MsgTypes::Archive
+            | MsgTypes::Unarchive => {
+                return Err(ProtocolError::Unexpected(msg.message_type))
+            }
+        })
+    }
 }
 
+// Two taps on a message this close together count as a double tap.
+const DOUBLE_TAP_MS: f64 = 300.0;
+
+// Minimum gap between our own typing frames, and how long someone else's
+// indicator survives without a fresh one.
+const TYPING_THROTTLE_MS: f64 = 3_000.0;
+const TYPING_EXPIRY_MS: u32 = 5_000;
+
 pub struct Chat {
+    /// The signed-in user shared with the rest of the app, kept so a rename
+    /// reaches it.
+    user: User,
+    username: String,
     users: Vec<UserProfile>,
     chat_input: NodeRef,
-    wss: WebsocketService,
+    wss: Box<dyn ChatTransport>,
     messages: Vec<MessageData>,
     _producer: Box<dyn Bridge<EventBus>>,
-    current_theme: Theme,
     show_emoji_picker: bool,
-    message_reactions: HashMap<usize, HashMap<String, usize>>
+    message_reactions: HashMap<u64, HashMap<String, usize>>,
+    /// Who reacted to each message, and how many times.
+    reactors: HashMap<u64, HashMap<String, usize>>,
+    show_leaderboard: bool,
+    /// Starred on this device, kept in localStorage.
+    starred: Starred,
+    show_starred: bool,
+    show_gallery: bool,
+    /// Members of each room, as far as they've been loaded since the
+    /// panel was last opened.
+    members: HashMap<String, MemberList>,
+    show_members: bool,
+    show_invite: bool,
+    /// The active conversation's privacy menu is open.
+    show_privacy: bool,
+    /// The rooms in the settings have been joined, once registration went
+    /// through.
+    auto_joined: bool,
+    rooms: Vec<Room>,
+    active_room: String,
+    connection: ConnectionState,
+    /// The server in use, once we know there's a choice of several.
+    endpoint: Option<String>,
+    /// Heartbeat round trip, in ms, while connected.
+    latency: Option<f64>,
+    /// Set once we've been connected, so the next `Connected` is a reconnect.
+    connected_before: bool,
+    resync: Option<Resync>,
+    /// The first message that came in while we were offline, marked in the
+    /// list with a divider.
+    first_missed: Option<u64>,
+    scroll_to_missed: bool,
+    notifier: Notifier,
+    toasts: Vec<Toast>,
+    next_toast_id: usize,
+    settings: Settings,
+    show_settings: bool,
+    /// The browser's last word on how full its storage is.
+    storage: Option<Estimate>,
+    /// We've already said storage is running low.
+    storage_warned: bool,
+    /// Which messages mention a watched word.
+    watchlist: Watchlist,
+    show_stats: bool,
+    /// The diagnostics panel, with the figures as of when it was opened.
+    traffic: Option<TrafficStats>,
+    /// The raw frame log, as of when it was opened.
+    frame_log: Option<Vec<LoggedFrame>>,
+    show_report: bool,
+    onboarding: Onboarding,
+    next_client_id: u64,
+    last_typing_sent: f64,
+    /// Who is typing where, with the time we last heard about it.
+    typing: HashMap<String, (String, f64)>,
+    _presence: PresenceTracker,
+    /// Per-room minimum gap between our messages, in seconds.
+    slow_mode: HashMap<String, u32>,
+    /// When we last sent in each room, for the slow-mode countdown.
+    last_sent: HashMap<String, f64>,
+    slow_mode_tick: Option<Interval>,
+    banners: Vec<Banner>,
+    next_banner_id: usize,
+    /// What the server can do, from its `hello`.
+    capabilities: Capabilities,
+    /// Set once the server kicks or bans us; the chat is replaced by an
+    /// explanation and the socket stays closed.
+    removed: Option<(RemovalKind, RemovalData)>,
+    /// Someone we're about to kick or ban, waiting for confirmation.
+    removing: Option<(String, RemovalKind)>,
+    show_announce: bool,
+    dismissed_announcements: DismissedAnnouncements,
+    health: Health,
+    _health_monitor: HealthMonitor,
+    messages_ref: NodeRef,
+    history: HashMap<String, HistoryCursor>,
+    /// Scroll height and offset of the message list before older messages
+    /// were prepended, so the view can be kept where the reader left it.
+    scroll_anchor: Option<(i32, i32)>,
+    recent: Recent,
+    show_palette: bool,
+    _shortcuts: Option<EventListener>,
+    /// The message being edited inline, if any.
+    editing: Option<u64>,
+    edit_input: NodeRef,
+    /// Pinned message IDs per room, in the order the room has arranged them.
+    pins: HashMap<String, Vec<u64>>,
+    pin_limit: usize,
+    show_pins: bool,
+    /// A link waiting for the user's go-ahead, and its host.
+    link_warning: Option<(String, String)>,
+    /// The new room dialog, when open.
+    new_room: Option<NewRoomForm>,
+    /// Rooms we've asked for, by the client ID of the request, with their
+    /// descriptions: enough to reopen the dialog if the server says no.
+    creating_rooms: HashMap<String, (String, String)>,
+    /// Edits shown before the server has taken them, by the client ID they
+    /// went with: the message and what it said before, to put back if the
+    /// server says no.
+    unconfirmed_edits: HashMap<String, (u64, String, bool)>,
+    /// Likewise for deletes, with everything the delete cleared away.
+    unconfirmed_deletes: HashMap<String, DeletedMessage>,
+    /// The last message tapped, and when, to spot a double tap.
+    last_tap: Option<(u64, f64)>,
+    /// The message the composer is answering.
+    replying_to: Option<u64>,
+    /// Blocked users' messages shown anyway.
+    revealed: HashSet<u64>,
+    /// A message to bring into view once rendered.
+    scroll_to_message: Option<u64>,
+    /// A room and the start of a day, in milliseconds, while older pages
+    /// are fetched to reach it.
+    jumping_to: Option<(String, f64)>,
+    /// The root of the thread open beside the room.
+    thread: Option<u64>,
+    thread_input: NodeRef,
+    /// The room in the second pane, when the view is split.
+    split_room: Option<String>,
+    split_ref: NodeRef,
+    split_input: NodeRef,
+    /// Checkboxes are showing on messages.
+    selecting: bool,
+    selected: BTreeSet<u64>,
+    role: Role,
+    /// How far each person has read, per room: the last message ID they saw.
+    read_cursors: HashMap<String, HashMap<String, u64>>,
+    /// The last read position we reported, per room.
+    read_sent: HashMap<String, u64>,
+    /// How far we've read in each room, for the unread counts.
+    last_read: LastRead,
+    /// The page's own title, and the unread count last put in front of it.
+    base_title: String,
+    title_unread: Option<usize>,
+    /// Each room's `unread` counts, worked out at most once between updates
+    /// however many times the view and the title ask.
+    unread_counts: RefCell<HashMap<String, (usize, usize)>>,
+    user_language: String,
+    translate_url: Option<String>,
+    translations: HashMap<u64, Translation>,
+    /// Set when the server's protocol doesn't match ours; the chat is
+    /// replaced by an explanation and the socket closed.
+    incompatible: Option<HelloData>,
+    /// When we last told the user a frame couldn't be read.
+    last_protocol_error: f64,
+    /// Left behind by the previous page load, until the user restores or
+    /// discards it.
+    restore: Option<Unsent>,
+    /// What was last written to session storage, so it's only written again
+    /// when something changed.
+    persisted: Unsent,
+    plugins: Vec<Rc<dyn Plugin>>,
+    /// Set while the server waits for us to prove we hold one of our name's
+    /// devices; the chat is replaced by a prompt until then.
+    verification: Option<VerifyRequest>,
+    verifying: bool,
+    verification_error: Option<String>,
+    /// Our name's registered devices, once the server has listed them.
+    devices: Option<Vec<Device>>,
+    /// The name for the device being registered, until the server's
+    /// challenge arrives.
+    enrolling: Option<String>,
+    flood: RateLimiter,
+    /// What we send, against `SEND_LIMIT`.
+    send_limit: RateLimiter,
+    /// Messages that arrived faster than we show them, until asked for.
+    held: Vec<MessageData>,
+    /// The file the conversation is being written to, if any.
+    transcript: Option<Transcript>,
+    /// How many times each unacknowledged message has gone out.
+    send_attempts: HashMap<String, u32>,
+    /// Our own presence, as last reported by the tracker.
+    status: PresenceStatus,
+    /// Who has had our away message since we went away.
+    auto_replied: HashSet<String>,
+    /// Pictures the server has given people in place of the generated ones.
+    avatars: HashMap<String, String>,
+    /// Files sent in the chat, by transfer ID.
+    transfers: HashMap<String, Transfer>,
+    /// A picture waiting for its alt text, and an object URL previewing it.
+    alt_prompt: Option<(File, String)>,
+    last_auto_reply: f64,
 }
 
-#[derive(Clone, PartialEq)]
-pub enum Theme {
-    Light,
-    Dark,
-    Ocean,
-    Forest,
+/// The stand-in shown in the message list for a file transfer.
+fn file_message(meta: &FileMeta, room: String) -> MessageData {
+    MessageData {
+        id: None,
+        from: meta.from.clone(),
+        message: meta.name.clone(),
+        room,
+        client_id: Some(meta.id.clone()),
+        time: Some(js_sys::Date::now()),
+        edited: false,
+        deleted: false,
+        status: DeliveryStatus::Sent,
+        lang: None,
+        file: Some(meta.id.clone()),
+        parent_id: None,
+        reply_to: None,
+        announcement: false,
+        sticky: false,
+    }
 }
 
-impl Theme {
-    fn get_css_classes(&self) -> &str {
-        match self {
-            Theme::Light => "bg-white text-black",
-            Theme::Dark => "bg-gray-800 text-black",
-            Theme::Ocean => "bg-blue-900 text-black",
-            Theme::Forest => "bg-green-900 text-black",
+// How much of a message a reply quotes.
+const REPLY_SNIPPET_CHARS: usize = 60;
+
+/// The start of `text`'s first line, for quoting in a reply.
+fn reply_snippet(text: &str) -> String {
+    // Skipping the quote that replies used to start with.
+    let line = text.lines().find(|l| !l.trim().is_empty() && !l.starts_with("> ")).unwrap_or_default().trim();
+    if line.chars().count() > REPLY_SNIPPET_CHARS {
+        format!("{}…", line.chars().take(REPLY_SNIPPET_CHARS).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+/// The DOM ID of message `id`, for scrolling to it.
+fn message_anchor(id: u64) -> String {
+    format!("message-{}", id)
+}
+
+/// Today where the reader is, as a date input wants it.
+fn today() -> String {
+    let now = js_sys::Date::new_0();
+    format!("{:04}-{:02}-{:02}", now.get_full_year(), now.get_month() + 1, now.get_date())
+}
+
+/// Whether the message list in `list` is scrolled to the bottom. A list
+/// that isn't laid out, like the second pane on a narrow screen, isn't.
+fn at_bottom_of(list: &NodeRef) -> bool {
+    list.cast::<Element>()
+        .map(|list| list.client_height() > 0 && list.scroll_top() + list.client_height() >= list.scroll_height() - 4)
+        .unwrap_or(false)
+}
+
+/// Puts `text` on the clipboard in the background, then toasts `note`, or
+/// why it couldn't.
+fn copy_text(ctx: &Context<Chat>, text: String, note: String) {
+    let link = ctx.link().clone();
+    spawn_local(async move {
+        let note = match clipboard::write_text(&text).await {
+            Ok(()) => note,
+            Err(e) => {
+                log::error!("copy failed: {}", e);
+                "Couldn't copy to the clipboard.".to_string()
+            }
+        };
+        link.send_message(Msg::ShowToast(note));
+    });
+}
+
+/// Reads `file` in the background, then sends it as `Msg::FileRead`.
+fn read_file(ctx: &Context<Chat>, file: File, alt: String) {
+    let link = ctx.link().clone();
+    spawn_local(async move {
+        let bytes = file_transfer::read(&file).await;
+        link.send_message(Msg::FileRead(file, alt, bytes));
+    });
+}
+
+fn open_in_new_tab(url: &str) {
+    if let Some(window) = web_sys::window() {
+        if let Err(e) = window.open_with_url_and_target_and_features(url, "_blank", "noopener,noreferrer") {
+            log::error!("failed to open {}: {:?}", url, e);
         }
     }
 }
 
-impl Component for Chat {
-    type Message = Msg;
-    type Properties = ();
-    fn create(ctx: &Context<Self>) -> Self {
-        let (user, _) = ctx
-            .link()
-            .context::<User>(Callback::noop())
-            .expect("context to be set");
-        let wss = WebsocketService::new();
-        let username = user.username.borrow().clone();
+/// The picture people get until the server gives them another.
+fn generated_avatar(name: &str) -> String {
+    format!("https://avatars.dicebear.com/api/adventurer-neutral/{}.svg", name)
+}
+
+fn register(wss: &dyn ChatTransport, username: &str) {
+    let message = WebSocketMessage {
+        data: Some(username.to_string()),
+        version: Some(PROTOCOL_VERSION),
+        token: webauthn::session_token(username),
+        auth: auth::token(username),
+        ..WebSocketMessage::new(MsgTypes::Register)
+    };
+
+    wss.set_handshake(serde_json::to_string(&message).unwrap());
+}
+
+impl Chat {
+    fn send_frame(&self, message: WebSocketMessage) {
+        if let Err(e) = self.wss.send(serde_json::to_string(&message).unwrap()) {
+            log::debug!("error sending to channel: {}", e);
+        }
+    }
+
+    fn view_typing(&self) -> Html {
+        if !self.settings.show_typing_indicators {
+            return html! {};
+        }
+        let mut names: Vec<&str> = self
+            .typing
+            .iter()
+            .filter(|(_, (room, _))| *room == self.active_room)
+            .map(|(name, _)| name.as_str())
+            .collect();
+        names.sort_unstable();
+        let text = match names.as_slice() {
+            [] => return html! {},
+            [one] => format!("{} is typing…", one),
+            [one, two] => format!("{} and {} are typing…", one, two),
+            _ => "Several people are typing…".to_string(),
+        };
+        html! {
+            <div class="px-4 pb-1 text-xs text-gray-500 italic" aria-live="polite">{text}</div>
+        }
+    }
+
+    fn view_health(&self) -> Html {
+        let (level, fallback) = match self.health.status {
+            HealthStatus::Ok => return html! {},
+            HealthStatus::Degraded => (BannerLevel::Warning, "The chat server is having problems. Messages may be slow."),
+            HealthStatus::Maintenance => (BannerLevel::Critical, "The chat server is down for maintenance."),
+        };
+        let until = self.health.until.map(|until| {
+            let time = js_sys::Date::new(&until.into()).to_locale_time_string("default");
+            format!(" Expected back at {}.", time)
+        });
+        html! {
+            <div role="status" class={format!("px-4 py-2 border-b text-sm {}", level.get_css_classes())}>
+                {self.health.message.as_deref().unwrap_or(fallback)}
+                {until.unwrap_or_default()}
+            </div>
+        }
+    }
 
+    /// Queues a chat message on the socket and arms the acknowledgement
+    /// timeout. Its client ID doubles as an idempotency key, so the server
+    /// stores a retransmission only once. Returns the status the message
+    /// should be shown with.
+    fn send_chat_message(&self, ctx: &Context<Self>, m: &MessageData) -> DeliveryStatus {
+        // The server files direct messages under `@sender` for the other
+        // side; all it needs from us is who they're for.
+        let (room, to) = match dm_peer(&m.room) {
+            Some(peer) => (None, Some(peer.to_string())),
+            None => (Some(m.room.clone()), None),
+        };
         let message = WebSocketMessage {
-            message_type: MsgTypes::Register,
-            data: Some(username.to_string()),
-            data_array: None,
+            data: Some(m.message.clone()),
+            room,
+            to,
+            client_id: m.client_id.clone(),
+            parent_id: m.parent_id,
+            reply_to: m.reply_to,
+            ..WebSocketMessage::new(MsgTypes::Message)
         };
+        if let Err(e) = self.wss.send(serde_json::to_string(&message).unwrap()) {
+            log::debug!("error sending to channel: {}", e);
+            return DeliveryStatus::Failed;
+        }
+        if let Some(client_id) = m.client_id.clone() {
+            Self::await_ack(ctx, client_id);
+        }
+        DeliveryStatus::Sending
+    }
 
-        if let Ok(_) = wss
-            .tx
-            .clone()
-            .try_send(serde_json::to_string(&message).unwrap())
-        {
-            log::debug!("message sent successfully");
+    fn await_ack(ctx: &Context<Self>, client_id: String) {
+        let link = ctx.link().clone();
+        Timeout::new(ACK_TIMEOUT_MS, move || link.send_message(Msg::SendTimedOut(client_id))).forget();
+    }
+
+    /// Marks our message with `client_id` as stored under the server's `id`.
+    /// Returns whether anything changed, or `None` if there's no such message.
+    fn confirm_own(&mut self, client_id: &str, id: Option<u64>) -> Option<bool> {
+        let index = self.own_message_index(client_id)?;
+        self.send_attempts.remove(client_id);
+        let pending = &mut self.messages[index];
+        let changed = pending.status != DeliveryStatus::Sent || pending.id != id;
+        pending.status = DeliveryStatus::Sent;
+        if pending.id != id {
+            // Now that it has an ID it belongs among the others, not at the end.
+            let mut confirmed = self.messages.remove(index);
+            confirmed.id = id;
+            self.insert_message(confirmed);
         }
+        Some(changed)
+    }
 
-        Self {
-            users: vec![],
-            messages: vec![],
-            chat_input: NodeRef::default(),
-            wss,
-            _producer: EventBus::bridge(ctx.link().callback(Msg::HandleMsg)),
-            current_theme: Theme::Dark,
-            show_emoji_picker: false,
-            message_reactions: HashMap::new(),
+    /// How many replies each thread has, by the ID of its first message.
+    fn reply_counts(&self) -> HashMap<u64, usize> {
+        let mut counts = HashMap::new();
+        for parent in self.messages.iter().filter(|m| !m.deleted).filter_map(|m| m.parent_id) {
+            *counts.entry(parent).or_insert(0) += 1;
         }
+        counts
     }
-    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
-        match msg {
-            Msg::HandleMsg(s) => {
-                let msg: WebSocketMessage = serde_json::from_str(&s).unwrap();
-                match msg.message_type {
-                    MsgTypes::Users => {
-                        let users_from_message = msg.data_array.unwrap_or_default();
-                        self.users = users_from_message
-                            .iter()
-                            .map(|u| UserProfile {
-                                name: u.into(),
-                                avatar: format!(
-                                    "https://avatars.dicebear.com/api/adventurer-neutral/{}.svg",
-                                    u
-                                )
-                                .into(),
-                            })
-                            .collect();
-                        return true;
-                    }
-                    MsgTypes::Message => {
-                        let message_data: MessageData =
-                            serde_json::from_str(&msg.data.unwrap()).unwrap();
-                        self.messages.push(message_data);
-                        return true;
+
+    fn view_message(
+        &self,
+        ctx: &Context<Self>,
+        m: &MessageData,
+        is_new_user: bool,
+        seen_by: Option<&Vec<String>>,
+        replies: usize,
+        anchored: bool,
+    ) -> Html {
+        let tokens = self.settings.theme.tokens();
+        // Left off the copy of a thread's root heading the thread, so the
+        // ID stays unique and jumping to it finds the one in the room.
+        let anchor = m.id.filter(|_| anchored).map(message_anchor);
+        let user_profile = self.users.iter()
+            .find(|u| u.name == m.from)
+            .cloned()
+            .unwrap_or_else(|| UserProfile {
+                name: m.from.clone(),
+                avatar: self.avatar(&m.from),
+                status: PresenceStatus::Offline,
+                role: Role::Member,
+            });
+        
+        let reactions = m.id.and_then(|id| self.message_reactions.get(&id)).cloned().unwrap_or_default();
+        let reacted_by = m.id.and_then(|id| self.reactors.get(&id)).map(|reactors| {
+            let mut names: Vec<&str> = reactors.keys().map(String::as_str).collect();
+            names.sort_unstable();
+            format!("Reacted by {}", names.join(", "))
+        });
+        
+        let id = m.id;
+        let add_reaction = ctx.link().batch_callback(move |emoji: String| {
+            id.map(|id| Msg::AddReaction(id, emoji))
+        });
+
+        if m.deleted {
+            return html! {
+                <div key={m.key()} class={if is_new_user { "mt-6" } else { "mt-1" }}>
+                    if is_new_user {
+                        <div class="font-medium mb-1">{user_profile.name.clone()}</div>
                     }
-                    _ => {
-                        return false;
+                    <div class="ml-10 text-sm italic text-gray-400">{"message deleted"}</div>
+                </div>
+            };
+        }
+        if self.settings.is_blocked(&m.from) && !m.id.is_some_and(|id| self.revealed.contains(&id)) {
+            return html! {
+                <div key={m.key()} id={anchor.clone()} class="mt-1 ml-10 text-sm italic text-gray-400">
+                    {"Blocked message — "}
+                    <button
+                        onclick={ctx.link().batch_callback(move |_| id.map(Msg::RevealBlocked))}
+                        class="underline hover:text-gray-600"
+                    >
+                        {"show anyway"}
+                    </button>
+                </div>
+            };
+        }
+
+        // Archived rooms are kept as they were left.
+        let archived = self.is_archived(&m.room);
+        let can_delete = m.id.is_some() && !archived && self.permissions().can_delete(&m.from);
+        if m.announcement {
+            return html! {
+                <div key={m.key()} id={anchor.clone()} role="note" class="mt-6 -mx-4 px-4 py-3 bg-amber-100 text-amber-900 border-y-2 border-amber-400">
+                    <div class="flex justify-between items-center text-xs font-bold uppercase tracking-wide mb-1">
+                        <span>{format!("📢 Announcement from {}", m.from)}</span>
+                        if let (Some(id), true) = (m.id, can_delete) {
+                            <button
+                                onclick={ctx.link().callback(move |_| Msg::DeleteMessage(id))}
+                                aria-label="Delete announcement"
+                                class="font-normal normal-case underline hover:text-amber-700"
+                            >
+                                {"Delete"}
+                            </button>
+                        }
+                    </div>
+                    <div class="text-sm whitespace-pre-wrap break-words">
+                        {links::render(&m.message, &self.settings, &ctx.link().callback(Msg::OpenLink))}
+                    </div>
+                </div>
+            };
+        }
+
+        let can_edit = m.from == self.username && m.id.is_some() && !archived;
+        // Without pins on the server there's nothing pinned to show either.
+        let can_pin = self.capabilities.has(Feature::Pins) && !archived && self.can_pin_in(&m.room);
+        let open_link = ctx.link().callback(Msg::OpenLink);
+        let selected = self.selecting && m.id.is_some_and(|id| self.selected.contains(&id));
+        let pinned = m.id.is_some_and(|id| {
+            self.pins.get(&m.room).is_some_and(|pins| pins.contains(&id))
+        });
+        let editing = m.id.is_some() && self.editing == m.id;
+        let watched = m.id.is_some_and(|id| self.watchlist.is_hit(id));
+        let starred = m.id.is_some_and(|id| self.starred.contains(id));
+        let threads = self.capabilities.has(Feature::Threads);
+        let reacting = self.capabilities.has(Feature::Reactions);
+        let can_react = reacting && !archived;
+        let double_tap = can_react && m.id.is_some() && !editing && !self.selecting && !self.settings.double_tap_reaction.is_empty();
+        let edit_keydown = ctx.link().batch_callback(move |e: KeyboardEvent| match e.key().as_str() {
+            "Enter" => {
+                let input: HtmlInputElement = e.target_unchecked_into();
+                id.map(|id| Msg::SubmitEdit(id, input.value()))
+            }
+            "Escape" => Some(Msg::CancelEdit),
+            _ => None,
+        });
+
+        let status = if m.from == self.username && m.client_id.is_some() {
+            match m.status {
+                DeliveryStatus::Sending => html! {
+                    <span class="text-xs text-gray-400 ml-2" title="Sending">{"⏳"}</span>
+                },
+                DeliveryStatus::Sent => html! {
+                    <span class="text-xs text-gray-400 ml-2" title="Sent">{"✓"}</span>
+                },
+                DeliveryStatus::Failed => {
+                    let client_id = m.client_id.clone().unwrap_or_default();
+                    let retry = ctx.link().callback(move |_| Msg::RetryMessage(client_id.clone()));
+                    html! {
+                        <span class="text-xs text-red-500 ml-2" role="status">
+                            {"Failed to send · "}
+                            <button onclick={retry} class="underline hover:text-red-700">{"Retry"}</button>
+                        </span>
                     }
                 }
             }
-            Msg::SubmitMessage => {
-                let input = self.chat_input.cast::<HtmlInputElement>();
-                if let Some(input) = input {
-                    //log::debug!("got input: {:?}", input.value());
-                    let message = WebSocketMessage {
-                        message_type: MsgTypes::Message,
-                        data: Some(input.value()),
-                        data_array: None,
-                    };
-                    if let Err(e) = self
-                        .wss
-                        .tx
-                        .clone()
-                        .try_send(serde_json::to_string(&message).unwrap())
-                    {
-                        log::debug!("error sending to channel: {:?}", e);
-                    }
-                    input.set_value("");
+        } else {
+            html! {}
+        };
+        
+        html!{
+            <div key={m.key()} id={anchor.clone()} class={if is_new_user { "mt-6" } else { "mt-1" }}>
+                if is_new_user {
+                    <div class="flex items-center mb-1">
+                        <Avatar
+                            name={user_profile.name.clone()}
+                            url={user_profile.avatar.clone()}
+                            class="w-8 h-8 rounded-full mr-2"
+                            low_data={self.settings.low_data}
+                        />
+                        <div class="font-medium">{user_profile.name.clone()}</div>
+                        <RoleBadge role={user_profile.role} />
+                    </div>
+                }
+                <div class={format!("flex flex-col ml-{}", if is_new_user { "0" } else { "10" })}>
+                    <div class="flex items-end">
+                        if self.selecting {
+                            if let Some(id) = m.id {
+                                <input
+                                    type="checkbox"
+                                    checked={selected}
+                                    onchange={ctx.link().callback(move |_| Msg::ToggleSelected(id))}
+                                    aria-label={format!("Select message from {}", m.from)}
+                                    class="mr-2 mb-3 h-4 w-4 shrink-0"
+                                />
+                            }
+                        }
+                        <div class={classes!(
+                            "max-w-3/4", "p-3", "rounded-lg", "shadow-sm", tokens.surface, tokens.text,
+                            (m.status != DeliveryStatus::Sent).then_some("opacity-60"),
+                            selected.then_some("ring-2 ring-blue-500"),
+                            watched.then_some("border-l-4 border-yellow-400")
+                        )}
+                            ondblclick={double_tap.then(|| add_reaction.reform({
+                                let emoji = self.settings.double_tap_reaction.clone();
+                                move |_: MouseEvent| emoji.clone()
+                            }))}
+                            // The second press would otherwise select the word under it.
+                            onmousedown={double_tap.then(|| Callback::from(|e: MouseEvent| {
+                                if e.detail() > 1 {
+                                    e.prevent_default();
+                                }
+                            }))}
+                            ontouchend={double_tap.then(|| ctx.link().callback(move |e: TouchEvent| Msg::MessageTapped(id.unwrap_or_default(), e)))}
+                        >
+                            if let Some(reply_to) = m.reply_to {
+                                {self.view_quote(ctx, reply_to)}
+                            }
+                            if editing {
+                                <input
+                                    type="text"
+                                    value={m.message.clone()}
+                                    onkeydown={edit_keydown}
+                                    aria-label="Edit message"
+                                    ref={self.edit_input.clone()}
+                                    class="text-sm w-64 px-2 py-1 rounded bg-white outline-none focus:ring-2 focus:ring-blue-600"
+                                />
+                                <div class="text-xs text-gray-500 mt-1">{"Enter to save · Esc to cancel"}</div>
+                            } else if let Some(transfer) = m.file.as_ref().and_then(|id| self.transfers.get(id)) {
+                                <FileCard
+                                    name={transfer.meta.name.clone()}
+                                    size={transfer.meta.size}
+                                    progress={transfer.progress()}
+                                    url={transfer.url().map(str::to_string)}
+                                    failed={transfer.failed()}
+                                    image={transfer.meta.is_image()}
+                                    alt={transfer.meta.alt.clone()}
+                                    max_image_bytes={self.settings.media_auto_load_limit}
+                                />
+                            } else if m.message.ends_with(".gif") {
+                                <LazyImage
+                                    class="max-h-64 rounded"
+                                    src={m.message.clone()}
+                                    low_data={self.settings.low_data}
+                                    max_bytes={self.settings.media_auto_load_limit}
+                                />
+                            } else {
+                                <div class={format!("text-sm whitespace-pre-wrap break-words {}", self.settings.message_font.get_css_class())}>
+                                    {links::render(&m.message, &self.settings, &open_link)}
+                                    if m.edited {
+                                        <span class={classes!("text-xs", "ml-1", tokens.muted)}>{"(edited)"}</span>
+                                    }
+                                    if m.is_foreign(&self.user_language) {
+                                        <span class={classes!("text-xs", "ml-1", "uppercase", tokens.muted)} title="Detected language">{m.lang.unwrap_or_default()}</span>
+                                        if let (Some(id), true) = (m.id, self.translate_url.is_some()) {
+                                            <button
+                                                onclick={ctx.link().callback(move |_| Msg::Translate(id))}
+                                                class={classes!("text-xs", "ml-1", "underline", tokens.muted)}
+                                            >
+                                                {
+                                                    match self.translations.get(&id) {
+                                                        None => "Translate",
+                                                        Some(Translation::Pending(_)) => "Translating…",
+                                                        Some(Translation::Done(_)) => "Hide translation",
+                                                    }
+                                                }
+                                            </button>
+                                        }
+                                    }
+                                    if let Some(Translation::Done(text)) = m.id.and_then(|id| self.translations.get(&id)) {
+                                        <div class={classes!("mt-1", "pl-2", "border-l-2", "italic", tokens.muted)} lang={self.user_language.clone()}>
+                                            {self.settings.masked(text).into_owned()}
+                                        </div>
+                                    }
+                                </div>
+                            }
+                        </div>
+                        {status}
+                        if can_edit && !editing {
+                            <button
+                                onclick={ctx.link().callback(move |_| Msg::StartEdit(id.unwrap_or_default()))}
+                                aria-label="Edit message"
+                                class="text-xs text-gray-400 hover:text-gray-700 ml-2"
+                            >
+                                {"✏️"}
+                            </button>
+                        }
+                        if can_delete && !editing {
+                            <button
+                                onclick={ctx.link().callback(move |_| Msg::DeleteMessage(id.unwrap_or_default()))}
+                                aria-label="Delete message"
+                                class={classes!("text-xs", "text-gray-400", "hover:text-red-600", if can_edit { "ml-1" } else { "ml-2" })}
+                            >
+                                {"🗑️"}
+                            </button>
+                        }
+                    </div>
+                    if let Some(readers) = seen_by {
+                        <div class="text-xs text-gray-400 mt-0.5">{format!("Seen by {}", readers.join(", "))}</div>
+                    }
+                    if threads && replies > 0 {
+                        <button
+                            onclick={ctx.link().callback(move |_| Msg::OpenThread(id.unwrap_or_default()))}
+                            class="text-xs text-blue-600 hover:underline mt-1 ml-2"
+                        >
+                            {format!("💬 {} {}", replies, if replies == 1 { "reply" } else { "replies" })}
+                        </button>
+                    }
+                    
+                    if reacting && !reactions.is_empty() {
+                        <div class="flex mt-1 ml-2 flex-wrap" title={reacted_by}>
+                            {
+                                reactions.iter().map(|(emoji, count)| {
+                                    let emoji_clone = emoji.clone();
+                                    html! {
+                                        <button 
+                                            onclick={add_reaction.reform(move |_| emoji_clone.clone())}
+                                            disabled={archived}
+                                            class="bg-gray-200 rounded-full px-2 py-1 text-xs mr-1 mb-1"
+                                        >
+                                            {format!("{} {}", emoji, count)}
+                                        </button>
+                                    }
+                                }).collect::<Html>()
+                            }
+                        </div>
+                    }
+                    
+                    if let Some(id) = m.id {
+                        <div class="flex mt-1 ml-2">
+                            if !can_pin {
+                                if pinned {
+                                    <span class="text-xs mr-2" title="Pinned">{"📌"}</span>
+                                }
+                            } else if pinned {
+                                <button
+                                    onclick={ctx.link().callback(move |_| Msg::UnpinMessage(id))}
+                                    aria-label="Unpin message"
+                                    aria-pressed="true"
+                                    class="text-xs mr-2"
+                                >
+                                    {"📌"}
+                                </button>
+                            } else {
+                                <button
+                                    onclick={ctx.link().callback(move |_| Msg::PinMessage(id))}
+                                    aria-label="Pin message"
+                                    aria-pressed="false"
+                                    class="text-xs mr-2 opacity-40 hover:opacity-100"
+                                >
+                                    {"📌"}
+                                </button>
+                            }
+                            <button
+                                onclick={ctx.link().callback(move |_| Msg::StarMessage(id))}
+                                aria-label={if starred { "Unstar message" } else { "Star message" }}
+                                aria-pressed={starred.to_string()}
+                                class={classes!("text-xs", "mr-2", (!starred).then_some("opacity-40 hover:opacity-100"))}
+                            >
+                                {if starred { "⭐" } else { "☆" }}
+                            </button>
+                            <button
+                                onclick={ctx.link().callback(move |_| Msg::Reply(id))}
+                                aria-label="Reply"
+                                class="text-xs mr-2 opacity-40 hover:opacity-100"
+                            >
+                                {"↩️"}
+                            </button>
+                            <button
+                                onclick={ctx.link().callback(move |_| Msg::CopyMessage(id, CopyFormat::Markdown))}
+                                aria-label="Copy as Markdown"
+                                title="Copy as Markdown"
+                                class="text-xs mr-2 opacity-40 hover:opacity-100"
+                            >
+                                {"📋"}
+                            </button>
+                            <button
+                                onclick={ctx.link().callback(move |_| Msg::CopyMessage(id, CopyFormat::Quote))}
+                                aria-label="Copy as quote"
+                                title="Copy as quote"
+                                class="text-xs mr-2 opacity-40 hover:opacity-100"
+                            >
+                                {"❝"}
+                            </button>
+                            // Threads go one level deep, and not in direct conversations.
+                            if threads && m.parent_id.is_none() && dm_peer(&m.room).is_none() {
+                                <button
+                                    onclick={ctx.link().callback(move |_| Msg::OpenThread(id))}
+                                    aria-label="Reply in thread"
+                                    class="text-xs mr-2 opacity-40 hover:opacity-100"
+                                >
+                                    {"🧵"}
+                                </button>
+                            }
+                            if can_react {
+                                {
+                                    self.settings.quick_reactions.iter().map(|emoji| {
+                                        let emoji_clone = emoji.clone();
+                                        html! {
+                                            <button 
+                                                onclick={add_reaction.reform(move |_| emoji_clone.clone())}
+                                                class="text-gray-500 hover:text-gray-700 text-xs mr-2"
+                                            >
+                                                {emoji}
+                                            </button>
+                                        }
+                                    }).collect::<Html>()
+                                }
+                            }
+                        </div>
+                    }
+                </div>
+            </div>
+        }
+    }
+
+    /// What the active conversation keeps to itself, overriding the
+    /// privacy settings that apply everywhere.
+    fn view_privacy_menu(&self, ctx: &Context<Self>) -> Html {
+        let room = self.active_room.clone();
+        let privacy = self.settings.privacy_in(&room);
+        let private = privacy != ConversationPrivacy::default();
+        let set = |change: fn(&mut ConversationPrivacy, bool)| {
+            let room = room.clone();
+            ctx.link().callback(move |e: Event| {
+                let mut next = privacy;
+                change(&mut next, !e.target_unchecked_into::<HtmlInputElement>().checked());
+                Msg::SetPrivacy(room.clone(), next)
+            })
+        };
+        html! {
+            <div class="relative mr-3">
+                <button
+                    onclick={ctx.link().callback(|_| Msg::TogglePrivacy)}
+                    aria-label={format!("Privacy in {}", room_label(&room))}
+                    aria-haspopup="true"
+                    aria-expanded={self.show_privacy.to_string()}
+                    class={classes!("px-2", "py-1", "rounded", "hover:bg-gray-200", (!private).then_some("opacity-40"))}
+                >
+                    {"🕶️"}
+                </button>
+                if self.show_privacy {
+                    <div class="absolute right-0 z-10 mt-1 w-64 p-3 bg-white text-gray-900 rounded shadow-lg border border-gray-200 text-sm">
+                        <div class="text-xs text-gray-500 mb-2">{format!("Just for {}. Turning something off in settings turns it off everywhere.", room_label(&room))}</div>
+                        // Direct conversations have no shared read cursors to report to.
+                        if dm_peer(&room).is_none() {
+                            <label class="flex items-center mb-1">
+                                <input
+                                    type="checkbox"
+                                    checked={self.settings.sends_read_receipts(&room)}
+                                    disabled={!self.settings.send_read_receipts}
+                                    onchange={set(|p, hide| p.hide_read_receipts = hide)}
+                                    class="mr-2"
+                                />
+                                {"Send read receipts"}
+                            </label>
+                        }
+                        <label class="flex items-center">
+                            <input
+                                type="checkbox"
+                                checked={self.settings.sends_typing(&room)}
+                                disabled={!self.settings.allows_typing_in(&room)}
+                                onchange={set(|p, hide| p.hide_typing = hide)}
+                                class="mr-2"
+                            />
+                            {"Send typing events"}
+                        </label>
+                    </div>
+                }
+            </div>
+        }
+    }
+
+    /// A dot coloured by connection quality, with the round trip in ms.
+    fn view_latency(&self) -> Html {
+        let ms = match self.latency {
+            Some(ms) => ms,
+            None => return html! {},
+        };
+        let (color, quality) = if ms < LATENCY_GOOD_MS {
+            ("bg-green-500", "Good")
+        } else if ms < LATENCY_FAIR_MS {
+            ("bg-yellow-400", "Fair")
+        } else {
+            ("bg-red-500", "Poor")
+        };
+        let label = format!("{} connection, {:.0} ms round trip", quality, ms);
+        html! {
+            <span class="mr-3 flex items-center text-xs text-gray-500" title={label}>
+                <span class={classes!("inline-block", "w-2", "h-2", "rounded-full", "mr-1", color)}></span>
+                {format!("{:.0} ms", ms)}
+            </span>
+        }
+    }
+
+    /// Joins the rooms the settings ask for and, unless something has
+    /// already taken us elsewhere, such as an invite, moves to the one to
+    /// start in.
+    fn auto_join(&mut self, ctx: &Context<Self>) {
+        let rooms: Vec<String> = self.settings.rooms_to_join().cloned().collect();
+        for room in rooms {
+            if !self.rooms.iter().any(|r| r.name == room) {
+                self.rooms.push(Room::new(&room));
+                self.request_history(&room, None);
+            }
+        }
+        if !self.settings.default_room.is_empty() && self.active_room == DEFAULT_ROOM {
+            ctx.link().send_message(Msg::SwitchRoom(self.settings.default_room.clone()));
+        }
+    }
+
+    fn permissions(&self) -> Permissions<'_> {
+        Permissions::new(&self.username, self.role)
+    }
+
+    /// Whether we may change `room`'s topic, archive it or pin in it.
+    fn can_manage(&self, room: &Room) -> bool {
+        !room.pending && self.permissions().can_manage_room(&room.created_by)
+    }
+
+    fn can_pin_in(&self, room: &str) -> bool {
+        self.rooms.iter().any(|r| r.name == room && !r.pending && self.permissions().can_pin(&r.created_by))
+    }
+
+    /// Archived rooms can be read, but take no new messages.
+    fn is_archived(&self, room: &str) -> bool {
+        self.rooms.iter().any(|r| r.name == room && r.archived)
+    }
+
+    /// Seconds left before slow mode lets us post in the active room again.
+    fn slow_mode_remaining(&self, room: &str) -> u32 {
+        let seconds = match self.slow_mode.get(room) {
+            Some(&seconds) if seconds > 0 => seconds,
+            _ => return 0,
+        };
+        let last = match self.last_sent.get(room) {
+            Some(&last) => last,
+            None => return 0,
+        };
+        let elapsed = (js_sys::Date::now() - last) / 1000.0;
+        (seconds as f64 - elapsed).ceil().max(0.0) as u32
+    }
+
+    /// Seconds left before our own send limit lets another message out.
+    fn throttle_remaining(&self) -> u32 {
+        (self.send_limit.wait(js_sys::Date::now()) / 1000.0).ceil() as u32
+    }
+
+    /// Seconds until we can send in the active room, for whichever reason.
+    fn send_cooldown(&self) -> u32 {
+        self.send_cooldown_in(&self.active_room)
+    }
+
+    fn send_cooldown_in(&self, room: &str) -> u32 {
+        self.slow_mode_remaining(room).max(self.throttle_remaining())
+    }
+
+    fn start_slow_mode_tick(&mut self, ctx: &Context<Self>) {
+        if self.slow_mode_tick.is_none() && self.send_cooldown() > 0 {
+            let link = ctx.link().clone();
+            self.slow_mode_tick = Some(Interval::new(1_000, move || link.send_message(Msg::SlowModeTick)));
+        }
+    }
+
+    /// Asks for the latest page of `room`, or the page before message
+    /// `before` when paging back.
+    fn request_history(&mut self, room: &str, before: Option<u64>) {
+        self.request_history_as(room, before, None);
+    }
+
+    /// Like `request_history`, tagging the request so its answer can be
+    /// told apart from any other page of the room.
+    fn request_history_as(&mut self, room: &str, before: Option<u64>, client_id: Option<String>) {
+        self.history.entry(room.to_string()).or_default().loading = true;
+        self.send_frame(WebSocketMessage {
+            room: Some(room.to_string()),
+            data: Some(HISTORY_PAGE_SIZE.to_string()),
+            id: before,
+            client_id,
+            ..WebSocketMessage::new(MsgTypes::History)
+        });
+    }
+
+    /// Moves a "go to date" along: pages back through the room's history
+    /// until it reaches the day, then scrolls to the first message on or
+    /// after it. Returns whether there's anything new to show.
+    fn continue_jump(&mut self, ctx: &Context<Self>) -> bool {
+        let (room, start) = match &self.jumping_to {
+            Some((room, start)) => (room.clone(), *start),
+            None => return false,
+        };
+        // Thread replies aren't in the room's list to scroll to.
+        let listed = || self.messages.iter().filter(|m| m.room == room && m.parent_id.is_none() && !m.deleted);
+        let oldest = listed().filter_map(|m| Some((m.time?, m.id?))).min_by(|a, b| a.0.total_cmp(&b.0));
+        let cursor = self.history.get(&room);
+        if cursor.is_some_and(|c| c.loading) {
+            return false;
+        }
+        if let Some((time, id)) = oldest {
+            if time > start && !cursor.is_some_and(|c| c.exhausted) {
+                self.request_history(&room, Some(id));
+                return false;
+            }
+        }
+        self.jumping_to = None;
+        let day = |time: f64| js_sys::Date::new(&time.into()).to_locale_date_string("default", &JsValue::UNDEFINED);
+        let first = listed()
+            .filter_map(|m| Some((m.time?, m.id?)))
+            .filter(|(time, _)| *time >= start)
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+        match first {
+            Some((time, id)) => {
+                if day(time) != day(start) {
+                    self.push_toast(ctx, format!("Nothing was said on {}; here's the next day that has messages.", day(start)));
+                }
+                self.scroll_to_message = Some(id);
+            }
+            None => self.push_toast(ctx, format!("Nothing was said on or after {}.", day(start))),
+        }
+        true
+    }
+
+    /// Asks for `room`'s members from `offset` on. The first page starts
+    /// the list over.
+    fn request_members(&mut self, room: &str, offset: usize) {
+        let list = self.members.entry(room.to_string()).or_default();
+        if offset == 0 {
+            *list = MemberList::default();
+        }
+        list.loading = true;
+        self.send_frame(WebSocketMessage {
+            room: Some(room.to_string()),
+            id: Some(offset as u64),
+            ..WebSocketMessage::new(MsgTypes::Members)
+        });
+    }
+
+    /// Swaps the composer's `/template name` for the expanded saved reply,
+    /// leaving it there to be reviewed before sending.
+    fn use_template(&mut self, ctx: &Context<Self>, input: &ComposerInput, name: &str) {
+        let template = match self.settings.template(name) {
+            Some(template) => template.clone(),
+            None => {
+                self.push_toast(ctx, format!("No saved reply called \"{}\".", name));
+                return;
+            }
+        };
+        let user = self
+            .messages
+            .iter()
+            .rev()
+            .find(|m| m.room == self.active_room && m.from != self.username)
+            .map(|m| m.from.as_str())
+            .unwrap_or_default();
+        input.set_value(&template.expand(user, &self.username, &self.active_room));
+    }
+
+    fn push_toast(&mut self, ctx: &Context<Self>, text: String) {
+        let id = self.next_toast_id;
+        self.next_toast_id += 1;
+        self.toasts.push(Toast { id, text, action: None });
+        let link = ctx.link().clone();
+        Timeout::new(TOAST_TIMEOUT_MS, move || link.send_message(Msg::DismissToast(id))).forget();
+    }
+
+    fn push_toast_with_action(&mut self, ctx: &Context<Self>, text: String, action: ToastAction) {
+        self.push_toast(ctx, text);
+        if let Some(toast) = self.toasts.last_mut() {
+            toast.action = Some(action);
+        }
+    }
+
+    /// Pages back through every room we're in until it meets what we
+    /// already had, to find what was said while we were away.
+    fn resync_history(&mut self, ctx: &Context<Self>) {
+        let rooms: Vec<String> = self.rooms.iter().map(|r| r.name.clone()).collect();
+        let mut resync = Resync { id: self.take_client_id(), ..Resync::default() };
+        for room in rooms {
+            if let Some(newest) = self.messages.iter().filter(|m| m.room == room).filter_map(|m| m.id).max() {
+                resync.newest.insert(room.clone(), newest);
+            }
+            let client_id = self.take_client_id();
+            self.request_history_as(&room, None, Some(client_id.clone()));
+            resync.waiting.insert(room, client_id);
+        }
+        let (link, id) = (ctx.link().clone(), resync.id.clone());
+        Timeout::new(RESYNC_TIMEOUT_MS, move || link.send_message(Msg::ResyncTimedOut(id))).forget();
+        self.resync = Some(resync);
+    }
+
+    /// Whether `client_id` tags the resync page we're waiting for in `room`.
+    fn is_resync_page(&self, room: &str, client_id: Option<&str>) -> bool {
+        match (&self.resync, client_id) {
+            (Some(resync), Some(client_id)) => resync.waiting.get(room).is_some_and(|id| id == client_id),
+            _ => false,
+        }
+    }
+
+    /// Counts what a resync page brought that we hadn't seen, and asks for
+    /// the page before it if there's still a gap. Once every room is in,
+    /// says so.
+    fn resynced(&mut self, ctx: &Context<Self>, room: &str, missed: &[MessageData], oldest: Option<u64>, exhausted: bool) {
+        let missed: Vec<u64> = missed.iter().filter(|m| m.from != self.username).filter_map(|m| m.id).collect();
+        let resync = match self.resync.as_mut() {
+            Some(resync) => resync,
+            None => return,
+        };
+        resync.missed += missed.len();
+        resync.first_missed = resync.first_missed.into_iter().chain(missed.iter().copied()).min();
+        let gap = match (resync.newest.get(room), oldest) {
+            (Some(&newest), Some(oldest)) => !exhausted && oldest > newest,
+            _ => false,
+        };
+        if gap {
+            let client_id = self.take_client_id();
+            self.request_history_as(room, oldest, Some(client_id.clone()));
+            if let Some(resync) = self.resync.as_mut() {
+                resync.waiting.insert(room.to_string(), client_id);
+            }
+            return;
+        }
+        resync.waiting.remove(room);
+        if resync.waiting.is_empty() {
+            self.finish_resync(ctx);
+        }
+    }
+
+    /// Reports what catching up found, whether or not every room answered.
+    fn finish_resync(&mut self, ctx: &Context<Self>) {
+        let resync = self.resync.take().unwrap_or_default();
+        if resync.missed == 0 {
+            return;
+        }
+        self.first_missed = resync.first_missed;
+        let text = match resync.missed {
+            1 => "Reconnected — 1 message received while offline".to_string(),
+            n => format!("Reconnected — {} messages received while offline", n),
+        };
+        let action = ToastAction { label: "Jump to first".to_string(), on_click: ctx.link().callback(|_| Msg::JumpToMissed) };
+        self.push_toast_with_action(ctx, text, action);
+    }
+
+    /// A frame we couldn't make sense of is dropped rather than taking the
+    /// chat down with it. The user hears about it, since something they
+    /// expected to see may be missing.
+    fn protocol_error(&mut self, ctx: &Context<Self>, frame: &str, error: ProtocolError) -> bool {
+        log::warn!("dropping frame {}: {}", frame, error);
+        let now = js_sys::Date::now();
+        if now - self.last_protocol_error < PROTOCOL_ERROR_TOAST_GAP_MS {
+            return false;
+        }
+        self.last_protocol_error = now;
+        self.push_toast(ctx, "⚠️ Couldn't read an update from the server; some messages may be missing.".to_string());
+        true
+    }
+
+    /// What's selected in the room on screen, in the order it's shown.
+    fn selected_messages(&self) -> Vec<&MessageData> {
+        self.messages
+            .iter()
+            .filter(|m| m.room == self.active_room && !m.deleted && m.id.is_some_and(|id| self.selected.contains(&id)))
+            .collect()
+    }
+
+    /// The selection as plain text, one `name: message` line each.
+    fn selection_text(&self) -> String {
+        self.selected_messages()
+            .iter()
+            .map(|m| format!("{}: {}", m.from, m.message))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Replaces a message with a "deleted" marker and drops everything we
+    /// kept about it.
+    fn tombstone(&mut self, id: u64) -> bool {
+        let m = match self.messages.iter_mut().find(|m| m.id == Some(id)) {
+            Some(m) if !m.deleted => m,
+            _ => return false,
+        };
+        m.deleted = true;
+        m.message.clear();
+        self.message_reactions.remove(&id);
+        self.reactors.remove(&id);
+        for pins in self.pins.values_mut() {
+            pins.retain(|p| *p != id);
+        }
+        if self.editing == Some(id) {
+            self.editing = None;
+        }
+        true
+    }
+
+    /// Asks the server to delete a message, showing it gone straight away
+    /// but keeping enough to bring it back if the server says no.
+    fn delete_message(&mut self, id: u64) -> bool {
+        let message = match self.messages.iter().find(|m| m.id == Some(id)) {
+            Some(m) if !m.deleted => m.message.clone(),
+            _ => return false,
+        };
+        let pins = self
+            .pins
+            .iter()
+            .filter_map(|(room, pins)| pins.iter().position(|p| *p == id).map(|at| (room.clone(), at)))
+            .collect();
+        let deleted = DeletedMessage {
+            id,
+            message,
+            reactions: self.message_reactions.get(&id).cloned(),
+            reactors: self.reactors.get(&id).cloned(),
+            pins,
+        };
+        let client_id = self.take_client_id();
+        self.unconfirmed_deletes.insert(client_id.clone(), deleted);
+        self.send_frame(WebSocketMessage {
+            id: Some(id),
+            client_id: Some(client_id),
+            ..WebSocketMessage::new(MsgTypes::Delete)
+        });
+        self.tombstone(id)
+    }
+
+    /// Puts back a message whose delete the server turned down.
+    fn undelete(&mut self, deleted: DeletedMessage) {
+        let id = deleted.id;
+        let m = match self.messages.iter_mut().find(|m| m.id == Some(id) && m.deleted) {
+            Some(m) => m,
+            None => return,
+        };
+        m.deleted = false;
+        m.message = deleted.message;
+        m.detect_language();
+        if let Some(reactions) = deleted.reactions {
+            self.message_reactions.insert(id, reactions);
+        }
+        if let Some(reactors) = deleted.reactors {
+            self.reactors.insert(id, reactors);
+        }
+        for (room, at) in deleted.pins {
+            let pins = self.pins.entry(room).or_default();
+            if !pins.contains(&id) {
+                pins.insert(at.min(pins.len()), id);
+            }
+        }
+    }
+
+    /// Whether the message list is scrolled to the bottom.
+    fn at_bottom(&self) -> bool {
+        at_bottom_of(&self.messages_ref)
+    }
+
+    /// The rooms on screen, with the list each is shown in.
+    fn panes(&self) -> Vec<(&str, &NodeRef)> {
+        let mut panes = vec![(self.active_room.as_str(), &self.messages_ref)];
+        if let Some(room) = &self.split_room {
+            panes.push((room.as_str(), &self.split_ref));
+        }
+        panes
+    }
+
+    /// The room message `id` is in, taken to be the active one if it isn't
+    /// loaded.
+    fn room_of(&self, id: u64) -> String {
+        match self.messages.iter().find(|m| m.id == Some(id)) {
+            Some(m) => m.room.clone(),
+            None => self.active_room.clone(),
+        }
+    }
+
+    fn newest_in(&self, room: &str) -> Option<u64> {
+        self.messages.iter().filter(|m| m.room == room).filter_map(|m| m.id).max()
+    }
+
+    /// The newest message in `room`, if someone looking at the bottom of
+    /// `list` has now seen it but we haven't counted it as read.
+    fn unseen_in(&self, room: &str, list: &NodeRef) -> Option<u64> {
+        let newest = self.newest_in(room)?;
+        (self.last_read.get(room).is_none_or(|seen| newest > seen) && !is_hidden() && at_bottom_of(list)).then_some(newest)
+    }
+
+    /// Whether a room on screen has messages seen but not yet counted.
+    fn unseen_in_view(&self) -> bool {
+        self.panes().into_iter().any(|(room, list)| self.unseen_in(room, list).is_some())
+    }
+
+    /// Moves our own read position in each room on screen to its newest
+    /// message, once it's been scrolled into view. Returns whether any moved.
+    fn mark_seen(&mut self) -> bool {
+        let seen: Vec<(String, u64)> = self
+            .panes()
+            .into_iter()
+            .filter_map(|(room, list)| Some((room.to_string(), self.unseen_in(room, list)?)))
+            .collect();
+        seen.into_iter().fold(false, |moved, (room, newest)| self.last_read.advance(&room, newest) | moved)
+    }
+
+    /// Unread messages from others in `room` since we last read it, and how
+    /// many of those mention us or a watched word.
+    fn unread(&self, room: &str) -> (usize, usize) {
+        if let Some(&counts) = self.unread_counts.borrow().get(room) {
+            return counts;
+        }
+        let seen = self.last_read.get(room).unwrap_or(0);
+        let counts = self
+            .messages
+            .iter()
+            .filter(|m| m.room == room && m.from != self.username && !m.deleted)
+            .filter_map(|m| Some((m.id.filter(|id| *id > seen)?, m)))
+            .fold((0, 0), |(unread, mentions), (id, m)| {
+                // Blocked people can't get our attention.
+                let mentioned = !self.settings.is_blocked(&m.from)
+                    && (notification_rules::mentions(&m.message, &self.username) || self.watchlist.is_hit(id));
+                (unread + 1, mentions + usize::from(mentioned))
+            });
+        self.unread_counts.borrow_mut().insert(room.to_string(), counts);
+        counts
+    }
+
+    /// Puts the unread count of rooms that aren't muted in the tab's title,
+    /// like "(3) Yewchat!".
+    fn update_title(&mut self) {
+        let unread: usize = self
+            .rooms
+            .iter()
+            .filter(|r| !self.settings.is_room_muted(&r.name))
+            .map(|r| self.unread(&r.name).0)
+            .sum();
+        if self.title_unread == Some(unread) {
+            return;
+        }
+        self.title_unread = Some(unread);
+        if let Some(document) = web_sys::window().and_then(|w| w.document()) {
+            match unread {
+                0 => document.set_title(&self.base_title),
+                n => document.set_title(&format!("({}) {}", n, self.base_title)),
+            }
+        }
+    }
+
+    /// The rooms we're in, with their unread counts filled in.
+    fn counted_rooms(&self) -> Vec<Room> {
+        self.rooms
+            .iter()
+            .map(|room| {
+                let (unread, mentions) = self.unread(&room.name);
+                let muted = self.settings.is_room_muted(&room.name);
+                Room { unread, mentions, muted, ..room.clone() }
+            })
+            .collect()
+    }
+
+    /// Reports the newest message in the active room as read, if the list is
+    /// scrolled to the bottom and someone is actually looking at it.
+    fn mark_read(&mut self) {
+        if !self.settings.sends_read_receipts(&self.active_room) || is_hidden() || !self.at_bottom() {
+            return;
+        }
+        let latest = self.newest_in(&self.active_room);
+        // Read cursors are per room and shared with everyone in it; there's
+        // no such room for a direct conversation.
+        if dm_peer(&self.active_room).is_some() {
+            return;
+        }
+        let latest = match latest {
+            Some(id) if self.read_sent.get(&self.active_room).is_none_or(|sent| id > *sent) => id,
+            _ => return,
+        };
+        self.read_sent.insert(self.active_room.clone(), latest);
+        self.send_frame(WebSocketMessage {
+            id: Some(latest),
+            room: Some(self.active_room.clone()),
+            ..WebSocketMessage::new(MsgTypes::Read)
+        });
+    }
+
+    /// Who has read up to each of our own messages in the active room. A
+    /// reader is listed under the last of our messages their cursor covers.
+    fn seen_by(&self) -> HashMap<u64, Vec<String>> {
+        let mut seen: HashMap<u64, Vec<String>> = HashMap::new();
+        let cursors = match self.read_cursors.get(&self.active_room) {
+            Some(cursors) => cursors,
+            None => return seen,
+        };
+        let own: Vec<u64> = self
+            .messages
+            .iter()
+            .filter(|m| m.room == self.active_room && m.from == self.username && !m.deleted)
+            .filter_map(|m| m.id)
+            .collect();
+        for (user, cursor) in cursors {
+            if let Some(id) = own.iter().copied().filter(|id| id <= cursor).max() {
+                seen.entry(id).or_default().push(user.clone());
+            }
+        }
+        for users in seen.values_mut() {
+            users.sort_unstable();
+        }
+        seen
+    }
+
+    fn avatar(&self, name: &str) -> String {
+        self.avatars.get(name).cloned().unwrap_or_else(|| generated_avatar(name))
+    }
+
+    /// Carries everything we hold about `from` over to `to`, so their past
+    /// messages, reactions and conversations show the new name straight away.
+    fn rename_user(&mut self, from: &str, to: &str) {
+        fn rekey<V>(map: &mut HashMap<String, V>, from: &str, to: &str) {
+            if let Some(value) = map.remove(from) {
+                map.insert(to.to_string(), value);
+            }
+        }
+        let (old_dm, new_dm) = (dm_room(from), dm_room(to));
+        let rename = |name: &mut String, old: &str, new: &str| {
+            if name == old {
+                *name = new.to_string();
+            }
+        };
+
+        for user in self.users.iter_mut() {
+            rename(&mut user.name, from, to);
+        }
+        // A generated picture follows the name; one the server set stays.
+        match self.avatars.remove(from) {
+            Some(avatar) => {
+                self.avatars.insert(to.to_string(), avatar);
+            }
+            None => {
+                if let Some(user) = self.users.iter_mut().find(|u| u.name == to) {
+                    user.avatar = generated_avatar(to);
+                }
+            }
+        }
+        for m in self.messages.iter_mut().chain(self.held.iter_mut()) {
+            rename(&mut m.from, from, to);
+            rename(&mut m.room, &old_dm, &new_dm);
+        }
+        self.starred.rename_user(from, to, &old_dm, &new_dm);
+        for reactors in self.reactors.values_mut() {
+            if let Some(n) = reactors.remove(from) {
+                *reactors.entry(to.to_string()).or_insert(0) += n;
+            }
+        }
+        for cursors in self.read_cursors.values_mut() {
+            rekey(cursors, from, to);
+        }
+        rekey(&mut self.typing, from, to);
+        if self.auto_replied.remove(from) {
+            self.auto_replied.insert(to.to_string());
+        }
+
+        for list in self.members.values_mut() {
+            for member in list.members.iter_mut() {
+                rename(&mut member.name, from, to);
+            }
+        }
+        for room in self.rooms.iter_mut() {
+            rename(&mut room.name, &old_dm, &new_dm);
+            rename(&mut room.created_by, from, to);
+        }
+        rename(&mut self.active_room, &old_dm, &new_dm);
+        if let Some(split) = self.split_room.as_mut() {
+            rename(split, &old_dm, &new_dm);
+        }
+        rekey(&mut self.history, &old_dm, &new_dm);
+        rekey(&mut self.read_cursors, &old_dm, &new_dm);
+        self.last_read.rename_room(&old_dm, &new_dm);
+
+        if self.settings.is_muted(from) {
+            self.settings.toggle_muted(from);
+            self.settings.toggle_muted(to);
+            self.settings.save();
+        }
+        if self.settings.is_blocked(from) {
+            self.settings.toggle_blocked(from);
+            self.settings.toggle_blocked(to);
+            self.settings.save();
+        }
+        if self.settings.is_room_muted(&old_dm) {
+            self.settings.toggle_room_muted(&old_dm);
+            self.settings.toggle_room_muted(&new_dm);
+            self.settings.save();
+        }
+        if let Some(privacy) = self.settings.conversation_privacy.remove(&old_dm) {
+            self.settings.set_privacy(&new_dm, privacy);
+            self.settings.save();
+        }
+        if self.username == from {
+            self.username = to.to_string();
+            *self.user.username.borrow_mut() = to.to_string();
+            self.last_read.rename_user(to);
+            self.dismissed_announcements.rename_user(to);
+            // Reconnecting under the old name would undo it.
+            register(&*self.wss, to);
+        }
+    }
+
+    /// What's been shared in the active room, newest first. Blocked
+    /// users' shares are left out, as their messages are.
+    fn shared_items(&self) -> Vec<SharedItem> {
+        let mut items = vec![];
+        for m in self.messages.iter().rev() {
+            let id = match m.id {
+                Some(id) if m.room == self.active_room && !m.deleted && !self.settings.is_blocked(&m.from) => id,
+                _ => continue,
+            };
+            let item = |shared| SharedItem { id, from: m.from.clone(), shared };
+            if let Some(transfer) = m.file.as_ref().and_then(|file| self.transfers.get(file)) {
+                let meta = &transfer.meta;
+                let url = transfer.url().filter(|_| !transfer.failed()).map(str::to_string);
+                items.push(item(match url {
+                    Some(src) if meta.is_image() => Shared::Image { src, alt: meta.alt.clone(), size: Some(meta.size) },
+                    url => Shared::File { name: meta.name.clone(), size: meta.size, url },
+                }));
+            } else if m.message.ends_with(".gif") {
+                items.push(item(Shared::Image { src: m.message.clone(), alt: String::new(), size: None }));
+            } else {
+                items.extend(links::urls(&m.message).map(|url| item(Shared::Link(url.to_string()))));
+            }
+        }
+        items
+    }
+
+    fn pinned_entries(&self) -> Vec<PinnedEntry> {
+        self.pins
+            .get(&self.active_room)
+            .map(|ids| {
+                ids.iter()
+                    .map(|&id| {
+                        let m = self.messages.iter().find(|m| m.id == Some(id));
+                        PinnedEntry {
+                            id,
+                            from: m.map(|m| m.from.clone()),
+                            text: m.map(|m| self.settings.masked(&m.message).into_owned()),
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The active room's reacted messages and reactors, for the leaderboard.
+    fn reaction_leaderboard(&self) -> (Vec<ReactedMessage>, Vec<(String, usize)>) {
+        let mut messages = Vec::new();
+        let mut reactors: HashMap<String, usize> = HashMap::new();
+        for m in self.messages.iter().filter(|m| m.room == self.active_room && !m.deleted) {
+            let id = match m.id {
+                Some(id) => id,
+                None => continue,
+            };
+            if let Some(reactions) = self.message_reactions.get(&id) {
+                let mut reactions: Vec<(String, usize)> = reactions.iter().map(|(e, n)| (e.clone(), *n)).collect();
+                reactions.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+                messages.push(ReactedMessage { id, from: m.from.clone(), text: m.message.clone(), reactions });
+            }
+            for (user, n) in self.reactors.get(&id).into_iter().flatten() {
+                *reactors.entry(user.clone()).or_default() += n;
+            }
+        }
+        (messages, reactors.into_iter().collect())
+    }
+
+    fn palette_items(&self) -> Vec<PaletteItem> {
+        let mut items: Vec<PaletteItem> = self.rooms.iter().map(|r| PaletteItem::Room(r.name.clone())).collect();
+        for (room, _) in &self.recent.rooms {
+            if !self.rooms.iter().any(|r| r.name == *room) {
+                items.push(PaletteItem::JoinRoom(room.clone()));
+            }
+        }
+        let mut users: Vec<&str> = self.users.iter().map(|u| u.name.as_str()).collect();
+        users.extend(self.recent.users.iter().map(|(u, _)| u.as_str()));
+        users.sort_unstable();
+        users.dedup();
+        items.extend(users.into_iter().filter(|u| *u != self.username).map(|u| PaletteItem::User(u.to_string())));
+        items.extend(self.settings.templates.iter().map(|t| PaletteItem::Template(t.name.clone())));
+        items
+    }
+
+    /// Sends `text` as a new message, after plugins have had their say.
+    fn submit_text(&mut self, ctx: &Context<Self>, text: String) -> Result<(), String> {
+        let reply_to = self
+            .replying_to
+            .take()
+            .filter(|id| self.messages.iter().any(|m| m.id == Some(*id) && m.room == self.active_room));
+        self.send_text_with(ctx, self.active_room.clone(), text, None, reply_to)?;
+        self.onboarding.complete(Step::SendMessage);
+        Ok(())
+    }
+
+    /// Whether to go ahead with sending `text`, asking first if it has
+    /// words the user filters.
+    fn confirm_filtered(&self, text: &str) -> bool {
+        let found = self.settings.filtered_in(text);
+        if found.is_empty() {
+            return true;
+        }
+        let question = format!("Your message contains \"{}\", which you filter. Send it anyway?", found.join("\", \""));
+        web_sys::window().and_then(|w| w.confirm_with_message(&question).ok()).unwrap_or(false)
+    }
+
+    fn send_text(&mut self, ctx: &Context<Self>, room: String, text: String) -> Result<(), String> {
+        self.send_text_with(ctx, room, text, None, None)
+    }
+
+    /// Sends `text` to `room`, as a reply in `parent_id`'s thread and
+    /// quoting `reply_to` when given. Fails, saying why, if a plugin holds
+    /// it back or we're over our send limit.
+    fn send_text_with(
+        &mut self,
+        ctx: &Context<Self>,
+        room: String,
+        text: String,
+        parent_id: Option<u64>,
+        reply_to: Option<u64>,
+    ) -> Result<(), String> {
+        let mut outgoing = OutgoingMessage { room, text };
+        for plugin in &self.plugins {
+            outgoing = match plugin.before_send(outgoing) {
+                Some(outgoing) => outgoing,
+                None => {
+                    log::debug!("plugin {} dropped an outgoing message", plugin.name());
+                    return Err(format!("{} held that message back.", plugin.name()));
+                }
+            };
+        }
+        // Only what actually goes out counts against the limit.
+        if !self.send_limit.allow(js_sys::Date::now()) {
+            return Err(format!("You're sending quickly. You can send again in {}s", self.throttle_remaining()));
+        }
+        let client_id = self.take_client_id();
+        let mut message_data = MessageData {
+            from: self.username.clone(),
+            message: outgoing.text,
+            room: outgoing.room,
+            client_id: Some(client_id),
+            status: DeliveryStatus::Sending,
+            id: None,
+            time: Some(js_sys::Date::now()),
+            edited: false,
+            deleted: false,
+            lang: None,
+            file: None,
+            parent_id,
+            reply_to,
+            announcement: false,
+            sticky: false,
+        };
+        message_data.detect_language();
+        message_data.status = self.send_chat_message(ctx, &message_data);
+        for user in &self.users {
+            if user.name != self.username && notification_rules::mentions(&message_data.message, &user.name) {
+                self.recent.message_user(&user.name);
+            }
+        }
+        self.last_typing_sent = 0.0;
+        self.last_sent.insert(message_data.room.clone(), js_sys::Date::now());
+        self.messages.push(message_data);
+        self.start_slow_mode_tick(ctx);
+        Ok(())
+    }
+
+    /// Answers a mention or a direct message with our away message while
+    /// we're away, privately: once per person per absence, never to another
+    /// auto-reply, and never more often than `AUTO_REPLY_GAP_MS`.
+    fn auto_reply(&mut self, ctx: &Context<Self>, m: &MessageData) {
+        let away_message = self.settings.away_message.trim().to_string();
+        if away_message.is_empty()
+            || self.status != PresenceStatus::Away
+            || m.message.starts_with(AUTO_REPLY_PREFIX)
+            || (dm_peer(&m.room).is_none() && !notification_rules::mentions(&m.message, &self.username))
+            || self.settings.is_muted(&m.from)
+            || self.settings.is_blocked(&m.from)
+            || self.auto_replied.contains(&m.from)
+        {
+            return;
+        }
+        let now = js_sys::Date::now();
+        if now - self.last_auto_reply < AUTO_REPLY_GAP_MS {
+            return;
+        }
+        // Only one that went out counts, so a dropped one is tried again
+        // next time they write.
+        match self.send_text(ctx, dm_room(&m.from), format!("{} {}", AUTO_REPLY_PREFIX, away_message)) {
+            Ok(()) => {
+                self.last_auto_reply = now;
+                self.auto_replied.insert(m.from.clone());
+            }
+            Err(reason) => log::debug!("auto-reply not sent: {}", reason),
+        }
+    }
+
+    fn command_context(&self) -> CommandContext {
+        CommandContext {
+            username: self.username.clone(),
+            room: self.active_room.clone(),
+            draft: ComposerInput::find(&self.chat_input).map(|input| input.value()).unwrap_or_default(),
+        }
+    }
+
+    /// Runs `/name args` if a plugin provides `name`.
+    fn run_plugin_command(&self, text: &str) -> Option<CommandOutcome> {
+        let command = text.strip_prefix('/')?;
+        let (name, args) = command.split_once(' ').unwrap_or((command, ""));
+        let plugin = self.plugins.iter().find(|p| p.commands().iter().any(|c| c.name == name))?;
+        Some(plugin.run_command(name, args.trim(), &self.command_context()))
+    }
+
+    fn apply_plugin_outcome(&mut self, ctx: &Context<Self>, input: &ComposerInput, outcome: CommandOutcome) {
+        match outcome {
+            // Kept for later rather than lost.
+            CommandOutcome::Send(text) if self.send_cooldown() > 0 => input.set_value(&text),
+            CommandOutcome::Send(text) => {
+                if let Err(reason) = self.submit_text(ctx, text.clone()) {
+                    input.set_value(&text);
+                    self.push_toast(ctx, reason);
+                }
+            }
+            CommandOutcome::Draft(text) => input.set_value(&text),
+            CommandOutcome::Notice(text) => self.push_toast(ctx, text),
+            CommandOutcome::Done => {}
+        }
+    }
+
+    fn take_client_id(&mut self) -> String {
+        let client_id = format!("{}-{}", self.username, self.next_client_id);
+        self.next_client_id += 1;
+        client_id
+    }
+
+    /// Mirrors the composer and our unconfirmed messages into session
+    /// storage, so a crash or a closed tab can pick up where it left off.
+    fn persist_unsent(&mut self) {
+        // What's on offer from last time stays put until the user decides.
+        if self.restore.is_some() {
+            return;
+        }
+        let unsent = Unsent {
+            user: self.username.clone(),
+            room: self.active_room.clone(),
+            draft: ComposerInput::find(&self.chat_input).map(|input| input.value()).unwrap_or_default(),
+            pending: self
+                .messages
+                .iter()
+                .filter(|m| m.from == self.username && m.status != DeliveryStatus::Sent && !m.deleted)
+                .map(|m| UnsentMessage {
+                    room: m.room.clone(),
+                    text: m.message.clone(),
+                    time: m.time.unwrap_or_else(js_sys::Date::now),
+                    client_id: m.client_id.clone(),
+                })
+                .collect(),
+        };
+        if unsent != self.persisted {
+            unsent.save();
+            self.persisted = unsent;
+        }
+    }
+
+    /// What each room is holding on to, biggest first.
+    fn room_usage(&self) -> Vec<RoomUsage> {
+        let mut usage: Vec<RoomUsage> = self
+            .rooms
+            .iter()
+            .map(|room| {
+                let mut usage = RoomUsage { room: room.name.clone(), messages: 0, text_bytes: 0, files: 0, file_bytes: 0 };
+                for m in self.messages.iter().filter(|m| m.room == room.name) {
+                    match m.file.as_ref().and_then(|id| self.transfers.get(id)) {
+                        Some(transfer) => {
+                            usage.files += 1;
+                            usage.file_bytes += transfer.meta.size;
+                        }
+                        None => {
+                            usage.messages += 1;
+                            usage.text_bytes += m.message.len();
+                        }
+                    }
+                }
+                usage
+            })
+            .collect();
+        usage.sort_by_key(|u| std::cmp::Reverse(u.bytes()));
+        usage
+    }
+
+    /// Drops `room`'s files and every message but its newest
+    /// `KEEP_WHEN_PRUNING`, keeping any of ours still on the way. Returns
+    /// how many messages and files went.
+    fn prune_room(&mut self, room: &str) -> (usize, usize) {
+        let texts = self.messages.iter().filter(|m| m.room == room && m.file.is_none()).count();
+        let mut old = texts.saturating_sub(KEEP_WHEN_PRUNING);
+        let mut dropped = Vec::new();
+        let mut files = 0;
+        self.messages.retain(|m| {
+            if m.room != room {
+                return true;
+            }
+            if let Some(id) = &m.file {
+                files += 1;
+                dropped.push((m.id, Some(id.clone())));
+                return false;
+            }
+            if old == 0 || m.status != DeliveryStatus::Sent {
+                return true;
+            }
+            old -= 1;
+            dropped.push((m.id, None));
+            false
+        });
+        for (id, file) in &dropped {
+            if let Some(id) = id {
+                self.message_reactions.remove(id);
+                self.reactors.remove(id);
+                self.selected.remove(id);
+            }
+            if let Some(file) = file {
+                // Dropping the transfer frees its object URL.
+                self.transfers.remove(file);
+            }
+        }
+        let messages = dropped.len() - files;
+        if messages > 0 {
+            // What went can come back from the server.
+            self.history.entry(room.to_string()).or_default().exhausted = false;
+        }
+        (messages, files)
+    }
+
+    /// Says once per session that storage is running low: the browser's
+    /// own for this site, or the files we're holding in memory.
+    fn warn_storage(&mut self, ctx: &Context<Self>) {
+        if self.storage_warned {
+            return;
+        }
+        let held: usize = self.transfers.values().filter(|t| t.url().is_some()).map(|t| t.meta.size).sum();
+        if !self.storage.is_some_and(|s| s.nearly_full()) && held < MEMORY_WARN_BYTES {
+            return;
+        }
+        self.storage_warned = true;
+        self.push_toast(ctx, "⚠️ Storage is running low. Clear old messages and files under Settings → Storage.".to_string());
+    }
+
+    /// Whether a message with this server ID is already shown or held back.
+    fn is_known(&self, id: Option<u64>) -> bool {
+        id.is_some() && self.messages.iter().chain(&self.held).any(|m| m.id == id)
+    }
+
+    /// Puts a message where its ID says it belongs. The server numbers
+    /// messages in the order it accepts them, so one that was overtaken
+    /// (a replay after reconnecting, a held-back batch) still lands in
+    /// sequence. Ours that are still waiting for an ID stay last.
+    fn insert_message(&mut self, message: MessageData) {
+        let id = match message.id {
+            Some(id) => id,
+            None => return self.messages.push(message),
+        };
+        // Everything from others passes the watchlist on its way in, bar
+        // what the blocked say.
+        if message.from != self.username && !self.settings.is_blocked(&message.from) {
+            self.watchlist.observe(&self.settings.watch_keywords, id, &message.message);
+        }
+        let at = self
+            .messages
+            .iter()
+            .rposition(|m| m.id.is_some_and(|known| known < id))
+            .map_or(0, |before| before + 1);
+        self.messages.insert(at, message);
+    }
+
+    /// Everything we hold that the server has confirmed, for export.
+    fn archive(&self) -> Archive {
+        let messages = self
+            .messages
+            .iter()
+            .filter_map(|m| {
+                let id = m.id?;
+                Some(ArchivedMessage {
+                    id,
+                    from: m.from.clone(),
+                    room: m.room.clone(),
+                    message: m.message.clone(),
+                    time: m.time,
+                    edited: m.edited,
+                    deleted: m.deleted,
+                    reactions: self.message_reactions.get(&id).map(|r| r.clone().into_iter().collect()).unwrap_or_default(),
+                    reactors: self.reactors.get(&id).map(|r| r.clone().into_iter().collect()).unwrap_or_default(),
+                    parent_id: m.parent_id,
+                    reply_to: m.reply_to,
+                })
+            })
+            .collect();
+        Archive::new(messages)
+    }
+
+    /// Adds an export's messages to what we hold, skipping any we already
+    /// have, and joins the rooms they came from. Returns how many were new.
+    fn restore_archive(&mut self, archive: Archive) -> usize {
+        let mut restored = 0;
+        for m in archive.messages {
+            if self.is_known(Some(m.id)) {
+                continue;
+            }
+            if !self.rooms.iter().any(|r| r.name == m.room) {
+                self.rooms.push(Room::new(&m.room));
+                self.request_history(&m.room, None);
+            }
+            if !m.reactions.is_empty() {
+                self.message_reactions.insert(m.id, m.reactions.into_iter().collect());
+            }
+            if !m.reactors.is_empty() {
+                self.reactors.insert(m.id, m.reactors.into_iter().collect());
+            }
+            let mut message_data = MessageData {
+                id: Some(m.id),
+                from: m.from,
+                message: m.message,
+                room: m.room,
+                client_id: None,
+                time: m.time,
+                edited: m.edited,
+                deleted: m.deleted,
+                status: DeliveryStatus::Sent,
+                lang: None,
+                file: None,
+                parent_id: m.parent_id,
+                reply_to: m.reply_to,
+                announcement: false,
+                sticky: false,
+            };
+            message_data.detect_language();
+            self.insert_message(message_data);
+            restored += 1;
+        }
+        restored
+    }
+
+    fn view_held(&self, ctx: &Context<Self>) -> Html {
+        let count = self.held.iter().filter(|m| m.room == self.active_room).count();
+        if count == 0 {
+            return html! {};
+        }
+        html! {
+            <button
+                onclick={ctx.link().callback(|_| Msg::ShowHeld)}
+                class="w-full my-2 py-1 rounded bg-gray-100 text-sm text-gray-600 hover:bg-gray-200"
+            >
+                {match count {
+                    1 => "1 message hidden, click to load".to_string(),
+                    n => format!("{} messages hidden, click to load", n),
+                }}
+            </button>
+        }
+    }
+
+    /// The open thread: its first message and every reply, with a composer
+    /// of its own.
+    fn view_thread(&self, ctx: &Context<Self>, reply_counts: &HashMap<u64, usize>) -> Html {
+        let root = match self.thread.and_then(|id| self.messages.iter().find(|m| m.id == Some(id))) {
+            Some(root) => root,
+            None => return html! {},
+        };
+        let replies = self.messages.iter().filter(|m| m.parent_id == root.id);
+        let enter_key = self.settings.enter_key.clone();
+        let onkeydown = ctx.link().batch_callback(move |e: KeyboardEvent| {
+            if e.key() == "Enter" && !e.is_composing() && enter_key.sends(e.ctrl_key() || e.meta_key(), e.shift_key()) {
+                e.prevent_default();
+                Some(Msg::SubmitThreadReply)
+            } else {
+                None
+            }
+        });
+        let count = reply_counts.get(&root.id.unwrap_or_default()).copied().unwrap_or_default();
+        let participants = self.thread_participants(root);
+        let mut current_user = root.from.clone();
+
+        html! {
+            <aside aria-labelledby="thread-title" class="thread-panel flex-none w-96 h-screen flex flex-col border-l-2 border-gray-300 bg-gray-50 text-black">
+                <div class="flex justify-between items-center p-3 border-b border-gray-300">
+                    <h2 id="thread-title" class="font-bold">{format!("🧵 Thread · {}", room_label(&root.room))}</h2>
+                    <div class="grow"></div>
+                    <button
+                        onclick={ctx.link().callback(|_| Msg::NotifyThread)}
+                        disabled={participants.is_empty()}
+                        title={if participants.is_empty() { "Nobody else has written here yet".to_string() } else { format!("Notify {}", participants.join(", ")) }}
+                        aria-label="Notify everyone in this thread"
+                        class="mr-3 text-gray-400 hover:text-gray-700 disabled:opacity-30"
+                    >
+                        {"📣"}
+                    </button>
+                    <button onclick={ctx.link().callback(|_| Msg::CloseThread)} aria-label="Close thread" class="text-gray-400 hover:text-gray-700">{"✕"}</button>
+                </div>
+                <div class="grow overflow-y-auto p-3">
+                    {self.view_message(ctx, root, true, None, 0, false)}
+                    <div class="flex items-center my-3 text-xs text-gray-500">
+                        <span class="pr-2">{format!("{} {}", count, if count == 1 { "reply" } else { "replies" })}</span>
+                        <div class="grow border-t border-gray-300"></div>
+                    </div>
+                    {
+                        replies.map(|m| {
+                            let is_new_user = current_user != m.from;
+                            current_user = m.from.clone();
+                            self.view_message(ctx, m, is_new_user, None, 0, true)
+                        }).collect::<Html>()
+                    }
+                </div>
+                <div class="p-3 border-t border-gray-300">
+                    <textarea
+                        ref={self.thread_input.clone()}
+                        {onkeydown}
+                        rows="2"
+                        aria-label="Reply in thread"
+                        placeholder="Reply in thread..."
+                        disabled={self.send_cooldown() > 0}
+                        class="block w-full py-2 px-3 bg-white rounded-lg outline-none resize-none focus:ring-2 focus:ring-blue-600"
+                    />
+                </div>
+            </aside>
+        }
+    }
+
+    /// Everyone but us who has written in the thread under `root`, root
+    /// included, in the order they first spoke.
+    fn thread_participants(&self, root: &MessageData) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for m in std::iter::once(root).chain(self.messages.iter().filter(|m| m.parent_id == root.id && !m.deleted)) {
+            if m.from != self.username && !names.contains(&m.from) {
+                names.push(m.from.clone());
+            }
+        }
+        names
+    }
+
+    /// The second room, when the view is split: its own list and composer.
+    /// Only wide screens have room for it.
+    fn view_split(&self, ctx: &Context<Self>, reply_counts: &HashMap<u64, usize>) -> Html {
+        let room = match &self.split_room {
+            Some(room) => room,
+            None => return html! {},
+        };
+        let loaded: HashSet<u64> = self.messages.iter().filter(|m| m.room == *room).filter_map(|m| m.id).collect();
+        let enter_key = self.settings.enter_key.clone();
+        let onkeydown = ctx.link().batch_callback(move |e: KeyboardEvent| {
+            if e.key() == "Enter" && !e.is_composing() && enter_key.sends(e.ctrl_key() || e.meta_key(), e.shift_key()) {
+                e.prevent_default();
+                Some(Msg::SubmitSplit)
+            } else {
+                None
+            }
+        });
+        let choose = ctx.link().callback(|e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            Msg::OpenSplit(select.value())
+        });
+        let swap = {
+            let room = room.clone();
+            ctx.link().callback(move |_| Msg::SwitchRoom(room.clone()))
+        };
+        let cooldown = self.send_cooldown_in(room);
+        let mut current_user = String::new();
+
+        html! {
+            <section aria-label={format!("Second pane: {}", room_label(room))} class="hidden xl:flex flex-none w-2/5 h-screen flex-col border-l-2 border-gray-300">
+                <div class="h-14 border-b-2 border-gray-300 flex items-center justify-between px-4">
+                    <select onchange={choose} aria-label="Room in the second pane" class="px-2 py-1 rounded bg-gray-100 text-sm">
+                        {
+                            self.rooms.iter().filter(|r| !r.pending && r.name != self.active_room).map(|r| html! {
+                                <option value={r.name.clone()} selected={r.name == *room}>{room_label(&r.name)}</option>
+                            }).collect::<Html>()
+                        }
+                    </select>
+                    <div class="flex items-center">
+                        <button onclick={swap} title="Swap the two panes" aria-label="Swap panes" class="mr-2 px-2 py-1 rounded hover:bg-gray-200">{"⇄"}</button>
+                        <button onclick={ctx.link().callback(|_| Msg::ToggleSplit)} aria-label="Close the second pane" class="text-gray-400 hover:text-gray-700">{"✕"}</button>
+                    </div>
+                </div>
+                <div
+                    ref={self.split_ref.clone()}
+                    onscroll={ctx.link().callback(|_: Event| Msg::MarkSeen)}
+                    class="w-full grow overflow-auto border-b-2 border-gray-300 p-4"
+                >
+                    {
+                        self.messages
+                            .iter()
+                            .filter(|m| m.room == *room)
+                            .filter(|m| m.parent_id.is_none_or(|parent| !loaded.contains(&parent)))
+                            .map(|m| {
+                                let is_new_user = current_user != m.from;
+                                current_user = m.from.clone();
+                                let replies = m.id.and_then(|id| reply_counts.get(&id)).copied().unwrap_or_default();
+                                self.view_message(ctx, m, is_new_user, None, replies, true)
+                            })
+                            .collect::<Html>()
+                    }
+                </div>
+                <div class="px-3 py-2">
+                    <textarea
+                        ref={self.split_input.clone()}
+                        {onkeydown}
+                        rows="1"
+                        aria-label={format!("Message {}", room_label(room))}
+                        placeholder={
+                            if cooldown > 0 {
+                                format!("You can send again in {}s", cooldown)
+                            } else {
+                                format!("Message {}...", room_label(room))
+                            }
+                        }
+                        disabled={cooldown > 0 || self.is_archived(room)}
+                        class="block w-full py-2 px-4 bg-gray-100 rounded-full outline-none resize-none focus:ring-2 focus:ring-blue-600"
+                    />
+                </div>
+            </section>
+        }
+    }
+
+    /// The room's latest announcement meant to stay at the top, until
+    /// we dismiss it.
+    fn view_sticky_announcement(&self, ctx: &Context<Self>) -> Html {
+        let latest = self.messages.iter().rev().find(|m| {
+            m.room == self.active_room
+                && m.announcement
+                && m.sticky
+                && !m.deleted
+                && !self.settings.is_blocked(&m.from)
+        });
+        let m = match latest {
+            Some(m) => m,
+            None => return html! {},
+        };
+        let id = match m.id {
+            Some(id) if !self.dismissed_announcements.contains(id) => id,
+            _ => return html! {},
+        };
+        html! {
+            <div role="status" class="sticky top-0 z-10 -mx-4 -mt-4 mb-2 px-4 py-2 flex items-start bg-amber-100 text-amber-900 border-b-2 border-amber-400 shadow-sm">
+                <span class="mr-2" aria-hidden="true">{"📢"}</span>
+                <button
+                    onclick={ctx.link().callback(move |_| Msg::ScrollToMessage(id))}
+                    aria-label={format!("Go to the announcement from {}", m.from)}
+                    class="grow min-w-0 text-left text-sm truncate hover:underline"
+                >
+                    <strong>{format!("{}: ", m.from)}</strong>{self.settings.masked(&m.message).into_owned()}
+                </button>
+                <button
+                    onclick={ctx.link().callback(move |_| Msg::DismissAnnouncement(id))}
+                    aria-label="Dismiss announcement"
+                    class="ml-2 text-amber-700 hover:text-amber-900"
+                >
+                    {"✕"}
+                </button>
+            </div>
+        }
+    }
+
+    /// The message another one quotes, as a link back to it.
+    fn view_quote(&self, ctx: &Context<Self>, id: u64) -> Html {
+        let quoted = self.messages.iter().find(|m| m.id == Some(id));
+        let (from, text) = match quoted {
+            Some(m) if m.deleted => (m.from.clone(), "message deleted".to_string()),
+            Some(m) => (m.from.clone(), reply_snippet(&self.settings.masked(&m.message))),
+            None => {
+                return html! {
+                    <div class="border-l-4 border-gray-300 pl-2 mb-1 text-xs italic opacity-70">{"Original message not loaded"}</div>
+                }
+            }
+        };
+        html! {
+            <button
+                onclick={ctx.link().callback(move |_| Msg::ScrollToMessage(id))}
+                aria-label={format!("Go to the message from {} this replies to", from)}
+                class="block w-full text-left border-l-4 border-blue-400 pl-2 mb-1 text-xs opacity-80 hover:opacity-100"
+            >
+                <strong class="block">{from}</strong>
+                <span class="block truncate">{text}</span>
+            </button>
+        }
+    }
+
+    /// What the composer is replying to, with a way to stop.
+    fn view_reply(&self, ctx: &Context<Self>) -> Html {
+        let m = match self.replying_to.and_then(|id| self.messages.iter().find(|m| m.id == Some(id))) {
+            Some(m) => m,
+            None => return html! {},
+        };
+        html! {
+            <div class="flex items-center justify-between mx-3 mb-2 px-3 py-1 rounded bg-gray-100 border-l-4 border-blue-600 text-sm text-gray-700">
+                <span class="truncate">
+                    {"Replying to "}<strong>{m.from.clone()}</strong>{": "}{reply_snippet(&self.settings.masked(&m.message))}
+                </span>
+                <button
+                    onclick={ctx.link().callback(|_| Msg::CancelReply)}
+                    aria-label="Cancel reply"
+                    class="flex-none ml-2 text-gray-400 hover:text-gray-700"
+                >
+                    {"✕"}
+                </button>
+            </div>
+        }
+    }
+
+    fn view_restore(&self, ctx: &Context<Self>) -> Html {
+        let unsent = match &self.restore {
+            Some(unsent) => unsent,
+            None => return html! {},
+        };
+        let mut what = Vec::new();
+        if !unsent.draft.trim().is_empty() {
+            what.push("a message you were writing".to_string());
+        }
+        match unsent.pending.len() {
+            0 => {}
+            1 => what.push("1 message that wasn't sent".to_string()),
+            n => what.push(format!("{} messages that weren't sent", n)),
+        }
+        html! {
+            <div role="alert" class="flex items-center justify-between mx-3 mb-2 px-3 py-2 rounded bg-blue-50 border border-blue-200 text-sm text-blue-900">
+                <span>{format!("Pick up where you left off? You had {}.", what.join(" and "))}</span>
+                <div class="flex-none ml-2">
+                    <button
+                        onclick={ctx.link().callback(|_| Msg::RestoreUnsent)}
+                        class="px-2 py-1 mr-1 rounded bg-blue-600 text-white hover:bg-blue-700"
+                    >
+                        {"Restore"}
+                    </button>
+                    <button
+                        onclick={ctx.link().callback(|_| Msg::DiscardUnsent)}
+                        class="px-2 py-1 rounded hover:bg-blue-100"
+                    >
+                        {"Discard"}
+                    </button>
+                </div>
+            </div>
+        }
+    }
+
+    fn own_message_index(&self, client_id: &str) -> Option<usize> {
+        self.messages
+            .iter()
+            .position(|m| m.from == self.username && m.client_id.as_deref() == Some(client_id))
+    }
+}
+
+impl Component for Chat {
+    type Message = Msg;
+    type Properties = ();
+    fn create(ctx: &Context<Self>) -> Self {
+        let (user, _) = ctx
+            .link()
+            .context::<User>(Callback::noop())
+            .expect("context to be set");
+        let wss = match ctx.link().context::<TransportContext>(Callback::noop()) {
+            Some((transport, _)) => transport.connect(),
+            None => Box::new(WebsocketService::new()) as Box<dyn ChatTransport>,
+        };
+        let username = user.username.borrow().clone();
+        let last_read = LastRead::load(&username);
+        let dismissed_announcements = DismissedAnnouncements::load(&username);
+
+        register(wss.as_ref(), &username);
+        Notifier::request_permission();
+        let restore = Unsent::load(&username);
+
+        let mut chat = Self {
+            user,
+            username,
+            users: vec![],
+            messages: vec![],
+            chat_input: NodeRef::default(),
+            wss,
+            _producer: EventBus::bridge(ctx.link().callback(|e| match e {
+                BusEvent::Frame(s) => Msg::HandleMsg(s),
+                BusEvent::Connection(state) => Msg::ConnectionChanged(state),
+                BusEvent::Endpoint(url) => Msg::EndpointChanged(url),
+                BusEvent::Latency(ms) => Msg::LatencyMeasured(ms),
+            })),
+            show_emoji_picker: false,
+            message_reactions: HashMap::new(),
+            reactors: HashMap::new(),
+            show_leaderboard: false,
+            starred: Starred::load(),
+            show_starred: false,
+            show_gallery: false,
+            members: HashMap::new(),
+            show_members: false,
+            show_invite: false,
+            show_privacy: false,
+            auto_joined: false,
+            rooms: vec![Room::new(DEFAULT_ROOM)],
+            active_room: DEFAULT_ROOM.to_string(),
+            connection: ConnectionState::Reconnecting,
+            endpoint: None,
+            latency: None,
+            connected_before: false,
+            resync: None,
+            first_missed: None,
+            scroll_to_missed: false,
+            notifier: Notifier::new(ctx.link().callback(|_| Msg::FlushNotifications)),
+            toasts: vec![],
+            next_toast_id: 0,
+            settings: Settings::load(),
+            show_settings: false,
+            storage: None,
+            storage_warned: false,
+            watchlist: Watchlist::default(),
+            show_stats: false,
+            traffic: None,
+            frame_log: None,
+            show_report: false,
+            onboarding: Onboarding::load(),
+            // Client IDs are idempotency keys on the server, so they mustn't
+            // repeat across page loads.
+            next_client_id: js_sys::Date::now() as u64,
+            last_typing_sent: 0.0,
+            typing: HashMap::new(),
+            _presence: PresenceTracker::new(ctx.link().callback(Msg::PresenceChanged)),
+            slow_mode: HashMap::new(),
+            last_sent: HashMap::new(),
+            slow_mode_tick: None,
+            banners: vec![],
+            next_banner_id: 0,
+            capabilities: Capabilities::default(),
+            removed: None,
+            removing: None,
+            show_announce: false,
+            health: Health::default(),
+            _health_monitor: HealthMonitor::new(ctx.link().callback(Msg::HealthChanged)),
+            messages_ref: NodeRef::default(),
+            history: HashMap::new(),
+            scroll_anchor: None,
+            recent: Recent::load(),
+            show_palette: false,
+            editing: None,
+            edit_input: NodeRef::default(),
+            pins: HashMap::new(),
+            pin_limit: DEFAULT_PIN_LIMIT,
+            show_pins: false,
+            last_tap: None,
+            link_warning: None,
+            new_room: None,
+            creating_rooms: HashMap::new(),
+            unconfirmed_edits: HashMap::new(),
+            unconfirmed_deletes: HashMap::new(),
+            replying_to: None,
+            revealed: HashSet::new(),
+            scroll_to_message: None,
+            jumping_to: None,
+            thread: None,
+            thread_input: NodeRef::default(),
+            split_room: None,
+            split_ref: NodeRef::default(),
+            split_input: NodeRef::default(),
+            selecting: false,
+            selected: BTreeSet::new(),
+            role: Role::Member,
+            read_cursors: HashMap::new(),
+            read_sent: HashMap::new(),
+            last_read,
+            dismissed_announcements,
+            base_title: web_sys::window().and_then(|w| w.document()).map(|d| d.title()).unwrap_or_default(),
+            title_unread: None,
+            unread_counts: RefCell::default(),
+            user_language: String::new(),
+            translate_url: None,
+            translations: HashMap::new(),
+            incompatible: None,
+            last_protocol_error: 0.0,
+            persisted: restore.clone().unwrap_or_default(),
+            restore,
+            plugins: plugins::registered(),
+            verification: None,
+            verifying: false,
+            verification_error: None,
+            devices: None,
+            enrolling: None,
+            flood: RateLimiter::new(FLOOD_MESSAGES_PER_SECOND, 1_000.0),
+            send_limit: RateLimiter::new(SEND_LIMIT, SEND_WINDOW_MS),
+            held: Vec::new(),
+            transcript: None,
+            send_attempts: HashMap::new(),
+            status: PresenceStatus::Online,
+            auto_replied: HashSet::new(),
+            avatars: HashMap::new(),
+            transfers: HashMap::new(),
+            alt_prompt: None,
+            last_auto_reply: 0.0,
+            _shortcuts: shortcuts::listen(ctx.link().callback(|shortcut| match shortcut {
+                Shortcut::Palette => Msg::TogglePalette,
+                Shortcut::FrameLog => Msg::ToggleFrameLog,
+            })),
+        };
+        chat.user_language = chat.settings.language();
+        chat.request_history(DEFAULT_ROOM, None);
+        if let Some(room) = invite::take() {
+            ctx.link().send_message(Msg::JoinRoom(room));
+        }
+        ctx.link().send_message(Msg::CheckStorage);
+        let link = ctx.link().clone();
+        spawn_local(async move {
+            link.send_message(Msg::TranslationEndpoint(Config::load().await.translate_url()));
+        });
+        chat
+    }
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        // Anything might change what's unread.
+        self.unread_counts.get_mut().clear();
+        match msg {
+            Msg::AttachFile(file) => {
+                if file.size() > MAX_FILE_BYTES as f64 {
+                    self.push_toast(ctx, format!("{} is too big to send; files can be up to {} MB.", file.name(), MAX_FILE_BYTES / (1024 * 1024)));
+                    return true;
+                }
+                if self.settings.ask_alt_text && file.type_().starts_with("image/") {
+                    match Url::create_object_url_with_blob(&file) {
+                        Ok(preview) => {
+                            self.alt_prompt = Some((file, preview));
+                            return true;
+                        }
+                        Err(e) => log::warn!("couldn't preview {}: {:?}", file.name(), e),
+                    }
+                }
+                read_file(ctx, file, String::new());
+                false
+            }
+            Msg::DescribeImage(alt) => {
+                if let Some((file, preview)) = self.alt_prompt.take() {
+                    let _ = Url::revoke_object_url(&preview);
+                    read_file(ctx, file, alt);
+                }
+                true
+            }
+            Msg::CancelImage => {
+                if let Some((_, preview)) = self.alt_prompt.take() {
+                    let _ = Url::revoke_object_url(&preview);
+                }
+                true
+            }
+            Msg::FileRead(file, alt, bytes) => {
+                let bytes = match bytes {
+                    Ok(bytes) => bytes,
+                    Err(e) => {
+                        log::error!("failed to read {}: {}", file.name(), e);
+                        self.push_toast(ctx, format!("Couldn't read {}.", file.name()));
+                        return true;
+                    }
+                };
+                // However many chunks it takes, a file counts as one send.
+                if !self.send_limit.allow(js_sys::Date::now()) {
+                    self.push_toast(ctx, format!("You're sending quickly. You can send {} in {}s", file.name(), self.throttle_remaining()));
+                    return true;
+                }
+                let mime = Some(file.type_()).filter(|mime| !mime.is_empty()).unwrap_or_else(|| "application/octet-stream".to_string());
+                let mut meta = FileMeta::new(self.take_client_id(), file.name(), mime, bytes.len());
+                meta.alt = alt;
+                let room = self.active_room.clone();
+                self.send_frame(WebSocketMessage {
+                    data: Some(serde_json::to_string(&meta).unwrap()),
+                    room: Some(room.clone()),
+                    ..WebSocketMessage::new(MsgTypes::FileStart)
+                });
+                for (index, chunk) in file_transfer::split(&bytes).into_iter().enumerate() {
+                    self.send_frame(WebSocketMessage {
+                        data: Some(chunk),
+                        client_id: Some(meta.id.clone()),
+                        id: Some(index as u64),
+                        ..WebSocketMessage::new(MsgTypes::FileChunk)
+                    });
+                }
+                self.send_frame(WebSocketMessage {
+                    client_id: Some(meta.id.clone()),
+                    ..WebSocketMessage::new(MsgTypes::FileEnd)
+                });
+                meta.from = self.username.clone();
+                self.messages.push(file_message(&meta, room));
+                self.transfers.insert(meta.id.clone(), Transfer::outgoing(meta, &bytes));
+                self.warn_storage(ctx);
+                true
+            }
+            Msg::HandleMsg(s) => {
+                let event = match ServerEvent::decode(&s) {
+                    Ok(event) => event,
+                    Err(e) => return self.protocol_error(ctx, &s, e),
+                };
+                match event {
+                    ServerEvent::Users { names: users_from_message, entries } => {
+                        // We're registered once the server lists us.
+                        if !self.auto_joined && users_from_message.contains(&self.username) {
+                            self.auto_joined = true;
+                            self.auto_join(ctx);
+                        }
+                        let connected = users_from_message
+                            .iter()
+                            .map(|u| UserProfile {
+                                name: u.into(),
+                                avatar: self.avatar(u),
+                                status: entries.get(u).map_or(PresenceStatus::Online, UserEntry::status),
+                                role: entries.get(u).map_or(Role::Member, UserEntry::role),
+                            })
+                            .collect();
+                        // People who dropped out of the list stay visible as offline.
+                        // Like their presence, the status of the blocked isn't followed.
+                        merge_users(&mut self.users, connected, |name| self.settings.is_blocked(name));
+                        true
+                    }
+                    ServerEvent::UserUpdated(update) => {
+                        if let Some(avatar) = update.avatar {
+                            match avatar.is_empty() {
+                                true => self.avatars.remove(&update.name),
+                                false => self.avatars.insert(update.name.clone(), avatar),
+                            };
+                            let avatar = self.avatar(&update.name);
+                            if let Some(user) = self.users.iter_mut().find(|u| u.name == update.name) {
+                                user.avatar = avatar;
+                            }
+                        }
+                        if let Some(new_name) = update.new_name.filter(|n| !n.is_empty() && *n != update.name) {
+                            let text = if update.name == self.username {
+                                format!("The server renamed you to {}.", new_name)
+                            } else {
+                                format!("{} is now known as {}.", update.name, new_name)
+                            };
+                            self.rename_user(&update.name, &new_name);
+                            self.push_toast(ctx, text);
+                        }
+                        true
+                    }
+                    ServerEvent::Presence(presence) => {
+                        if self.settings.is_blocked(&presence.from) {
+                            return false;
+                        }
+                        match self.users.iter_mut().find(|u| u.name == presence.from) {
+                            Some(user) => {
+                                user.status = presence.status;
+                                true
+                            }
+                            None => false,
+                        }
+                    }
+                    ServerEvent::Message(message_data) => {
+                        if message_data.message.chars().count() > MAX_RECEIVED_MESSAGE_CHARS {
+                            log::warn!("dropping oversized message from {}", message_data.from);
+                            return false;
+                        }
+                        // Replayed after a reconnect, or delivered twice.
+                        if self.is_known(message_data.id) {
+                            return false;
+                        }
+                        if let Some(transcript) = &self.transcript {
+                            if self.rooms.iter().any(|r| r.name == message_data.room) {
+                                transcript.append(&message_data.room, &message_data.from, &message_data.message, message_data.time);
+                            }
+                        }
+                        // Our own echo confirms the optimistic copy already in the
+                        // list, in case the acknowledgement went missing.
+                        if message_data.from == self.username {
+                            if let Some(client_id) = &message_data.client_id {
+                                if let Some(changed) = self.confirm_own(client_id, message_data.id) {
+                                    return changed;
+                                }
+                            }
+                        }
+                        // Someone starting a conversation with us opens it, unless
+                        // they're blocked: a hidden message shouldn't leave a trace.
+                        if dm_peer(&message_data.room).is_some()
+                            && !self.settings.is_blocked(&message_data.from)
+                            && !self.rooms.iter().any(|r| r.name == message_data.room)
+                        {
+                            self.rooms.push(Room::new(&message_data.room));
+                        }
+                        // Traffic for rooms we haven't joined is not ours to show.
+                        let room = match self.rooms.iter_mut().find(|r| r.name == message_data.room) {
+                            Some(room) => room,
+                            None => return false,
+                        };
+                        if !self.flood.allow(js_sys::Date::now()) {
+                            if self.held.len() >= FLOOD_HELD_LIMIT {
+                                self.held.remove(0);
+                            }
+                            self.held.push(message_data);
+                            // The count catches up with the next message that does get shown.
+                            return self.held.len() == 1;
+                        }
+                        if message_data.from != self.username
+                            && !self.settings.is_muted(&message_data.from)
+                            && !self.settings.is_blocked(&message_data.from)
+                            && !self.settings.is_room_muted(&message_data.room)
+                            && (room.name != self.active_room || is_hidden())
+                        {
+                            let message = notification_rules::Message {
+                                room: &message_data.room,
+                                from: &message_data.from,
+                                text: &message_data.message,
+                            };
+                            let actions = self.settings.notification_actions(&message, &self.username);
+                            // Badge only, or nothing at all: the unread count above is enough.
+                            if actions.iter().any(|a| *a != Action::Badge) {
+                                self.notifier.push(Incoming {
+                                    room: message_data.room.clone(),
+                                    from: message_data.from.clone(),
+                                    text: self.settings.masked(&message_data.message).into_owned(),
+                                    actions,
+                                });
+                            }
+                        }
+                        if message_data.from != self.username {
+                            let received = ReceivedMessage {
+                                id: message_data.id,
+                                from: message_data.from.clone(),
+                                room: message_data.room.clone(),
+                                text: message_data.message.clone(),
+                            };
+                            for plugin in &self.plugins {
+                                plugin.on_receive(&received);
+                            }
+                            self.auto_reply(ctx, &message_data);
+                        }
+                        self.typing.remove(&message_data.from);
+                        self.insert_message(message_data);
+                        true
+                    }
+                    ServerEvent::Typing(typing) => {
+                        if typing.from == self.username || self.settings.is_blocked(&typing.from) {
+                            return false;
+                        }
+                        self.typing.insert(typing.from, (typing.room, js_sys::Date::now()));
+                        let link = ctx.link().clone();
+                        Timeout::new(TYPING_EXPIRY_MS, move || link.send_message(Msg::ExpireTyping)).forget();
+                        self.settings.show_typing_indicators
+                    }
+                    ServerEvent::Edit(edit) => {
+                        self.unconfirmed_edits.retain(|_, (id, ..)| *id != edit.id);
+                        match self.messages.iter_mut().find(|m| m.id == Some(edit.id)) {
+                            Some(m) if m.message != edit.message || !m.edited => {
+                                m.message = edit.message;
+                                m.detect_language();
+                                m.edited = true;
+                                self.translations.remove(&edit.id);
+                                true
+                            }
+                            _ => false,
+                        }
+                    }
+                    ServerEvent::Delete(delete) => {
+                        self.unconfirmed_deletes.retain(|_, d| d.id != delete.id);
+                        self.tombstone(delete.id)
+                    }
+                    ServerEvent::Notify(NotifyData { count: Some(count), .. }) => {
+                        let text = if count == 0 {
+                            "Nobody else in this thread is online to notify".to_string()
+                        } else {
+                            format!("Notified {} {} in this thread", count, if count == 1 { "person" } else { "people" })
+                        };
+                        self.push_toast(ctx, text);
+                        true
+                    }
+                    ServerEvent::Notify(notify) => {
+                        if self.settings.is_muted(&notify.from)
+                            || self.settings.is_blocked(&notify.from)
+                            || self.settings.is_room_muted(&notify.room)
+                        {
+                            return false;
+                        }
+                        let text = format!("{} wants everyone in a thread in {} to take a look", notify.from, room_label(&notify.room));
+                        let (room, id) = (notify.room, notify.thread_id);
+                        let action = ToastAction {
+                            label: "Open thread".to_string(),
+                            on_click: ctx.link().batch_callback(move |_| vec![Msg::SwitchRoom(room.clone()), Msg::OpenThread(id)]),
+                        };
+                        self.push_toast_with_action(ctx, text, action);
+                        true
+                    }
+                    ServerEvent::Pins { room, ids, limit } => {
+                        if let Some(limit) = limit {
+                            self.pin_limit = limit;
+                        }
+                        self.pins.insert(room, ids);
+                        true
+                    }
+                    ServerEvent::Hello(hello) => {
+                        let role = hello.role.unwrap_or(if hello.moderator { Role::Moderator } else { Role::Member });
+                        // Promoted or demoted while away: the controls shown depend on it.
+                        let role_changed = role != self.role;
+                        self.role = role;
+                        if hello.version >= MIN_SERVER_VERSION && hello.min_client_version <= PROTOCOL_VERSION {
+                            let capabilities = Capabilities::negotiate(hello.features.as_deref());
+                            if capabilities == self.capabilities {
+                                return role_changed;
+                            }
+                            // Said once per change, not on every reconnect.
+                            if let Some(text) = capabilities.describe_missing() {
+                                let id = self.next_banner_id;
+                                self.next_banner_id += 1;
+                                self.banners.push(Banner { id, room: None, text, level: BannerLevel::Info });
+                            }
+                            if !capabilities.has(Feature::Pins) {
+                                self.show_pins = false;
+                            }
+                            if !capabilities.has(Feature::Threads) {
+                                self.thread = None;
+                            }
+                            self.show_leaderboard &= capabilities.has(Feature::Reactions);
+                            self.capabilities = capabilities;
+                            return true;
+                        }
+                        log::error!(
+                            "incompatible server: speaks v{}, needs client v{}; we speak v{}",
+                            hello.version,
+                            hello.min_client_version,
+                            PROTOCOL_VERSION
+                        );
+                        self.wss.close();
+                        self.incompatible = Some(hello);
+                        true
+                    }
+                    ServerEvent::Read(read) => {
+                        if read.from == self.username {
+                            return false;
+                        }
+                        let cursor = self.read_cursors.entry(read.room).or_default().entry(read.from).or_insert(0);
+                        if read.id <= *cursor {
+                            return false;
+                        }
+                        *cursor = read.id;
+                        true
+                    }
+                    ServerEvent::Reaction(reaction) => {
+                        // Our own reactions were counted when we sent them.
+                        if reaction.from == self.username {
+                            return false;
+                        }
+                        *self
+                            .message_reactions
+                            .entry(reaction.id)
+                            .or_default()
+                            .entry(reaction.emoji)
+                            .or_insert(0) += 1;
+                        *self.reactors.entry(reaction.id).or_default().entry(reaction.from).or_insert(0) += 1;
+                        true
+                    }
+                    ServerEvent::SlowMode { room, seconds } => {
+                        self.slow_mode.insert(room, seconds);
+                        self.start_slow_mode_tick(ctx);
+                        true
+                    }
+                    ServerEvent::Banner { room, banner } => {
+                        let id = self.next_banner_id;
+                        self.next_banner_id += 1;
+                        self.banners.push(Banner { id, room, text: banner.text, level: banner.level });
+                        true
+                    }
+                    ServerEvent::Error(error) => {
+                        log::warn!("server error {}: {}", error.code, error.message);
+                        if let Some(idx) = error.client_id.as_deref().and_then(|id| self.own_message_index(id)) {
+                            self.messages[idx].status = DeliveryStatus::Failed;
+                        }
+                        // A room we asked for was turned down: take back the
+                        // placeholder and let the user try another name.
+                        if let Some((name, description)) = error.client_id.as_ref().and_then(|id| self.creating_rooms.remove(id)) {
+                            self.rooms.retain(|r| !(r.pending && r.name == name));
+                            self.new_room = Some(NewRoomForm { name, description, error: Some(error.message) });
+                            return true;
+                        }
+                        if let Some(deleted) = error.client_id.as_ref().and_then(|id| self.unconfirmed_deletes.remove(id)) {
+                            self.undelete(deleted);
+                        }
+                        // An edit we'd already shown was turned down: go back
+                        // to what the message said before.
+                        if let Some((id, message, edited)) = error.client_id.as_ref().and_then(|id| self.unconfirmed_edits.remove(id)) {
+                            if let Some(m) = self.messages.iter_mut().find(|m| m.id == Some(id) && !m.deleted) {
+                                m.message = message;
+                                m.edited = edited;
+                                m.detect_language();
+                                self.translations.remove(&id);
+                            }
+                        }
+                        // The verification prompt hides the toasts, so it shows this itself.
+                        if error.code == "verification_failed" {
+                            self.verification_error = Some(error.message.clone());
+                        }
+                        // Errors are about something we did, so they show even with toasts off.
+                        self.push_toast(ctx, format!("⚠️ {}", error.message));
+                        true
+                    }
+                    ServerEvent::History { room, page, exhausted, older, client_id } => {
+                        if !self.rooms.iter().any(|r| r.name == room) {
+                            return false;
+                        }
+                        let oldest = page.iter().filter_map(|m| m.id).min();
+                        let cursor = self.history.entry(room.clone()).or_default();
+                        cursor.loading = false;
+                        cursor.exhausted = exhausted;
+                        let mut backfill: Vec<MessageData> = page
+                            .into_iter()
+                            .filter(|m| m.room == room)
+                            .filter(|m| !self.messages.iter().any(|known| known.id.is_some() && known.id == m.id))
+                            .collect();
+                        if self.is_resync_page(&room, client_id.as_deref()) {
+                            self.resynced(ctx, &room, &backfill, oldest, exhausted);
+                            // Missed messages are newer than some we hold.
+                            for message in backfill {
+                                self.insert_message(message);
+                            }
+                            return true;
+                        }
+                        if backfill.is_empty() {
+                            return self.continue_jump(ctx);
+                        }
+                        // Keep the reader's place when an older page lands above them.
+                        if older && room == self.active_room {
+                            self.scroll_anchor = self
+                                .messages_ref
+                                .cast::<Element>()
+                                .map(|list| (list.scroll_height(), list.scroll_top()));
+                        }
+                        for m in backfill.iter().filter(|m| m.from != self.username && !self.settings.is_blocked(&m.from)) {
+                            if let Some(id) = m.id {
+                                self.watchlist.observe(&self.settings.watch_keywords, id, &m.message);
+                            }
+                        }
+                        // The first we've ever seen of a room counts as read.
+                        if let (false, None, Some(newest)) = (older, self.last_read.get(&room), backfill.iter().filter_map(|m| m.id).max()) {
+                            self.last_read.advance(&room, newest);
+                        }
+                        // History is older than anything live, so it goes first.
+                        backfill.append(&mut self.messages);
+                        self.messages = backfill;
+                        self.continue_jump(ctx);
+                        true
+                    }
+                    ServerEvent::Verify(request) => {
+                        self.verification = Some(request);
+                        self.verifying = false;
+                        true
+                    }
+                    ServerEvent::Verified(token) => {
+                        webauthn::save_session_token(&self.username, &token);
+                        register(self.wss.as_ref(), &self.username);
+                        self.verification = None;
+                        self.verification_error = None;
+                        true
+                    }
+                    ServerEvent::Devices(devices) => {
+                        self.devices = Some(devices);
+                        true
+                    }
+                    ServerEvent::Enroll(request) => {
+                        let name = match self.enrolling.take() {
+                            Some(name) => name,
+                            None => return false,
+                        };
+                        let username = self.username.clone();
+                        let link = ctx.link().clone();
+                        spawn_local(async move {
+                            link.send_message(Msg::DeviceEnrolled(webauthn::enroll(&request, &username, name).await));
+                        });
+                        false
+                    }
+                    ServerEvent::Ack { client_id, id } => self.confirm_own(&client_id, Some(id)).unwrap_or(false),
+                    ServerEvent::RoomCreated { client_id, room, description } => {
+                        let ours = client_id.and_then(|id| self.creating_rooms.remove(&id)).is_some();
+                        // Someone else's new room stays out of our list until we join it.
+                        match self.rooms.iter_mut().find(|r| r.name == room) {
+                            Some(known) => {
+                                known.pending = false;
+                                known.description = description;
+                            }
+                            None => return false,
+                        }
+                        if ours {
+                            self.request_history(&room, None);
+                            self.onboarding.complete(Step::JoinRoom);
+                            ctx.link().send_message(Msg::SwitchRoom(room));
+                        }
+                        true
+                    }
+                    ServerEvent::Members { room, offset, total, page } => {
+                        let list = match self.members.get_mut(&room) {
+                            Some(list) => list,
+                            None => return false,
+                        };
+                        // A page from before the list started over.
+                        if offset != list.members.len() {
+                            return false;
+                        }
+                        list.members.extend(page);
+                        list.total = total;
+                        list.loading = false;
+                        true
+                    }
+                    ServerEvent::RoomUpdate { room, update } => {
+                        let known = match self.rooms.iter_mut().find(|r| r.name == room) {
+                            Some(known) => known,
+                            None => return false,
+                        };
+                        let archiving = known.archived != update.archived;
+                        known.description = update.description;
+                        known.created_by = update.created_by;
+                        known.archived = update.archived;
+                        if let Some(by) = update.by.filter(|by| *by != self.username && room == self.active_room) {
+                            let text = if archiving {
+                                format!("{} {} the room", by, if known.archived { "archived" } else { "unarchived" })
+                            } else if known.description.is_empty() {
+                                format!("{} cleared the topic", by)
+                            } else {
+                                format!("{} changed the topic to \"{}\"", by, known.description)
+                            };
+                            self.push_toast(ctx, text);
+                        }
+                        true
+                    }
+                    ServerEvent::FileStart { room, meta } => {
+                        if !meta.plausible() || self.transfers.contains_key(&meta.id) {
+                            log::warn!("ignoring file transfer {:?}", meta);
+                            return false;
+                        }
+                        // Its chunks are then ignored too, as for any unknown transfer.
+                        if !self.rooms.iter().any(|r| r.name == room) {
+                            return false;
+                        }
+                        self.messages.push(file_message(&meta, room));
+                        self.transfers.insert(meta.id.clone(), Transfer::incoming(meta));
+                        true
+                    }
+                    ServerEvent::FileChunk { id, index, data } => match self.transfers.get_mut(&id) {
+                        Some(transfer) => {
+                            transfer.add(index, &data);
+                            true
+                        }
+                        None => false,
+                    },
+                    ServerEvent::FileEnd(id) => match self.transfers.get_mut(&id) {
+                        Some(transfer) => {
+                            transfer.finish();
+                            self.warn_storage(ctx);
+                            true
+                        }
+                        None => false,
+                    },
+                    ServerEvent::AuthFailed(reason) => {
+                        self.wss.close();
+                        auth::fail(reason);
+                        if let Some(history) = ctx.link().history() {
+                            history.push(Route::Login);
+                        }
+                        false
+                    }
+                    ServerEvent::Removed(kind, removal) => {
+                        self.wss.close();
+                        self.removed = Some((kind, removal));
+                        true
+                    }
+                }
+            }
+            Msg::EndpointChanged(url) => {
+                self.endpoint = Some(url);
+                true
+            }
+            Msg::LatencyMeasured(ms) => {
+                self.latency = Some(ms);
+                true
+            }
+            Msg::ConnectionChanged(state) => {
+                if state == ConnectionState::Connected {
+                    if self.connected_before && self.connection != ConnectionState::Connected {
+                        self.resync_history(ctx);
+                    }
+                    self.connected_before = true;
+                } else {
+                    self.latency = None;
+                }
+                self.connection = state;
+                true
+            }
+            Msg::SubmitMessage => {
+                if self.send_cooldown() > 0 || self.is_archived(&self.active_room) {
+                    return false;
+                }
+                let input = ComposerInput::find(&self.chat_input);
+                if let Some(input) = input {
+                    if let Some(name) = input.value().trim().strip_prefix("/template ") {
+                        self.use_template(ctx, &input, name.trim());
+                        return true;
+                    }
+                    if let Some(outcome) = self.run_plugin_command(input.value().trim()) {
+                        input.set_value("");
+                        self.apply_plugin_outcome(ctx, &input, outcome);
+                        return true;
+                    }
+                    //log::debug!("got input: {:?}", input.value());
+                    if !self.confirm_filtered(&input.value()) {
+                        return false;
+                    }
+                    match self.submit_text(ctx, input.value()) {
+                        Ok(()) => input.set_value(""),
+                        Err(reason) => self.push_toast(ctx, reason),
+                    }
+                    return true;
+                };
+                false
+            }
+            Msg::ResyncTimedOut(id) => {
+                if self.resync.as_ref().is_none_or(|resync| resync.id != id) {
+                    return false;
+                }
+                self.finish_resync(ctx);
+                true
+            }
+            Msg::SendTimedOut(client_id) => {
+                let idx = match self.own_message_index(&client_id) {
+                    Some(idx) if self.messages[idx].status == DeliveryStatus::Sending => idx,
+                    _ => return false,
+                };
+                // While we're disconnected it waits in the outbox and goes
+                // out on reconnect; resending would only queue it twice.
+                if self.connection != ConnectionState::Connected {
+                    Self::await_ack(ctx, client_id);
+                    return false;
+                }
+                let attempts = self.send_attempts.entry(client_id.clone()).or_insert(1);
+                if *attempts >= MAX_SEND_ATTEMPTS {
+                    self.send_attempts.remove(&client_id);
+                    self.messages[idx].status = DeliveryStatus::Failed;
+                    return true;
+                }
+                *attempts += 1;
+                log::debug!("no ack for {}, sending again", client_id);
+                self.messages[idx].status = self.send_chat_message(ctx, &self.messages[idx]);
+                self.messages[idx].status != DeliveryStatus::Sending
+            }
+            Msg::RetryMessage(client_id) => {
+                self.send_attempts.remove(&client_id);
+                match self.own_message_index(&client_id) {
+                    Some(idx) if self.messages[idx].status == DeliveryStatus::Failed => {
+                        self.messages[idx].status = self.send_chat_message(ctx, &self.messages[idx]);
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            Msg::ComposerKeyUp(e) => {
+                self.persist_unsent();
+                let now = js_sys::Date::now();
+                let composing = !e.key().starts_with("Enter")
+                    && ComposerInput::find(&self.chat_input)
+                        .map(|input| !input.value().is_empty())
+                        .unwrap_or(false);
+                if composing && self.settings.sends_typing(&self.active_room) && now - self.last_typing_sent > TYPING_THROTTLE_MS {
+                    self.last_typing_sent = now;
+                    // In a direct conversation only the other person hears of it.
+                    let to = dm_peer(&self.active_room).map(str::to_string);
+                    self.send_frame(WebSocketMessage {
+                        room: to.is_none().then(|| self.active_room.clone()),
+                        to,
+                        ..WebSocketMessage::new(MsgTypes::Typing)
+                    });
+                }
+                false
+            }
+            Msg::ExpireTyping => {
+                let cutoff = js_sys::Date::now() - TYPING_EXPIRY_MS as f64;
+                let before = self.typing.len();
+                self.typing.retain(|_, (_, seen)| *seen > cutoff);
+                self.typing.len() != before
+            }
+            Msg::PresenceChanged(status) => {
+                self.status = status;
+                if status == PresenceStatus::Online {
+                    self.auto_replied.clear();
+                    self.mark_read();
+                }
+                if self.settings.broadcast_presence {
+                    self.send_frame(WebSocketMessage {
+                        data: Some(status.label().to_lowercase()),
+                        ..WebSocketMessage::new(MsgTypes::Presence)
+                    });
+                }
+                false
+            }
+            Msg::DismissBanner(id) => {
+                self.banners.retain(|b| b.id != id);
+                true
+            }
+            Msg::HealthChanged(health) => {
+                let hold = match health.status {
+                    HealthStatus::Maintenance => health
+                        .until
+                        .unwrap_or_else(|| js_sys::Date::now() + MAINTENANCE_HOLD_MS),
+                    _ => 0.0,
+                };
+                self.wss.hold_reconnects_until(hold);
+                self.health = health;
+                true
+            }
+            Msg::MarkSeen => self.mark_seen(),
+            Msg::MessagesScrolled => {
+                let seen = self.mark_seen();
+                self.mark_read();
+                let near_top = self
+                    .messages_ref
+                    .cast::<Element>()
+                    .map(|list| list.scroll_top() < LOAD_OLDER_THRESHOLD_PX)
+                    .unwrap_or(false);
+                let cursor = self.history.entry(self.active_room.clone()).or_default();
+                if !near_top || cursor.loading || cursor.exhausted {
+                    return seen;
+                }
+                let oldest = self
+                    .messages
+                    .iter()
+                    .filter(|m| m.room == self.active_room)
+                    .filter_map(|m| m.id)
+                    .min();
+                if let Some(oldest) = oldest {
+                    let room = self.active_room.clone();
+                    self.request_history(&room, Some(oldest));
+                }
+                seen
+            }
+            Msg::StartEdit(id) => {
+                self.editing = Some(id);
+                let edit_input = self.edit_input.clone();
+                Timeout::new(0, move || {
+                    if let Some(input) = edit_input.cast::<HtmlInputElement>() {
+                        let _ = input.focus();
+                    }
+                })
+                .forget();
+                true
+            }
+            Msg::CancelEdit => {
+                self.editing = None;
+                true
+            }
+            Msg::SubmitEdit(id, text) => {
+                self.editing = None;
+                let text = text.trim().to_string();
+                let m = match self.messages.iter_mut().find(|m| m.id == Some(id) && m.from == self.username) {
+                    Some(m) if !text.is_empty() && m.message != text => m,
+                    _ => return true,
+                };
+                let previous = (id, std::mem::replace(&mut m.message, text.clone()), m.edited);
+                m.edited = true;
+                m.detect_language();
+                let client_id = self.take_client_id();
+                self.unconfirmed_edits.insert(client_id.clone(), previous);
+                self.send_frame(WebSocketMessage {
+                    id: Some(id),
+                    data: Some(text),
+                    client_id: Some(client_id),
+                    ..WebSocketMessage::new(MsgTypes::Edit)
+                });
+                true
+            }
+            Msg::DeleteMessage(id) => {
+                let confirmed = web_sys::window()
+                    .and_then(|w| w.confirm_with_message("Delete this message for everyone?").ok())
+                    .unwrap_or(false);
+                if !confirmed {
+                    return false;
+                }
+                self.delete_message(id)
+            }
+            Msg::Moderate(name, kind) => {
+                self.removing = Some((name, kind));
+                true
+            }
+            Msg::ConfirmRemoval(reason, minutes) => {
+                let (name, kind) = match self.removing.take() {
+                    Some(removing) => removing,
+                    None => return false,
+                };
+                let (message_type, minutes) = match kind {
+                    RemovalKind::Kick => (MsgTypes::Kick, None),
+                    RemovalKind::Ban => (MsgTypes::Ban, Some(minutes)),
+                };
+                let request = RemovalRequest { name, reason, minutes };
+                self.send_frame(WebSocketMessage {
+                    data: serde_json::to_string(&request).ok(),
+                    ..WebSocketMessage::new(message_type)
+                });
+                true
+            }
+            Msg::CancelRemoval => self.removing.take().is_some(),
+            Msg::OpenDirect(name) => {
+                ctx.link().send_message(Msg::JoinRoom(dm_room(&name)));
+                false
+            }
+            Msg::MessageTapped(id, e) => {
+                let now = js_sys::Date::now();
+                match self.last_tap.take() {
+                    Some((last, at)) if last == id && now - at <= DOUBLE_TAP_MS => {
+                        // Keeps the browser from zooming or firing its own dblclick too.
+                        e.prevent_default();
+                        ctx.link().send_message(Msg::AddReaction(id, self.settings.double_tap_reaction.clone()));
+                    }
+                    _ => self.last_tap = Some((id, now)),
+                }
+                false
+            }
+            Msg::OpenLink(url) => {
+                let host = match links::host(&url) {
+                    Some(host) => host,
+                    None => return false,
+                };
+                if links::is_trusted(&host, &self.settings.trusted_domains) {
+                    open_in_new_tab(&url);
+                    return false;
+                }
+                self.link_warning = Some((url, host));
+                true
+            }
+            Msg::FollowLink(trust) => {
+                let (url, host) = match self.link_warning.take() {
+                    Some(link) => link,
+                    None => return false,
+                };
+                if trust && !self.settings.trusted_domains.contains(&host) {
+                    self.settings.trusted_domains.push(host);
+                    self.settings.save();
+                }
+                open_in_new_tab(&url);
+                true
+            }
+            Msg::CloseLinkWarning => {
+                self.link_warning = None;
+                true
+            }
+            Msg::OpenNewRoom => {
+                self.new_room = Some(NewRoomForm::default());
+                true
+            }
+            Msg::CloseNewRoom => {
+                self.new_room = None;
+                true
+            }
+            Msg::CreateRoom(name, description) => {
+                let client_id = self.take_client_id();
+                self.send_frame(WebSocketMessage {
+                    room: Some(name.clone()),
+                    data: Some(description.clone()),
+                    client_id: Some(client_id.clone()),
+                    ..WebSocketMessage::new(MsgTypes::CreateRoom)
+                });
+                // Listed straight away, but only enterable once confirmed.
+                let mut room = Room::new(&name);
+                room.description = description.clone();
+                room.created_by = self.username.clone();
+                room.pending = true;
+                self.rooms.push(room);
+                self.creating_rooms.insert(client_id.clone(), (name, description));
+                self.new_room = None;
+                let link = ctx.link().clone();
+                Timeout::new(CREATE_ROOM_TIMEOUT_MS, move || link.send_message(Msg::CreateRoomTimedOut(client_id))).forget();
+                true
+            }
+            Msg::CreateRoomTimedOut(client_id) => {
+                let (name, description) = match self.creating_rooms.remove(&client_id) {
+                    Some(request) => request,
+                    None => return false,
+                };
+                self.rooms.retain(|r| !(r.pending && r.name == name));
+                let error = Some("The server didn't answer. Try again.".to_string());
+                self.new_room = Some(NewRoomForm { name, description, error });
+                true
+            }
+            Msg::SetArchived(archived) => {
+                // Shown once the server sends it back to everyone.
+                let message_type = if archived { MsgTypes::Archive } else { MsgTypes::Unarchive };
+                self.send_frame(WebSocketMessage {
+                    room: Some(self.active_room.clone()),
+                    ..WebSocketMessage::new(message_type)
+                });
+                false
+            }
+            Msg::SetTopic(topic) => {
+                // Shown once the server sends it back to everyone.
+                self.send_frame(WebSocketMessage {
+                    room: Some(self.active_room.clone()),
+                    data: Some(topic),
+                    ..WebSocketMessage::new(MsgTypes::RoomUpdate)
+                });
+                false
+            }
+            Msg::Reply(id) => {
+                let (author, room) = match self.messages.iter().find(|m| m.id == Some(id)) {
+                    Some(m) => (m.from.clone(), m.room.clone()),
+                    None => return false,
+                };
+                // Replying from the second pane brings its room to the composer.
+                if room != self.active_room {
+                    self.update(ctx, Msg::SwitchRoom(room));
+                }
+                self.replying_to = Some(id);
+                if let Some(input) = ComposerInput::find(&self.chat_input) {
+                    let mention = format!("@{}", author);
+                    // In a direct conversation they'll hear about it anyway.
+                    if self.settings.mention_on_reply
+                        && author != self.username
+                        && dm_peer(&self.active_room).is_none()
+                        && !input.value().contains(&mention)
+                    {
+                        input.set_value(&format!("{} {}", mention, input.value().trim_start()));
+                    }
+                    input.focus();
+                }
+                true
+            }
+            Msg::ScrollToMessage(id) => {
+                let parent = match self.messages.iter().find(|m| m.id == Some(id)) {
+                    Some(m) => m.parent_id,
+                    None => return false,
+                };
+                // Opened now, so the reply is there to scroll to on this render.
+                if let Some(parent) = parent {
+                    self.update(ctx, Msg::OpenThread(parent));
+                }
+                self.scroll_to_message = Some(id);
+                true
+            }
+            Msg::GoToDate(date) => {
+                let mut parts = date.splitn(3, '-').map(|p| p.parse::<u32>().ok());
+                let start = match (parts.next().flatten(), parts.next().flatten(), parts.next().flatten()) {
+                    // Midnight where the reader is, not in UTC.
+                    (Some(year), Some(month), Some(day)) if month >= 1 => {
+                        js_sys::Date::new_with_year_month_day(year, month as i32 - 1, day as i32).get_time()
+                    }
+                    _ => return false,
+                };
+                self.jumping_to = Some((self.active_room.clone(), start));
+                self.continue_jump(ctx);
+                true
+            }
+            Msg::CancelReply => {
+                self.replying_to = None;
+                true
+            }
+            Msg::ToggleSelecting => {
+                self.selecting = !self.selecting;
+                self.selected.clear();
+                true
+            }
+            Msg::ToggleSelected(id) => {
+                if !self.selected.remove(&id) {
+                    self.selected.insert(id);
+                }
+                true
+            }
+            Msg::CopySelected => {
+                let count = self.selected_messages().len();
+                copy_text(ctx, self.selection_text(), format!("Copied {} message{}", count, if count == 1 { "" } else { "s" }));
+                false
+            }
+            Msg::CopyMessage(id, format) => {
+                if let Some(m) = self.messages.iter().find(|m| m.id == Some(id) && !m.deleted) {
+                    let note = match format {
+                        CopyFormat::Markdown => "Copied as Markdown",
+                        CopyFormat::Quote => "Copied as a quote",
+                    };
+                    copy_text(ctx, m.copied_as(format), note.to_string());
+                }
+                false
+            }
+            Msg::ForwardSelected(room) => {
+                let text = format!("Forwarded from {}:\n{}", room_label(&self.active_room), self.selection_text());
+                if text.chars().count() > MAX_MESSAGE_LENGTH {
+                    self.push_toast(ctx, "That's too much to forward at once; select fewer messages.".to_string());
+                    return true;
+                }
+                if let Err(reason) = self.send_text(ctx, room.clone(), text) {
+                    self.push_toast(ctx, reason);
+                    return true;
+                }
+                self.selecting = false;
+                self.selected.clear();
+                self.push_toast(ctx, format!("Forwarded to {}", room_label(&room)));
+                true
+            }
+            Msg::DeleteSelected => {
+                let ids: Vec<u64> = self.selected_messages().iter().filter_map(|m| m.id).collect();
+                let confirmed = web_sys::window()
+                    .and_then(|w| w.confirm_with_message(&format!("Delete {} messages for everyone?", ids.len())).ok())
+                    .unwrap_or(false);
+                if !confirmed {
+                    return false;
+                }
+                for id in ids {
+                    self.delete_message(id);
+                }
+                self.selecting = false;
+                self.selected.clear();
+                true
+            }
+            Msg::TogglePins => {
+                if !self.capabilities.has(Feature::Pins) {
+                    return false;
+                }
+                self.show_pins = !self.show_pins;
+                self.show_leaderboard &= !self.show_pins;
+                self.show_starred &= !self.show_pins;
+                self.show_gallery &= !self.show_pins;
+                self.show_members &= !self.show_pins;
+                if self.show_pins {
+                    self.thread = None;
+                }
+                true
+            }
+            Msg::ToggleLeaderboard => {
+                self.show_leaderboard = !self.show_leaderboard;
+                self.show_pins &= !self.show_leaderboard;
+                self.show_starred &= !self.show_leaderboard;
+                self.show_gallery &= !self.show_leaderboard;
+                self.show_members &= !self.show_leaderboard;
+                if self.show_leaderboard {
+                    self.thread = None;
+                }
+                true
+            }
+            Msg::ToggleStarred => {
+                self.show_starred = !self.show_starred;
+                self.show_pins &= !self.show_starred;
+                self.show_leaderboard &= !self.show_starred;
+                self.show_gallery &= !self.show_starred;
+                self.show_members &= !self.show_starred;
+                if self.show_starred {
+                    self.thread = None;
+                }
+                true
+            }
+            Msg::ToggleGallery => {
+                self.show_gallery = !self.show_gallery;
+                self.show_pins &= !self.show_gallery;
+                self.show_leaderboard &= !self.show_gallery;
+                self.show_starred &= !self.show_gallery;
+                self.show_members &= !self.show_gallery;
+                if self.show_gallery {
+                    self.thread = None;
+                }
+                true
+            }
+            Msg::ToggleMembers => {
+                self.show_members = !self.show_members;
+                self.show_pins &= !self.show_members;
+                self.show_leaderboard &= !self.show_members;
+                self.show_starred &= !self.show_members;
+                self.show_gallery &= !self.show_members;
+                if self.show_members {
+                    self.thread = None;
+                    let room = self.active_room.clone();
+                    self.request_members(&room, 0);
+                }
+                true
+            }
+            Msg::ToggleInvite => {
+                self.show_invite = !self.show_invite;
+                true
+            }
+            Msg::ToggleAnnounce => {
+                self.show_announce = !self.show_announce;
+                true
+            }
+            Msg::SendAnnouncement(text, sticky) => {
+                self.show_announce = false;
+                if text.is_empty() || self.is_archived(&self.active_room) {
+                    return true;
+                }
+                let request = AnnouncementRequest { text, sticky };
+                self.send_frame(WebSocketMessage {
+                    room: Some(self.active_room.clone()),
+                    data: serde_json::to_string(&request).ok(),
+                    ..WebSocketMessage::new(MsgTypes::Announce)
+                });
+                true
+            }
+            Msg::DismissAnnouncement(id) => {
+                self.dismissed_announcements.dismiss(id);
+                true
+            }
+            Msg::TogglePrivacy => {
+                self.show_privacy = !self.show_privacy;
+                true
+            }
+            Msg::SetPrivacy(room, privacy) => {
+                self.settings.set_privacy(&room, privacy);
+                self.settings.save();
+                true
+            }
+            Msg::CopyInvite(url) => {
+                copy_text(ctx, url, "Invite link copied".to_string());
+                false
+            }
+            Msg::LoadMoreMembers => {
+                let room = self.active_room.clone();
+                match self.members.get(&room).and_then(MemberList::next_offset) {
+                    Some(offset) => {
+                        self.request_members(&room, offset);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            Msg::StarMessage(id) => {
+                if self.starred.contains(id) {
+                    self.starred.unstar(id);
+                    return true;
+                }
+                let m = match self.messages.iter().find(|m| m.id == Some(id)) {
+                    Some(m) => m,
+                    None => return false,
+                };
+                self.starred.star(StarredMessage {
+                    id,
+                    room: m.room.clone(),
+                    from: m.from.clone(),
+                    text: m.message.clone(),
+                    starred_at: js_sys::Date::now(),
+                });
+                true
+            }
+            Msg::JumpToStarred(id) => {
+                let room = match self.messages.iter().find(|m| m.id == Some(id)) {
+                    Some(m) => m.room.clone(),
+                    None => {
+                        self.push_toast(ctx, "That message isn't loaded any more. Scroll back through its room to find it.".to_string());
+                        return true;
+                    }
+                };
+                if !self.rooms.iter().any(|r| r.name == room) {
+                    self.push_toast(ctx, format!("Join {} to go back to that message.", room_label(&room)));
+                    return true;
+                }
+                self.update(ctx, Msg::SwitchRoom(room));
+                // Opening its thread, if it's a reply, closes this panel.
+                self.update(ctx, Msg::ScrollToMessage(id))
+            }
+            Msg::OpenThread(id) => {
+                if !self.capabilities.has(Feature::Threads) {
+                    return false;
+                }
+                self.thread = Some(id);
+                self.show_pins = false;
+                self.show_leaderboard = false;
+                self.show_starred = false;
+                self.show_gallery = false;
+                self.show_members = false;
+                true
+            }
+            Msg::ToggleSplit => {
+                if self.split_room.take().is_some() {
+                    return true;
+                }
+                let other = self
+                    .rooms
+                    .iter()
+                    .filter(|r| !r.pending && r.name != self.active_room)
+                    .max_by(|a, b| self.recent.room_weight(&a.name).total_cmp(&self.recent.room_weight(&b.name)));
+                match other {
+                    Some(room) => self.split_room = Some(room.name.clone()),
+                    None => self.push_toast(ctx, "Join another room or start a conversation to split the view.".to_string()),
+                }
+                true
+            }
+            Msg::OpenSplit(room) => {
+                if room == self.active_room || !self.rooms.iter().any(|r| r.name == room) {
+                    return false;
+                }
+                self.split_room = Some(room);
+                true
+            }
+            Msg::SubmitSplit => {
+                let (room, input) = match (self.split_room.clone(), self.split_input.cast::<HtmlTextAreaElement>()) {
+                    (Some(room), Some(input)) => (room, input),
+                    _ => return false,
+                };
+                if self.send_cooldown_in(&room) > 0 || self.is_archived(&room) || input.value().trim().is_empty() {
+                    return false;
+                }
+                if !self.confirm_filtered(&input.value()) {
+                    return false;
+                }
+                match self.send_text(ctx, room, input.value()) {
+                    Ok(()) => input.set_value(""),
+                    Err(reason) => self.push_toast(ctx, reason),
+                }
+                true
+            }
+            Msg::CloseThread => {
+                self.thread = None;
+                true
+            }
+            Msg::NotifyThread => {
+                let root = match self.thread.and_then(|id| self.messages.iter().find(|m| m.id == Some(id))) {
+                    Some(root) => root,
+                    None => return false,
+                };
+                if self.thread_participants(root).is_empty() {
+                    return false;
+                }
+                // The server says how many it reached, or why it didn't.
+                self.send_frame(WebSocketMessage {
+                    room: Some(root.room.clone()),
+                    id: root.id,
+                    ..WebSocketMessage::new(MsgTypes::Notify)
+                });
+                false
+            }
+            Msg::SubmitThreadReply => {
+                let (root, input) = match (self.thread, self.thread_input.cast::<HtmlTextAreaElement>()) {
+                    (Some(root), Some(input)) => (root, input),
+                    _ => return false,
+                };
+                let room = match self.messages.iter().find(|m| m.id == Some(root)) {
+                    Some(m) => m.room.clone(),
+                    None => return false,
+                };
+                if self.send_cooldown() > 0 || input.value().trim().is_empty() || !self.confirm_filtered(&input.value()) {
+                    return false;
+                }
+                match self.send_text_with(ctx, room, input.value(), Some(root), None) {
+                    Ok(()) => input.set_value(""),
+                    Err(reason) => self.push_toast(ctx, reason),
+                }
+                true
+            }
+            Msg::PinMessage(id) => {
+                let room = self.room_of(id);
+                let pins = self.pins.entry(room.clone()).or_default();
+                if pins.contains(&id) {
+                    return false;
+                }
+                if pins.len() >= self.pin_limit {
+                    let text = format!(
+                        "#{} already has {} pins, the most allowed. Unpin one to make room.",
+                        room, self.pin_limit
+                    );
+                    self.push_toast(ctx, text);
+                    return true;
+                }
+                pins.push(id);
+                self.send_frame(WebSocketMessage {
+                    id: Some(id),
+                    room: Some(room),
+                    ..WebSocketMessage::new(MsgTypes::Pin)
+                });
+                true
+            }
+            Msg::UnpinMessage(id) => {
+                let room = self.room_of(id);
+                self.pins.entry(room.clone()).or_default().retain(|p| *p != id);
+                self.send_frame(WebSocketMessage {
+                    id: Some(id),
+                    room: Some(room),
+                    ..WebSocketMessage::new(MsgTypes::Unpin)
+                });
+                true
+            }
+            Msg::ReorderPins(ids) => {
+                let room = self.active_room.clone();
+                self.send_frame(WebSocketMessage {
+                    room: Some(room.clone()),
+                    data_array: Some(ids.iter().map(u64::to_string).collect()),
+                    ..WebSocketMessage::new(MsgTypes::Pins)
+                });
+                self.pins.insert(room, ids);
+                true
+            }
+            Msg::TogglePalette => {
+                self.show_palette = !self.show_palette;
+                true
+            }
+            Msg::PalettePick(item) => {
+                self.show_palette = false;
+                match item {
+                    PaletteItem::Room(name) | PaletteItem::JoinRoom(name) => {
+                        ctx.link().send_message(Msg::JoinRoom(name));
+                    }
+                    PaletteItem::User(name) => {
+                        self.update(ctx, Msg::OpenDirect(name));
+                    }
+                    PaletteItem::Mention(name) => {
+                        if let Some(input) = ComposerInput::find(&self.chat_input) {
+                            let current = input.value();
+                            let separator = if current.is_empty() || current.ends_with(' ') { "" } else { " " };
+                            input.set_value(&format!("{}{}@{} ", current, separator, name));
+                            input.focus();
+                        }
+                        self.recent.message_user(&name);
+                    }
+                    PaletteItem::Template(name) => {
+                        if let Some(input) = ComposerInput::find(&self.chat_input) {
+                            self.use_template(ctx, &input, &name);
+                            input.focus();
+                        }
+                    }
+                }
+                true
+            }
+            Msg::RestoreUnsent => {
+                let unsent = match self.restore.take() {
+                    Some(unsent) => unsent,
+                    None => return false,
+                };
+                for room in std::iter::once(&unsent.room).chain(unsent.pending.iter().map(|m| &m.room)) {
+                    if !self.rooms.iter().any(|r| r.name == *room) {
+                        self.rooms.push(Room::new(room));
+                        self.request_history(room, None);
+                    }
+                }
+                // Resent under their old client IDs, so anything the server
+                // did get before the tab went away isn't stored twice.
+                for m in unsent.pending {
+                    let client_id = m.client_id.unwrap_or_else(|| self.take_client_id());
+                    let mut message_data = MessageData {
+                        from: self.username.clone(),
+                        message: m.text,
+                        room: m.room,
+                        client_id: Some(client_id),
+                        status: DeliveryStatus::Sending,
+                        id: None,
+                        time: Some(m.time),
+                        edited: false,
+                        deleted: false,
+                        lang: None,
+                        file: None,
+                        parent_id: None,
+                        reply_to: None,
+                        announcement: false,
+                        sticky: false,
+                    };
+                    message_data.detect_language();
+                    message_data.status = self.send_chat_message(ctx, &message_data);
+                    self.messages.push(message_data);
+                }
+                if let Some(input) = ComposerInput::find(&self.chat_input) {
+                    input.set_value(&unsent.draft);
+                }
+                ctx.link().send_message(Msg::SwitchRoom(unsent.room));
+                true
+            }
+            Msg::PluginButton(index, id) => {
+                let (plugin, input) = match (self.plugins.get(index), ComposerInput::find(&self.chat_input)) {
+                    (Some(plugin), Some(input)) => (plugin.clone(), input),
+                    _ => return false,
                 };
-                false
+                let outcome = plugin.on_button(id, &self.command_context());
+                self.apply_plugin_outcome(ctx, &input, outcome);
+                true
+            }
+            Msg::ShowHeld => {
+                let (shown, held): (Vec<MessageData>, Vec<MessageData>) = std::mem::take(&mut self.held)
+                    .into_iter()
+                    .partition(|m| m.room == self.active_room);
+                self.held = held;
+                for message in shown {
+                    self.insert_message(message);
+                }
+                true
+            }
+            Msg::DiscardUnsent => {
+                self.restore = None;
+                true
+            }
+            Msg::SlowModeTick => {
+                if self.send_cooldown() == 0 {
+                    self.slow_mode_tick = None;
+                }
+                true
             }
             Msg::ChangeTheme(theme) => {
-                self.current_theme = theme;
+                self.settings.theme = theme;
+                self.settings.save();
+                self.onboarding.complete(Step::ChangeTheme);
                 true
             },
             
@@ -175,7 +4695,7 @@ impl Component for Chat {
             },
             
             Msg::AddEmoji(emoji) => {
-                let input = self.chat_input.cast::<HtmlInputElement>();
+                let input = ComposerInput::find(&self.chat_input);
                 if let Some(input) = input {
                     let current_value = input.value();
                     input.set_value(&format!("{} {}", current_value, emoji));
@@ -184,17 +4704,461 @@ impl Component for Chat {
                 true
             },
             
-            Msg::AddReaction(msg_idx, emoji) => {
-                let reactions = self.message_reactions.entry(msg_idx).or_insert_with(HashMap::new);
+            Msg::AddReaction(id, emoji) => {
+                if !self.capabilities.has(Feature::Reactions) {
+                    return false;
+                }
+                self.send_frame(WebSocketMessage {
+                    id: Some(id),
+                    data: Some(emoji.clone()),
+                    ..WebSocketMessage::new(MsgTypes::Reaction)
+                });
+                let reactions = self.message_reactions.entry(id).or_default();
                 let count = reactions.entry(emoji).or_insert(0);
                 *count += 1;
+                *self.reactors.entry(id).or_default().entry(self.username.clone()).or_insert(0) += 1;
+                self.onboarding.complete(Step::AddReaction);
+                true
+            },
+
+            Msg::SwitchRoom(name) => {
+                if name != self.active_room {
+                    self.replying_to = None;
+                    self.thread = None;
+                    self.jumping_to = None;
+                    self.show_privacy = false;
+                }
+                if self.rooms.iter().any(|r| r.name == name) {
+                    self.recent.visit_room(&name);
+                    if self.show_members && name != self.active_room {
+                        // A direct conversation has no member list to show.
+                        self.show_members = dm_peer(&name).is_none();
+                        if self.show_members {
+                            self.request_members(&name, 0);
+                        }
+                    }
+                    // Picking the room in the second pane swaps the two.
+                    if self.split_room.as_ref() == Some(&name) {
+                        self.split_room = Some(self.active_room.clone());
+                    }
+                    self.active_room = name;
+                }
+                self.start_slow_mode_tick(ctx);
+                true
+            },
+
+            Msg::JoinRoom(name) => {
+                if !self.rooms.iter().any(|r| r.name == name) {
+                    self.rooms.push(Room::new(&name));
+                    self.request_history(&name, None);
+                    self.onboarding.complete(Step::JoinRoom);
+                }
+                ctx.link().send_message(Msg::SwitchRoom(name));
+                false
+            },
+
+            Msg::FlushNotifications => {
+                let burst = self.notifier.flush(&self.settings.quiet_hours);
+                if burst.sound {
+                    play_chime();
+                }
+                let shown = !burst.toasts.is_empty();
+                for text in burst.toasts {
+                    self.push_toast(ctx, text);
+                }
+                shown
+            },
+
+            Msg::JumpToMissed => {
+                let room = self
+                    .first_missed
+                    .and_then(|id| self.messages.iter().find(|m| m.id == Some(id)))
+                    .map(|m| m.room.clone());
+                if let Some(room) = room {
+                    ctx.link().send_message(Msg::SwitchRoom(room));
+                    self.scroll_to_missed = true;
+                }
+                false
+            }
+            Msg::ShowToast(text) => {
+                self.push_toast(ctx, text);
+                true
+            }
+            Msg::DismissToast(id) => {
+                self.toasts.retain(|t| t.id != id);
+                true
+            },
+
+            Msg::ToggleSettings => {
+                self.show_settings = !self.show_settings;
+                if self.show_settings {
+                    self.send_frame(WebSocketMessage::new(MsgTypes::Devices));
+                    ctx.link().send_message(Msg::CheckStorage);
+                }
+                true
+            },
+
+            Msg::CheckStorage => {
+                ctx.link().send_future(async { Msg::StorageEstimated(storage::estimate().await) });
+                false
+            },
+
+            Msg::StorageEstimated(estimate) => {
+                self.storage = estimate;
+                self.warn_storage(ctx);
+                self.show_settings
+            },
+
+            Msg::PruneRoom(room) => {
+                let (messages, files) = self.prune_room(&room);
+                let text = match (messages, files) {
+                    (0, 0) => format!("Nothing to clear in {}.", room_label(&room)),
+                    (messages, 0) => format!("Cleared {} old messages from {}.", messages, room_label(&room)),
+                    (messages, files) => format!("Cleared {} old messages and {} files from {}.", messages, files, room_label(&room)),
+                };
+                self.push_toast(ctx, text);
+                ctx.link().send_message(Msg::CheckStorage);
+                true
+            },
+
+            Msg::VerifyDevice => {
+                let request = match &self.verification {
+                    Some(request) if !self.verifying => request.clone(),
+                    _ => return false,
+                };
+                self.verifying = true;
+                self.verification_error = None;
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    link.send_message(Msg::DeviceVerified(webauthn::verify(&request).await));
+                });
+                true
+            },
+
+            // Stays busy until the server accepts or asks again.
+            Msg::DeviceVerified(Ok(assertion)) => {
+                self.send_frame(WebSocketMessage {
+                    data: serde_json::to_string(&assertion).ok(),
+                    ..WebSocketMessage::new(MsgTypes::Verify)
+                });
+                false
+            },
+
+            Msg::DeviceVerified(Err(error)) => {
+                self.verifying = false;
+                self.verification_error = Some(error);
+                true
+            },
+
+            Msg::AddDevice(name) => {
+                self.enrolling = Some(name);
+                self.send_frame(WebSocketMessage::new(MsgTypes::Enroll));
+                false
+            },
+
+            Msg::DeviceEnrolled(Ok(device)) => {
+                self.send_frame(WebSocketMessage {
+                    data: serde_json::to_string(&device).ok(),
+                    ..WebSocketMessage::new(MsgTypes::Enroll)
+                });
+                false
+            },
+
+            Msg::DeviceEnrolled(Err(error)) => {
+                self.push_toast(ctx, format!("⚠️ {}", error));
+                true
+            },
+
+            Msg::TranslationEndpoint(url) => {
+                self.translate_url = url;
+                self.translate_url.is_some()
+            },
+
+            Msg::Translate(id) => {
+                match self.translations.get(&id) {
+                    Some(Translation::Pending(_)) => return false,
+                    Some(Translation::Done(_)) => {
+                        self.translations.remove(&id);
+                        return true;
+                    }
+                    None => {}
+                }
+                let (url, message) = match (&self.translate_url, self.messages.iter().find(|m| m.id == Some(id))) {
+                    (Some(url), Some(message)) => (url.clone(), message),
+                    _ => return false,
+                };
+                let text = message.message.clone();
+                let source = message.lang;
+                let target = self.user_language.clone();
+                let glossary = self.settings.translation_glossary.clone();
+                self.translations.insert(id, Translation::Pending(target.clone()));
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let result = translate::translate(&url, &text, source, &target, &glossary).await;
+                    link.send_message(Msg::Translated(id, target, result));
+                });
+                true
+            },
+
+            Msg::Translated(id, language, result) => {
+                // Hidden, edited or asked again in another language meanwhile.
+                if self.translations.get(&id) != Some(&Translation::Pending(language)) {
+                    return false;
+                }
+                match result {
+                    Ok(text) => {
+                        self.translations.insert(id, Translation::Done(text));
+                    }
+                    Err(error) => {
+                        self.translations.remove(&id);
+                        self.push_toast(ctx, format!("⚠️ {}", error));
+                    }
+                }
+                true
+            },
+
+            Msg::StartTranscript => {
+                let link = ctx.link().clone();
+                let on_error = ctx.link().callback(Msg::TranscriptFailed);
+                spawn_local(async move {
+                    link.send_message(Msg::TranscriptStarted(Transcript::start(on_error).await));
+                });
+                false
+            },
+
+            Msg::TranscriptStarted(Ok(transcript)) => {
+                if transcript.is_some() {
+                    self.transcript = transcript;
+                }
+                true
+            },
+
+            Msg::TranscriptStarted(Err(error)) | Msg::TranscriptFailed(error) => {
+                self.transcript = None;
+                self.push_toast(ctx, format!("⚠️ Transcript stopped: {}", error));
+                true
+            },
+
+            Msg::StopTranscript => {
+                self.transcript = None;
+                true
+            },
+
+            Msg::ExportHistory => {
+                let date = String::from(js_sys::Date::new_0().to_iso_string());
+                let filename = format!("yewchat-history-{}.json", &date[..10]);
+                files::download(&filename, "application/json", &self.archive().export());
+                false
+            },
+
+            Msg::ImportHistory(json) => {
+                let text = match Archive::import(&json) {
+                    Ok(archive) => match self.restore_archive(archive) {
+                        1 => "Imported 1 message.".to_string(),
+                        n => format!("Imported {} messages.", n),
+                    },
+                    Err(e) => format!("⚠️ Couldn't import history: {}", e),
+                };
+                self.push_toast(ctx, text);
+                true
+            },
+
+            Msg::RemoveDevice(id) => {
+                self.send_frame(WebSocketMessage {
+                    data: Some(id),
+                    ..WebSocketMessage::new(MsgTypes::Unenroll)
+                });
+                false
+            },
+
+            Msg::ToggleStats => {
+                self.show_stats = !self.show_stats;
+                true
+            },
+
+            Msg::ToggleTraffic => {
+                self.traffic = match self.traffic {
+                    Some(_) => None,
+                    None => Some(self.wss.traffic()),
+                };
+                true
+            },
+
+            Msg::CelebrateOnboarding => {
+                self.onboarding.celebrate();
+                true
+            },
+
+            Msg::DismissOnboarding => {
+                self.onboarding.dismiss();
+                true
+            },
+
+            Msg::ToggleFrameLog => {
+                self.frame_log = match self.frame_log {
+                    Some(_) => None,
+                    None => Some(self.wss.frame_log()),
+                };
+                true
+            },
+
+            Msg::RefreshFrameLog => {
+                self.frame_log = Some(self.wss.frame_log());
                 true
             },
+
+            Msg::RefreshTraffic => {
+                self.traffic = Some(self.wss.traffic());
+                true
+            },
+
+            Msg::ToggleReport => {
+                self.show_report = !self.show_report;
+                if self.show_report {
+                    self.show_settings = false;
+                }
+                true
+            },
+
+            Msg::DownloadReport(count, redact) => {
+                let frames = self.wss.frame_log();
+                let report = Report {
+                    protocol_version: PROTOCOL_VERSION,
+                    endpoint: self.endpoint.as_deref(),
+                    connection: format!("{:?}", self.connection),
+                    settings: &self.settings,
+                    frames: &frames[frames.len().saturating_sub(count)..],
+                    logs: logbook::lines(),
+                    redact,
+                };
+                let date = String::from(js_sys::Date::new_0().to_iso_string());
+                let filename = format!("yewchat-report-{}.json", date.replace(':', "-"));
+                files::download(&filename, "application/json", &report.to_json());
+                self.show_report = false;
+                true
+            },
+
+            Msg::UpdateSettings(settings) => {
+                settings.save();
+                if settings.display_language != self.settings.display_language
+                    || settings.translation_glossary != self.settings.translation_glossary
+                {
+                    self.user_language = settings.language();
+                    self.translations.clear();
+                }
+                if settings.theme != self.settings.theme {
+                    self.onboarding.complete(Step::ChangeTheme);
+                }
+                if settings.watch_keywords != self.settings.watch_keywords {
+                    let me = &self.username;
+                    let stored = self
+                        .messages
+                        .iter()
+                        .filter(|m| m.from != *me && !settings.is_blocked(&m.from))
+                        .filter_map(|m| Some((m.id?, m.message.as_str())));
+                    self.watchlist.rescan(&settings.watch_keywords, stored);
+                }
+                self.settings = *settings;
+                true
+            },
+
+            Msg::ToggleMute(user) => {
+                self.settings.toggle_muted(&user);
+                self.settings.save();
+                true
+            },
+
+            Msg::ToggleBlock(user) => {
+                self.settings.toggle_blocked(&user);
+                self.settings.save();
+                self.typing.remove(&user);
+                true
+            },
+
+            Msg::RevealBlocked(id) => self.revealed.insert(id),
+
+            Msg::ToggleRoomMute(room) => {
+                self.settings.toggle_room_muted(&room);
+                self.settings.save();
+                true
+            },
+        }
+    }
+    fn rendered(&mut self, ctx: &Context<Self>, _first_render: bool) {
+        if let Some((height, top)) = self.scroll_anchor.take() {
+            if let Some(list) = self.messages_ref.cast::<Element>() {
+                list.set_scroll_top(list.scroll_height() - height + top);
+            }
+        }
+        if let Some(id) = self.scroll_to_message.take() {
+            let message = web_sys::window()
+                .and_then(|w| w.document())
+                .and_then(|d| d.get_element_by_id(&message_anchor(id)));
+            if let Some(message) = message {
+                message.scroll_into_view();
+            }
         }
+        if std::mem::take(&mut self.scroll_to_missed) {
+            let divider = web_sys::window()
+                .and_then(|w| w.document())
+                .and_then(|d| d.get_element_by_id(MISSED_DIVIDER_ID));
+            if let Some(divider) = divider {
+                divider.scroll_into_view();
+            }
+        }
+        self.mark_read();
+        // Counting it as read changes the badges, so that's another render.
+        if self.unseen_in_view() {
+            ctx.link().send_message(Msg::MarkSeen);
+        }
+        self.persist_unsent();
+        self.update_title();
     }
+
     fn view(&self, ctx: &Context<Self>) -> Html {
+        if let Some(hello) = &self.incompatible {
+            return html! {
+                <Incompatible
+                    client_version={PROTOCOL_VERSION}
+                    server_version={hello.version}
+                    client_outdated={hello.min_client_version > PROTOCOL_VERSION}
+                />
+            };
+        }
+        if self.verification.is_some() {
+            return html! {
+                <DeviceVerification
+                    username={self.username.clone()}
+                    supported={webauthn::supported()}
+                    busy={self.verifying}
+                    error={self.verification_error.clone()}
+                    on_verify={ctx.link().callback(|_| Msg::VerifyDevice)}
+                />
+            };
+        }
+        if let Some((kind, removal)) = &self.removed {
+            return html! {
+                <Removed kind={*kind} reason={removal.reason.clone()} seconds={removal.seconds} by={removal.by.clone()} />
+            };
+        }
         let submit = ctx.link().callback(|_| Msg::SubmitMessage);
         let toggle_emoji = ctx.link().callback(|_| Msg::ToggleEmojiPicker);
+        let rooms = self.counted_rooms();
+        let room_muted = self.settings.is_room_muted(&self.active_room);
+        let direct: Vec<&Room> = rooms.iter().filter(|r| dm_peer(&r.name).is_some()).collect();
+        let selection = self.selected_messages();
+        let selected_count = selection.len();
+        let attach_file = ctx.link().batch_callback(|e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let file = input.files().and_then(|files| files.get(0));
+            // Cleared so picking the same file again still counts as a change.
+            input.set_value("");
+            file.map(Msg::AttachFile)
+        });
+        let select_room = ctx.link().callback(Msg::SwitchRoom);
+        let join_room = ctx.link().callback(Msg::JoinRoom);
+        let dismiss_toast = ctx.link().callback(Msg::DismissToast);
+        let toggle_settings = ctx.link().callback(|_| Msg::ToggleSettings);
         
         let theme_callback = ctx.link().callback(|e: Event| {
             let select = e.target_dyn_into::<HtmlSelectElement>().unwrap();
@@ -208,141 +5172,326 @@ impl Component for Chat {
             Msg::ChangeTheme(theme)
         });
 
-        let theme_classes = self.current_theme.get_css_classes();
+        let theme_classes = self.settings.theme.get_css_classes();
 
         let mut current_user = String::new();
-        let mut message_index = 0;
+        let slow_mode_remaining = self.slow_mode_remaining(&self.active_room);
+        let send_cooldown = self.send_cooldown();
+        let enter_key = self.settings.enter_key.clone();
+        let mention_chips = self.settings.mention_chips;
+        let chat_input = self.chat_input.clone();
+        // Handled here rather than in `update` so the new line can still be
+        // prevented.
+        let composer_keydown = ctx.link().batch_callback(move |e: KeyboardEvent| {
+            if e.key() != "Enter" || e.is_composing() {
+                return None;
+            }
+            if enter_key.sends(e.ctrl_key() || e.meta_key(), e.shift_key()) {
+                e.prevent_default();
+                return Some(Msg::SubmitMessage);
+            }
+            // Left to itself, the chip composer wraps new lines in markup.
+            if let (true, Some(ComposerInput::Chips(root))) = (mention_chips, ComposerInput::find(&chat_input)) {
+                e.prevent_default();
+                insert_text(&root, "\n");
+            }
+            None
+        });
+        let chat_input = self.chat_input.clone();
+        let composer_paste = Callback::from(move |e: Event| {
+            if let (Some(text), Some(ComposerInput::Chips(root))) = (pasted_text(&e), ComposerInput::find(&chat_input)) {
+                e.prevent_default();
+                insert_text(&root, &text);
+            }
+        });
+        let composer_placeholder = if slow_mode_remaining > 0 {
+            format!("Slow mode is on. You can send again in {}s", slow_mode_remaining)
+        } else if send_cooldown > 0 {
+            format!("You're sending quickly. You can send again in {}s", send_cooldown)
+        } else {
+            "Type a message...".to_string()
+        };
+        let seen_by = self.seen_by();
+        let reply_counts = self.reply_counts();
+        // Replies live in their thread, unless what they answer isn't loaded.
+        let loaded: HashSet<u64> = self.messages.iter().filter(|m| m.room == self.active_room).filter_map(|m| m.id).collect();
+        let (reacted_messages, reactors) = self.reaction_leaderboard();
+        let banners: Vec<Banner> = self
+            .banners
+            .iter()
+            .filter(|b| b.room.as_ref().is_none_or(|r| *r == self.active_room))
+            .cloned()
+            .collect();
 
         html! {
-            <div class={format!("flex w-screen {}", theme_classes)}>
+            <ContextProvider<ThemeTokens> context={self.settings.theme.tokens()}>
+            <div class={format!("flex w-screen {}{}", theme_classes, if self.settings.low_data { " low-data" } else { "" })}>
                 <div class="flex-none w-56 h-screen bg-opacity-90 bg-gray-100">
                     <div class="p-3 flex justify-between items-center">
                         <div class="text-xl">{"Users"}</div>
+                        <button onclick={toggle_settings} aria-label="Settings" class="px-2 py-1 rounded hover:bg-gray-200">{"⚙️"}</button>
+                        if self.settings.message_stats {
+                            <button onclick={ctx.link().callback(|_| Msg::ToggleStats)} aria-label="My stats" class="px-2 py-1 rounded hover:bg-gray-200">{"📊"}</button>
+                        }
+                        if self.settings.diagnostics {
+                            <button onclick={ctx.link().callback(|_| Msg::ToggleTraffic)} aria-label="Connection diagnostics" class="px-2 py-1 rounded hover:bg-gray-200">{"📡"}</button>
+                        }
                         <select onchange={theme_callback} class="px-2 py-1 rounded bg-white">
-                            <option value="light" selected={self.current_theme == Theme::Light}>{"☀️ Light"}</option>
-                            <option value="dark" selected={self.current_theme == Theme::Dark}>{"🌙 Dark"}</option>
-                            <option value="ocean" selected={self.current_theme == Theme::Ocean}>{"🌊 Ocean"}</option>
-                            <option value="forest" selected={self.current_theme == Theme::Forest}>{"🌲 Forest"}</option>
+                            <option value="light" selected={self.settings.theme == Theme::Light}>{"☀️ Light"}</option>
+                            <option value="dark" selected={self.settings.theme == Theme::Dark}>{"🌙 Dark"}</option>
+                            <option value="ocean" selected={self.settings.theme == Theme::Ocean}>{"🌊 Ocean"}</option>
+                            <option value="forest" selected={self.settings.theme == Theme::Forest}>{"🌲 Forest"}</option>
                         </select>
                     </div>
                     
                     <div class="overflow-y-auto max-h-[calc(100vh-80px)]">
-                        {
-                            self.users.clone().iter().map(|u| {
-                                html!{
-                                    <div class="flex m-3 bg-white rounded-lg p-2 shadow-sm hover:shadow-md transition-shadow duration-200">
-                                        <div>
-                                            <img class="w-12 h-12 rounded-full border-2 border-gray-200" src={u.avatar.clone()} alt="avatar"/>
-                                        </div>
-                                        <div class="flex-grow p-3">
-                                            <div class="flex text-xs justify-between font-bold">
-                                                <div>{u.name.clone()}</div>
-                                            </div>
-                                            <div class="text-xs text-gray-400">
-                                                {"Online"}
-                                            </div>
-                                        </div>
-                                    </div>
+                        if !direct.is_empty() {
+                            <div class="mx-3 mt-1">
+                                <div class="text-xs uppercase tracking-wide text-gray-500 mb-1">{"Direct messages"}</div>
+                                {
+                                    direct.iter().map(|room| {
+                                        let name = room.name.clone();
+                                        let active = room.name == self.active_room;
+                                        html! {
+                                            <button
+                                                onclick={ctx.link().callback(move |_| Msg::SwitchRoom(name.clone()))}
+                                                aria-current={active.then_some("true")}
+                                                class={classes!("flex", "w-full", "justify-between", "items-center", "px-2", "py-1", "rounded", "text-sm", if active { "bg-gray-300 font-bold" } else { "hover:bg-gray-200" })}
+                                            >
+                                                <span>{dm_peer(&room.name).unwrap_or_default().to_string()}</span>
+                                                {unread_badges(room)}
+                                            </button>
+                                        }
+                                    }).collect::<Html>()
                                 }
-                            }).collect::<Html>()
+                            </div>
                         }
+                        <UserList
+                            users={self.users.clone()}
+                            me={self.username.clone()}
+                            muted={self.settings.muted_users.clone()}
+                            low_data={self.settings.low_data}
+                            on_direct={ctx.link().callback(Msg::OpenDirect)}
+                            on_toggle_mute={ctx.link().callback(Msg::ToggleMute)}
+                            blocked={self.settings.blocked_users.clone()}
+                            on_toggle_block={ctx.link().callback(Msg::ToggleBlock)}
+                            role={self.role}
+                            on_remove={ctx.link().callback(|(name, kind)| Msg::Moderate(name, kind))}
+                        />
                     </div>
                 </div>
                 
                 <div class="grow h-screen flex flex-col">
                     <div class="w-full h-14 border-b-2 border-gray-300 flex items-center justify-between px-4">
-                        <div class="text-xl font-bold">{"💬 Chat Room"}</div>
-                        <div class="text-sm text-gray-500">{format!("{} Active Users", self.users.len())}</div>
+                        <div class="flex items-center min-w-0">
+                            <RoomSwitcher
+                                rooms={rooms.iter().filter(|r| dm_peer(&r.name).is_none()).cloned().collect::<Vec<_>>()}
+                                active={self.active_room.clone()}
+                                on_select={select_room}
+                                on_join={join_room}
+                                on_new={ctx.link().callback(|_| Msg::OpenNewRoom)}
+                            />
+                            if let Some(room) = self.rooms.iter().find(|r| r.name == self.active_room && dm_peer(&r.name).is_none()) {
+                                <RoomTopic
+                                    topic={room.description.clone()}
+                                    editable={self.can_manage(room)}
+                                    on_save={ctx.link().callback(Msg::SetTopic)}
+                                />
+                            }
+                        </div>
+                        <div class="flex items-center text-sm text-gray-500">
+                            {
+                                match self.connection {
+                                    ConnectionState::Connected => html! {},
+                                    ConnectionState::Reconnecting => html! {
+                                        <span class="mr-3 px-2 py-0.5 rounded-full bg-yellow-200 text-yellow-800 text-xs" role="status">{"Reconnecting…"}</span>
+                                    },
+                                    ConnectionState::Offline => html! {
+                                        <span class="mr-3 px-2 py-0.5 rounded-full bg-red-200 text-red-800 text-xs" role="status">{"Offline"}</span>
+                                    },
+                                }
+                            }
+                            if let Some(endpoint) = &self.endpoint {
+                                <span class="mr-3 text-xs text-gray-400" title="Chat server">
+                                    {endpoint.split_once("://").map_or(endpoint.as_str(), |(_, host)| host)}
+                                </span>
+                            }
+                            if self.capabilities.has(Feature::Pins) {
+                                <button
+                                    onclick={ctx.link().callback(|_| Msg::TogglePins)}
+                                    aria-label="Pinned messages"
+                                    aria-pressed={self.show_pins.to_string()}
+                                    class="mr-3 px-2 py-1 rounded hover:bg-gray-200"
+                                >
+                                    {format!("📌 {}", self.pins.get(&self.active_room).map_or(0, Vec::len))}
+                                </button>
+                            }
+                            <input
+                                type="date"
+                                max={today()}
+                                onchange={ctx.link().callback(|e: Event| Msg::GoToDate(e.target_unchecked_into::<HtmlInputElement>().value()))}
+                                aria-label="Go to date"
+                                title="Go to date"
+                                class="mr-3 px-1 py-0.5 text-xs rounded bg-transparent border border-gray-300"
+                            />
+                            if self.jumping_to.is_some() {
+                                <span class="mr-3 text-xs text-gray-500" role="status">{"Going back…"}</span>
+                            }
+                            <button
+                                onclick={ctx.link().callback(|_| Msg::ToggleSelecting)}
+                                aria-label="Select messages"
+                                aria-pressed={self.selecting.to_string()}
+                                class="mr-3 px-2 py-1 rounded hover:bg-gray-200"
+                            >
+                                {"☑️"}
+                            </button>
+                            if self.capabilities.has(Feature::Reactions) {
+                                <button
+                                    onclick={ctx.link().callback(|_| Msg::ToggleLeaderboard)}
+                                    aria-label="Reaction leaderboard"
+                                    aria-pressed={self.show_leaderboard.to_string()}
+                                    class="mr-3 px-2 py-1 rounded hover:bg-gray-200"
+                                >
+                                    {"🏆"}
+                                </button>
+                            }
+                            if self.capabilities.has(Feature::Announcements) && self.permissions().can_announce() && dm_peer(&self.active_room).is_none() {
+                                <button
+                                    onclick={ctx.link().callback(|_| Msg::ToggleAnnounce)}
+                                    aria-label={format!("Announce in {}", room_label(&self.active_room))}
+                                    class="mr-3 px-2 py-1 rounded hover:bg-gray-200"
+                                >
+                                    {"📢"}
+                                </button>
+                            }
+                            <button
+                                onclick={ctx.link().callback(|_| Msg::ToggleStarred)}
+                                aria-label="Starred messages"
+                                aria-pressed={self.show_starred.to_string()}
+                                class="mr-3 px-2 py-1 rounded hover:bg-gray-200"
+                            >
+                                {"⭐"}
+                            </button>
+                            <button
+                                onclick={ctx.link().callback(|_| Msg::ToggleGallery)}
+                                aria-label="Links, pictures and files shared here"
+                                aria-pressed={self.show_gallery.to_string()}
+                                class="mr-3 px-2 py-1 rounded hover:bg-gray-200"
+                            >
+                                {"🖼️"}
+                            </button>
+                            <button
+                                onclick={ctx.link().callback(|_| Msg::ToggleSplit)}
+                                aria-label="Split view"
+                                aria-pressed={self.split_room.is_some().to_string()}
+                                title="Show a second room side by side"
+                                class="hidden xl:inline-block mr-3 px-2 py-1 rounded hover:bg-gray-200"
+                            >
+                                {"◫"}
+                            </button>
+                            <button
+                                onclick={
+                                    let room = self.active_room.clone();
+                                    ctx.link().callback(move |_| Msg::ToggleRoomMute(room.clone()))
+                                }
+                                aria-label={format!("{} notifications from {}", if room_muted { "Unmute" } else { "Mute" }, room_label(&self.active_room))}
+                                aria-pressed={room_muted.to_string()}
+                                class="mr-3 px-2 py-1 rounded hover:bg-gray-200"
+                            >
+                                {if room_muted { "🔕" } else { "🔔" }}
+                            </button>
+                            {self.view_privacy_menu(ctx)}
+                            if dm_peer(&self.active_room).is_none() {
+                                <button
+                                    onclick={ctx.link().callback(|_| Msg::ToggleMembers)}
+                                    aria-label="Room members"
+                                    aria-pressed={self.show_members.to_string()}
+                                    class="mr-3 px-2 py-1 rounded hover:bg-gray-200"
+                                >
+                                    {"👥"}
+                                </button>
+                                <button
+                                    onclick={ctx.link().callback(|_| Msg::ToggleInvite)}
+                                    aria-label="Invite people"
+                                    title="Share a link or QR code to this room"
+                                    class="mr-3 px-2 py-1 rounded hover:bg-gray-200"
+                                >
+                                    {"🔗"}
+                                </button>
+                            }
+                            if let Some(room) = self.rooms.iter().find(|r| {
+                                r.name == self.active_room && !r.archived && r.name != DEFAULT_ROOM && dm_peer(&r.name).is_none()
+                            }) {
+                                if self.can_manage(room) {
+                                    <button
+                                        onclick={ctx.link().callback(|_| Msg::SetArchived(true))}
+                                        aria-label="Archive room"
+                                        title="Archive: hide it from the room list and stop new messages"
+                                        class="mr-3 px-2 py-1 rounded hover:bg-gray-200"
+                                    >
+                                        {"🗄️"}
+                                    </button>
+                                }
+                            }
+                            {self.view_latency()}
+                            {format!("{} Active Users", self.users.iter().filter(|u| u.status != PresenceStatus::Offline).count())}
+                        </div>
                     </div>
                     
-                    <div class="w-full grow overflow-auto border-b-2 border-gray-300 p-4">
+                    {self.view_health()}
+                    <div
+                        ref={self.messages_ref.clone()}
+                        onscroll={ctx.link().callback(|_: Event| Msg::MessagesScrolled)}
+                        class="w-full grow overflow-auto border-b-2 border-gray-300 p-4"
+                    >
+                        <Banners banners={banners} on_dismiss={ctx.link().callback(Msg::DismissBanner)} />
+                        {self.view_sticky_announcement(ctx)}
                         {
-                            self.messages.iter().map(|m| {
-                                let user_profile = self.users.iter()
-                                    .find(|u| u.name == m.from)
-                                    .cloned()
-                                    .unwrap_or_else(|| UserProfile {
-                                        name: m.from.clone(),
-                                        avatar: format!("https://avatars.dicebear.com/api/adventurer-neutral/{}.svg", m.from)
-                                    });
-                                
-                                let is_new_user = current_user != m.from;
-                                current_user = m.from.clone();
-                                
-                                let msg_idx = message_index;
-                                message_index += 1;
-                                
-                                let reactions = self.message_reactions.get(&msg_idx).cloned().unwrap_or_default();
-                                
-                                let add_reaction = ctx.link().callback(move |emoji: String| {
-                                    Msg::AddReaction(msg_idx, emoji)
-                                });
-                                
-                                html!{
-                                    <div class={if is_new_user { "mt-6" } else { "mt-1" }}>
-                                        if is_new_user {
-                                            <div class="flex items-center mb-1">
-                                                <img class="w-8 h-8 rounded-full mr-2" src={user_profile.avatar.clone()} alt="avatar"/>
-                                                <div class="font-medium">{user_profile.name.clone()}</div>
-                                            </div>
-                                        }
-                                        <div class={format!("flex flex-col ml-{}", if is_new_user { "0" } else { "10" })}>
-                                            <div class="max-w-3/4 bg-gray-100 p-3 rounded-lg shadow-sm">
-                                                if m.message.ends_with(".gif") {
-                                                    <img class="max-h-64 rounded" src={m.message.clone()}/>
-                                                } else {
-                                                    <div class="text-sm whitespace-pre-wrap break-words">
-                                                        {m.message.clone()}
-                                                    </div>
-                                                }
-                                            </div>
-                                            
-                                            if !reactions.is_empty() {
-                                                <div class="flex mt-1 ml-2 flex-wrap">
-                                                    {
-                                                        reactions.iter().map(|(emoji, count)| {
-                                                            let emoji_clone = emoji.clone();
-                                                            html! {
-                                                                <button 
-                                                                    onclick={add_reaction.reform(move |_| emoji_clone.clone())}
-                                                                    class="bg-gray-200 rounded-full px-2 py-1 text-xs mr-1 mb-1"
-                                                                >
-                                                                    {format!("{} {}", emoji, count)}
-                                                                </button>
-                                                            }
-                                                        }).collect::<Html>()
-                                                    }
+                            self.messages
+                                .iter()
+                                .filter(|m| m.room == self.active_room)
+                                .filter(|m| m.parent_id.is_none_or(|parent| !loaded.contains(&parent)))
+                                .map(|m| {
+                                    let is_new_user = current_user != m.from;
+                                    // A banner breaks the run; whoever writes next is named again.
+                                    current_user = if m.announcement { String::new() } else { m.from.clone() };
+                                    let replies = m.id.and_then(|id| reply_counts.get(&id)).copied().unwrap_or_default();
+                                    let message = self.view_message(ctx, m, is_new_user, m.id.and_then(|id| seen_by.get(&id)), replies, true);
+                                    if m.id.is_some() && m.id == self.first_missed {
+                                        html! {
+                                            <>
+                                                <div id={MISSED_DIVIDER_ID} key={MISSED_DIVIDER_ID} class="flex items-center my-4 text-xs text-red-500">
+                                                    <div class="grow border-t border-red-300"></div>
+                                                    <span class="px-2">{"While you were offline"}</span>
+                                                    <div class="grow border-t border-red-300"></div>
                                                 </div>
-                                            }
-                                            
-                                            <div class="flex mt-1 ml-2">
-                                                <button 
-                                                    onclick={add_reaction.reform(move |_| "👍".to_string())}
-                                                    class="text-gray-500 hover:text-gray-700 text-xs mr-2"
-                                                >
-                                                    {"👍"}
-                                                </button>
-                                                <button 
-                                                    onclick={add_reaction.reform(move |_| "❤️".to_string())}
-                                                    class="text-gray-500 hover:text-gray-700 text-xs mr-2"
-                                                >
-                                                    {"❤️"}
-                                                </button>
-                                                <button 
-                                                    onclick={add_reaction.reform(move |_| "😂".to_string())}
-                                                    class="text-gray-500 hover:text-gray-700 text-xs mr-2"
-                                                >
-                                                    {"😂"}
-                                                </button>
-                                            </div>
-                                        </div>
-                                    </div>
-                                }
-                            }).collect::<Html>()
+                                                {message}
+                                            </>
+                                        }
+                                    } else {
+                                        message
+                                    }
+                                })
+                                .collect::<Html>()
                         }
+                        {self.view_held(ctx)}
                     </div>
                     
                     <div class="w-full flex flex-col px-3 py-2 relative">
+                        {self.view_restore(ctx)}
+                        {self.view_reply(ctx)}
+                        {self.view_typing()}
+                        if self.selecting {
+                            <SelectionBar
+                                count={selected_count}
+                                rooms={self.rooms.iter().map(|r| r.name.clone()).collect::<Vec<_>>()}
+                                can_delete={selection.iter().all(|m| self.permissions().can_delete(&m.from))}
+                                on_copy={ctx.link().callback(|_| Msg::CopySelected)}
+                                on_forward={ctx.link().callback(Msg::ForwardSelected)}
+                                on_delete={ctx.link().callback(|_| Msg::DeleteSelected)}
+                                on_cancel={ctx.link().callback(|_| Msg::ToggleSelecting)}
+                            />
+                        }
                         if self.show_emoji_picker {
                             <div class="absolute bottom-16 right-5 bg-white shadow-lg rounded-lg p-2 w-64 h-48 overflow-auto">
                                 <div class="grid grid-cols-8 gap-1">
@@ -372,15 +5521,76 @@ impl Component for Chat {
                             </div>
                         }
                         
-                        <div class="flex items-center">
-                            <input 
-                                ref={self.chat_input.clone()} 
-                                type="text" 
-                                placeholder="Type a message..." 
-                                class="block w-full py-2 pl-4 mx-3 bg-gray-100 rounded-full outline-none focus:ring-2 focus:ring-blue-600" 
-                                name="message" 
-                                required=true 
-                            />
+                        if let Some(room) = self.rooms.iter().find(|r| r.name == self.active_room && r.archived) {
+                            <div role="status" class="flex items-center justify-between px-4 py-3 mx-3 rounded-lg bg-gray-100 text-sm text-gray-600">
+                                <span>{"🗄️ This room is archived. You can read it, but not post in it."}</span>
+                                if self.can_manage(room) {
+                                    <button
+                                        onclick={ctx.link().callback(|_| Msg::SetArchived(false))}
+                                        class="ml-3 px-3 py-1 rounded bg-blue-600 text-white hover:bg-blue-700"
+                                    >
+                                        {"Unarchive"}
+                                    </button>
+                                }
+                            </div>
+                        }
+                        // Hidden rather than removed in an archived room, so
+                        // the draft is still there on the way back.
+                        <div class={classes!("flex", "items-center", self.is_archived(&self.active_room).then_some("hidden"))}>
+                            if mention_chips {
+                                <div
+                                    ref={self.chat_input.clone()}
+                                    contenteditable={(send_cooldown == 0).to_string()}
+                                    role="textbox"
+                                    aria-multiline="true"
+                                    aria-label="Message"
+                                    aria-disabled={(send_cooldown > 0).to_string()}
+                                    data-placeholder={composer_placeholder}
+                                    onkeydown={composer_keydown}
+                                    onkeyup={ctx.link().callback(Msg::ComposerKeyUp)}
+                                    onpaste={composer_paste}
+                                    class="block w-full py-2 pl-4 pr-2 mx-3 max-h-40 overflow-y-auto whitespace-pre-wrap break-words bg-gray-100 rounded-3xl outline-none focus:ring-2 focus:ring-blue-600 empty:before:content-[attr(data-placeholder)] empty:before:text-gray-400"
+                                />
+                            } else {
+                                <textarea
+                                    ref={self.chat_input.clone()} 
+                                    onkeydown={composer_keydown}
+                                    onkeyup={ctx.link().callback(Msg::ComposerKeyUp)}
+                                    rows="1"
+                                    placeholder={composer_placeholder}
+                                    disabled={send_cooldown > 0}
+                                    class="block w-full py-2 pl-4 mx-3 bg-gray-100 rounded-full outline-none resize-none focus:ring-2 focus:ring-blue-600" 
+                                    name="message" 
+                                    required=true 
+                                />
+                            }
+                            {
+                                self.plugins
+                                    .iter()
+                                    .enumerate()
+                                    .flat_map(|(index, plugin)| plugin.composer_buttons().into_iter().map(move |b| (index, b)))
+                                    .map(|(index, button)| {
+                                        let id = button.id;
+                                        html! {
+                                            <button
+                                                onclick={ctx.link().callback(move |_| Msg::PluginButton(index, id))}
+                                                aria-label={button.title.clone()}
+                                                title={button.title}
+                                                class="p-3 bg-gray-200 rounded-full flex justify-center items-center mr-2 hover:bg-gray-300"
+                                            >
+                                                {button.label}
+                                            </button>
+                                        }
+                                    })
+                                    .collect::<Html>()
+                            }
+                            <label
+                                title="Send a file"
+                                class="p-3 bg-gray-200 rounded-full flex justify-center items-center mr-2 hover:bg-gray-300 cursor-pointer focus-within:ring-2 focus-within:ring-blue-600"
+                            >
+                                <input type="file" class="sr-only" aria-label="Send a file" onchange={attach_file} />
+                                {"📎"}
+                            </label>
                             <button 
                                 onclick={toggle_emoji} 
                                 class="p-3 bg-gray-200 rounded-full flex justify-center items-center mr-2 hover:bg-gray-300"
@@ -389,16 +5599,241 @@ impl Component for Chat {
                             </button>
                             <button 
                                 onclick={submit} 
-                                class="p-3 shadow-sm bg-blue-600 w-10 h-10 rounded-full flex justify-center items-center hover:bg-blue-700 transition-colors duration-200"
+                                disabled={send_cooldown > 0}
+                                aria-label={if send_cooldown > 0 { format!("Send, available in {}s", send_cooldown) } else { "Send".to_string() }}
+                                class={classes!(
+                                    "p-3", "shadow-sm", "w-10", "h-10", "rounded-full", "flex", "justify-center", "items-center", "transition-colors", "duration-200",
+                                    if send_cooldown > 0 { "bg-gray-400 cursor-not-allowed" } else { "bg-blue-600 hover:bg-blue-700" }
+                                )}
                             >
-                                <svg fill="#000000" viewBox="0 0 24 24" xmlns="http://www.w3.org/2000/svg" class="fill-white w-5 h-5">
-                                    <path d="M0 0h24v24H0z" fill="none"></path><path d="M2.01 21L23 12 2.01 3 2 10l15 2-15 2z"></path>
-                                </svg>
+                                if send_cooldown > 0 {
+                                    <span class="text-sm font-semibold text-white">{send_cooldown}</span>
+                                } else {
+                                    <svg fill="#000000" viewBox="0 0 24 24" xmlns="http://www.w3.org/2000/svg" class="fill-white w-5 h-5">
+                                        <path d="M0 0h24v24H0z" fill="none"></path><path d="M2.01 21L23 12 2.01 3 2 10l15 2-15 2z"></path>
+                                    </svg>
+                                }
                             </button>
                         </div>
                     </div>
                 </div>
+                {self.view_split(ctx, &reply_counts)}
+                {self.view_thread(ctx, &reply_counts)}
+                if self.show_pins {
+                    <PinnedPanel
+                        pins={self.pinned_entries()}
+                        limit={self.pin_limit}
+                        editable={self.can_pin_in(&self.active_room)}
+                        on_unpin={ctx.link().callback(Msg::UnpinMessage)}
+                        on_reorder={ctx.link().callback(Msg::ReorderPins)}
+                        on_close={ctx.link().callback(|_| Msg::TogglePins)}
+                    />
+                }
+                if self.show_starred {
+                    <StarredPanel
+                        starred={self.starred.list()}
+                        on_jump={ctx.link().callback(Msg::JumpToStarred)}
+                        on_unstar={ctx.link().callback(Msg::StarMessage)}
+                        on_close={ctx.link().callback(|_| Msg::ToggleStarred)}
+                    />
+                }
+                if self.show_gallery {
+                    <GalleryPanel
+                        items={self.shared_items()}
+                        low_data={self.settings.low_data}
+                        max_image_bytes={self.settings.media_auto_load_limit}
+                        on_jump={ctx.link().callback(Msg::ScrollToMessage)}
+                        on_open_link={ctx.link().callback(Msg::OpenLink)}
+                        on_close={ctx.link().callback(|_| Msg::ToggleGallery)}
+                    />
+                }
+                if self.show_members {
+                    if let Some(list) = self.members.get(&self.active_room) {
+                        <MembersPanel
+                            room={self.active_room.clone()}
+                            members={list.members.clone()}
+                            total={list.total}
+                            loading={list.loading}
+                            online={self.users.iter().filter(|u| u.status != PresenceStatus::Offline).map(|u| u.name.clone()).collect::<Vec<_>>()}
+                            on_more={ctx.link().callback(|_| Msg::LoadMoreMembers)}
+                            on_close={ctx.link().callback(|_| Msg::ToggleMembers)}
+                        />
+                    }
+                }
+                if self.show_leaderboard {
+                    <Leaderboard
+                        messages={reacted_messages}
+                        {reactors}
+                        on_close={ctx.link().callback(|_| Msg::ToggleLeaderboard)}
+                    />
+                }
+                <Toasts toasts={self.toasts.clone()} on_dismiss={dismiss_toast} />
+                if let Some((file, preview)) = &self.alt_prompt {
+                    <AltTextPrompt
+                        name={file.name()}
+                        preview={preview.clone()}
+                        on_send={ctx.link().callback(Msg::DescribeImage)}
+                        on_close={ctx.link().callback(|_| Msg::CancelImage)}
+                    />
+                }
+                if let Some(form) = &self.new_room {
+                    <NewRoomDialog
+                        name={form.name.clone()}
+                        description={form.description.clone()}
+                        error={form.error.clone()}
+                        taken={self.rooms.iter().map(|r| r.name.clone()).collect::<Vec<_>>()}
+                        on_create={ctx.link().callback(|(name, description)| Msg::CreateRoom(name, description))}
+                        on_close={ctx.link().callback(|_| Msg::CloseNewRoom)}
+                    />
+                }
+                if let Some((url, host)) = self.link_warning.clone() {
+                    <LinkWarning
+                        {url}
+                        {host}
+                        on_open={ctx.link().callback(Msg::FollowLink)}
+                        on_close={ctx.link().callback(|_| Msg::CloseLinkWarning)}
+                    />
+                }
+                if self.show_settings {
+                    <SettingsPanel
+                        settings={self.settings.clone()}
+                        on_change={ctx.link().callback(|settings| Msg::UpdateSettings(Box::new(settings)))}
+                        on_close={ctx.link().callback(|_| Msg::ToggleSettings)}
+                        devices={self.devices.clone()}
+                        on_add_device={ctx.link().callback(Msg::AddDevice)}
+                        on_remove_device={ctx.link().callback(Msg::RemoveDevice)}
+                        transcript={self.transcript.as_ref().map(|t| t.name().to_string())}
+                        on_transcript={ctx.link().callback(|on: bool| if on { Msg::StartTranscript } else { Msg::StopTranscript })}
+                        on_export_history={ctx.link().callback(|_| Msg::ExportHistory)}
+                        on_import_history={ctx.link().callback(Msg::ImportHistory)}
+                        storage={self.storage}
+                        room_usage={self.room_usage()}
+                        on_prune={ctx.link().callback(Msg::PruneRoom)}
+                        on_report={ctx.link().callback(|_| Msg::ToggleReport)}
+                    />
+                }
+                if self.show_palette {
+                    <CommandPalette
+                        items={self.palette_items()}
+                        recent={self.recent.clone()}
+                        on_pick={ctx.link().callback(Msg::PalettePick)}
+                        on_close={ctx.link().callback(|_| Msg::TogglePalette)}
+                    />
+                }
+                if self.show_stats && self.settings.message_stats {
+                    <StatsDialog
+                        sent={
+                            self.messages
+                                .iter()
+                                .filter(|m| m.from == self.username && m.status != DeliveryStatus::Failed && !m.deleted)
+                                .map(|m| SentMessage { text: m.message.clone(), time: m.time.unwrap_or_else(js_sys::Date::now) })
+                                .collect::<Vec<_>>()
+                        }
+                        on_close={ctx.link().callback(|_| Msg::ToggleStats)}
+                    />
+                }
+                if !self.onboarding.dismissed {
+                    <OnboardingChecklist
+                        progress={self.onboarding.clone()}
+                        on_celebrate={ctx.link().callback(|_| Msg::CelebrateOnboarding)}
+                        on_dismiss={ctx.link().callback(|_| Msg::DismissOnboarding)}
+                    />
+                }
+                if let Some(frames) = &self.frame_log {
+                    <FrameLogPanel
+                        frames={frames.clone()}
+                        on_refresh={ctx.link().callback(|_| Msg::RefreshFrameLog)}
+                        on_close={ctx.link().callback(|_| Msg::ToggleFrameLog)}
+                    />
+                }
+                if self.show_invite {
+                    <InviteDialog
+                        room={self.active_room.clone()}
+                        on_copy={ctx.link().callback(Msg::CopyInvite)}
+                        on_close={ctx.link().callback(|_| Msg::ToggleInvite)}
+                    />
+                }
+                if let Some((name, kind)) = &self.removing {
+                    <RemoveDialog
+                        name={name.clone()}
+                        kind={*kind}
+                        on_confirm={ctx.link().callback(|(reason, minutes)| Msg::ConfirmRemoval(reason, minutes))}
+                        on_close={ctx.link().callback(|_| Msg::CancelRemoval)}
+                    />
+                }
+                if self.show_announce {
+                    <AnnounceDialog
+                        room={self.active_room.clone()}
+                        on_send={ctx.link().callback(|(text, sticky)| Msg::SendAnnouncement(text, sticky))}
+                        on_close={ctx.link().callback(|_| Msg::ToggleAnnounce)}
+                    />
+                }
+                if self.show_report {
+                    <ReportDialog
+                        available={self.wss.frame_log().len()}
+                        on_download={ctx.link().callback(|(count, redact)| Msg::DownloadReport(count, redact))}
+                        on_close={ctx.link().callback(|_| Msg::ToggleReport)}
+                    />
+                }
+                if let (Some(stats), true) = (&self.traffic, self.settings.diagnostics) {
+                    <TrafficDialog
+                        stats={stats.clone()}
+                        on_refresh={ctx.link().callback(|_| Msg::RefreshTraffic)}
+                        on_close={ctx.link().callback(|_| Msg::ToggleTraffic)}
+                    />
+                }
             </div>
+            </ContextProvider<ThemeTokens>>
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn decodes_users_with_and_without_roles() {
+        let frame = json!({
+            "messageType": "users",
+            "dataArray": ["ann", "bob"],
+            "data": json!({ "ann": { "status": "away", "role": "moderator" }, "bob": "online" }).to_string(),
+        });
+        let (names, entries) = match ServerEvent::decode(&frame.to_string()) {
+            Ok(ServerEvent::Users { names, entries }) => (names, entries),
+            _ => panic!("not a users event"),
+        };
+        assert_eq!(names, ["ann", "bob"]);
+        assert_eq!(entries["ann"].status(), PresenceStatus::Away);
+        assert_eq!(entries["ann"].role(), Role::Moderator);
+        assert_eq!(entries["bob"].role(), Role::Member);
+    }
+
+    #[test]
+    fn decodes_messages_and_announcements() {
+        let stored = json!({ "id": 7, "from": "ann", "message": "hi there", "room": "rust", "clientId": "c1" }).to_string();
+        for (kind, announcement) in [("message", false), ("announce", true)] {
+            let frame = json!({ "messageType": kind, "data": stored }).to_string();
+            let message = match ServerEvent::decode(&frame) {
+                Ok(ServerEvent::Message(message)) => message,
+                _ => panic!("not a message event"),
+            };
+            assert_eq!((message.id, message.from.as_str(), message.room.as_str()), (Some(7), "ann", "rust"));
+            assert_eq!(message.client_id.as_deref(), Some("c1"));
+            assert_eq!(message.announcement, announcement);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn sends_messages_in_the_servers_shape() {
+        let frame = WebSocketMessage {
+            data: Some("hi".to_string()),
+            room: Some("rust".to_string()),
+            client_id: Some("c1".to_string()),
+            ..WebSocketMessage::new(MsgTypes::Message)
+        };
+        let sent: serde_json::Value = serde_json::from_str(&serde_json::to_string(&frame).unwrap()).unwrap();
+        assert_eq!(sent["messageType"], "message");
+        assert_eq!((&sent["data"], &sent["room"], &sent["clientId"]), (&json!("hi"), &json!("rust"), &json!("c1")));
+    }
+}
@@ -0,0 +1,229 @@
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::components::room_switcher::room_label;
+use crate::services::recent::Recent;
+
+// How much a fresh visit counts against match quality; a perfect prefix
+// match scores a little over 1.0.
+const RECENCY_WEIGHT: f64 = 0.75;
+const MAX_RESULTS: usize = 8;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PaletteItem {
+    Room(String),
+    JoinRoom(String),
+    /// Picked, opens the conversation with them.
+    User(String),
+    /// Puts `@name` in the composer: Shift+Enter on a `User`.
+    Mention(String),
+    Template(String),
+}
+
+impl PaletteItem {
+    fn label(&self) -> String {
+        match self {
+            PaletteItem::Room(name) => room_label(name),
+            PaletteItem::JoinRoom(name) => format!("Join #{}", name),
+            PaletteItem::User(name) | PaletteItem::Mention(name) => format!("@{}", name),
+            PaletteItem::Template(name) => format!("/template {}", name),
+        }
+    }
+
+    fn hint(&self) -> &str {
+        match self {
+            PaletteItem::Room(_) => "Switch room",
+            PaletteItem::JoinRoom(_) => "New room",
+            PaletteItem::User(_) => "Message · ⇧↵ mention",
+            PaletteItem::Mention(_) => "Mention",
+            PaletteItem::Template(_) => "Saved reply",
+        }
+    }
+
+    fn key(&self) -> &str {
+        match self {
+            PaletteItem::Room(s)
+            | PaletteItem::JoinRoom(s)
+            | PaletteItem::User(s)
+            | PaletteItem::Mention(s)
+            | PaletteItem::Template(s) => s,
+        }
+    }
+}
+
+/// Scores `candidate` against `query` as a case-insensitive subsequence,
+/// rewarding runs of consecutive characters and matches at word starts.
+/// `None` if the query doesn't match at all.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<f64> {
+    if query.is_empty() {
+        return Some(0.0);
+    }
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0.0;
+    let mut pos = 0;
+    let mut previous: Option<usize> = None;
+    for q in query.to_lowercase().chars() {
+        let found = (pos..candidate.len()).find(|&i| candidate[i] == q)?;
+        let word_start = found == 0 || !candidate[found - 1].is_alphanumeric();
+        score += if previous == Some(found.wrapping_sub(1)) {
+            1.0
+        } else if word_start {
+            0.8
+        } else {
+            0.3
+        };
+        previous = Some(found);
+        pos = found + 1;
+    }
+    // Normalise so short queries and long names compare fairly.
+    let query_len = query.chars().count() as f64;
+    Some(score / query_len + query_len / candidate.len().max(1) as f64 * 0.25)
+}
+
+fn rank(items: &[PaletteItem], query: &str, recent: &Recent) -> Vec<PaletteItem> {
+    let query = query.trim().trim_start_matches(['#', '@', '/']);
+    let mut scored: Vec<(f64, &PaletteItem)> = items
+        .iter()
+        .filter_map(|item| {
+            let score = fuzzy_score(query, item.key())?;
+            let recency = match item {
+                PaletteItem::Room(name) | PaletteItem::JoinRoom(name) => recent.room_weight(name),
+                PaletteItem::User(name) | PaletteItem::Mention(name) => recent.user_weight(name),
+                PaletteItem::Template(_) => 0.0,
+            };
+            Some((score + recency * RECENCY_WEIGHT, item))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    let mut ranked: Vec<PaletteItem> = scored.into_iter().map(|(_, item)| item.clone()).take(MAX_RESULTS).collect();
+    let exact_room = items
+        .iter()
+        .any(|i| matches!(i, PaletteItem::Room(name) | PaletteItem::JoinRoom(name) if name == query));
+    if !query.is_empty() && !exact_room {
+        ranked.push(PaletteItem::JoinRoom(query.to_lowercase()));
+    }
+    ranked
+}
+
+#[derive(Properties, PartialEq)]
+pub struct CommandPaletteProps {
+    pub items: Vec<PaletteItem>,
+    pub recent: Recent,
+    pub on_pick: Callback<PaletteItem>,
+    pub on_close: Callback<()>,
+}
+
+/// Quick switcher (Ctrl+K) over rooms, people and saved replies. Arrow
+/// keys move through the results and Enter picks one.
+#[function_component(CommandPalette)]
+pub fn command_palette(props: &CommandPaletteProps) -> Html {
+    let query = use_state(String::new);
+    let highlighted = use_state(|| 0usize);
+    let input_ref = use_node_ref();
+
+    {
+        let input_ref = input_ref.clone();
+        use_effect_with_deps(
+            move |_| {
+                if let Some(input) = input_ref.cast::<HtmlInputElement>() {
+                    let _ = input.focus();
+                }
+                || ()
+            },
+            (),
+        );
+    }
+
+    let results = rank(&props.items, &query, &props.recent);
+
+    let oninput = {
+        let query = query.clone();
+        let highlighted = highlighted.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            query.set(input.value());
+            highlighted.set(0);
+        })
+    };
+
+    let onkeydown = {
+        let highlighted = highlighted.clone();
+        let results = results.clone();
+        let on_pick = props.on_pick.clone();
+        let on_close = props.on_close.clone();
+        Callback::from(move |e: KeyboardEvent| {
+            let len = results.len().max(1);
+            match e.key().as_str() {
+                "ArrowDown" => {
+                    e.prevent_default();
+                    highlighted.set((*highlighted + 1) % len);
+                }
+                "ArrowUp" => {
+                    e.prevent_default();
+                    highlighted.set((*highlighted + len - 1) % len);
+                }
+                "Enter" => {
+                    e.prevent_default();
+                    match results.get(*highlighted) {
+                        Some(PaletteItem::User(name)) if e.shift_key() => on_pick.emit(PaletteItem::Mention(name.clone())),
+                        Some(item) => on_pick.emit(item.clone()),
+                        None => {}
+                    }
+                }
+                "Escape" => on_close.emit(()),
+                _ => {}
+            }
+        })
+    };
+
+    let close = props.on_close.reform(|_: MouseEvent| ());
+
+    html! {
+        <div class="fixed inset-0 bg-black bg-opacity-40 flex justify-center items-start pt-24 z-30" onclick={close}>
+            <div
+                role="dialog"
+                aria-modal="true"
+                aria-label="Command palette"
+                class="bg-white text-black rounded-lg shadow-xl w-96"
+                onclick={Callback::from(|e: MouseEvent| e.stop_propagation())}
+            >
+                <input
+                    ref={input_ref}
+                    type="text"
+                    {oninput}
+                    {onkeydown}
+                    value={(*query).clone()}
+                    role="combobox"
+                    aria-expanded="true"
+                    aria-controls="palette-results"
+                    aria-activedescendant={format!("palette-option-{}", *highlighted)}
+                    placeholder="Jump to a room, person or saved reply…"
+                    class="w-full px-4 py-3 rounded-t-lg outline-none border-b border-gray-200"
+                />
+                <ul id="palette-results" role="listbox" aria-label="Results" class="py-1 max-h-80 overflow-y-auto">
+                    {
+                        results.iter().enumerate().map(|(i, item)| {
+                            let onclick = {
+                                let item = item.clone();
+                                props.on_pick.reform(move |_: MouseEvent| item.clone())
+                            };
+                            let classes = if i == *highlighted { "bg-gray-200" } else { "hover:bg-gray-100" };
+                            html! {
+                                <li
+                                    id={format!("palette-option-{}", i)}
+                                    role="option"
+                                    aria-selected={(i == *highlighted).to_string()}
+                                    {onclick}
+                                    class={format!("flex justify-between px-4 py-2 cursor-pointer {}", classes)}
+                                >
+                                    <span>{item.label()}</span>
+                                    <span class="text-xs text-gray-400">{item.hint()}</span>
+                                </li>
+                            }
+                        }).collect::<Html>()
+                    }
+                </ul>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,177 @@
+use js_sys::Reflect;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{DataTransfer, Document, Element, Event, HtmlElement, HtmlTextAreaElement, Node, Range};
+use yew::NodeRef;
+
+// Marks a mention chip, holding the name it stands for.
+const MENTION_ATTR: &str = "data-mention";
+const CHIP_CLASSES: &str = "inline-block px-1 mx-px rounded bg-blue-100 text-blue-800 font-medium";
+
+/// Whether `c` can be part of a name after `@`.
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.')
+}
+
+/// The composer's input, whichever kind is showing: a plain textarea, or
+/// the contenteditable box that shows mentions as chips, which are deleted
+/// as a unit rather than a character at a time.
+pub enum ComposerInput {
+    Plain(HtmlTextAreaElement),
+    Chips(HtmlElement),
+}
+
+impl ComposerInput {
+    pub fn find(node: &NodeRef) -> Option<Self> {
+        match node.cast::<HtmlTextAreaElement>() {
+            Some(textarea) => Some(ComposerInput::Plain(textarea)),
+            None => node.cast::<HtmlElement>().map(ComposerInput::Chips),
+        }
+    }
+
+    /// What's been written, with chips back as `@name`.
+    pub fn value(&self) -> String {
+        match self {
+            ComposerInput::Plain(textarea) => textarea.value(),
+            ComposerInput::Chips(root) => {
+                let mut text = String::new();
+                text_of(root, &mut text);
+                // Line breaks at the end only hold the caret's line open.
+                text.trim_end_matches('\n').to_string()
+            }
+        }
+    }
+
+    /// Replaces what's written with `text`, turning each `@name` into a
+    /// chip, and leaves the caret at the end.
+    pub fn set_value(&self, text: &str) {
+        let root = match self {
+            ComposerInput::Plain(textarea) => return textarea.set_value(text),
+            ComposerInput::Chips(root) => root,
+        };
+        let document = match root.owner_document() {
+            Some(document) => document,
+            None => return,
+        };
+        root.set_inner_html("");
+        let mut plain = String::new();
+        let mut chars = text.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            let at_word_start = !plain.ends_with(is_name_char);
+            let name_len = text[i + c.len_utf8()..].chars().take_while(|c| is_name_char(*c)).map(char::len_utf8).sum::<usize>();
+            if c != '@' || !at_word_start || name_len == 0 {
+                plain.push(c);
+                continue;
+            }
+            let _ = root.append_child(&document.create_text_node(&std::mem::take(&mut plain)));
+            let name = &text[i + 1..i + 1 + name_len];
+            if let Some(chip) = chip(&document, name) {
+                let _ = root.append_child(&chip);
+            }
+            while chars.peek().is_some_and(|(j, _)| *j < i + 1 + name_len) {
+                chars.next();
+            }
+        }
+        let _ = root.append_child(&document.create_text_node(&plain));
+        if let Ok(range) = document.create_range() {
+            if range.select_node_contents(root).is_ok() {
+                range.collapse_with_to_start(false);
+                place_caret(&range);
+            }
+        }
+    }
+
+    pub fn focus(&self) {
+        let _ = match self {
+            ComposerInput::Plain(textarea) => textarea.focus(),
+            ComposerInput::Chips(root) => root.focus(),
+        };
+    }
+}
+
+/// `@name` as a chip the caret can't go into.
+fn chip(document: &Document, name: &str) -> Option<Element> {
+    let chip = document.create_element("span").ok()?;
+    chip.set_attribute(MENTION_ATTR, name).ok()?;
+    chip.set_attribute("contenteditable", "false").ok()?;
+    chip.set_class_name(CHIP_CLASSES);
+    chip.set_text_content(Some(&format!("@{}", name)));
+    Some(chip)
+}
+
+fn text_of(node: &Node, text: &mut String) {
+    let children = node.child_nodes();
+    for child in (0..children.length()).filter_map(|i| children.get(i)) {
+        let element = match child.dyn_ref::<Element>() {
+            Some(element) => element,
+            None => {
+                text.push_str(&child.text_content().unwrap_or_default());
+                continue;
+            }
+        };
+        if let Some(name) = element.get_attribute(MENTION_ATTR) {
+            text.push('@');
+            text.push_str(&name);
+        } else if element.tag_name() == "BR" {
+            text.push('\n');
+        } else {
+            // Browsers put each new line they make in a block of its own.
+            if element.tag_name() == "DIV" && !text.is_empty() && !text.ends_with('\n') {
+                text.push('\n');
+            }
+            text_of(&child, text);
+        }
+    }
+}
+
+/// Where the caret is in `root`, or the end of it if the caret is elsewhere.
+fn caret_range(root: &HtmlElement) -> Option<Range> {
+    let selection = web_sys::window()?.get_selection().ok()??;
+    if selection.range_count() > 0 {
+        let range = selection.get_range_at(0).ok()?;
+        if range.common_ancestor_container().is_ok_and(|node| root.contains(Some(&node))) {
+            return Some(range);
+        }
+    }
+    let range = root.owner_document()?.create_range().ok()?;
+    range.select_node_contents(root).ok()?;
+    range.collapse_with_to_start(false);
+    Some(range)
+}
+
+fn place_caret(range: &Range) {
+    if let Some(selection) = web_sys::window().and_then(|w| w.get_selection().ok().flatten()) {
+        let _ = selection.remove_all_ranges();
+        let _ = selection.add_range(range);
+    }
+}
+
+/// Puts `text` at the caret in the chip composer `root`, in place of
+/// anything selected, as plain text: pasted markup would otherwise come
+/// along with it.
+pub fn insert_text(root: &HtmlElement, text: &str) {
+    let (range, document) = match (caret_range(root), root.owner_document()) {
+        (Some(range), Some(document)) => (range, document),
+        _ => return,
+    };
+    let _ = range.delete_contents();
+    let node = document.create_text_node(text);
+    if range.insert_node(&node).is_err() {
+        return;
+    }
+    // A line break with nothing after it doesn't show until something
+    // follows, so the line is held open with another.
+    if text.ends_with('\n') && node.next_sibling().is_none() {
+        let _ = root.append_child(&document.create_text_node("\n"));
+    }
+    if range.set_start_after(&node).is_ok() {
+        range.collapse_with_to_start(true);
+        place_caret(&range);
+    }
+}
+
+/// The plain text a paste event carries. `ClipboardEvent` is behind
+/// web-sys's unstable APIs, so its data is looked up by name.
+pub fn pasted_text(e: &Event) -> Option<String> {
+    let data: DataTransfer = Reflect::get(e, &JsValue::from_str("clipboardData")).ok()?.dyn_into().ok()?;
+    data.get_data("text/plain").ok()
+}
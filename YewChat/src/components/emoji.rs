@@ -0,0 +1,103 @@
+use yew::prelude::*;
+
+use crate::services::settings::EmojiStyle;
+
+// Copied out of the @twemoji/svg package into the build by webpack, so
+// nothing is fetched from elsewhere. Relative, to follow the page's base.
+const TWEMOJI_BASE: &str = "twemoji";
+
+const ZWJ: char = '\u{200D}';
+const VARIATION_SELECTOR: char = '\u{FE0F}';
+
+enum Segment<'a> {
+    Text(&'a str),
+    Emoji(&'a str),
+}
+
+fn is_emoji(c: char) -> bool {
+    matches!(c as u32,
+        0x1F000..=0x1FAFF | 0x2600..=0x27BF | 0x2300..=0x23FF | 0x2B00..=0x2BFF | 0x3030 | 0x303D)
+}
+
+fn is_regional(c: char) -> bool {
+    matches!(c as u32, 0x1F1E6..=0x1F1FF)
+}
+
+fn is_modifier(c: char) -> bool {
+    c == VARIATION_SELECTOR || matches!(c as u32, 0x1F3FB..=0x1F3FF | 0xE0020..=0xE007F)
+}
+
+/// Splits `text` into plain runs and whole emoji sequences (modifiers and
+/// ZWJ joins included), so a family or a flag stays one glyph.
+fn segments(text: &str) -> Vec<Segment<'_>> {
+    let mut out = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    let mut text_start = 0;
+
+    while let Some((start, c)) = chars.next() {
+        if !is_emoji(c) {
+            continue;
+        }
+        let mut end = start + c.len_utf8();
+        while let Some(&(i, next)) = chars.peek() {
+            let joined = text[start..i].ends_with(ZWJ);
+            // Flags are a pair of regional indicators and nothing more.
+            let flag = is_regional(c) && is_regional(next) && i == start + c.len_utf8();
+            if is_modifier(next) || next == ZWJ || flag || (joined && is_emoji(next)) {
+                end = i + next.len_utf8();
+                chars.next();
+            } else {
+                break;
+            }
+        }
+        if text_start < start {
+            out.push(Segment::Text(&text[text_start..start]));
+        }
+        out.push(Segment::Emoji(text[start..end].trim_end_matches(ZWJ)));
+        text_start = end;
+    }
+    if text_start < text.len() {
+        out.push(Segment::Text(&text[text_start..]));
+    }
+    out
+}
+
+/// Every emoji sequence in `text`, in order of appearance.
+pub fn extract(text: &str) -> Vec<&str> {
+    segments(text)
+        .into_iter()
+        .filter_map(|segment| match segment {
+            Segment::Emoji(e) => Some(e),
+            Segment::Text(_) => None,
+        })
+        .collect()
+}
+
+fn twemoji_url(emoji: &str) -> String {
+    // Twemoji drops the variation selector from file names unless the
+    // sequence is ZWJ-joined.
+    let keep_vs = emoji.contains(ZWJ);
+    let codepoints: Vec<String> = emoji
+        .chars()
+        .filter(|&c| keep_vs || c != VARIATION_SELECTOR)
+        .map(|c| format!("{:x}", c as u32))
+        .collect();
+    format!("{}/{}.svg", TWEMOJI_BASE, codepoints.join("-"))
+}
+
+/// Renders message text, swapping emoji for images when the user prefers a
+/// consistent set over the platform's native glyphs.
+pub fn render(text: &str, style: &EmojiStyle) -> Html {
+    if *style == EmojiStyle::Native {
+        return html! { {text} };
+    }
+    segments(text)
+        .into_iter()
+        .map(|segment| match segment {
+            Segment::Text(t) => html! { {t} },
+            Segment::Emoji(e) => html! {
+                <img class="inline-block w-5 h-5 align-text-bottom" src={twemoji_url(e)} alt={e.to_string()} draggable="false"/>
+            },
+        })
+        .collect::<Html>()
+}
@@ -0,0 +1,78 @@
+use yew::prelude::*;
+
+use crate::components::media::LazyImage;
+
+#[derive(Properties, PartialEq)]
+pub struct FileCardProps {
+    pub name: String,
+    pub size: usize,
+    /// Chunks received, out of how many.
+    pub progress: (usize, usize),
+    /// Where to download it from, once it's all here.
+    pub url: Option<String>,
+    pub failed: bool,
+    /// Pictures are shown once they're here, described by `alt`.
+    #[prop_or_default]
+    pub image: bool,
+    #[prop_or_default]
+    pub alt: String,
+    /// Pictures larger than this wait to be asked for.
+    #[prop_or_default]
+    pub max_image_bytes: Option<usize>,
+}
+
+pub fn human_size(bytes: usize) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1_048_575 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+/// A file sent in the chat: its name and size, a progress bar while it
+/// arrives, then a download link.
+#[function_component(FileCard)]
+pub fn file_card(props: &FileCardProps) -> Html {
+    let (received, total) = props.progress;
+    let percent = (received * 100).checked_div(total).unwrap_or(100);
+
+    if let Some(url) = props.url.clone().filter(|_| props.image && !props.failed) {
+        // Without a description the name is still better than nothing.
+        let alt = if props.alt.is_empty() { format!("Image: {}", props.name) } else { props.alt.clone() };
+        return html! {
+            <figure class="w-64">
+                <LazyImage src={url.clone()} {alt} class="max-h-64 rounded" size={props.size} max_bytes={props.max_image_bytes} />
+                <figcaption class="text-xs mt-1 flex justify-between">
+                    <span class="truncate opacity-70" title={props.name.clone()}>{props.name.clone()}</span>
+                    <a href={url} download={props.name.clone()} class="ml-2 text-blue-600 hover:underline">{"Download"}</a>
+                </figcaption>
+            </figure>
+        };
+    }
+
+    html! {
+        <div class="flex items-center w-64">
+            <div class="text-3xl mr-3" aria-hidden="true">{"📄"}</div>
+            <div class="grow min-w-0">
+                <div class="text-sm font-medium truncate" title={props.name.clone()}>{props.name.clone()}</div>
+                <div class="text-xs opacity-70">{human_size(props.size)}</div>
+                if props.failed {
+                    <div class="text-xs text-red-500" role="status">{"Transfer failed"}</div>
+                } else if let Some(url) = props.url.clone() {
+                    <a href={url} download={props.name.clone()} class="text-xs text-blue-600 hover:underline">{"Download"}</a>
+                } else {
+                    <div
+                        role="progressbar"
+                        aria-label={format!("Receiving {}", props.name)}
+                        aria-valuenow={percent.to_string()}
+                        aria-valuemin="0"
+                        aria-valuemax="100"
+                        class="h-1.5 mt-1 bg-gray-300 rounded"
+                    >
+                        <div class="h-1.5 bg-blue-600 rounded" style={format!("width: {}%", percent)}></div>
+                    </div>
+                }
+            </div>
+        </div>
+    }
+}
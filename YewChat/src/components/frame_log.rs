@@ -0,0 +1,86 @@
+use web_sys::HtmlSelectElement;
+use yew::prelude::*;
+
+use crate::services::traffic::LoggedFrame;
+
+#[derive(Properties, PartialEq)]
+pub struct FrameLogPanelProps {
+    pub frames: Vec<LoggedFrame>,
+    pub on_refresh: Callback<()>,
+    pub on_close: Callback<()>,
+}
+
+fn clock(time: f64) -> String {
+    let date = js_sys::Date::new(&time.into());
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        date.get_hours(),
+        date.get_minutes(),
+        date.get_seconds(),
+        date.get_milliseconds()
+    )
+}
+
+/// Every frame sent and received, newest first, for debugging the protocol
+/// without the browser's devtools. Toggled with Alt+Shift+F.
+#[function_component(FrameLogPanel)]
+pub fn frame_log_panel(props: &FrameLogPanelProps) -> Html {
+    let kind = use_state(String::new);
+    let close = props.on_close.reform(|_: MouseEvent| ());
+    let refresh = props.on_refresh.reform(|_: MouseEvent| ());
+    let on_kind = {
+        let kind = kind.clone();
+        Callback::from(move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            kind.set(select.value());
+        })
+    };
+    let mut kinds: Vec<&str> = props.frames.iter().map(|f| f.kind.as_str()).collect();
+    kinds.sort_unstable();
+    kinds.dedup();
+
+    html! {
+        <div role="dialog" aria-labelledby="frame-log-title" class="fixed bottom-0 inset-x-0 h-1/2 bg-gray-900 text-gray-100 shadow-2xl z-40 flex flex-col text-xs font-mono">
+            <div class="flex items-center px-3 py-2 border-b border-gray-700">
+                <h2 id="frame-log-title" class="font-bold grow">{format!("Frames ({})", props.frames.len())}</h2>
+                <label class="mr-3">
+                    {"Type "}
+                    <select onchange={on_kind} class="bg-gray-800 rounded px-1">
+                        <option value="" selected={kind.is_empty()}>{"all"}</option>
+                        {
+                            kinds.iter().map(|k| html! {
+                                <option value={k.to_string()} selected={*kind == *k}>{k.to_string()}</option>
+                            }).collect::<Html>()
+                        }
+                    </select>
+                </label>
+                <button onclick={refresh} class="mr-3 text-blue-300 hover:underline">{"Refresh"}</button>
+                <button onclick={close} aria-label="Close frame log" class="text-gray-400 hover:text-white">{"✕"}</button>
+            </div>
+            <ol class="overflow-y-auto grow">
+                {
+                    props.frames
+                        .iter()
+                        .rev()
+                        .filter(|f| kind.is_empty() || f.kind == *kind)
+                        .map(|f| html! {
+                            <li class="px-3 py-1 border-b border-gray-800">
+                                <details>
+                                    <summary class="cursor-pointer truncate">
+                                        <span class="text-gray-500">{clock(f.time)}</span>
+                                        <span class={if f.outbound { "text-green-400 mx-2" } else { "text-blue-400 mx-2" }}>
+                                            {if f.outbound { "↑" } else { "↓" }}
+                                        </span>
+                                        <span class="text-yellow-300 mr-2">{f.kind.clone()}</span>
+                                        {format!("{} B", f.frame.len())}
+                                    </summary>
+                                    <pre class="whitespace-pre-wrap break-all text-gray-300 mt-1">{f.frame.clone()}</pre>
+                                </details>
+                            </li>
+                        })
+                        .collect::<Html>()
+                }
+            </ol>
+        </div>
+    }
+}
@@ -0,0 +1,177 @@
+use yew::prelude::*;
+
+use crate::components::file_card::human_size;
+use crate::components::links;
+use crate::components::media::LazyImage;
+
+#[derive(Clone, PartialEq)]
+pub enum Shared {
+    Link(String),
+    Image { src: String, alt: String, size: Option<usize> },
+    /// `url` is `None` until the whole file has arrived.
+    File { name: String, size: usize, url: Option<String> },
+}
+
+/// Something shared in a room, and the message it was shared in.
+#[derive(Clone, PartialEq)]
+pub struct SharedItem {
+    pub id: u64,
+    pub from: String,
+    pub shared: Shared,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Tab {
+    Links,
+    Media,
+    Files,
+}
+
+impl Tab {
+    const ALL: [Tab; 3] = [Tab::Links, Tab::Media, Tab::Files];
+
+    fn label(&self) -> &str {
+        match self {
+            Tab::Links => "Links",
+            Tab::Media => "Media",
+            Tab::Files => "Files",
+        }
+    }
+
+    fn holds(&self, shared: &Shared) -> bool {
+        matches!(
+            (self, shared),
+            (Tab::Links, Shared::Link(_)) | (Tab::Media, Shared::Image { .. }) | (Tab::Files, Shared::File { .. })
+        )
+    }
+
+    fn empty(&self) -> &str {
+        match self {
+            Tab::Links => "No links shared here yet.",
+            Tab::Media => "No pictures shared here yet.",
+            Tab::Files => "No files shared here yet.",
+        }
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct GalleryPanelProps {
+    /// Newest first.
+    pub items: Vec<SharedItem>,
+    pub low_data: bool,
+    pub max_image_bytes: Option<usize>,
+    /// Takes the message ID to go back to.
+    pub on_jump: Callback<u64>,
+    /// Links go where the ones in messages do, to be checked first.
+    pub on_open_link: Callback<String>,
+    pub on_close: Callback<()>,
+}
+
+/// Side panel gathering the links, pictures and files shared in the room
+/// from the messages loaded, each with a way back to where it was shared.
+#[function_component(GalleryPanel)]
+pub fn gallery_panel(props: &GalleryPanelProps) -> Html {
+    let tab = use_state(|| Tab::Links);
+    let close = props.on_close.reform(|_: MouseEvent| ());
+    let shown: Vec<&SharedItem> = props.items.iter().filter(|item| tab.holds(&item.shared)).collect();
+
+    let view_item = |item: &SharedItem| {
+        let id = item.id;
+        let jump = props.on_jump.reform(move |_: MouseEvent| id);
+        let from = item.from.clone();
+        match &item.shared {
+            Shared::Link(url) => {
+                let open = {
+                    let url = url.clone();
+                    props.on_open_link.reform(move |e: MouseEvent| {
+                        e.prevent_default();
+                        url.clone()
+                    })
+                };
+                let host = links::host(url).unwrap_or_default();
+                let initial = host.trim_start_matches("www.").chars().next().unwrap_or('🔗').to_uppercase().to_string();
+                html! {
+                    <li key={format!("{}-{}", id, url)} class="flex items-start mb-2 p-2 bg-white rounded shadow-sm">
+                        <div class="flex-none w-8 h-8 mr-2 rounded bg-blue-100 text-blue-800 flex items-center justify-center font-bold" aria-hidden="true">{initial}</div>
+                        <div class="grow min-w-0 text-sm">
+                            <a href={url.clone()} onclick={open} rel="noopener noreferrer" class="block truncate text-blue-600 hover:underline" title={url.clone()}>{host}</a>
+                            <button onclick={jump} class="text-xs text-gray-500 hover:underline" aria-label={format!("Go to the message from {} sharing this link", from)}>
+                                {format!("{} · Go to message", from)}
+                            </button>
+                        </div>
+                    </li>
+                }
+            }
+            Shared::Image { src, alt, size } => {
+                let alt = if alt.is_empty() { format!("Picture from {}", from) } else { alt.clone() };
+                html! {
+                    <li key={id} class="relative">
+                        <LazyImage src={src.clone()} {alt} class="w-full h-24 object-cover rounded" low_data={props.low_data} size={*size} max_bytes={props.max_image_bytes} />
+                        <button onclick={jump} aria-label={format!("Go to the message from {} sharing this picture", from)}
+                            class="absolute bottom-1 right-1 px-1 text-xs rounded bg-black bg-opacity-60 text-white hover:bg-opacity-80">
+                            {"Go to"}
+                        </button>
+                    </li>
+                }
+            }
+            Shared::File { name, size, url } => html! {
+                <li key={id} class="flex items-start mb-2 p-2 bg-white rounded shadow-sm">
+                    <div class="flex-none text-2xl mr-2" aria-hidden="true">{"📄"}</div>
+                    <div class="grow min-w-0 text-sm">
+                        <div class="truncate font-medium" title={name.clone()}>{name.clone()}</div>
+                        <div class="text-xs text-gray-500">
+                            {format!("{} · {} · ", human_size(*size), from)}
+                            <button onclick={jump} class="hover:underline" aria-label={format!("Go to the message from {} sharing {}", from, name)}>{"Go to message"}</button>
+                        </div>
+                    </div>
+                    if let Some(url) = url.clone() {
+                        <a href={url} download={name.clone()} class="ml-1 text-xs text-blue-600 hover:underline">{"Download"}</a>
+                    }
+                </li>
+            },
+        }
+    };
+
+    html! {
+        <aside aria-labelledby="gallery-title" class="flex-none w-72 h-screen overflow-y-auto border-l-2 border-gray-300 bg-gray-50 text-black p-3">
+            <div class="flex justify-between items-center mb-2">
+                <h2 id="gallery-title" class="font-bold">{"🖼️ Shared here"}</h2>
+                <button onclick={close} aria-label="Close shared links and files" class="text-gray-400 hover:text-gray-700">{"✕"}</button>
+            </div>
+            <div role="tablist" class="flex mb-3 border-b border-gray-300">
+                {
+                    Tab::ALL.into_iter().map(|t| {
+                        let count = props.items.iter().filter(|item| t.holds(&item.shared)).count();
+                        let select = {
+                            let tab = tab.clone();
+                            Callback::from(move |_: MouseEvent| tab.set(t))
+                        };
+                        html! {
+                            <button
+                                role="tab"
+                                aria-selected={(*tab == t).to_string()}
+                                onclick={select}
+                                class={classes!("px-2", "py-1", "text-sm", "-mb-px", "border-b-2",
+                                    if *tab == t { "border-blue-600 font-medium" } else { "border-transparent text-gray-500" })}
+                            >
+                                {format!("{} ({})", t.label(), count)}
+                            </button>
+                        }
+                    }).collect::<Html>()
+                }
+            </div>
+            if shown.is_empty() {
+                <div class="text-sm text-gray-500">{tab.empty()}</div>
+            } else if *tab == Tab::Media {
+                <ul role="tabpanel" class="grid grid-cols-2 gap-2">
+                    { shown.into_iter().map(view_item).collect::<Html>() }
+                </ul>
+            } else {
+                <ul role="tabpanel">
+                    { shown.into_iter().map(view_item).collect::<Html>() }
+                </ul>
+            }
+            <div class="text-xs text-gray-500 mt-3">{"From the messages loaded. Scroll back or go to a date to find older ones."}</div>
+        </aside>
+    }
+}
@@ -0,0 +1,38 @@
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct IncompatibleProps {
+    pub client_version: u32,
+    pub server_version: u32,
+    /// The server expects a newer client, so reloading should fix it.
+    pub client_outdated: bool,
+}
+
+/// Shown instead of the chat when the server speaks a protocol version we
+/// can't. The socket is closed before this is rendered.
+#[function_component(Incompatible)]
+pub fn incompatible(props: &IncompatibleProps) -> Html {
+    let reload = Callback::from(|_: MouseEvent| {
+        if let Some(window) = web_sys::window() {
+            let _ = window.location().reload();
+        }
+    });
+
+    html! {
+        <div class="bg-gray-800 flex w-screen">
+            <div role="alert" class="container mx-auto flex flex-col justify-center items-center text-white text-center">
+                if props.client_outdated {
+                    <h1 class="text-2xl font-bold mb-3">{"A new version of the chat is available"}</h1>
+                    <p class="mb-6 text-gray-300">{"Please refresh the page to keep chatting."}</p>
+                    <button onclick={reload} class="px-6 py-3 rounded-lg bg-violet-600 font-bold uppercase">{"Refresh"}</button>
+                } else {
+                    <h1 class="text-2xl font-bold mb-3">{"Incompatible server"}</h1>
+                    <p class="mb-6 text-gray-300">{"This server runs an older version of the chat than this app supports."}</p>
+                }
+                <p class="mt-6 text-xs text-gray-500">
+                    {format!("Client protocol v{}, server protocol v{}", props.client_version, props.server_version)}
+                </p>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,127 @@
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+use crate::components::room_switcher::room_label;
+use crate::services::invite;
+use crate::services::qr::QrCode;
+use crate::{Route, User};
+
+// Light modules scanners want around the code, on every side.
+const QUIET_ZONE: usize = 4;
+
+#[derive(Properties, PartialEq)]
+pub struct InviteLandingProps {
+    /// As it came in the path, still encoded.
+    pub room: String,
+}
+
+/// Where an invite link lands: keeps the room for after sign-in and moves
+/// on. The chat opens it as soon as it starts.
+#[function_component(InviteLanding)]
+pub fn invite_landing(props: &InviteLandingProps) -> Html {
+    let user = use_context::<User>().expect("No context found.");
+    let remembered = use_state(|| false);
+    {
+        let remembered = remembered.clone();
+        use_effect_with_deps(
+            move |room| {
+                invite::remember(room);
+                remembered.set(true);
+                || ()
+            },
+            props.room.clone(),
+        );
+    }
+    // Moving on any sooner, the chat could start before the room's kept.
+    if !*remembered {
+        return html! {};
+    }
+    // Anyone still at the initial name hasn't signed in.
+    let signed_in = *user.username.borrow() != "initial";
+    html! {
+        <Redirect<Route> to={if signed_in { Route::Chat } else { Route::Login }} />
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct QrSvgProps {
+    pub text: String,
+    #[prop_or_default]
+    pub class: Classes,
+}
+
+/// `text` as a QR code, dark on light whatever the theme, as scanners
+/// expect.
+#[function_component(QrSvg)]
+pub fn qr_svg(props: &QrSvgProps) -> Html {
+    let code = match QrCode::encode(props.text.as_bytes()) {
+        Some(code) => code,
+        None => return html! {},
+    };
+    let side = code.size + QUIET_ZONE * 2;
+    // One path for every dark module keeps the DOM small.
+    let path: String = (0..code.size)
+        .flat_map(|y| (0..code.size).map(move |x| (x, y)))
+        .filter(|&(x, y)| code.is_dark(x, y))
+        .map(|(x, y)| format!("M{},{}h1v1h-1z", x + QUIET_ZONE, y + QUIET_ZONE))
+        .collect();
+    html! {
+        <svg
+            class={props.class.clone()}
+            viewBox={format!("0 0 {} {}", side, side)}
+            shape-rendering="crispEdges"
+            role="img"
+            aria-label="QR code"
+        >
+            <rect width={side.to_string()} height={side.to_string()} fill="#ffffff" />
+            <path d={path} fill="#000000" />
+        </svg>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct InviteDialogProps {
+    pub room: String,
+    /// Takes the link to put on the clipboard.
+    pub on_copy: Callback<String>,
+    pub on_close: Callback<()>,
+}
+
+/// A room's invite link, to copy or to scan with a phone.
+#[function_component(InviteDialog)]
+pub fn invite_dialog(props: &InviteDialogProps) -> Html {
+    let link = match invite::link(&props.room) {
+        Some(link) => link,
+        None => return html! {},
+    };
+    let copy = {
+        let link = link.clone();
+        props.on_copy.reform(move |_: MouseEvent| link.clone())
+    };
+    let select_all = Callback::from(|e: FocusEvent| e.target_unchecked_into::<HtmlInputElement>().select());
+    let close = props.on_close.reform(|_: MouseEvent| ());
+
+    html! {
+        <div class="fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center z-50">
+            <div role="dialog" aria-modal="true" aria-labelledby="invite-title" class="bg-white text-gray-900 rounded-lg shadow-xl w-96 max-w-full p-6">
+                <h2 id="invite-title" class="text-lg font-bold mb-2">{format!("Invite to {}", room_label(&props.room))}</h2>
+                <p class="text-sm mb-4">{"Anyone with this link can join the room once they've signed in."}</p>
+                <QrSvg text={link.clone()} class="w-48 h-48 mx-auto mb-4" />
+                <div class="flex mb-4">
+                    <input
+                        readonly=true
+                        value={link}
+                        onfocus={select_all}
+                        aria-label="Invite link"
+                        class="grow min-w-0 px-2 py-1 rounded-l border border-gray-300 text-sm bg-gray-50"
+                    />
+                    <button onclick={copy} class="px-3 py-1 rounded-r bg-blue-600 text-white text-sm hover:bg-blue-700">{"Copy"}</button>
+                </div>
+                <div class="flex justify-end">
+                    <button onclick={close} class="px-4 py-2 rounded hover:bg-gray-100">{"Done"}</button>
+                </div>
+            </div>
+        </div>
+    }
+}
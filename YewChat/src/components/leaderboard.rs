@@ -0,0 +1,112 @@
+use yew::prelude::*;
+
+// Rows shown in each half of the panel.
+const LEADERBOARD_SIZE: usize = 5;
+
+#[derive(Clone, PartialEq)]
+pub struct ReactedMessage {
+    pub id: u64,
+    pub from: String,
+    pub text: String,
+    /// Every reaction on the message, largest count first.
+    pub reactions: Vec<(String, usize)>,
+}
+
+impl ReactedMessage {
+    fn total(&self) -> usize {
+        self.reactions.iter().map(|(_, n)| n).sum()
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct LeaderboardProps {
+    pub messages: Vec<ReactedMessage>,
+    /// How many reactions each person has handed out in the room.
+    pub reactors: Vec<(String, usize)>,
+    pub on_close: Callback<()>,
+}
+
+/// Ties go to the earlier message / the alphabetically first name, so the
+/// order doesn't shuffle between renders.
+fn top_messages(messages: &[ReactedMessage]) -> Vec<&ReactedMessage> {
+    let mut top: Vec<&ReactedMessage> = messages.iter().filter(|m| m.total() > 0).collect();
+    top.sort_by(|a, b| b.total().cmp(&a.total()).then(a.id.cmp(&b.id)));
+    top.truncate(LEADERBOARD_SIZE);
+    top
+}
+
+fn top_reactors(reactors: &[(String, usize)]) -> Vec<&(String, usize)> {
+    let mut top: Vec<&(String, usize)> = reactors.iter().filter(|(_, n)| *n > 0).collect();
+    top.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    top.truncate(LEADERBOARD_SIZE);
+    top
+}
+
+fn medal(rank: usize) -> String {
+    match rank {
+        0 => "🥇".to_string(),
+        1 => "🥈".to_string(),
+        2 => "🥉".to_string(),
+        n => format!("{}.", n + 1),
+    }
+}
+
+/// Side panel with the room's most-reacted messages and its most generous
+/// reactors, from the reactions seen this session.
+#[function_component(Leaderboard)]
+pub fn leaderboard(props: &LeaderboardProps) -> Html {
+    let close = props.on_close.reform(|_: MouseEvent| ());
+    let messages = top_messages(&props.messages);
+    let reactors = top_reactors(&props.reactors);
+
+    html! {
+        <aside aria-labelledby="leaderboard-title" class="flex-none w-72 h-screen overflow-y-auto border-l-2 border-gray-300 bg-gray-50 text-black p-3">
+            <div class="flex justify-between items-center mb-2">
+                <h2 id="leaderboard-title" class="font-bold">{"🏆 Reaction leaderboard"}</h2>
+                <button onclick={close} aria-label="Close leaderboard" class="text-gray-400 hover:text-gray-700">{"✕"}</button>
+            </div>
+            <h3 class="text-xs uppercase tracking-wide text-gray-500 mt-3 mb-1">{"Most reacted"}</h3>
+            if messages.is_empty() {
+                <div class="text-sm text-gray-500">{"No reactions in this room yet."}</div>
+            }
+            <ol>
+                {
+                    messages.iter().enumerate().map(|(rank, m)| html! {
+                        <li key={m.id} class="flex items-start mb-2 p-2 bg-white rounded shadow-sm">
+                            <span class="w-6 flex-none">{medal(rank)}</span>
+                            <div class="grow text-sm min-w-0">
+                                <div class="text-xs font-bold">{m.from.clone()}</div>
+                                <div class="truncate">{m.text.clone()}</div>
+                                <div class="text-xs text-gray-500">
+                                    {
+                                        m.reactions
+                                            .iter()
+                                            .map(|(emoji, n)| format!("{} {}", emoji, n))
+                                            .collect::<Vec<_>>()
+                                            .join("  ")
+                                    }
+                                </div>
+                            </div>
+                            <span class="ml-1 text-sm font-medium">{m.total()}</span>
+                        </li>
+                    }).collect::<Html>()
+                }
+            </ol>
+            <h3 class="text-xs uppercase tracking-wide text-gray-500 mt-4 mb-1">{"Top reactors"}</h3>
+            if reactors.is_empty() {
+                <div class="text-sm text-gray-500">{"Nobody has reacted yet."}</div>
+            }
+            <ol>
+                {
+                    reactors.iter().enumerate().map(|(rank, (name, n))| html! {
+                        <li key={name.clone()} class="flex items-center mb-1 text-sm">
+                            <span class="w-6 flex-none">{medal(rank)}</span>
+                            <span class="grow truncate">{name.clone()}</span>
+                            <span class="font-medium">{*n}</span>
+                        </li>
+                    }).collect::<Html>()
+                }
+            </ol>
+        </aside>
+    }
+}
@@ -0,0 +1,53 @@
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct LinkWarningProps {
+    pub url: String,
+    pub host: String,
+    /// Takes whether to trust the host from now on.
+    pub on_open: Callback<bool>,
+    pub on_close: Callback<()>,
+}
+
+/// Shown before following a link to a domain the user hasn't trusted, with
+/// the whole address spelled out so a lookalike has nowhere to hide.
+#[function_component(LinkWarning)]
+pub fn link_warning(props: &LinkWarningProps) -> Html {
+    let trust = use_node_ref();
+    let close = props.on_close.reform(|_: MouseEvent| ());
+    let open = {
+        let trust = trust.clone();
+        props.on_open.reform(move |_: MouseEvent| {
+            trust.cast::<HtmlInputElement>().map(|input| input.checked()).unwrap_or(false)
+        })
+    };
+    // Punycode means the name is spelled with characters that may only
+    // look like the ones you'd expect.
+    let unusual = props.host.split('.').any(|label| label.starts_with("xn--"));
+
+    html! {
+        <div class="fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center z-50">
+            <div role="alertdialog" aria-modal="true" aria-labelledby="link-warning-title" aria-describedby="link-warning-url" class="bg-white text-gray-900 rounded-lg shadow-xl w-[28rem] max-w-full p-6">
+                <h2 id="link-warning-title" class="text-lg font-bold mb-2">{"Leaving the chat"}</h2>
+                <p class="text-sm mb-2">
+                    {"This link goes to "}<strong>{props.host.clone()}</strong>{". Check it's where you expect before opening it."}
+                </p>
+                <p id="link-warning-url" class="font-mono text-xs bg-gray-100 rounded p-2 break-all mb-3">{props.url.clone()}</p>
+                if unusual {
+                    <p class="text-sm text-red-600 mb-3" role="alert">
+                        {"The address uses unusual characters and may be imitating another site."}
+                    </p>
+                }
+                <label class="flex items-center text-sm mb-4">
+                    <input type="checkbox" ref={trust} class="mr-2" />
+                    {format!("Always open links to {}", props.host)}
+                </label>
+                <div class="flex justify-end">
+                    <button onclick={close} class="px-4 py-2 rounded hover:bg-gray-100 mr-2">{"Cancel"}</button>
+                    <button onclick={open} class="px-4 py-2 rounded bg-blue-600 text-white hover:bg-blue-700">{"Open link"}</button>
+                </div>
+            </div>
+        </div>
+    }
+}
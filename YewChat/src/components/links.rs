@@ -0,0 +1,109 @@
+use yew::prelude::*;
+
+use crate::components::emoji;
+use crate::services::settings::Settings;
+
+const SCHEMES: [&str; 2] = ["https://", "http://"];
+// Left off the end of a link: more likely the sentence's than the URL's.
+const TRAILING: &[char] = &['.', ',', ';', ':', '!', '?', ')', ']', '\'', '"'];
+
+enum Segment<'a> {
+    Text(&'a str),
+    Link(&'a str),
+}
+
+/// Splits `text` into plain runs and the http(s) URLs between them.
+fn segments(text: &str) -> Vec<Segment<'_>> {
+    let mut out = Vec::new();
+    let mut rest = text;
+    while let Some(start) = SCHEMES.iter().filter_map(|scheme| rest.find(scheme)).min() {
+        let end = rest[start..].find(char::is_whitespace).map_or(rest.len(), |len| start + len);
+        let link = rest[start..end].trim_end_matches(TRAILING);
+        if SCHEMES.contains(&link) {
+            // A bare scheme isn't a link.
+            out.push(Segment::Text(&rest[..end]));
+        } else {
+            if start > 0 {
+                out.push(Segment::Text(&rest[..start]));
+            }
+            out.push(Segment::Link(link));
+            let after = start + link.len();
+            if after < end {
+                out.push(Segment::Text(&rest[after..end]));
+            }
+        }
+        rest = &rest[end..];
+    }
+    if !rest.is_empty() {
+        out.push(Segment::Text(rest));
+    }
+    out
+}
+
+/// The http(s) URLs in `text`, in order.
+pub fn urls(text: &str) -> impl Iterator<Item = &str> {
+    segments(text).into_iter().filter_map(|segment| match segment {
+        Segment::Link(url) => Some(url),
+        Segment::Text(_) => None,
+    })
+}
+
+/// The host `url` points at, lowercased and in its ASCII (punycode) form,
+/// which is what gives a lookalike away.
+pub fn host(url: &str) -> Option<String> {
+    web_sys::Url::new(url).ok().map(|url| url.hostname().to_lowercase())
+}
+
+/// Whether `host` is one of `trusted` or below one, or the app's own.
+pub fn is_trusted(host: &str, trusted: &[String]) -> bool {
+    let own = web_sys::window().and_then(|w| w.location().hostname().ok());
+    own.as_deref() == Some(host)
+        || trusted.iter().any(|domain| {
+            let domain = domain.trim().trim_start_matches("*.").to_lowercase();
+            !domain.is_empty() && (host == domain || host.ends_with(&format!(".{}", domain)))
+        })
+}
+
+/// Renders message text with its URLs as links, filtered words starred out
+/// of what's shown but not out of where a link goes. Clicking one never
+/// navigates directly; the URL goes to `on_link` to decide.
+pub fn render(text: &str, settings: &Settings, on_link: &Callback<String>) -> Html {
+    let style = settings.rendered_emoji_style();
+    segments(text)
+        .into_iter()
+        .map(|segment| match segment {
+            Segment::Text(t) => emoji::render(&settings.masked(t), style),
+            Segment::Link(url) => {
+                let open = {
+                    let url = url.to_string();
+                    on_link.reform(move |e: MouseEvent| {
+                        e.prevent_default();
+                        url.clone()
+                    })
+                };
+                // Auxclick fires for the right button too, which should
+                // still bring up the context menu.
+                let middle = {
+                    let (url, on_link) = (url.to_string(), on_link.clone());
+                    Callback::from(move |e: MouseEvent| {
+                        if e.button() == 1 {
+                            e.prevent_default();
+                            on_link.emit(url.clone());
+                        }
+                    })
+                };
+                html! {
+                    <a
+                        href={url.to_string()}
+                        onclick={open}
+                        onauxclick={middle}
+                        rel="noopener noreferrer"
+                        class="underline text-blue-600 break-all"
+                    >
+                        {settings.masked(url).into_owned()}
+                    </a>
+                }
+            }
+        })
+        .collect::<Html>()
+}
@@ -1,16 +1,26 @@
 
+use wasm_bindgen_futures::spawn_local;
 use web_sys::HtmlInputElement;
 use yew::functional::*;
 use yew::prelude::*;
 use yew_router::prelude::*;
 
+use crate::services::auth;
+use crate::services::chat_transport::TransportContext;
 use crate::Route;
 use crate::User;
 
 #[function_component(Login)]
 pub fn login() -> Html {
-    let username = use_state(|| String::new());
+    let username = use_state(String::new);
+    let password = use_state(String::new);
     let user = use_context::<User>().expect("No context found.");
+    let history = use_history().expect("Login outside a router.");
+    // Set when the server turned down our last token and sent us back here.
+    let error = use_state(auth::take_failure);
+    let signing_in = use_state(|| false);
+    // Unless the transport says there's no server to sign in to.
+    let needs_login = use_context::<TransportContext>().is_none_or(|transport| transport.needs_login());
 
     let oninput = {
         let current_username = username.clone();
@@ -21,19 +31,57 @@ pub fn login() -> Html {
         })
     };
 
-    let onclick = {
+    let onpassword = {
+        let password = password.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            password.set(input.value());
+        })
+    };
+
+    let onsubmit = {
         let username = username.clone();
-        let user = user.clone();
-        Callback::from(move |_| *user.username.borrow_mut() = (*username).clone())
+        let password = password.clone();
+        let error = error.clone();
+        let signing_in = signing_in.clone();
+        Callback::from(move |e: FocusEvent| {
+            e.prevent_default();
+            let username = (*username).clone();
+            let password = (*password).clone();
+            let user = user.clone();
+            let history = history.clone();
+            let error = error.clone();
+            let signing_in = signing_in.clone();
+            signing_in.set(true);
+            spawn_local(async move {
+                let login = if needs_login { auth::login(&username, &password).await } else { Ok(String::new()) };
+                match login {
+                    Ok(_) => {
+                        *user.username.borrow_mut() = username;
+                        history.push(Route::Chat);
+                    }
+                    Err(reason) => {
+                        signing_in.set(false);
+                        error.set(Some(reason));
+                    }
+                }
+            });
+        })
     };
 
     html! {
         <div class="bg-gray-800 flex w-screen">
             <div class="container mx-auto flex flex-col justify-center items-center	">
-                <form class="m-4 flex">
-                    <input {oninput} class="rounded-l-lg p-4 border-t mr-0 border-b border-l text-gray-800 border-gray-200 bg-white" placeholder="Username"/>
-                    <Link<Route> to={Route::Chat}> <button {onclick} disabled={username.len()<1} class="px-8 rounded-r-lg bg-violet-600	  text-white font-bold p-4 uppercase border-violet-600 border-t border-b border-r" >{"Go Chatting!"}</button></Link<Route>>
+                <form {onsubmit} class="m-4 flex">
+                    <input {oninput} class="rounded-l-lg p-4 border-t mr-0 border-b border-l text-gray-800 border-gray-200 bg-white" placeholder="Username" aria-label="Username"/>
+                    if needs_login {
+                        <input oninput={onpassword} type="password" autocomplete="current-password" class="p-4 border-t mr-0 border-b border-l text-gray-800 border-gray-200 bg-white" placeholder="Password" aria-label="Password"/>
+                    }
+                    <button type="submit" disabled={username.is_empty() || (needs_login && password.is_empty()) || *signing_in} class="px-8 rounded-r-lg bg-violet-600	  text-white font-bold p-4 uppercase border-violet-600 border-t border-b border-r" >{"Go Chatting!"}</button>
                 </form>
+                if let Some(error) = (*error).clone() {
+                    <p role="alert" class="text-red-300 text-sm">{error}</p>
+                }
             </div>
         </div>
     }
@@ -0,0 +1,141 @@
+use yew::prelude::*;
+
+use crate::components::file_card::human_size;
+use crate::services::settings::ThemeTokens;
+
+#[derive(Properties, PartialEq)]
+pub struct AvatarProps {
+    pub name: String,
+    pub url: String,
+    #[prop_or_default]
+    pub class: Classes,
+    /// In low-data mode the picture is replaced by the name's initial.
+    #[prop_or_default]
+    pub low_data: bool,
+}
+
+#[function_component(Avatar)]
+pub fn avatar(props: &AvatarProps) -> Html {
+    if props.low_data {
+        let initial = props.name.chars().next().map(|c| c.to_uppercase().to_string()).unwrap_or_default();
+        return html! {
+            <div class={classes!("flex", "items-center", "justify-center", "bg-gray-300", "text-gray-700", "font-bold", props.class.clone())} aria-hidden="true">
+                {initial}
+            </div>
+        };
+    }
+    html! {
+        <img class={props.class.clone()} src={props.url.clone()} alt="avatar"/>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct LazyImageProps {
+    pub src: String,
+    #[prop_or_default]
+    pub class: Classes,
+    #[prop_or_default]
+    pub alt: String,
+    /// In low-data mode nothing is fetched until the reader asks for it.
+    #[prop_or_default]
+    pub low_data: bool,
+    /// In bytes, when it's already known. Otherwise, if there's a
+    /// `max_bytes`, the image waits for the reader like a large one would.
+    #[prop_or_default]
+    pub size: Option<usize>,
+    /// Anything larger waits until the reader asks for it.
+    #[prop_or_default]
+    pub max_bytes: Option<usize>,
+}
+
+#[function_component(LazyImage)]
+pub fn lazy_image(props: &LazyImageProps) -> Html {
+    let requested = use_state(|| false);
+    let failed = use_state(|| false);
+    let tokens = use_context::<ThemeTokens>().unwrap_or_default();
+    {
+        // A new image waits its turn like the first one did.
+        let requested = requested.clone();
+        let failed = failed.clone();
+        use_effect_with_deps(
+            move |_| {
+                requested.set(false);
+                failed.set(false);
+                || ()
+            },
+            props.src.clone(),
+        );
+    }
+    let load = {
+        let requested = requested.clone();
+        Callback::from(move |_: MouseEvent| requested.set(true))
+    };
+    if props.low_data && !*requested {
+        return html! {
+            <button onclick={load} class={classes!("flex", "items-center", "px-3", "py-2", "rounded", "text-xs", tokens.raised, tokens.text)}>
+                {"🖼️ Load image"}
+            </button>
+        };
+    }
+    if let (Some(max), false) = (props.max_bytes, *requested) {
+        match props.size {
+            Some(size) if size > max => {
+                return html! {
+                    <button
+                        onclick={load}
+                        title={format!("Larger than the {} you load automatically", human_size(max))}
+                        class={classes!("flex", "items-center", "px-3", "py-2", "rounded", "text-xs", tokens.raised, tokens.text)}
+                    >
+                        {format!("🖼️ Load image · {}", human_size(size))}
+                    </button>
+                };
+            }
+            // Asking the server would mean fetching from it before the
+            // reader has said so, and it might not answer anyway.
+            None => {
+                return html! {
+                    <button
+                        onclick={load}
+                        title={format!("Size unknown, so it could be larger than the {} you load automatically", human_size(max))}
+                        class={classes!("flex", "items-center", "px-3", "py-2", "rounded", "text-xs", tokens.raised, tokens.text)}
+                    >
+                        {"🖼️ Load image · size unknown"}
+                    </button>
+                };
+            }
+            _ => {}
+        }
+    }
+    if *failed {
+        // The `<img>` that comes back in its place is a new one, so it's
+        // fetched again.
+        let retry = {
+            let failed = failed.clone();
+            Callback::from(move |_: MouseEvent| failed.set(false))
+        };
+        // In place of the browser's broken-image icon: gone (404), or kept
+        // from us (CORS), it's all the same to the reader.
+        return html! {
+            <div class={classes!("flex", "items-center", "max-w-sm", "px-3", "py-2", "rounded", "border", "border-dashed", "text-xs", tokens.border, tokens.raised)} role="img" aria-label={format!("Image that couldn't be loaded: {}", props.src)}>
+                <span class="flex-none mr-2" aria-hidden="true">{"🖼️"}</span>
+                <div class="grow min-w-0">
+                    <div class={tokens.text}>{"Couldn't load this image"}</div>
+                    <div class={classes!("truncate", tokens.muted)} title={props.src.clone()}>{props.src.clone()}</div>
+                </div>
+                <button onclick={retry} class="flex-none ml-2 px-2 py-1 rounded text-blue-600 hover:underline">{"Retry"}</button>
+            </div>
+        };
+    }
+    let onerror = {
+        let failed = failed.clone();
+        Callback::from(move |_: Event| failed.set(true))
+    };
+    html! {
+        <img
+            class={classes!(props.class.clone(), "border", tokens.border)}
+            src={props.src.clone()}
+            alt={props.alt.clone()}
+            {onerror}
+        />
+    }
+}
@@ -0,0 +1,96 @@
+use serde::Deserialize;
+use wasm_bindgen::JsValue;
+use yew::prelude::*;
+
+use crate::components::room_switcher::room_label;
+use crate::components::user_list::RoleBadge;
+use crate::services::permissions::Role;
+
+/// Someone who has been in a room, as the server's `members` frame lists
+/// them.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Member {
+    pub name: String,
+    pub role: Role,
+    /// When they first opened the room, in milliseconds.
+    pub joined_at: f64,
+}
+
+/// A room's members as far as they've been loaded, oldest first.
+#[derive(Default)]
+pub struct MemberList {
+    pub members: Vec<Member>,
+    /// How many the server says there are in all.
+    pub total: usize,
+    /// A page has been asked for and hasn't come back.
+    pub loading: bool,
+}
+
+impl MemberList {
+    /// Where the next page starts, if there's one to ask for.
+    pub fn next_offset(&self) -> Option<usize> {
+        (!self.loading && self.members.len() < self.total).then_some(self.members.len())
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct MembersPanelProps {
+    pub room: String,
+    pub members: Vec<Member>,
+    pub total: usize,
+    pub loading: bool,
+    /// Who's connected right now, to mark them.
+    pub online: Vec<String>,
+    pub on_more: Callback<()>,
+    pub on_close: Callback<()>,
+}
+
+/// Side panel listing everyone who has been in the room, with their role
+/// and when they joined. Unlike the user list, this is per room and
+/// includes people who aren't connected.
+#[function_component(MembersPanel)]
+pub fn members_panel(props: &MembersPanelProps) -> Html {
+    let close = props.on_close.reform(|_: MouseEvent| ());
+    let more = props.on_more.reform(|_: MouseEvent| ());
+
+    html! {
+        <aside aria-labelledby="members-title" class="flex-none w-72 h-screen overflow-y-auto border-l-2 border-gray-300 bg-gray-50 text-black p-3">
+            <div class="flex justify-between items-center mb-2">
+                <h2 id="members-title" class="font-bold">{format!("👥 {} ({})", room_label(&props.room), props.total)}</h2>
+                <button onclick={close} aria-label="Close members" class="text-gray-400 hover:text-gray-700">{"✕"}</button>
+            </div>
+            if props.members.is_empty() && !props.loading {
+                <div class="text-sm text-gray-500">{"Nobody has joined yet."}</div>
+            }
+            <ul>
+                {
+                    props.members.iter().map(|member| {
+                        let joined = js_sys::Date::new(&member.joined_at.into()).to_locale_date_string("default", &JsValue::UNDEFINED);
+                        let online = props.online.contains(&member.name);
+                        html! {
+                            <li key={member.name.clone()} class="flex items-center mb-2 p-2 bg-white rounded shadow-sm text-sm">
+                                <span
+                                    class={classes!("flex-none", "w-2", "h-2", "mr-2", "rounded-full", if online { "bg-green-500" } else { "bg-gray-300" })}
+                                    title={if online { "Online" } else { "Offline" }}
+                                />
+                                <div class="grow min-w-0">
+                                    <div class="font-bold truncate">{member.name.clone()}</div>
+                                    <div class="text-xs text-gray-500">{format!("Joined {}", String::from(joined))}</div>
+                                </div>
+                                <RoleBadge role={member.role} />
+                            </li>
+                        }
+                    }).collect::<Html>()
+                }
+            </ul>
+            if props.loading {
+                <div class="text-sm text-gray-500" role="status">{"Loading…"}</div>
+            } else if props.members.len() < props.total {
+                <button onclick={more} class="w-full px-2 py-1 rounded text-sm hover:bg-gray-200">
+                    {format!("Show more ({} left)", props.total - props.members.len())}
+                </button>
+            }
+        </aside>
+    }
+}
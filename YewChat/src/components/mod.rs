@@ -1,2 +1,34 @@
+pub mod a11y_audit;
+pub mod alt_text;
+pub mod banner;
 pub mod chat;
-pub mod login;
\ No newline at end of file
+pub mod command_palette;
+pub mod composer;
+pub mod emoji;
+pub mod file_card;
+pub mod frame_log;
+pub mod gallery;
+pub mod incompatible;
+pub mod invite;
+pub mod leaderboard;
+pub mod link_warning;
+pub mod links;
+pub mod login;
+pub mod media;
+pub mod members;
+pub mod moderation;
+pub mod new_room;
+pub mod onboarding;
+pub mod pinned;
+pub mod removed;
+pub mod report;
+pub mod room_switcher;
+pub mod room_topic;
+pub mod selection_bar;
+pub mod settings;
+pub mod starred;
+pub mod stats;
+pub mod toast;
+pub mod traffic;
+pub mod user_list;
+pub mod verify;
\ No newline at end of file
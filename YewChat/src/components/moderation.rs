@@ -0,0 +1,156 @@
+use web_sys::{HtmlInputElement, HtmlSelectElement, HtmlTextAreaElement};
+use yew::prelude::*;
+
+use crate::components::removed::RemovalKind;
+use crate::components::room_switcher::room_label;
+
+// How long a ban can last, in minutes; 0 is for good.
+const BAN_CHOICES: [(u32, &str); 4] = [(60, "1 hour"), (1_440, "1 day"), (10_080, "1 week"), (0, "Permanently")];
+
+#[derive(Properties, PartialEq)]
+pub struct RemoveDialogProps {
+    pub name: String,
+    pub kind: RemovalKind,
+    /// Takes the reason, which may be empty, and for a ban how many
+    /// minutes it lasts, 0 for good.
+    pub on_confirm: Callback<(String, u32)>,
+    pub on_close: Callback<()>,
+}
+
+/// Asks a moderator to confirm removing someone, and why.
+#[function_component(RemoveDialog)]
+pub fn remove_dialog(props: &RemoveDialogProps) -> Html {
+    let reason = use_state(String::new);
+    let minutes = use_state(|| BAN_CHOICES[0].0);
+    let on_reason = {
+        let reason = reason.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            reason.set(input.value());
+        })
+    };
+    let on_minutes = {
+        let minutes = minutes.clone();
+        Callback::from(move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            minutes.set(select.value().parse().unwrap_or_default());
+        })
+    };
+    let confirm = {
+        let reason = reason.clone();
+        let minutes = *minutes;
+        props.on_confirm.reform(move |_: MouseEvent| (reason.trim().to_string(), minutes))
+    };
+    let close = props.on_close.reform(|_: MouseEvent| ());
+    let (title, detail, action) = match props.kind {
+        RemovalKind::Kick => (
+            format!("Remove {}?", props.name),
+            "They're disconnected, but can sign in again straight away.",
+            "Remove",
+        ),
+        RemovalKind::Ban => (
+            format!("Ban {}?", props.name),
+            "They're disconnected and can't sign in under this name until the ban lifts.",
+            "Ban",
+        ),
+    };
+
+    html! {
+        <div class="fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center z-50">
+            <div role="alertdialog" aria-modal="true" aria-labelledby="remove-title" class="bg-white text-gray-900 rounded-lg shadow-xl w-96 max-w-full p-6">
+                <h2 id="remove-title" class="text-lg font-bold mb-2">{title}</h2>
+                <p class="text-sm mb-4">{detail}</p>
+                if props.kind == RemovalKind::Ban {
+                    <label class="flex items-center justify-between text-sm mb-3">
+                        {"For"}
+                        <select onchange={on_minutes} class="px-2 py-1 rounded bg-gray-100">
+                            {
+                                BAN_CHOICES.iter().map(|(value, label)| html! {
+                                    <option value={value.to_string()} selected={*value == *minutes}>{*label}</option>
+                                }).collect::<Html>()
+                            }
+                        </select>
+                    </label>
+                }
+                <label class="block text-sm mb-4">
+                    {"Reason (they'll see it)"}
+                    <input
+                        value={(*reason).clone()}
+                        oninput={on_reason}
+                        maxlength="200"
+                        class="block w-full mt-1 px-2 py-1 rounded border border-gray-300"
+                    />
+                </label>
+                <div class="flex justify-end">
+                    <button onclick={close} class="px-4 py-2 rounded hover:bg-gray-100 mr-2">{"Cancel"}</button>
+                    <button onclick={confirm} class="px-4 py-2 rounded bg-red-600 text-white hover:bg-red-700">{action}</button>
+                </div>
+            </div>
+        </div>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct AnnounceDialogProps {
+    pub room: String,
+    /// Takes the text and whether it stays at the top until dismissed.
+    pub on_send: Callback<(String, bool)>,
+    pub on_close: Callback<()>,
+}
+
+/// Lets a moderator write an announcement for everyone in the room.
+#[function_component(AnnounceDialog)]
+pub fn announce_dialog(props: &AnnounceDialogProps) -> Html {
+    let text = use_state(String::new);
+    let sticky = use_state(|| false);
+    let on_text = {
+        let text = text.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlTextAreaElement = e.target_unchecked_into();
+            text.set(input.value());
+        })
+    };
+    let on_sticky = {
+        let sticky = sticky.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            sticky.set(input.checked());
+        })
+    };
+    let send = {
+        let text = text.clone();
+        let sticky = *sticky;
+        props.on_send.reform(move |_: MouseEvent| (text.trim().to_string(), sticky))
+    };
+    let close = props.on_close.reform(|_: MouseEvent| ());
+
+    html! {
+        <div class="fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center z-50">
+            <div role="dialog" aria-modal="true" aria-labelledby="announce-title" class="bg-white text-gray-900 rounded-lg shadow-xl w-96 max-w-full p-6">
+                <h2 id="announce-title" class="text-lg font-bold mb-2">{format!("Announce in {}", room_label(&props.room))}</h2>
+                <p class="text-sm mb-4">{"Shown to everyone in the room as a banner across the conversation."}</p>
+                <textarea
+                    value={(*text).clone()}
+                    oninput={on_text}
+                    rows="4"
+                    aria-label="Announcement"
+                    class="block w-full mb-3 px-2 py-1 rounded border border-gray-300"
+                />
+                <label class="flex items-center text-sm mb-4">
+                    <input type="checkbox" checked={*sticky} onchange={on_sticky} class="mr-2" />
+                    {"Keep it at the top until each reader dismisses it"}
+                </label>
+                <div class="flex justify-end">
+                    <button onclick={close} class="px-4 py-2 rounded hover:bg-gray-100 mr-2">{"Cancel"}</button>
+                    <button
+                        onclick={send}
+                        disabled={text.trim().is_empty()}
+                        class="px-4 py-2 rounded bg-blue-600 text-white hover:bg-blue-700 disabled:opacity-50"
+                    >
+                        {"Announce"}
+                    </button>
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,139 @@
+use web_sys::{HtmlInputElement, HtmlTextAreaElement};
+use yew::prelude::*;
+
+const MAX_NAME_LENGTH: usize = 32;
+const MAX_DESCRIPTION_LENGTH: usize = 200;
+
+/// `name` as the server will store it: no leading `#`, lowercase, and
+/// dashes for spaces.
+fn normalize_room_name(name: &str) -> String {
+    name.trim().trim_start_matches('#').to_lowercase().split_whitespace().collect::<Vec<_>>().join("-")
+}
+
+/// Why `name` (already normalized) can't be created, if it can't.
+fn name_problem(name: &str, taken: &[String]) -> Option<String> {
+    if name.is_empty() {
+        Some("Give the room a name.".to_string())
+    } else if name.chars().count() > MAX_NAME_LENGTH {
+        Some(format!("Room names can be at most {} characters.", MAX_NAME_LENGTH))
+    } else if !name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_') {
+        Some("Use only letters, digits, dashes and underscores.".to_string())
+    } else if taken.iter().any(|room| room == name) {
+        Some(format!("#{} already exists.", name))
+    } else {
+        None
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct NewRoomDialogProps {
+    /// What to fill the form with, e.g. after the server turned it down.
+    #[prop_or_default]
+    pub name: String,
+    #[prop_or_default]
+    pub description: String,
+    /// Why the server turned the last attempt down.
+    #[prop_or_default]
+    pub error: Option<String>,
+    /// Rooms we already know exist.
+    pub taken: Vec<String>,
+    /// Takes the normalized name and the description.
+    pub on_create: Callback<(String, String)>,
+    pub on_close: Callback<()>,
+}
+
+/// Names and describes a new room. Names we can tell are unusable are
+/// caught here; the server has the final say on duplicates.
+#[function_component(NewRoomDialog)]
+pub fn new_room_dialog(props: &NewRoomDialogProps) -> Html {
+    let name = {
+        let initial = props.name.clone();
+        use_state(move || initial)
+    };
+    let description = {
+        let initial = props.description.clone();
+        use_state(move || initial)
+    };
+    // The server's complaint stands until the name changes.
+    let error = {
+        let initial = props.error.clone();
+        use_state(move || initial)
+    };
+    let normalized = normalize_room_name(&name);
+    let problem = name_problem(&normalized, &props.taken);
+
+    let on_name = {
+        let name = name.clone();
+        let error = error.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            name.set(input.value());
+            error.set(None);
+        })
+    };
+    let on_description = {
+        let description = description.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlTextAreaElement = e.target_unchecked_into();
+            description.set(input.value());
+        })
+    };
+    let onsubmit = {
+        let on_create = props.on_create.clone();
+        let description = description.clone();
+        let normalized = normalized.clone();
+        let blocked = problem.is_some();
+        Callback::from(move |e: FocusEvent| {
+            e.prevent_default();
+            if !blocked {
+                on_create.emit((normalized.clone(), description.trim().to_string()));
+            }
+        })
+    };
+    let close = props.on_close.reform(|_: MouseEvent| ());
+    let message = (*error).clone().or_else(|| if name.is_empty() { None } else { problem.clone() });
+
+    html! {
+        <div class="fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center z-50">
+            <form {onsubmit} role="dialog" aria-modal="true" aria-labelledby="new-room-title" class="bg-white text-gray-900 rounded-lg shadow-xl w-96 max-w-full p-6">
+                <h2 id="new-room-title" class="text-lg font-bold mb-4">{"New room"}</h2>
+                <label class="block text-sm font-medium mb-1" for="new-room-name">{"Name"}</label>
+                <input
+                    id="new-room-name"
+                    value={(*name).clone()}
+                    oninput={on_name}
+                    placeholder="e.g. design-reviews"
+                    maxlength={(MAX_NAME_LENGTH + 1).to_string()}
+                    aria-invalid={message.is_some().to_string()}
+                    aria-describedby="new-room-error"
+                    class="w-full px-2 py-1 rounded bg-gray-100 outline-none focus:ring-2 focus:ring-blue-600"
+                />
+                if !normalized.is_empty() && normalized != *name {
+                    <p class="text-xs text-gray-500 mt-1">{format!("Will be created as #{}", normalized)}</p>
+                }
+                <p id="new-room-error" role="alert" class="text-xs text-red-600 mt-1 min-h-[1rem]">
+                    {message.unwrap_or_default()}
+                </p>
+                <label class="block text-sm font-medium mt-3 mb-1" for="new-room-description">{"Description (optional)"}</label>
+                <textarea
+                    id="new-room-description"
+                    value={(*description).clone()}
+                    oninput={on_description}
+                    rows="3"
+                    maxlength={MAX_DESCRIPTION_LENGTH.to_string()}
+                    class="w-full px-2 py-1 rounded bg-gray-100 outline-none resize-none focus:ring-2 focus:ring-blue-600"
+                />
+                <div class="flex justify-end mt-4">
+                    <button type="button" onclick={close} class="px-4 py-2 rounded hover:bg-gray-100 mr-2">{"Cancel"}</button>
+                    <button
+                        type="submit"
+                        disabled={problem.is_some() || error.is_some()}
+                        class="px-4 py-2 rounded bg-blue-600 text-white hover:bg-blue-700 disabled:opacity-50"
+                    >
+                        {"Create"}
+                    </button>
+                </div>
+            </form>
+        </div>
+    }
+}
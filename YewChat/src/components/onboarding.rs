@@ -0,0 +1,87 @@
+use yew::prelude::*;
+
+use crate::services::onboarding::{Onboarding, Step};
+
+const CONFETTI_COLORS: [&str; 5] = ["#f43f5e", "#f59e0b", "#10b981", "#3b82f6", "#a855f7"];
+const CONFETTI_PIECES: usize = 24;
+
+#[derive(Properties, PartialEq)]
+pub struct OnboardingChecklistProps {
+    pub progress: Onboarding,
+    /// Called as the confetti starts, to remember it's been shown.
+    pub on_celebrate: Callback<()>,
+    pub on_dismiss: Callback<()>,
+}
+
+/// A shower of coloured bits, spread out by index so it looks random
+/// without being different on every render.
+fn confetti() -> Html {
+    html! {
+        <div class="confetti" aria-hidden="true">
+            {
+                (0..CONFETTI_PIECES).map(|i| html! {
+                    <span style={format!(
+                        "left:{}%;background:{};animation-delay:{}ms",
+                        (i * 37) % 100,
+                        CONFETTI_COLORS[i % CONFETTI_COLORS.len()],
+                        (i * 53) % 400
+                    )} />
+                }).collect::<Html>()
+            }
+        </div>
+    }
+}
+
+/// Getting started: a few things worth trying, ticked off as they're done.
+#[function_component(OnboardingChecklist)]
+pub fn onboarding_checklist(props: &OnboardingChecklistProps) -> Html {
+    let dismiss = props.on_dismiss.reform(|_: MouseEvent| ());
+    let finished = props.progress.finished();
+    let done = Step::ALL.iter().filter(|&&step| props.progress.is_done(step)).count();
+    // Kept here rather than read off `celebrated`, which turns true as
+    // soon as it starts.
+    let celebrating = use_state(|| false);
+
+    {
+        let celebrating = celebrating.clone();
+        let on_celebrate = props.on_celebrate.clone();
+        let first_time = finished && !props.progress.celebrated;
+        use_effect_with_deps(
+            move |&first_time| {
+                if first_time {
+                    celebrating.set(true);
+                    on_celebrate.emit(());
+                }
+                || ()
+            },
+            first_time,
+        );
+    }
+
+    html! {
+        <div class="fixed bottom-4 left-4 w-52 bg-white text-black rounded-lg shadow-lg p-3 z-20 overflow-hidden" aria-labelledby="onboarding-title">
+            if *celebrating {
+                { confetti() }
+            }
+            <div class="flex justify-between items-center mb-2">
+                <h2 id="onboarding-title" class="text-sm font-bold">
+                    { if finished { "You're all set! 🎉".to_string() } else { format!("Getting started {}/{}", done, Step::ALL.len()) } }
+                </h2>
+                <button onclick={dismiss} aria-label="Dismiss checklist" class="text-gray-400 hover:text-gray-700">{"✕"}</button>
+            </div>
+            <ul class="text-sm">
+                {
+                    Step::ALL.iter().map(|&step| {
+                        let done = props.progress.is_done(step);
+                        html! {
+                            <li class={classes!("py-0.5", done.then_some("text-gray-400 line-through"))}>
+                                { if done { "✅ " } else { "⬜ " } }
+                                { step.label() }
+                            </li>
+                        }
+                    }).collect::<Html>()
+                }
+            </ul>
+        </div>
+    }
+}
@@ -0,0 +1,109 @@
+use yew::prelude::*;
+
+#[derive(Clone, PartialEq)]
+pub struct PinnedEntry {
+    pub id: u64,
+    pub from: Option<String>,
+    pub text: Option<String>,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct PinnedPanelProps {
+    pub pins: Vec<PinnedEntry>,
+    pub limit: usize,
+    /// Whether we may unpin and reorder, or only look.
+    pub editable: bool,
+    pub on_unpin: Callback<u64>,
+    /// Emits the full new order of pinned message IDs.
+    pub on_reorder: Callback<Vec<u64>>,
+    pub on_close: Callback<()>,
+}
+
+fn moved(ids: &[u64], from: usize, to: usize) -> Vec<u64> {
+    let mut ids = ids.to_vec();
+    let id = ids.remove(from);
+    ids.insert(to.min(ids.len()), id);
+    ids
+}
+
+/// Side panel listing the room's pins. Entries can be dragged into a new
+/// order, or moved with the arrow buttons from the keyboard.
+#[function_component(PinnedPanel)]
+pub fn pinned_panel(props: &PinnedPanelProps) -> Html {
+    let dragging = use_state(|| None::<usize>);
+    let ids: Vec<u64> = props.pins.iter().map(|p| p.id).collect();
+    let close = props.on_close.reform(|_: MouseEvent| ());
+
+    html! {
+        <aside aria-labelledby="pinned-title" class="flex-none w-72 h-screen overflow-y-auto border-l-2 border-gray-300 bg-gray-50 text-black p-3">
+            <div class="flex justify-between items-center mb-2">
+                <h2 id="pinned-title" class="font-bold">{format!("📌 Pinned ({}/{})", props.pins.len(), props.limit)}</h2>
+                <button onclick={close} aria-label="Close pinned messages" class="text-gray-400 hover:text-gray-700">{"✕"}</button>
+            </div>
+            if props.pins.is_empty() {
+                <div class="text-sm text-gray-500">{"Nothing pinned in this room yet."}</div>
+            }
+            <ol>
+                {
+                    props.pins.iter().enumerate().map(|(index, pin)| {
+                        let ondragstart = {
+                            let dragging = dragging.clone();
+                            Callback::from(move |_: DragEvent| dragging.set(Some(index)))
+                        };
+                        let ondragover = Callback::from(|e: DragEvent| e.prevent_default());
+                        let ondrop = {
+                            let dragging = dragging.clone();
+                            let ids = ids.clone();
+                            props.on_reorder.reform(move |e: DragEvent| {
+                                e.prevent_default();
+                                let from = dragging.unwrap_or(index);
+                                dragging.set(None);
+                                moved(&ids, from, index)
+                            })
+                        };
+                        let ondragend = {
+                            let dragging = dragging.clone();
+                            Callback::from(move |_: DragEvent| dragging.set(None))
+                        };
+                        let up = {
+                            let ids = ids.clone();
+                            props.on_reorder.reform(move |_: MouseEvent| moved(&ids, index, index.saturating_sub(1)))
+                        };
+                        let down = {
+                            let ids = ids.clone();
+                            props.on_reorder.reform(move |_: MouseEvent| moved(&ids, index, index + 1))
+                        };
+                        let id = pin.id;
+                        let unpin = props.on_unpin.reform(move |_: MouseEvent| id);
+                        let classes = if *dragging == Some(index) { "opacity-50" } else { "" };
+                        html! {
+                            <li
+                                key={pin.id}
+                                draggable={props.editable.to_string()}
+                                {ondragstart}
+                                {ondragover}
+                                {ondrop}
+                                {ondragend}
+                                class={classes!("flex", "items-start", "mb-2", "p-2", "bg-white", "rounded", "shadow-sm", props.editable.then_some("cursor-move"), classes)}
+                            >
+                                <div class="grow text-sm min-w-0">
+                                    <div class="text-xs font-bold">{pin.from.clone().unwrap_or_default()}</div>
+                                    <div class="truncate">
+                                        {pin.text.clone().unwrap_or_else(|| format!("Message #{} (not loaded)", pin.id))}
+                                    </div>
+                                </div>
+                                if props.editable {
+                                    <div class="flex flex-col ml-1 text-xs text-gray-400">
+                                        <button onclick={up} disabled={index == 0} aria-label="Move pin up" class="hover:text-gray-700 disabled:opacity-30">{"▲"}</button>
+                                        <button onclick={down} disabled={index + 1 == props.pins.len()} aria-label="Move pin down" class="hover:text-gray-700 disabled:opacity-30">{"▼"}</button>
+                                    </div>
+                                    <button onclick={unpin} aria-label="Unpin message" class="ml-1 text-xs text-gray-400 hover:text-gray-700">{"✕"}</button>
+                                }
+                            </li>
+                        }
+                    }).collect::<Html>()
+                }
+            </ol>
+        </aside>
+    }
+}
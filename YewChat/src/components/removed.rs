@@ -0,0 +1,62 @@
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+use crate::Route;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum RemovalKind {
+    Kick,
+    Ban,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct RemovedProps {
+    pub kind: RemovalKind,
+    pub reason: String,
+    /// How long the ban lasts; `None` for a kick or a permanent ban.
+    pub seconds: Option<u64>,
+    /// The moderator who did it, if it wasn't done from the server.
+    #[prop_or_default]
+    pub by: Option<String>,
+}
+
+fn describe_duration(seconds: u64) -> String {
+    match seconds {
+        s if s < 60 => format!("{} seconds", s),
+        s if s < 3_600 => format!("{} minutes", s.div_ceil(60)),
+        s if s < 86_400 => format!("{} hours", s.div_ceil(3_600)),
+        s => format!("{} days", s.div_ceil(86_400)),
+    }
+}
+
+/// Shown in place of the chat once the server has removed us. The socket is
+/// already closed by then, so nothing here reconnects on its own.
+#[function_component(Removed)]
+pub fn removed(props: &RemovedProps) -> Html {
+    let (title, detail) = match (props.kind, props.seconds) {
+        (RemovalKind::Kick, _) => ("You were removed from the chat", "You can sign in again whenever you're ready.".to_string()),
+        (RemovalKind::Ban, Some(seconds)) => (
+            "You have been banned",
+            format!("The ban lifts in about {}.", describe_duration(seconds)),
+        ),
+        (RemovalKind::Ban, None) => ("You have been banned", "This ban does not expire.".to_string()),
+    };
+
+    html! {
+        <div class="bg-gray-800 flex w-screen">
+            <div role="alert" class="container mx-auto flex flex-col justify-center items-center text-white text-center">
+                <h1 class="text-2xl font-bold mb-3">{title}</h1>
+                if let Some(by) = &props.by {
+                    <p class="mb-2">{format!("By {}.", by)}</p>
+                }
+                if !props.reason.is_empty() {
+                    <p class="mb-2">{format!("Reason: {}", props.reason)}</p>
+                }
+                <p class="mb-6 text-gray-300">{detail}</p>
+                if props.kind == RemovalKind::Kick {
+                    <Link<Route> to={Route::Login} classes="px-6 py-3 rounded-lg bg-violet-600 font-bold uppercase">{"Back to sign in"}</Link<Route>>
+                }
+            </div>
+        </div>
+    }
+}
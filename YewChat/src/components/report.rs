@@ -0,0 +1,75 @@
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use yew::prelude::*;
+
+const FRAME_CHOICES: [usize; 4] = [50, 100, 250, 500];
+
+#[derive(Properties, PartialEq)]
+pub struct ReportDialogProps {
+    /// How many frames there are to include.
+    pub available: usize,
+    /// Takes how many of the latest frames to include, and whether to
+    /// redact what people wrote.
+    pub on_download: Callback<(usize, bool)>,
+    pub on_close: Callback<()>,
+}
+
+/// Bundles what's needed to look into a problem into one file to attach
+/// to an issue: recent frames, log lines, settings and versions.
+#[function_component(ReportDialog)]
+pub fn report_dialog(props: &ReportDialogProps) -> Html {
+    let count = use_state(|| 100usize);
+    let redact = use_state(|| true);
+    let on_count = {
+        let count = count.clone();
+        Callback::from(move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            count.set(select.value().parse().unwrap_or(100));
+        })
+    };
+    let on_redact = {
+        let redact = redact.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            redact.set(input.checked());
+        })
+    };
+    let download = {
+        let count = *count;
+        let redact = *redact;
+        props.on_download.reform(move |_: MouseEvent| (count, redact))
+    };
+    let close = props.on_close.reform(|_: MouseEvent| ());
+
+    html! {
+        <div class="fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center z-50">
+            <div role="dialog" aria-modal="true" aria-labelledby="report-title" class="bg-white text-gray-900 rounded-lg shadow-xl w-96 max-w-full p-6">
+                <h2 id="report-title" class="text-lg font-bold mb-2">{"Report a problem"}</h2>
+                <p class="text-sm mb-4">
+                    {"Download a diagnostic file to attach to an issue. It holds the latest frames sent and received, recent log lines, your settings, and the app and browser versions."}
+                </p>
+                <label class="flex items-center justify-between text-sm mb-3">
+                    {"Frames to include"}
+                    <select onchange={on_count} class="px-2 py-1 rounded bg-gray-100">
+                        {
+                            FRAME_CHOICES.iter().map(|n| html! {
+                                <option value={n.to_string()} selected={*n == *count}>{format!("Last {}", n)}</option>
+                            }).collect::<Html>()
+                        }
+                    </select>
+                </label>
+                <div class="text-xs text-gray-500 mb-3">{format!("{} recorded so far.", props.available)}</div>
+                <label class="flex items-start text-sm mb-4">
+                    <input type="checkbox" checked={*redact} onchange={on_redact} class="mr-2 mt-1" />
+                    <span>
+                        {"Redact message text"}
+                        <span class="block text-xs text-gray-500">{"Replaces what people wrote, file contents and your saved replies with their length. Sign-in tokens are always left out."}</span>
+                    </span>
+                </label>
+                <div class="flex justify-end">
+                    <button onclick={close} class="px-4 py-2 rounded hover:bg-gray-100 mr-2">{"Cancel"}</button>
+                    <button onclick={download} class="px-4 py-2 rounded bg-blue-600 text-white hover:bg-blue-700">{"Download"}</button>
+                </div>
+            </div>
+        </div>
+    }
+}
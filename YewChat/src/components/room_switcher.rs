@@ -0,0 +1,297 @@
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+pub const DEFAULT_ROOM: &str = "general";
+
+/// A direct conversation with `name` is kept as a room called `@name`.
+pub fn dm_room(name: &str) -> String {
+    format!("@{}", name)
+}
+
+/// Who a direct conversation is with, or `None` for a public room.
+pub fn dm_peer(room: &str) -> Option<&str> {
+    room.strip_prefix('@')
+}
+
+/// `#room`, or `@name` for a direct conversation.
+pub fn room_label(room: &str) -> String {
+    match dm_peer(room) {
+        Some(_) => room.to_string(),
+        None => format!("#{}", room),
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Room {
+    pub name: String,
+    pub unread: usize,
+    /// Unread messages that mention us or a watched word.
+    pub mentions: usize,
+    /// The topic: set by whoever created the room, if they said anything,
+    /// and changed later by them or a moderator.
+    pub description: String,
+    /// Who made the room with `createroom`; empty if it just sprang up.
+    pub created_by: String,
+    /// We asked the server to create it and haven't heard back.
+    pub pending: bool,
+    /// Counts its unread messages, but never notifies.
+    pub muted: bool,
+    /// Read-only, and listed apart from the others.
+    pub archived: bool,
+}
+
+impl Room {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            unread: 0,
+            mentions: 0,
+            description: String::new(),
+            created_by: String::new(),
+            pending: false,
+            muted: false,
+            archived: false,
+        }
+    }
+}
+
+/// A room's mention and unread counts, greyed out if it's muted.
+pub fn unread_badges(room: &Room) -> Html {
+    let (mentions, unread) = if room.muted {
+        ("bg-gray-300 text-gray-700", "bg-gray-400 text-white")
+    } else {
+        ("bg-yellow-400 text-black", "bg-red-500 text-white")
+    };
+    html! {
+        <span>
+            if room.muted {
+                <span class="text-xs mr-1" title="Muted">{"🔕"}</span>
+            }
+            if room.mentions > 0 {
+                <span class={classes!(mentions, "text-xs", "rounded-full", "px-2", "py-0.5", "mr-1")} title="Mentions and watched words">{format!("@{}", room.mentions)}</span>
+            }
+            if room.unread > 0 {
+                <span class={classes!(unread, "text-xs", "rounded-full", "px-2", "py-0.5")}>{room.unread}</span>
+            }
+        </span>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct RoomSwitcherProps {
+    pub rooms: Vec<Room>,
+    pub active: String,
+    pub on_select: Callback<String>,
+    pub on_join: Callback<String>,
+    pub on_new: Callback<()>,
+}
+
+#[function_component(RoomSwitcher)]
+pub fn room_switcher(props: &RoomSwitcherProps) -> Html {
+    let open = use_state(|| false);
+    let highlighted = use_state(|| 0usize);
+    let show_archived = use_state(|| false);
+    let (archived, rooms): (Vec<Room>, Vec<Room>) = props.rooms.iter().cloned().partition(|r| r.archived);
+
+    // Muted rooms keep their own counts, but don't call us away from this one.
+    let other_unread: usize = rooms
+        .iter()
+        .filter(|r| r.name != props.active && !r.muted)
+        .map(|r| r.unread)
+        .sum();
+    let other_mentions: usize = rooms
+        .iter()
+        .filter(|r| r.name != props.active && !r.muted)
+        .map(|r| r.mentions)
+        .sum();
+
+    let toggle = {
+        let open = open.clone();
+        Callback::from(move |_: MouseEvent| open.set(!*open))
+    };
+
+    // The toggle button keeps focus while the menu is open, and the
+    // highlighted entry is announced through aria-activedescendant.
+    let onkeydown = {
+        let open = open.clone();
+        let highlighted = highlighted.clone();
+        let rooms = rooms.clone();
+        let on_select = props.on_select.clone();
+        Callback::from(move |e: KeyboardEvent| {
+            if rooms.is_empty() {
+                return;
+            }
+            match e.key().as_str() {
+                "ArrowDown" => {
+                    e.prevent_default();
+                    if *open {
+                        highlighted.set((*highlighted + 1) % rooms.len());
+                    } else {
+                        open.set(true);
+                    }
+                }
+                "ArrowUp" => {
+                    e.prevent_default();
+                    if *open {
+                        highlighted.set((*highlighted + rooms.len() - 1) % rooms.len());
+                    } else {
+                        open.set(true);
+                    }
+                }
+                "Enter" | " " if *open => {
+                    e.prevent_default();
+                    if let Some(room) = rooms.get(*highlighted).filter(|r| !r.pending) {
+                        on_select.emit(room.name.clone());
+                    }
+                    open.set(false);
+                }
+                "Escape" => open.set(false),
+                _ => {}
+            }
+        })
+    };
+
+    let join_keydown = {
+        let open = open.clone();
+        let on_join = props.on_join.clone();
+        Callback::from(move |e: KeyboardEvent| {
+            match e.key().as_str() {
+                "Enter" => {
+                    let input: HtmlInputElement = e.target_unchecked_into();
+                    let name = input.value().trim().trim_start_matches('#').to_lowercase();
+                    if !name.is_empty() {
+                        on_join.emit(name);
+                        input.set_value("");
+                        open.set(false);
+                    }
+                }
+                "Escape" => open.set(false),
+                _ => {}
+            }
+        })
+    };
+
+    let new_room = {
+        let open = open.clone();
+        props.on_new.reform(move |_: MouseEvent| open.set(false))
+    };
+
+    html! {
+        <div class="relative">
+            <button
+                onclick={toggle}
+                {onkeydown}
+                aria-haspopup="listbox"
+                aria-expanded={open.to_string()}
+                aria-activedescendant={if *open { format!("room-option-{}", *highlighted) } else { String::new() }}
+                class="flex items-center text-xl font-bold rounded px-2 py-1 hover:bg-gray-200 focus:outline-none focus:ring-2 focus:ring-blue-600"
+            >
+                {format!("💬 {}", room_label(&props.active))}
+                if other_mentions > 0 {
+                    <span class="ml-2 bg-yellow-400 text-black text-xs rounded-full px-2 py-0.5" title="Mentions and watched words">{format!("@{}", other_mentions)}</span>
+                }
+                if other_unread > 0 {
+                    <span class="ml-2 bg-red-500 text-white text-xs rounded-full px-2 py-0.5">{other_unread}</span>
+                }
+                <span class="ml-1 text-sm">{"▾"}</span>
+            </button>
+            if *open {
+                <div class="absolute left-0 mt-1 w-56 bg-white text-black shadow-lg rounded-lg z-10">
+                    <ul role="listbox" aria-label="Rooms" class="py-1">
+                        {
+                            rooms.iter().enumerate().map(|(i, room)| {
+                                let name = room.name.clone();
+                                // Not enterable until the server has agreed to it.
+                                let onclick = (!room.pending).then(|| {
+                                    let open = open.clone();
+                                    props.on_select.reform(move |_: MouseEvent| {
+                                        open.set(false);
+                                        name.clone()
+                                    })
+                                });
+                                let active = room.name == props.active;
+                                let classes = if i == *highlighted { "bg-gray-200" } else { "hover:bg-gray-100" };
+                                html! {
+                                    <li
+                                        id={format!("room-option-{}", i)}
+                                        role="option"
+                                        aria-selected={active.to_string()}
+                                        aria-disabled={room.pending.then_some("true")}
+                                        title={(!room.description.is_empty()).then(|| room.description.clone())}
+                                        {onclick}
+                                        class={format!("flex justify-between items-center px-3 py-2 {} {}", if room.pending { "text-gray-400 italic" } else { "cursor-pointer" }, classes)}
+                                    >
+                                        <span class={if active { "font-bold" } else { "" }}>{room_label(&room.name)}</span>
+                                        if room.pending {
+                                            <span class="text-xs">{"creating…"}</span>
+                                        }
+                                        {unread_badges(room)}
+                                    </li>
+                                }
+                            }).collect::<Html>()
+                        }
+                    </ul>
+                    if !archived.is_empty() {
+                        <div class="border-t border-gray-200 py-1">
+                            <button
+                                onclick={
+                                    let show_archived = show_archived.clone();
+                                    Callback::from(move |_: MouseEvent| show_archived.set(!*show_archived))
+                                }
+                                aria-expanded={show_archived.to_string()}
+                                class="w-full flex justify-between px-3 py-1 text-xs text-gray-500 hover:bg-gray-100"
+                            >
+                                {format!("Archived ({})", archived.len())}
+                                <span>{if *show_archived { "▾" } else { "▸" }}</span>
+                            </button>
+                            if *show_archived {
+                                <ul aria-label="Archived rooms">
+                                    {
+                                        archived.iter().map(|room| {
+                                            let onclick = {
+                                                let open = open.clone();
+                                                let name = room.name.clone();
+                                                props.on_select.reform(move |_: MouseEvent| {
+                                                    open.set(false);
+                                                    name.clone()
+                                                })
+                                            };
+                                            let active = room.name == props.active;
+                                            html! {
+                                                <li key={room.name.clone()}>
+                                                    <button
+                                                        {onclick}
+                                                        title={(!room.description.is_empty()).then(|| room.description.clone())}
+                                                        aria-current={active.then_some("true")}
+                                                        class={classes!("w-full", "text-left", "px-3", "py-2", "text-gray-500", "hover:bg-gray-100", active.then_some("font-bold"))}
+                                                    >
+                                                        {format!("🗄️ {}", room_label(&room.name))}
+                                                    </button>
+                                                </li>
+                                            }
+                                        }).collect::<Html>()
+                                    }
+                                </ul>
+                            }
+                        </div>
+                    }
+                    <div class="border-t border-gray-200 p-2">
+                        <input
+                            onkeydown={join_keydown}
+                            type="text"
+                            placeholder="Join room..."
+                            aria-label="Join room"
+                            class="w-full px-2 py-1 text-sm bg-gray-100 rounded outline-none focus:ring-2 focus:ring-blue-600"
+                        />
+                        <button
+                            onclick={new_room}
+                            class="w-full mt-2 px-2 py-1 text-sm text-left text-blue-600 rounded hover:bg-gray-100"
+                        >
+                            {"+ New room"}
+                        </button>
+                    </div>
+                </div>
+            }
+        </div>
+    }
+}
@@ -0,0 +1,101 @@
+use web_sys::{HtmlInputElement, KeyboardEvent};
+use yew::prelude::*;
+
+// The server cuts anything longer.
+const MAX_TOPIC_LENGTH: usize = 200;
+
+#[derive(Properties, PartialEq)]
+pub struct RoomTopicProps {
+    pub topic: String,
+    /// Whether we may change it: moderators and whoever made the room.
+    pub editable: bool,
+    /// Takes the new topic, trimmed. Only called when it changed.
+    pub on_save: Callback<String>,
+}
+
+/// The room's topic beside its name, edited in place: Enter saves, Escape
+/// or clicking away leaves it as it was.
+#[function_component(RoomTopic)]
+pub fn room_topic(props: &RoomTopicProps) -> Html {
+    let editing = use_state(|| false);
+    let input = use_node_ref();
+    {
+        let input = input.clone();
+        use_effect_with_deps(
+            move |editing| {
+                if *editing {
+                    if let Some(input) = input.cast::<HtmlInputElement>() {
+                        let _ = input.focus();
+                        input.select();
+                    }
+                }
+                || ()
+            },
+            *editing,
+        );
+    }
+
+    if *editing {
+        let onkeydown = {
+            let editing = editing.clone();
+            let on_save = props.on_save.clone();
+            let current = props.topic.clone();
+            Callback::from(move |e: KeyboardEvent| match e.key().as_str() {
+                "Enter" => {
+                    e.prevent_default();
+                    let input: HtmlInputElement = e.target_unchecked_into();
+                    let topic = input.value().trim().to_string();
+                    if topic != current {
+                        on_save.emit(topic);
+                    }
+                    editing.set(false);
+                }
+                "Escape" => editing.set(false),
+                _ => {}
+            })
+        };
+        let onblur = {
+            let editing = editing.clone();
+            Callback::from(move |_: FocusEvent| editing.set(false))
+        };
+        return html! {
+            <input
+                ref={input}
+                value={props.topic.clone()}
+                {onkeydown}
+                {onblur}
+                maxlength={MAX_TOPIC_LENGTH.to_string()}
+                placeholder="What's this room about?"
+                aria-label="Room topic"
+                class="ml-3 w-96 max-w-full px-2 py-1 text-sm rounded border border-gray-300 text-gray-900"
+            />
+        };
+    }
+
+    if !props.editable {
+        if props.topic.is_empty() {
+            return html! {};
+        }
+        return html! {
+            <span class="ml-3 text-sm text-gray-500 truncate" title={props.topic.clone()}>{props.topic.clone()}</span>
+        };
+    }
+    let edit = {
+        let editing = editing.clone();
+        Callback::from(move |_: MouseEvent| editing.set(true))
+    };
+    html! {
+        <button
+            onclick={edit}
+            title="Edit the topic"
+            class="ml-3 text-sm text-gray-500 truncate text-left rounded px-1 hover:bg-gray-100"
+        >
+            if props.topic.is_empty() {
+                <span class="italic text-gray-400">{"Add a topic"}</span>
+            } else {
+                {props.topic.clone()}
+            }
+            <span class="ml-1 text-gray-400" aria-hidden="true">{"✎"}</span>
+        </button>
+    }
+}
@@ -0,0 +1,55 @@
+use web_sys::HtmlSelectElement;
+use yew::prelude::*;
+
+use crate::components::room_switcher::room_label;
+
+#[derive(Properties, PartialEq)]
+pub struct SelectionBarProps {
+    pub count: usize,
+    /// Where the selection can be forwarded to.
+    pub rooms: Vec<String>,
+    /// Whether we may delete everything selected.
+    pub can_delete: bool,
+    pub on_copy: Callback<()>,
+    pub on_forward: Callback<String>,
+    pub on_delete: Callback<()>,
+    pub on_cancel: Callback<()>,
+}
+
+/// Floats over the message list while selecting, with what can be done to
+/// the selection as a whole.
+#[function_component(SelectionBar)]
+pub fn selection_bar(props: &SelectionBarProps) -> Html {
+    let target = use_state(|| props.rooms.first().cloned().unwrap_or_default());
+    let on_target = {
+        let target = target.clone();
+        Callback::from(move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            target.set(select.value());
+        })
+    };
+    let forward = {
+        let target = target.clone();
+        props.on_forward.reform(move |_: MouseEvent| (*target).clone())
+    };
+    let empty = props.count == 0;
+
+    html! {
+        <div role="toolbar" aria-label="Selected messages" class="absolute bottom-16 left-1/2 -translate-x-1/2 flex items-center bg-gray-900 text-white text-sm rounded-full shadow-xl px-4 py-2 z-20">
+            <span class="mr-4 font-medium" aria-live="polite">{format!("{} selected", props.count)}</span>
+            <button onclick={props.on_copy.reform(|_: MouseEvent| ())} disabled={empty} class="mr-3 hover:underline disabled:opacity-40">{"Copy"}</button>
+            <select onchange={on_target} aria-label="Forward to room" class="bg-gray-800 rounded px-1 mr-1">
+                {
+                    props.rooms.iter().map(|room| html! {
+                        <option value={room.clone()} selected={*target == *room}>{room_label(room)}</option>
+                    }).collect::<Html>()
+                }
+            </select>
+            <button onclick={forward} disabled={empty} class="mr-3 hover:underline disabled:opacity-40">{"Forward"}</button>
+            if props.can_delete {
+                <button onclick={props.on_delete.reform(|_: MouseEvent| ())} disabled={empty} class="mr-3 text-red-300 hover:underline disabled:opacity-40">{"Delete"}</button>
+            }
+            <button onclick={props.on_cancel.reform(|_: MouseEvent| ())} aria-label="Stop selecting" class="text-gray-400 hover:text-white">{"✕"}</button>
+        </div>
+    }
+}
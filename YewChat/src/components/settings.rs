@@ -0,0 +1,1001 @@
+use std::collections::BTreeSet;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{HtmlInputElement, HtmlSelectElement, HtmlTextAreaElement};
+use yew::prelude::*;
+
+use crate::components::file_card::human_size;
+use crate::components::room_switcher::room_label;
+use crate::services::files;
+use crate::services::notification_rules::{Action, Condition, Rule};
+use crate::services::notifications;
+use crate::services::settings::{EmojiStyle, EnterKey, MessageFont, Settings, Template};
+use crate::services::storage::{Estimate, RoomUsage, KEEP_WHEN_PRUNING};
+use crate::services::transcript;
+use crate::services::webauthn::{self, Device};
+
+// What messages can be shown in: the browser's own, or one the language
+// detector knows.
+const DISPLAY_LANGUAGES: [(&str, &str); 18] = [
+    ("", "Same as the browser"),
+    ("en", "English"),
+    ("id", "Bahasa Indonesia"),
+    ("es", "Español"),
+    ("fr", "Français"),
+    ("de", "Deutsch"),
+    ("pt", "Português"),
+    ("it", "Italiano"),
+    ("nl", "Nederlands"),
+    ("ja", "日本語"),
+    ("ko", "한국어"),
+    ("zh", "中文"),
+    ("ru", "Русский"),
+    ("ar", "العربية"),
+    ("he", "עברית"),
+    ("el", "Ελληνικά"),
+    ("th", "ไทย"),
+    ("hi", "हिन्दी"),
+];
+
+#[derive(Properties, PartialEq)]
+pub struct SettingsPanelProps {
+    pub settings: Settings,
+    pub on_change: Callback<Settings>,
+    pub on_close: Callback<()>,
+    /// Devices registered for our name; `None` until the server has listed them.
+    pub devices: Option<Vec<Device>>,
+    /// Registers this device under the given name.
+    pub on_add_device: Callback<String>,
+    /// Takes a device ID.
+    pub on_remove_device: Callback<String>,
+    /// The file the conversation is being saved to, while it is.
+    pub transcript: Option<String>,
+    /// Starts (`true`) or stops saving the conversation to a file.
+    pub on_transcript: Callback<bool>,
+    pub on_export_history: Callback<()>,
+    /// Takes the contents of the chosen file.
+    pub on_import_history: Callback<String>,
+    /// The browser's storage estimate, where it gives one.
+    pub storage: Option<Estimate>,
+    pub room_usage: Vec<RoomUsage>,
+    /// Takes the room to drop old messages and files from.
+    pub on_prune: Callback<String>,
+    /// Opens the problem report.
+    pub on_report: Callback<()>,
+}
+
+fn toggle(
+    props: &SettingsPanelProps,
+    label: &str,
+    hint: &str,
+    get: fn(&Settings) -> bool,
+    set: fn(&mut Settings, bool),
+) -> Html {
+    let settings = props.settings.clone();
+    let onchange = props.on_change.reform(move |_: Event| {
+        let mut next = settings.clone();
+        set(&mut next, !get(&settings));
+        next
+    });
+    html! {
+        <label class="flex items-start py-2 cursor-pointer">
+            <input type="checkbox" class="mt-1 mr-3" checked={get(&props.settings)} {onchange} />
+            <div>
+                <div class="text-sm font-medium">{label}</div>
+                <div class="text-xs text-gray-500">{hint}</div>
+            </div>
+        </label>
+    }
+}
+
+fn select<T: Clone + PartialEq + 'static>(
+    props: &SettingsPanelProps,
+    label: &str,
+    options: &[(&str, &str, T)],
+    get: fn(&Settings) -> T,
+    set: fn(&mut Settings, T),
+) -> Html {
+    let settings = props.settings.clone();
+    let values: Vec<(String, T)> = options.iter().map(|(v, _, t)| (v.to_string(), t.clone())).collect();
+    let onchange = props.on_change.reform(move |e: Event| {
+        let select: HtmlSelectElement = e.target_unchecked_into();
+        let mut next = settings.clone();
+        if let Some((_, value)) = values.iter().find(|(v, _)| *v == select.value()) {
+            set(&mut next, value.clone());
+        }
+        next
+    });
+    let current = get(&props.settings);
+    html! {
+        <label class="flex justify-between items-center py-2">
+            <span class="text-sm font-medium">{label}</span>
+            <select {onchange} class="px-2 py-1 rounded bg-gray-100 text-sm">
+                {
+                    options.iter().map(|(value, text, t)| html! {
+                        <option value={value.to_string()} selected={*t == current}>{text.to_string()}</option>
+                    }).collect::<Html>()
+                }
+            </select>
+        </label>
+    }
+}
+
+fn templates(props: &SettingsPanelProps) -> Html {
+    let edit = |index: usize, apply: fn(&mut Template, String)| {
+        let settings = props.settings.clone();
+        props.on_change.reform(move |value: String| {
+            let mut next = settings.clone();
+            if let Some(template) = next.templates.get_mut(index) {
+                apply(template, value);
+            }
+            next
+        })
+    };
+    let add = {
+        let settings = props.settings.clone();
+        props.on_change.reform(move |_: MouseEvent| {
+            let mut next = settings.clone();
+            next.templates.push(Template {
+                name: format!("reply{}", next.templates.len() + 1),
+                text: String::new(),
+            });
+            next
+        })
+    };
+    html! {
+        <>
+            {
+                props.settings.templates.iter().enumerate().map(|(index, template)| {
+                    let rename = edit(index, |t, v| t.name = v.split_whitespace().collect::<Vec<_>>().join("-"))
+                        .reform(|e: Event| e.target_unchecked_into::<HtmlInputElement>().value());
+                    let retext = edit(index, |t, v| t.text = v)
+                        .reform(|e: Event| e.target_unchecked_into::<HtmlTextAreaElement>().value());
+                    let settings = props.settings.clone();
+                    let remove = props.on_change.reform(move |_: MouseEvent| {
+                        let mut next = settings.clone();
+                        next.templates.remove(index);
+                        next
+                    });
+                    html! {
+                        <div class="py-2 border-b border-gray-100">
+                            <div class="flex items-center">
+                                <span class="text-sm text-gray-500 mr-1">{"/template"}</span>
+                                <input
+                                    type="text"
+                                    aria-label="Template name"
+                                    value={template.name.clone()}
+                                    onchange={rename}
+                                    class="grow px-2 py-1 bg-gray-100 rounded text-sm outline-none focus:ring-2 focus:ring-blue-600"
+                                />
+                                <button onclick={remove} aria-label={format!("Delete template {}", template.name)} class="ml-2 text-gray-400 hover:text-gray-700">{"✕"}</button>
+                            </div>
+                            <textarea
+                                aria-label={format!("Text of template {}", template.name)}
+                                value={template.text.clone()}
+                                onchange={retext}
+                                rows="2"
+                                class="w-full mt-1 px-2 py-1 bg-gray-100 rounded text-sm outline-none focus:ring-2 focus:ring-blue-600"
+                            />
+                        </div>
+                    }
+                }).collect::<Html>()
+            }
+            <button onclick={add} class="mt-2 px-3 py-1 rounded bg-gray-200 text-sm hover:bg-gray-300">{"Add saved reply"}</button>
+            <div class="text-xs text-gray-500 mt-1">{"Use {user}, {me} and {room} as placeholders."}</div>
+        </>
+    }
+}
+
+fn notification_rules(props: &SettingsPanelProps) -> Html {
+    // Every edit is a change to one rule.
+    let edit = |index: usize| {
+        let settings = props.settings.clone();
+        props.on_change.reform(move |apply: Box<dyn FnOnce(&mut Rule)>| {
+            let mut next = settings.clone();
+            if let Some(rule) = next.notification_rules.get_mut(index) {
+                apply(rule);
+            }
+            next
+        })
+    };
+    let add = {
+        let settings = props.settings.clone();
+        props.on_change.reform(move |_: MouseEvent| {
+            let mut next = settings.clone();
+            next.notification_rules.push(Rule::default());
+            next
+        })
+    };
+    let rules = &props.settings.notification_rules;
+    html! {
+        <>
+            {
+                rules.iter().enumerate().map(|(index, rule)| {
+                    let edit = edit(index);
+                    let enable = edit.reform(|e: Event| {
+                        let on = e.target_unchecked_into::<HtmlInputElement>().checked();
+                        Box::new(move |r: &mut Rule| r.enabled = on) as Box<dyn FnOnce(&mut Rule)>
+                    });
+                    let add_condition = edit.reform(|e: Event| {
+                        let select: HtmlSelectElement = e.target_unchecked_into();
+                        let kind = select.value();
+                        select.set_value("");
+                        Box::new(move |r: &mut Rule| r.conditions.push(Condition::of_kind(&kind))) as Box<dyn FnOnce(&mut Rule)>
+                    });
+                    let settings = props.settings.clone();
+                    let remove = props.on_change.reform(move |_: MouseEvent| {
+                        let mut next = settings.clone();
+                        next.notification_rules.remove(index);
+                        next
+                    });
+                    let settings = props.settings.clone();
+                    let raise = props.on_change.reform(move |_: MouseEvent| {
+                        let mut next = settings.clone();
+                        next.notification_rules.swap(index - 1, index);
+                        next
+                    });
+                    html! {
+                        <div class="py-2 border-b border-gray-100">
+                            <div class="flex items-center text-sm">
+                                <input type="checkbox" checked={rule.enabled} onchange={enable} class="mr-2"
+                                    aria-label={format!("Rule {} on", index + 1)} />
+                                <span class="grow font-medium">{format!("Rule {}", index + 1)}</span>
+                                if index > 0 {
+                                    <button onclick={raise} aria-label={format!("Check rule {} sooner", index + 1)} class="ml-2 text-gray-400 hover:text-gray-700">{"↑"}</button>
+                                }
+                                <button onclick={remove} aria-label={format!("Delete rule {}", index + 1)} class="ml-2 text-gray-400 hover:text-gray-700">{"✕"}</button>
+                            </div>
+                            <div class="text-xs text-gray-500 mt-1">{"When"}</div>
+                            {
+                                rule.conditions.iter().enumerate().map(|(at, condition)| {
+                                    let drop = edit.reform(move |_: MouseEvent| {
+                                        Box::new(move |r: &mut Rule| { r.conditions.remove(at); }) as Box<dyn FnOnce(&mut Rule)>
+                                    });
+                                    let retext = edit.reform(move |e: Event| {
+                                        let value = e.target_unchecked_into::<HtmlInputElement>().value();
+                                        Box::new(move |r: &mut Rule| {
+                                            if let Some(c) = r.conditions.get_mut(at) {
+                                                c.set_value(value);
+                                            }
+                                        }) as Box<dyn FnOnce(&mut Rule)>
+                                    });
+                                    let (label, placeholder) = match condition {
+                                        Condition::Room(_) => ("in room", "ops"),
+                                        Condition::Sender(_) => ("from", "name"),
+                                        Condition::Keyword(_) => ("says", "deploy"),
+                                        Condition::Mention => ("mentions me", ""),
+                                    };
+                                    html! {
+                                        <div class="flex items-center text-sm mt-1 pl-4">
+                                            <span class="w-24 flex-none">{label}</span>
+                                            if let Some(value) = condition.value() {
+                                                <input
+                                                    type="text"
+                                                    value={value.to_string()}
+                                                    onchange={retext}
+                                                    {placeholder}
+                                                    aria-label={format!("Rule {} {}", index + 1, label)}
+                                                    class="grow px-2 py-1 bg-gray-100 rounded outline-none focus:ring-2 focus:ring-blue-600"
+                                                />
+                                            } else {
+                                                <span class="grow" />
+                                            }
+                                            <button onclick={drop} aria-label={format!("Remove \"{}\" from rule {}", label, index + 1)} class="ml-2 text-gray-400 hover:text-gray-700">{"✕"}</button>
+                                        </div>
+                                    }
+                                }).collect::<Html>()
+                            }
+                            <select onchange={add_condition} aria-label={format!("Add a condition to rule {}", index + 1)}
+                                class="mt-1 ml-4 px-1 bg-gray-100 rounded text-sm">
+                                <option value="" selected=true>{"+ condition"}</option>
+                                { Condition::KINDS.iter().map(|kind| html! { <option value={*kind}>{*kind}</option> }).collect::<Html>() }
+                            </select>
+                            <div class="text-xs text-gray-500 mt-2">{"Then"}</div>
+                            <div class="flex flex-wrap pl-4">
+                                {
+                                    Action::ALL.iter().map(|action| {
+                                        let action = *action;
+                                        let flip = edit.reform(move |_: Event| {
+                                            Box::new(move |r: &mut Rule| r.toggle_action(action)) as Box<dyn FnOnce(&mut Rule)>
+                                        });
+                                        html! {
+                                            <label class="flex items-center text-sm mr-3">
+                                                <input type="checkbox" checked={rule.actions.contains(&action)} onchange={flip} class="mr-1" />
+                                                {action.label()}
+                                            </label>
+                                        }
+                                    }).collect::<Html>()
+                                }
+                            </div>
+                        </div>
+                    }
+                }).collect::<Html>()
+            }
+            <button onclick={add} class="mt-2 px-3 py-1 rounded bg-gray-200 text-sm hover:bg-gray-300">{"Add rule"}</button>
+            <div class="text-xs text-gray-500 mt-1">{"The first rule a message matches decides; anything none match uses the toggles above. Muted users and quiet hours still win."}</div>
+        </>
+    }
+}
+
+fn devices(props: &SettingsPanelProps, platform: Option<bool>, device_name: UseStateHandle<String>) -> Html {
+    let rename = {
+        let device_name = device_name.clone();
+        Callback::from(move |e: InputEvent| device_name.set(e.target_unchecked_into::<HtmlInputElement>().value()))
+    };
+    let add = {
+        let device_name = device_name.clone();
+        props.on_add_device.reform(move |_: MouseEvent| (*device_name).clone())
+    };
+    html! {
+        <>
+            <div class="text-xs text-gray-500 py-1">
+                {"Once a device is registered, joining under your name needs one of your devices."}
+            </div>
+            {
+                match &props.devices {
+                    None => html! { <div class="text-sm text-gray-500 py-1">{"Loading…"}</div> },
+                    Some(devices) if devices.is_empty() => html! {
+                        <div class="text-sm text-gray-500 py-1">{"No devices registered; anyone can use your name."}</div>
+                    },
+                    Some(devices) => devices.iter().map(|device| {
+                        let id = device.id.clone();
+                        let remove = props.on_remove_device.reform(move |_: MouseEvent| id.clone());
+                        let added = js_sys::Date::new(&device.added.into()).to_locale_date_string("default", &JsValue::UNDEFINED);
+                        html! {
+                            <div class="flex items-center justify-between py-1">
+                                <div>
+                                    <div class="text-sm font-medium">{device.name.clone()}</div>
+                                    <div class="text-xs text-gray-500">{format!("Added {}", String::from(added))}</div>
+                                </div>
+                                <button onclick={remove} aria-label={format!("Remove device {}", device.name)} class="text-gray-400 hover:text-gray-700">{"✕"}</button>
+                            </div>
+                        }
+                    }).collect::<Html>(),
+                }
+            }
+            {
+                match platform {
+                    Some(true) => html! {
+                        <div class="flex items-center py-2">
+                            <input
+                                type="text"
+                                aria-label="Device name"
+                                value={(*device_name).clone()}
+                                oninput={rename}
+                                class="grow px-2 py-1 mr-2 bg-gray-100 rounded text-sm outline-none focus:ring-2 focus:ring-blue-600"
+                            />
+                            <button
+                                onclick={add}
+                                disabled={device_name.trim().is_empty()}
+                                class="px-3 py-1 rounded bg-blue-600 text-white text-sm hover:bg-blue-700 disabled:opacity-50"
+                            >
+                                {"Register this device"}
+                            </button>
+                        </div>
+                    },
+                    Some(false) => html! {
+                        <div class="text-xs text-gray-500 py-1">{"This browser or device has no built-in authenticator to register."}</div>
+                    },
+                    None => html! {},
+                }
+            }
+        </>
+    }
+}
+
+fn transcript(props: &SettingsPanelProps) -> Html {
+    if !transcript::supported() {
+        return html! {
+            <div class="text-xs text-gray-500 py-1">{"This browser can't save straight to a file."}</div>
+        };
+    }
+    match &props.transcript {
+        Some(name) => html! {
+            <div class="flex items-center justify-between py-2">
+                <div class="text-sm">{format!("Saving to {}", name)}</div>
+                <button
+                    onclick={props.on_transcript.reform(|_: MouseEvent| false)}
+                    class="px-3 py-1 rounded bg-gray-200 text-sm hover:bg-gray-300"
+                >
+                    {"Stop"}
+                </button>
+            </div>
+        },
+        None => html! {
+            <>
+                <div class="text-xs text-gray-500 py-1">
+                    {"Write every message to a file on this computer as it arrives, for as long as the tab is open."}
+                </div>
+                <button
+                    onclick={props.on_transcript.reform(|_: MouseEvent| true)}
+                    class="px-3 py-1 my-1 rounded bg-blue-600 text-white text-sm hover:bg-blue-700"
+                >
+                    {"Save transcript to a file…"}
+                </button>
+            </>
+        },
+    }
+}
+
+fn storage(props: &SettingsPanelProps) -> Html {
+    html! {
+        <>
+            if let Some(estimate) = props.storage {
+                <div class="py-2">
+                    <div class="flex justify-between text-sm">
+                        <span>{"Browser storage"}</span>
+                        <span>{format!("{} of {}", human_size(estimate.usage as usize), human_size(estimate.quota as usize))}</span>
+                    </div>
+                    <div
+                        role="progressbar"
+                        aria-label="Browser storage used"
+                        aria-valuenow={((estimate.ratio() * 100.0).round() as u32).to_string()}
+                        aria-valuemin="0"
+                        aria-valuemax="100"
+                        class="h-1.5 mt-1 bg-gray-200 rounded"
+                    >
+                        <div
+                            class={classes!("h-1.5", "rounded", if estimate.nearly_full() { "bg-red-500" } else { "bg-blue-600" })}
+                            style={format!("width: {:.0}%", (estimate.ratio() * 100.0).min(100.0))}
+                        ></div>
+                    </div>
+                    if estimate.nearly_full() {
+                        <div class="text-xs text-red-600 mt-1" role="alert">{"Almost full. Clearing rooms below frees what this tab is holding."}</div>
+                    }
+                </div>
+            }
+            <div class="text-xs text-gray-500 py-1">
+                {format!("Clearing a room drops the files sent in it and all but its newest {} messages. The messages can be loaded again by scrolling up; the files can't.", KEEP_WHEN_PRUNING)}
+            </div>
+            <ul>
+                {
+                    props.room_usage.iter().map(|usage| {
+                        let room = usage.room.clone();
+                        html! {
+                            <li class="flex items-center justify-between py-1 text-sm">
+                                <span>
+                                    {room_label(&usage.room)}
+                                    <span class="text-xs text-gray-500 ml-2">
+                                        {format!("{} messages, {} files · {}", usage.messages, usage.files, human_size(usage.bytes()))}
+                                    </span>
+                                </span>
+                                <button
+                                    onclick={props.on_prune.reform(move |_: MouseEvent| room.clone())}
+                                    disabled={usage.messages <= KEEP_WHEN_PRUNING && usage.files == 0}
+                                    class="px-2 py-0.5 rounded bg-gray-200 text-xs hover:bg-gray-300 disabled:opacity-50"
+                                >
+                                    {"Clear old"}
+                                </button>
+                            </li>
+                        }
+                    }).collect::<Html>()
+                }
+            </ul>
+        </>
+    }
+}
+
+#[function_component(SettingsPanel)]
+pub fn settings_panel(props: &SettingsPanelProps) -> Html {
+    let import_error = use_state(|| None::<String>);
+    let close = props.on_close.reform(|_: MouseEvent| ());
+    let platform = use_state(|| None::<bool>);
+    let device_name = use_state(|| "This device".to_string());
+    {
+        let platform = platform.clone();
+        use_effect_with_deps(
+            move |_| {
+                spawn_local(async move { platform.set(Some(webauthn::platform_authenticator_available().await)) });
+                || ()
+            },
+            (),
+        );
+    }
+
+    let away_message = {
+        let settings = props.settings.clone();
+        props.on_change.reform(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut next = settings.clone();
+            next.away_message = input.value().trim().to_string();
+            next
+        })
+    };
+
+    let quiet_hours = |apply: fn(&mut Settings, String)| {
+        let settings = props.settings.clone();
+        props.on_change.reform(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut next = settings.clone();
+            apply(&mut next, input.value().trim().to_string());
+            next
+        })
+    };
+
+    let double_tap_reaction = {
+        let settings = props.settings.clone();
+        props.on_change.reform(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut next = settings.clone();
+            next.double_tap_reaction = input.value().trim().to_string();
+            next
+        })
+    };
+
+    let watch_keywords = {
+        let settings = props.settings.clone();
+        props.on_change.reform(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut next = settings.clone();
+            next.watch_keywords = input.value().split(',').map(str::trim).filter(|k| !k.is_empty()).map(str::to_string).collect();
+            next
+        })
+    };
+
+    let filtered_words = {
+        let settings = props.settings.clone();
+        props.on_change.reform(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut next = settings.clone();
+            next.filtered_words = input.value().split(',').map(str::trim).filter(|w| !w.is_empty()).map(str::to_string).collect();
+            next
+        })
+    };
+
+    // As the room switcher takes them: no `#`, lower case.
+    let room_name = |name: &str| name.trim().trim_start_matches('#').to_lowercase();
+    let auto_join_rooms = {
+        let settings = props.settings.clone();
+        props.on_change.reform(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut next = settings.clone();
+            next.auto_join_rooms = input.value().split(',').map(room_name).filter(|r| !r.is_empty()).collect();
+            next
+        })
+    };
+    let default_room = {
+        let settings = props.settings.clone();
+        props.on_change.reform(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut next = settings.clone();
+            next.default_room = room_name(&input.value());
+            next
+        })
+    };
+
+    let translation_glossary = {
+        let settings = props.settings.clone();
+        props.on_change.reform(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut next = settings.clone();
+            next.translation_glossary = input.value().split(',').map(str::trim).filter(|t| !t.is_empty()).map(str::to_string).collect();
+            next
+        })
+    };
+
+    let trusted_domains = {
+        let settings = props.settings.clone();
+        props.on_change.reform(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut next = settings.clone();
+            next.trusted_domains = input.value().split_whitespace().map(str::to_lowercase).collect();
+            next
+        })
+    };
+
+    let quick_reactions = {
+        let settings = props.settings.clone();
+        props.on_change.reform(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut next = settings.clone();
+            next.quick_reactions = input.value().split_whitespace().map(String::from).collect();
+            next
+        })
+    };
+
+    let export = {
+        let settings = props.settings.clone();
+        Callback::from(move |_: MouseEvent| {
+            files::download("yewchat-settings.json", "application/json", &settings.export());
+        })
+    };
+
+    let import = {
+        let on_change = props.on_change.clone();
+        let import_error = import_error.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let file = match input.files().and_then(|files| files.get(0)) {
+                Some(file) => file,
+                None => return,
+            };
+            input.set_value("");
+            let on_change = on_change.clone();
+            let import_error = import_error.clone();
+            spawn_local(async move {
+                let text = JsFuture::from(file.text()).await.ok().and_then(|v| v.as_string());
+                match text.as_deref().map(Settings::import) {
+                    Some(Ok(settings)) => {
+                        import_error.set(None);
+                        on_change.emit(settings);
+                    }
+                    Some(Err(e)) => import_error.set(Some(format!("Not a settings file: {}", e))),
+                    None => import_error.set(Some("Could not read the file.".into())),
+                }
+            });
+        })
+    };
+
+    let export_history = props.on_export_history.reform(|_: MouseEvent| ());
+    let report = props.on_report.reform(|_: MouseEvent| ());
+
+    let import_history = {
+        let on_import_history = props.on_import_history.clone();
+        let import_error = import_error.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let file = match input.files().and_then(|files| files.get(0)) {
+                Some(file) => file,
+                None => return,
+            };
+            input.set_value("");
+            let on_import_history = on_import_history.clone();
+            let import_error = import_error.clone();
+            spawn_local(async move {
+                match JsFuture::from(file.text()).await.ok().and_then(|v| v.as_string()) {
+                    Some(text) => {
+                        import_error.set(None);
+                        on_import_history.emit(text);
+                    }
+                    None => import_error.set(Some("Could not read the file.".into())),
+                }
+            });
+        })
+    };
+
+    html! {
+        <div class="fixed inset-0 bg-black bg-opacity-40 flex justify-center items-center z-30">
+            <div role="dialog" aria-modal="true" aria-labelledby="settings-title" class="bg-white text-black rounded-lg shadow-xl w-96 max-h-[80vh] overflow-y-auto p-5">
+                <div class="flex justify-between items-center mb-3">
+                    <h2 id="settings-title" class="text-lg font-bold">{"Settings"}</h2>
+                    <button onclick={close} aria-label="Close settings" class="text-gray-400 hover:text-gray-700">{"✕"}</button>
+                </div>
+
+                <h3 class="text-xs uppercase tracking-wide text-gray-500 mt-2">{"Appearance"}</h3>
+                { select(props, "Message font",
+                    &[("system", "System", MessageFont::System),
+                      ("serif", "Serif", MessageFont::Serif),
+                      ("monospace", "Monospace", MessageFont::Monospace)],
+                    |s| s.message_font.clone(), |s, v| s.message_font = v) }
+                { select(props, "Emoji",
+                    &[("native", "Native", EmojiStyle::Native),
+                      ("twemoji", "Twemoji", EmojiStyle::Twemoji)],
+                    |s| s.emoji_style.clone(), |s, v| s.emoji_style = v) }
+                { select(props, "Enter key",
+                    &[("auto", "Sends (new line on touch screens)", EnterKey::Auto),
+                      ("send", "Sends, Shift+Enter for a new line", EnterKey::Send),
+                      ("ctrlsend", "New line, Ctrl+Enter sends", EnterKey::CtrlSend)],
+                    |s| s.enter_key.clone(), |s, v| s.enter_key = v) }
+                { toggle(props, "Mention chips", "Show @mentions you're writing as chips, so Backspace takes out the whole name.",
+                    |s| s.mention_chips, |s, v| s.mention_chips = v) }
+
+                { select(props, "Language",
+                    &DISPLAY_LANGUAGES.iter().map(|(code, name)| (*code, *name, code.to_string())).collect::<Vec<_>>(),
+                    |s| s.display_language.clone(), |s, v| s.display_language = v) }
+                <label class="block py-2">
+                    <div class="text-sm font-medium">{"Don't translate"}</div>
+                    <input
+                        type="text"
+                        value={props.settings.translation_glossary.join(", ")}
+                        onchange={translation_glossary}
+                        placeholder="YewChat, Ragnall, borrow checker"
+                        class="w-full mt-1 px-2 py-1 bg-gray-100 rounded outline-none focus:ring-2 focus:ring-blue-600"
+                    />
+                    <div class="text-xs text-gray-500">{"Names and jargon kept as written when messages are translated into your language. Separate them with commas."}</div>
+                </label>
+                { toggle(props, "Low-data mode", "Skip avatars, images and animations until you ask for them.",
+                    |s| s.low_data, |s, v| s.low_data = v) }
+                { select(props, "Load pictures up to",
+                    &[("512k", "512 KB", Some(512 * 1024)),
+                      ("1m", "1 MB", Some(1024 * 1024)),
+                      ("2m", "2 MB", Some(2 * 1024 * 1024)),
+                      ("5m", "5 MB", Some(5 * 1024 * 1024)),
+                      ("10m", "10 MB", Some(10 * 1024 * 1024)),
+                      ("none", "Any size", None)],
+                    |s| s.media_auto_load_limit, |s, v| s.media_auto_load_limit = v) }
+                { toggle(props, "Describe images", "Ask for alt text when sending a picture, for people using screen readers.",
+                    |s| s.ask_alt_text, |s, v| s.ask_alt_text = v) }
+                { toggle(props, "Connection diagnostics", "Count the frames and bytes going over the connection.",
+                    |s| s.diagnostics, |s, v| s.diagnostics = v) }
+
+                <h3 class="text-xs uppercase tracking-wide text-gray-500 mt-4">{"Rooms"}</h3>
+                <label class="block py-2">
+                    <div class="text-sm font-medium">{"Join on sign-in"}</div>
+                    <input
+                        type="text"
+                        value={props.settings.auto_join_rooms.join(", ")}
+                        onchange={auto_join_rooms}
+                        placeholder="announcements, random"
+                        class="w-full mt-1 px-2 py-1 bg-gray-100 rounded outline-none focus:ring-2 focus:ring-blue-600"
+                    />
+                    <div class="text-xs text-gray-500">{"Rooms to join every time you sign in, besides #general. Separate them with commas."}</div>
+                </label>
+                <label class="block py-2">
+                    <div class="text-sm font-medium">{"Start in"}</div>
+                    <input
+                        type="text"
+                        value={props.settings.default_room.clone()}
+                        onchange={default_room}
+                        placeholder="general"
+                        class="w-full mt-1 px-2 py-1 bg-gray-100 rounded outline-none focus:ring-2 focus:ring-blue-600"
+                    />
+                    <div class="text-xs text-gray-500">{"The room you see first after signing in. It's joined too."}</div>
+                </label>
+
+                <h3 class="text-xs uppercase tracking-wide text-gray-500 mt-4">{"Privacy"}</h3>
+                { toggle(props, "Show typing indicators", "See when others are typing.",
+                    |s| s.show_typing_indicators, |s, v| s.show_typing_indicators = v) }
+                { toggle(props, "Send typing events", "Let others see when you are typing.",
+                    |s| s.send_typing_events, |s, v| s.send_typing_events = v) }
+                { toggle(props, "Send typing events in direct messages", "Let the person you're writing to privately see when you are typing. Off unless you turn it on.",
+                    |s| s.send_direct_typing_events, |s, v| s.send_direct_typing_events = v) }
+                { toggle(props, "Send read receipts", "Let others see how far you have read.",
+                    |s| s.send_read_receipts, |s, v| s.send_read_receipts = v) }
+                <div class="py-2">
+                    <div class="text-sm font-medium">{"Private conversations"}</div>
+                    if props.settings.conversation_privacy.is_empty() {
+                        <div class="text-xs text-gray-500">{"None. Use 🕶️ in a conversation's header to stop sending read receipts or typing events in just that one."}</div>
+                    } else {
+                        <div class="flex flex-wrap mt-1">
+                            {
+                                // Sorted, so the chips don't shuffle between renders.
+                                props.settings.conversation_privacy.keys().collect::<BTreeSet<_>>().into_iter().map(|room| {
+                                    let settings = props.settings.clone();
+                                    let name = room.clone();
+                                    let reset = props.on_change.reform(move |_: MouseEvent| {
+                                        let mut next = settings.clone();
+                                        next.conversation_privacy.remove(&name);
+                                        next
+                                    });
+                                    html! {
+                                        <button onclick={reset} aria-label={format!("Stop keeping {} private", room_label(room))} class="bg-gray-200 rounded-full px-2 py-1 text-xs mr-1 mb-1">
+                                            {format!("{} ✕", room_label(room))}
+                                        </button>
+                                    }
+                                }).collect::<Html>()
+                            }
+                        </div>
+                    }
+                </div>
+                { toggle(props, "Broadcast presence", "Share your online/away status.",
+                    |s| s.broadcast_presence, |s, v| s.broadcast_presence = v) }
+                { toggle(props, "Message stats", "Show a summary of what you send. Worked out locally, never uploaded.",
+                    |s| s.message_stats, |s, v| s.message_stats = v) }
+                <label class="block py-2">
+                    <div class="text-sm font-medium">{"Away message"}</div>
+                    <input
+                        type="text"
+                        value={props.settings.away_message.clone()}
+                        onchange={away_message}
+                        placeholder="I'm away until 3pm"
+                        class="w-full mt-1 px-2 py-1 bg-gray-100 rounded outline-none focus:ring-2 focus:ring-blue-600"
+                    />
+                    <div class="text-xs text-gray-500">{"Sent privately, once, to each person who mentions or messages you while you're away. Leave empty to turn off."}</div>
+                </label>
+                <label class="block py-2">
+                    <div class="text-sm font-medium">{"Trusted link domains"}</div>
+                    <input
+                        type="text"
+                        value={props.settings.trusted_domains.join(" ")}
+                        onchange={trusted_domains}
+                        placeholder="example.com"
+                        class="w-full mt-1 px-2 py-1 bg-gray-100 rounded outline-none focus:ring-2 focus:ring-blue-600"
+                    />
+                    <div class="text-xs text-gray-500">{"Links elsewhere ask before opening. Separate domains with spaces."}</div>
+                </label>
+
+                <h3 class="text-xs uppercase tracking-wide text-gray-500 mt-4">{"Notifications"}</h3>
+                { toggle(props, "In-app toasts", "Pop up a summary of new messages.",
+                    |s| s.toast_notifications, |s, v| s.toast_notifications = v) }
+                { toggle(props, "Desktop notifications", "Notify while the tab is in the background.",
+                    |s| s.desktop_notifications, |s, v| s.desktop_notifications = v) }
+                <label class="block py-2">
+                    <div class="text-sm font-medium">{"Watched words"}</div>
+                    <input
+                        type="text"
+                        value={props.settings.watch_keywords.join(", ")}
+                        onchange={watch_keywords}
+                        placeholder="deploy, outage"
+                        class="w-full mt-1 px-2 py-1 bg-gray-100 rounded outline-none focus:ring-2 focus:ring-blue-600"
+                    />
+                    <div class="text-xs text-gray-500">{"Messages containing any of these are highlighted and counted with your mentions. Separate them with commas."}</div>
+                </label>
+                { toggle(props, "Filter words", "Star out the words below wherever they're said, and ask before you send one.",
+                    |s| s.word_filter, |s, v| s.word_filter = v) }
+                if props.settings.word_filter {
+                    <label class="block py-2">
+                        <div class="text-sm font-medium">{"Filtered words"}</div>
+                        <input
+                            type="text"
+                            value={props.settings.filtered_words.join(", ")}
+                            onchange={filtered_words}
+                            class="w-full mt-1 px-2 py-1 bg-gray-100 rounded outline-none focus:ring-2 focus:ring-blue-600"
+                        />
+                        <div class="text-xs text-gray-500">{"Whole words or phrases, in any case. Separate them with commas."}</div>
+                    </label>
+                }
+                <div class="py-2">
+                    <div class="text-sm font-medium">{"Rules"}</div>
+                    { notification_rules(props) }
+                </div>
+                { toggle(props, "Mention when replying", "Start a reply with @name, so the author hears about it even if they've moved on.",
+                    |s| s.mention_on_reply, |s, v| s.mention_on_reply = v) }
+                { toggle(props, "Quiet hours", "Hold back toasts and desktop notifications at the same time every day, and sum up what came in once it ends.",
+                    |s| s.quiet_hours.enabled, |s, v| s.quiet_hours.enabled = v) }
+                if props.settings.quiet_hours.enabled {
+                    <div class="flex items-center text-sm pb-2 pl-7">
+                        <label class="mr-3">
+                            {"From "}
+                            <input type="time" value={props.settings.quiet_hours.start.clone()}
+                                onchange={quiet_hours(|s, v| s.quiet_hours.start = v)}
+                                class="px-1 bg-gray-100 rounded" />
+                        </label>
+                        <label>
+                            {"to "}
+                            <input type="time" value={props.settings.quiet_hours.end.clone()}
+                                onchange={quiet_hours(|s, v| s.quiet_hours.end = v)}
+                                class="px-1 bg-gray-100 rounded" />
+                        </label>
+                    </div>
+                    <label class="block text-sm pb-2 pl-7">
+                        {"Time zone"}
+                        <input
+                            type="text"
+                            value={props.settings.quiet_hours.timezone.clone()}
+                            onchange={quiet_hours(|s, v| s.quiet_hours.timezone = v)}
+                            placeholder={notifications::browser_timezone()}
+                            class="w-full mt-1 px-2 py-1 bg-gray-100 rounded outline-none focus:ring-2 focus:ring-blue-600"
+                        />
+                        <div class="text-xs text-gray-500">{"Leave empty to follow this device's clock."}</div>
+                    </label>
+                }
+                <div class="py-2">
+                    <div class="text-sm font-medium">{"Muted users"}</div>
+                    if props.settings.muted_users.is_empty() {
+                        <div class="text-xs text-gray-500">{"Nobody is muted. Use 🔕 next to a user to mute them."}</div>
+                    } else {
+                        <div class="flex flex-wrap mt-1">
+                            {
+                                props.settings.muted_users.iter().map(|user| {
+                                    let settings = props.settings.clone();
+                                    let name = user.clone();
+                                    let unmute = props.on_change.reform(move |_: MouseEvent| {
+                                        let mut next = settings.clone();
+                                        next.toggle_muted(&name);
+                                        next
+                                    });
+                                    html! {
+                                        <button onclick={unmute} aria-label={format!("Unmute {}", user)} class="bg-gray-200 rounded-full px-2 py-1 text-xs mr-1 mb-1">
+                                            {format!("{} ✕", user)}
+                                        </button>
+                                    }
+                                }).collect::<Html>()
+                            }
+                        </div>
+                    }
+                </div>
+
+                <div class="py-2">
+                    <div class="text-sm font-medium">{"Blocked users"}</div>
+                    if props.settings.blocked_users.is_empty() {
+                        <div class="text-xs text-gray-500">{"Nobody is blocked. Use ⋯ next to a user to block them."}</div>
+                    } else {
+                        <div class="flex flex-wrap mt-1">
+                            {
+                                props.settings.blocked_users.iter().map(|user| {
+                                    let settings = props.settings.clone();
+                                    let name = user.clone();
+                                    let unblock = props.on_change.reform(move |_: MouseEvent| {
+                                        let mut next = settings.clone();
+                                        next.toggle_blocked(&name);
+                                        next
+                                    });
+                                    html! {
+                                        <button onclick={unblock} aria-label={format!("Unblock {}", user)} class="bg-gray-200 rounded-full px-2 py-1 text-xs mr-1 mb-1">
+                                            {format!("{} ✕", user)}
+                                        </button>
+                                    }
+                                }).collect::<Html>()
+                            }
+                        </div>
+                    }
+                </div>
+
+                <div class="py-2">
+                    <div class="text-sm font-medium">{"Muted rooms"}</div>
+                    if props.settings.muted_rooms.is_empty() {
+                        <div class="text-xs text-gray-500">{"No rooms are muted. Use 🔔 in a room's header to mute it; its unread count still grows."}</div>
+                    } else {
+                        <div class="flex flex-wrap mt-1">
+                            {
+                                props.settings.muted_rooms.iter().map(|room| {
+                                    let settings = props.settings.clone();
+                                    let name = room.clone();
+                                    let unmute = props.on_change.reform(move |_: MouseEvent| {
+                                        let mut next = settings.clone();
+                                        next.toggle_room_muted(&name);
+                                        next
+                                    });
+                                    html! {
+                                        <button onclick={unmute} aria-label={format!("Unmute {}", room_label(room))} class="bg-gray-200 rounded-full px-2 py-1 text-xs mr-1 mb-1">
+                                            {format!("{} ✕", room_label(room))}
+                                        </button>
+                                    }
+                                }).collect::<Html>()
+                            }
+                        </div>
+                    }
+                </div>
+
+                <h3 class="text-xs uppercase tracking-wide text-gray-500 mt-4">{"Reactions"}</h3>
+                <label class="block py-2">
+                    <div class="text-sm font-medium">{"Quick reactions"}</div>
+                    <input
+                        type="text"
+                        value={props.settings.quick_reactions.join(" ")}
+                        onchange={quick_reactions}
+                        class="w-full mt-1 px-2 py-1 bg-gray-100 rounded outline-none focus:ring-2 focus:ring-blue-600"
+                    />
+                    <div class="text-xs text-gray-500">{"Separate emoji with spaces."}</div>
+                </label>
+                <label class="block py-2">
+                    <div class="text-sm font-medium">{"Double-tap reaction"}</div>
+                    <input
+                        type="text"
+                        value={props.settings.double_tap_reaction.clone()}
+                        onchange={double_tap_reaction}
+                        class="w-20 mt-1 px-2 py-1 bg-gray-100 rounded outline-none focus:ring-2 focus:ring-blue-600"
+                    />
+                    <div class="text-xs text-gray-500">{"Added when you double-click or double-tap a message. Leave empty to turn off."}</div>
+                </label>
+
+                <h3 class="text-xs uppercase tracking-wide text-gray-500 mt-4">{"Saved replies"}</h3>
+                { templates(props) }
+
+                <h3 class="text-xs uppercase tracking-wide text-gray-500 mt-4">{"Devices"}</h3>
+                { devices(props, *platform, device_name) }
+
+                <h3 class="text-xs uppercase tracking-wide text-gray-500 mt-4">{"Transcript"}</h3>
+                { transcript(props) }
+
+                <h3 class="text-xs uppercase tracking-wide text-gray-500 mt-4">{"Storage"}</h3>
+                { storage(props) }
+
+                <h3 class="text-xs uppercase tracking-wide text-gray-500 mt-4">{"Backup"}</h3>
+                <div class="flex items-center py-2">
+                    <button onclick={export} class="px-3 py-1 mr-2 rounded bg-blue-600 text-white text-sm hover:bg-blue-700">{"Export settings"}</button>
+                    <label class="px-3 py-1 rounded bg-gray-200 text-sm cursor-pointer hover:bg-gray-300">
+                        {"Import settings"}
+                        <input type="file" accept="application/json,.json" class="hidden" onchange={import} />
+                    </label>
+                </div>
+                <div class="flex items-center py-2">
+                    <button onclick={export_history} class="px-3 py-1 mr-2 rounded bg-blue-600 text-white text-sm hover:bg-blue-700">{"Export history"}</button>
+                    <label class="px-3 py-1 rounded bg-gray-200 text-sm cursor-pointer hover:bg-gray-300">
+                        {"Import history"}
+                        <input type="file" accept="application/json,.json" class="hidden" onchange={import_history} />
+                    </label>
+                </div>
+                if let Some(error) = (*import_error).clone() {
+                    <div class="text-xs text-red-600" role="alert">{error}</div>
+                }
+
+                <h3 class="text-xs uppercase tracking-wide text-gray-500 mt-4">{"Help"}</h3>
+                <div class="py-2">
+                    <button onclick={report} class="px-3 py-1 rounded bg-gray-200 text-sm hover:bg-gray-300">{"Report a problem…"}</button>
+                    <div class="text-xs text-gray-500 mt-1">{"Download recent traffic and logs to attach to an issue."}</div>
+                </div>
+            </div>
+        </div>
+    }
+}
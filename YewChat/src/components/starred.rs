@@ -0,0 +1,54 @@
+use yew::prelude::*;
+
+use crate::components::room_switcher::room_label;
+use crate::services::starred::StarredMessage;
+
+#[derive(Properties, PartialEq)]
+pub struct StarredPanelProps {
+    pub starred: Vec<StarredMessage>,
+    /// Takes the message ID to go back to.
+    pub on_jump: Callback<u64>,
+    pub on_unstar: Callback<u64>,
+    pub on_close: Callback<()>,
+}
+
+/// Side panel listing every message starred on this device, across rooms.
+/// Clicking one goes back to it in the conversation.
+#[function_component(StarredPanel)]
+pub fn starred_panel(props: &StarredPanelProps) -> Html {
+    let close = props.on_close.reform(|_: MouseEvent| ());
+
+    html! {
+        <aside aria-labelledby="starred-title" class="flex-none w-72 h-screen overflow-y-auto border-l-2 border-gray-300 bg-gray-50 text-black p-3">
+            <div class="flex justify-between items-center mb-2">
+                <h2 id="starred-title" class="font-bold">{format!("⭐ Starred ({})", props.starred.len())}</h2>
+                <button onclick={close} aria-label="Close starred messages" class="text-gray-400 hover:text-gray-700">{"✕"}</button>
+            </div>
+            if props.starred.is_empty() {
+                <div class="text-sm text-gray-500">{"Nothing starred yet. Use ☆ under a message to keep it here."}</div>
+            }
+            <ul>
+                {
+                    props.starred.iter().map(|message| {
+                        let id = message.id;
+                        let jump = props.on_jump.reform(move |_: MouseEvent| id);
+                        let unstar = props.on_unstar.reform(move |_: MouseEvent| id);
+                        html! {
+                            <li key={id} class="flex items-start mb-2 p-2 bg-white rounded shadow-sm">
+                                <button onclick={jump} class="grow text-left text-sm min-w-0 hover:underline"
+                                    aria-label={format!("Go to message from {} in {}", message.from, room_label(&message.room))}>
+                                    <div class="text-xs">
+                                        <span class="font-bold">{message.from.clone()}</span>
+                                        <span class="text-gray-500">{format!(" in {}", room_label(&message.room))}</span>
+                                    </div>
+                                    <div class="truncate">{message.text.clone()}</div>
+                                </button>
+                                <button onclick={unstar} aria-label="Unstar message" class="ml-1 text-xs text-gray-400 hover:text-gray-700">{"✕"}</button>
+                            </li>
+                        }
+                    }).collect::<Html>()
+                }
+            </ul>
+        </aside>
+    }
+}
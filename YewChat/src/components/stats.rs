@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use yew::prelude::*;
+
+use crate::components::emoji;
+
+/// One of our own sent messages, as far as the stats care.
+#[derive(Clone, PartialEq)]
+pub struct SentMessage {
+    pub text: String,
+    /// Milliseconds since the epoch.
+    pub time: f64,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct StatsDialogProps {
+    pub sent: Vec<SentMessage>,
+    pub on_close: Callback<()>,
+}
+
+struct Summary {
+    count: usize,
+    average_length: f64,
+    favorite_emoji: Option<(String, usize)>,
+    by_hour: [usize; 24],
+}
+
+fn summarize(sent: &[SentMessage]) -> Summary {
+    let mut emoji_counts: HashMap<&str, usize> = HashMap::new();
+    let mut by_hour = [0; 24];
+    let mut total_length = 0;
+    for m in sent {
+        total_length += m.text.chars().count();
+        for e in emoji::extract(&m.text) {
+            *emoji_counts.entry(e).or_default() += 1;
+        }
+        let hour = js_sys::Date::new(&m.time.into()).get_hours() as usize;
+        by_hour[hour % 24] += 1;
+    }
+    let favorite_emoji = emoji_counts
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(a.0)))
+        .map(|(e, n)| (e.to_string(), n));
+    Summary {
+        count: sent.len(),
+        average_length: if sent.is_empty() { 0.0 } else { total_length as f64 / sent.len() as f64 },
+        favorite_emoji,
+        by_hour,
+    }
+}
+
+/// "My stats": a breakdown of what we've sent this session. Everything is
+/// worked out from messages already in memory; nothing leaves the browser.
+#[function_component(StatsDialog)]
+pub fn stats_dialog(props: &StatsDialogProps) -> Html {
+    let summary = summarize(&props.sent);
+    let close = props.on_close.reform(|_: MouseEvent| ());
+    let busiest = summary.by_hour.iter().copied().max().unwrap_or(0).max(1);
+
+    html! {
+        <div class="fixed inset-0 bg-black bg-opacity-40 flex justify-center items-center z-30">
+            <div role="dialog" aria-modal="true" aria-labelledby="stats-title" class="bg-white text-black rounded-lg shadow-xl w-96 p-5">
+                <div class="flex justify-between items-center mb-3">
+                    <h2 id="stats-title" class="text-lg font-bold">{"My stats"}</h2>
+                    <button onclick={close} aria-label="Close stats" class="text-gray-400 hover:text-gray-700">{"✕"}</button>
+                </div>
+                <dl class="grid grid-cols-2 gap-y-2 text-sm">
+                    <dt class="text-gray-500">{"Messages sent"}</dt>
+                    <dd class="font-medium text-right">{summary.count}</dd>
+                    <dt class="text-gray-500">{"Average length"}</dt>
+                    <dd class="font-medium text-right">{format!("{:.1} characters", summary.average_length)}</dd>
+                    <dt class="text-gray-500">{"Favorite emoji"}</dt>
+                    <dd class="font-medium text-right">
+                        {
+                            match &summary.favorite_emoji {
+                                Some((e, n)) => format!("{} ×{}", e, n),
+                                None => "—".to_string(),
+                            }
+                        }
+                    </dd>
+                </dl>
+                <h3 class="text-xs uppercase tracking-wide text-gray-500 mt-4 mb-1">{"Active hours"}</h3>
+                <div class="flex items-end h-16" role="img" aria-label="Messages sent per hour of the day">
+                    {
+                        summary.by_hour.iter().enumerate().map(|(hour, n)| html! {
+                            <div
+                                class="flex-1 mx-px bg-blue-500 rounded-t"
+                                style={format!("height:{}%", n * 100 / busiest)}
+                                title={format!("{:02}:00 – {} sent", hour, n)}
+                            />
+                        }).collect::<Html>()
+                    }
+                </div>
+                <div class="flex justify-between text-xs text-gray-400 mt-1">
+                    <span>{"00"}</span><span>{"06"}</span><span>{"12"}</span><span>{"18"}</span><span>{"23"}</span>
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,58 @@
+use yew::prelude::*;
+
+pub const TOAST_TIMEOUT_MS: u32 = 5_000;
+
+#[derive(Clone, PartialEq)]
+pub struct Toast {
+    pub id: usize,
+    pub text: String,
+    pub action: Option<ToastAction>,
+}
+
+/// A button on a toast. Using it also dismisses the toast.
+#[derive(Clone, PartialEq)]
+pub struct ToastAction {
+    pub label: String,
+    pub on_click: Callback<()>,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct ToastsProps {
+    pub toasts: Vec<Toast>,
+    pub on_dismiss: Callback<usize>,
+}
+
+#[function_component(Toasts)]
+pub fn toasts(props: &ToastsProps) -> Html {
+    html! {
+        <div class="fixed bottom-20 right-5 flex flex-col items-end z-20" aria-live="polite">
+            {
+                props.toasts.iter().map(|toast| {
+                    let id = toast.id;
+                    let onclick = props.on_dismiss.reform(move |_: MouseEvent| id);
+                    html! {
+                        <div key={toast.id} class="flex items-center mt-2 max-w-sm bg-white text-black shadow-lg rounded-lg px-4 py-2 text-sm">
+                            <span class="grow truncate">{toast.text.clone()}</span>
+                            if let Some(action) = &toast.action {
+                                <button
+                                    onclick={
+                                        let on_click = action.on_click.clone();
+                                        let on_dismiss = props.on_dismiss.clone();
+                                        move |_: MouseEvent| {
+                                            on_click.emit(());
+                                            on_dismiss.emit(id);
+                                        }
+                                    }
+                                    class="ml-3 font-medium text-blue-600 hover:underline whitespace-nowrap"
+                                >
+                                    {action.label.clone()}
+                                </button>
+                            }
+                            <button {onclick} aria-label="Dismiss" class="ml-3 text-gray-400 hover:text-gray-700">{"✕"}</button>
+                        </div>
+                    }
+                }).collect::<Html>()
+            }
+        </div>
+    }
+}
@@ -0,0 +1,92 @@
+use yew::prelude::*;
+
+use crate::services::traffic::{FrameCount, TrafficStats};
+
+#[derive(Properties, PartialEq)]
+pub struct TrafficDialogProps {
+    pub stats: TrafficStats,
+    pub on_refresh: Callback<()>,
+    pub on_close: Callback<()>,
+}
+
+fn format_bytes(bytes: f64) -> String {
+    if bytes >= 1024.0 * 1024.0 {
+        format!("{:.1} MB", bytes / (1024.0 * 1024.0))
+    } else if bytes >= 1024.0 {
+        format!("{:.1} KB", bytes / 1024.0)
+    } else {
+        format!("{:.0} B", bytes)
+    }
+}
+
+fn count_cells(count: Option<&FrameCount>) -> Html {
+    match count {
+        Some(count) => html! {
+            <>
+                <td class="text-right">{count.frames}</td>
+                <td class="text-right">{format_bytes(count.bytes as f64)}</td>
+                <td class="text-right">{format_bytes(count.average())}</td>
+            </>
+        },
+        None => html! { <><td class="text-right text-gray-400">{"0"}</td><td /><td /></> },
+    }
+}
+
+/// Connection diagnostics: how many frames of each type went each way and
+/// how big they were, for tuning the protocol.
+#[function_component(TrafficDialog)]
+pub fn traffic_dialog(props: &TrafficDialogProps) -> Html {
+    let stats = &props.stats;
+    let close = props.on_close.reform(|_: MouseEvent| ());
+    let refresh = props.on_refresh.reform(|_: MouseEvent| ());
+    let minutes = ((js_sys::Date::now() - stats.since) / 60_000.0).max(0.0);
+    let mut types: Vec<&String> = stats.sent.by_type.keys().chain(stats.received.by_type.keys()).collect();
+    types.sort();
+    types.dedup();
+
+    html! {
+        <div class="fixed inset-0 bg-black bg-opacity-40 flex justify-center items-center z-30">
+            <div role="dialog" aria-modal="true" aria-labelledby="traffic-title" class="bg-white text-black rounded-lg shadow-xl w-[36rem] max-h-[80vh] overflow-y-auto p-5">
+                <div class="flex justify-between items-center mb-3">
+                    <h2 id="traffic-title" class="text-lg font-bold">{"Connection diagnostics"}</h2>
+                    <div>
+                        <button onclick={refresh} class="text-sm text-blue-600 hover:underline mr-3">{"Refresh"}</button>
+                        <button onclick={close} aria-label="Close diagnostics" class="text-gray-400 hover:text-gray-700">{"✕"}</button>
+                    </div>
+                </div>
+                <p class="text-xs text-gray-500 mb-3">
+                    {format!("Over the last {:.0} minutes. Sizes are of the JSON text, before any compact encoding.", minutes)}
+                </p>
+                <table class="w-full text-sm">
+                    <thead class="text-xs text-gray-500">
+                        <tr>
+                            <th class="text-left font-normal">{"Type"}</th>
+                            <th class="text-right font-normal">{"Sent"}</th>
+                            <th class="text-right font-normal">{"Bytes"}</th>
+                            <th class="text-right font-normal">{"Avg"}</th>
+                            <th class="text-right font-normal">{"Received"}</th>
+                            <th class="text-right font-normal">{"Bytes"}</th>
+                            <th class="text-right font-normal">{"Avg"}</th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        {
+                            types.into_iter().map(|kind| html! {
+                                <tr>
+                                    <td class="font-mono">{kind}</td>
+                                    { count_cells(stats.sent.by_type.get(kind)) }
+                                    { count_cells(stats.received.by_type.get(kind)) }
+                                </tr>
+                            }).collect::<Html>()
+                        }
+                        <tr class="border-t font-medium">
+                            <td>{"Total"}</td>
+                            { count_cells(Some(&stats.sent.total)) }
+                            { count_cells(Some(&stats.received.total)) }
+                        </tr>
+                    </tbody>
+                </table>
+            </div>
+        </div>
+    }
+}
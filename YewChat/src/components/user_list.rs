@@ -0,0 +1,211 @@
+use yew::prelude::*;
+
+use crate::components::media::Avatar;
+use crate::components::removed::RemovalKind;
+use crate::services::permissions::{Permissions, Role};
+use crate::services::presence::PresenceStatus;
+
+#[derive(Clone, PartialEq)]
+pub struct UserProfile {
+    pub name: String,
+    pub avatar: String,
+    pub status: PresenceStatus,
+    pub role: Role,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct RoleBadgeProps {
+    pub role: Role,
+}
+
+#[function_component(RoleBadge)]
+pub fn role_badge(props: &RoleBadgeProps) -> Html {
+    match props.role.badge() {
+        Some((label, colors)) => html! {
+            <span class={classes!("ml-2", "px-1.5", "rounded", "text-xs", "font-medium", colors.to_string())}>{label}</span>
+        },
+        None => html! {},
+    }
+}
+
+/// Brings `users` up to date with the server's list of who's connected,
+/// without moving anyone: people already listed keep their place, whether
+/// they're still here or now offline, and newcomers go at the end. That
+/// way the list doesn't jump under the cursor as people come and go.
+/// Whoever `ignored` picks keeps the status they had, or shows offline if
+/// they're new.
+pub fn merge_users(users: &mut Vec<UserProfile>, connected: Vec<UserProfile>, ignored: impl Fn(&str) -> bool) {
+    let mut connected = connected;
+    for user in users.iter_mut() {
+        let status = user.status;
+        match connected.iter().position(|c| c.name == user.name) {
+            Some(i) => *user = connected.remove(i),
+            None => user.status = PresenceStatus::Offline,
+        }
+        if ignored(&user.name) {
+            user.status = status;
+        }
+    }
+    for user in connected.iter_mut().filter(|u| ignored(&u.name)) {
+        user.status = PresenceStatus::Offline;
+    }
+    users.extend(connected);
+}
+
+#[derive(Properties, PartialEq)]
+pub struct UserListProps {
+    pub users: Vec<UserProfile>,
+    /// Our own name, which isn't a link to a conversation with ourselves.
+    pub me: String,
+    pub muted: Vec<String>,
+    pub blocked: Vec<String>,
+    #[prop_or_default]
+    pub low_data: bool,
+    pub on_direct: Callback<String>,
+    pub on_toggle_mute: Callback<String>,
+    pub on_toggle_block: Callback<String>,
+    /// Our own role, which decides who we can remove.
+    #[prop_or_default]
+    pub role: Role,
+    /// Asks to kick or ban someone; the caller confirms first.
+    pub on_remove: Callback<(String, RemovalKind)>,
+}
+
+/// Everyone we've seen, in the order they turned up. Entries are keyed by
+/// name, so only newcomers slide in, and someone going offline fades in
+/// place. Everyone else has a menu, from the ⋯ button or a right click,
+/// to block them and, for moderators, to remove them.
+#[function_component(UserList)]
+pub fn user_list(props: &UserListProps) -> Html {
+    let permissions = Permissions::new(&props.me, props.role);
+    // Whose menu is open.
+    let menu = use_state(|| None::<String>);
+    props
+        .users
+        .iter()
+        .map(|u| {
+            let muted = props.muted.contains(&u.name);
+            let blocked = props.blocked.contains(&u.name);
+            let has_menu = u.name != props.me;
+            let offline = u.status == PresenceStatus::Offline;
+            let toggle_mute = {
+                let name = u.name.clone();
+                props.on_toggle_mute.reform(move |_: MouseEvent| name.clone())
+            };
+            let removable = permissions.can_remove(&u.name, u.role);
+            let menu_open = menu.as_deref() == Some(u.name.as_str());
+            let toggle_menu = {
+                let menu = menu.clone();
+                let name = u.name.clone();
+                Callback::from(move |_: MouseEvent| menu.set((!menu_open).then(|| name.clone())))
+            };
+            let oncontextmenu = has_menu.then(|| {
+                let menu = menu.clone();
+                let name = u.name.clone();
+                Callback::from(move |e: MouseEvent| {
+                    e.prevent_default();
+                    menu.set(Some(name.clone()));
+                })
+            });
+            let close_on_escape = {
+                let menu = menu.clone();
+                Callback::from(move |e: KeyboardEvent| {
+                    if e.key() == "Escape" {
+                        menu.set(None);
+                    }
+                })
+            };
+            let toggle_block = {
+                let menu = menu.clone();
+                let name = u.name.clone();
+                props.on_toggle_block.reform(move |_: MouseEvent| {
+                    menu.set(None);
+                    name.clone()
+                })
+            };
+            let remove = |kind: RemovalKind| {
+                let menu = menu.clone();
+                let name = u.name.clone();
+                props.on_remove.reform(move |_: MouseEvent| {
+                    menu.set(None);
+                    (name.clone(), kind)
+                })
+            };
+            html! {
+                <div
+                    key={u.name.clone()}
+                    class={classes!(
+                        "user-entry", "flex", "m-3", "bg-white", "rounded-lg", "p-2", "shadow-sm", "hover:shadow-md",
+                        "transition", "duration-500", offline.then_some("opacity-60"),
+                    )}
+                    {oncontextmenu}
+                >
+                    <div class="relative flex-none">
+                        <Avatar
+                            name={u.name.clone()}
+                            url={u.avatar.clone()}
+                            class="w-12 h-12 rounded-full border-2 border-gray-200"
+                            low_data={props.low_data}
+                        />
+                        <span class={format!("absolute bottom-0 right-0 w-3 h-3 rounded-full border-2 border-white transition-colors duration-500 {}", u.status.dot_class())}></span>
+                    </div>
+                    <div class="flex-grow p-3">
+                        <div class="flex text-xs justify-between items-center font-bold">
+                            if u.name == props.me {
+                                <div>{u.name.clone()}<RoleBadge role={u.role} /></div>
+                            } else {
+                                <button
+                                    onclick={
+                                        let name = u.name.clone();
+                                        props.on_direct.reform(move |_: MouseEvent| name.clone())
+                                    }
+                                    aria-label={format!("Message {} privately", u.name)}
+                                    class="hover:underline text-left"
+                                >
+                                    {u.name.clone()}
+                                    <RoleBadge role={u.role} />
+                                </button>
+                            }
+                            <div class="flex-grow"></div>
+                            if has_menu {
+                                <div class="relative mr-2" onkeydown={close_on_escape}>
+                                    <button
+                                        onclick={toggle_menu}
+                                        aria-label={format!("More for {}", u.name)}
+                                        aria-haspopup="menu"
+                                        aria-expanded={menu_open.to_string()}
+                                        class="text-gray-300 hover:text-gray-700"
+                                    >
+                                        {"⋯"}
+                                    </button>
+                                    if menu_open {
+                                        <div role="menu" class="absolute right-0 z-10 mt-1 w-28 py-1 bg-white rounded shadow-lg border border-gray-200 font-normal">
+                                            <button role="menuitem" onclick={toggle_block} class="block w-full px-3 py-1 text-left hover:bg-gray-100">
+                                                {if blocked { "Unblock" } else { "Block" }}
+                                            </button>
+                                            if removable {
+                                                <button role="menuitem" onclick={remove(RemovalKind::Kick)} class="block w-full px-3 py-1 text-left hover:bg-gray-100">{"Remove…"}</button>
+                                                <button role="menuitem" onclick={remove(RemovalKind::Ban)} class="block w-full px-3 py-1 text-left text-red-600 hover:bg-gray-100">{"Ban…"}</button>
+                                            }
+                                        </div>
+                                    }
+                                </div>
+                            }
+                            <button
+                                onclick={toggle_mute}
+                                aria-label={format!("{} notifications from {}", if muted { "Unmute" } else { "Mute" }, u.name)}
+                                aria-pressed={muted.to_string()}
+                                class={if muted { "text-gray-700" } else { "text-gray-300 hover:text-gray-600" }}
+                            >
+                                {if muted { "🔕" } else { "🔔" }}
+                            </button>
+                        </div>
+                        <div class="text-xs text-gray-400">
+                            {if blocked { "Blocked" } else { u.status.label() }}
+                        </div>
+                    </div>
+                </div>
+            }
+        })
+        .collect::<Html>()
+}
@@ -0,0 +1,50 @@
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+use crate::Route;
+
+#[derive(Properties, PartialEq)]
+pub struct DeviceVerificationProps {
+    pub username: String,
+    /// The browser has WebAuthn; without it there's no way through here.
+    pub supported: bool,
+    /// The browser's prompt is open.
+    pub busy: bool,
+    pub error: Option<String>,
+    pub on_verify: Callback<()>,
+}
+
+/// Shown instead of the chat when our name is protected by a registered
+/// device and the server wants proof that we hold one.
+#[function_component(DeviceVerification)]
+pub fn device_verification(props: &DeviceVerificationProps) -> Html {
+    let verify = props.on_verify.reform(|_: MouseEvent| ());
+
+    html! {
+        <div class="bg-gray-800 flex w-screen">
+            <div class="container mx-auto flex flex-col justify-center items-center text-white text-center">
+                <h1 class="text-2xl font-bold mb-3">{"Verify it's you"}</h1>
+                if props.supported {
+                    <p class="mb-6 text-gray-300">
+                        {format!("The name \"{}\" is protected. Confirm with one of its registered devices to continue.", props.username)}
+                    </p>
+                    <button
+                        onclick={verify}
+                        disabled={props.busy}
+                        class="px-6 py-3 rounded-lg bg-violet-600 font-bold uppercase disabled:opacity-50"
+                    >
+                        {if props.busy { "Waiting for your device…" } else { "Verify with this device" }}
+                    </button>
+                } else {
+                    <p class="mb-6 text-gray-300">
+                        {format!("The name \"{}\" is protected by a registered device, but this browser can't use security keys or passkeys. Open the chat on a device you registered, or pick another name.", props.username)}
+                    </p>
+                }
+                if let Some(error) = &props.error {
+                    <p role="alert" class="mt-4 text-sm text-red-300">{error.clone()}</p>
+                }
+                <Link<Route> to={Route::Login} classes="mt-6 text-sm text-gray-400 underline">{"Use a different name"}</Link<Route>>
+            </div>
+        </div>
+    }
+}
@@ -1,8 +1,17 @@
 #![recursion_limit = "512"]
+// The yew 0.19 `html!` expansion for component props trips these lints.
+#![allow(clippy::unnecessary_operation, clippy::let_unit_value)]
 
 mod components;
+pub mod plugins;
 mod services;
 
+pub use services::chat_transport::{ChatTransport, MockTransport, TransportContext};
+pub use services::demo::DemoTransport;
+pub use services::websocket::ConnectionState;
+
+use components::a11y_audit::A11yAudit;
+use components::invite::InviteLanding;
 use components::login::Login;
 use components::chat::Chat;
 use wasm_bindgen::prelude::*;
@@ -27,12 +36,15 @@ pub struct UserInner {
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
-#[derive(Debug, Clone, Copy, PartialEq, Routable)]
+#[derive(Debug, Clone, PartialEq, Routable)]
 pub enum Route {
     #[at("/")]
     Login,
     #[at("/chat")]
     Chat,
+    /// Shared from the invite dialog; opens the room after sign-in.
+    #[at("/invite/:room")]
+    Invite { room: String },
     #[not_found]
     #[at("/404")]
     NotFound,
@@ -42,6 +54,7 @@ fn switch(selected_route: &Route) -> Html {
     match selected_route {
         Route::Login => html! {<Login />},
         Route::Chat => html! {<Chat/>},
+        Route::Invite { room } => html! {<InviteLanding room={room.clone()} />},
         Route::NotFound => html! {<h1>{"404 baby"}</h1>},
     }
 }
@@ -54,21 +67,34 @@ fn main() -> Html {
             username: RefCell::new("initial".into()),
         })
     });
+    // Read once: the query string is gone after the first navigation.
+    let demo = use_state(|| DemoTransport::requested().then(DemoTransport::context));
 
-    html! {
-        <ContextProvider<User> context={(*ctx).clone()}>
+    let app = html! {
         <BrowserRouter>
             <div class="flex w-screen h-screen">
                 <Switch<Route> render={Switch::render(switch)}/>
             </div>
+            if cfg!(debug_assertions) {
+                <A11yAudit />
+            }
         </BrowserRouter>
+    };
+
+    html! {
+        <ContextProvider<User> context={(*ctx).clone()}>
+            if let Some(demo) = (*demo).clone() {
+                <ContextProvider<TransportContext> context={demo}>{app}</ContextProvider<TransportContext>>
+            } else {
+                {app}
+            }
         </ContextProvider<User>>
     }
 }
 
 #[wasm_bindgen]
 pub fn run_app() -> Result<(), JsValue> {
-    wasm_logger::init(wasm_logger::Config::default());
+    services::logbook::init();
     yew::start_app::<Main>();
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,111 @@
+//! Extension points for features that live outside the core chat.
+//!
+//! A plugin implements [`Plugin`], overriding only the hooks it cares about,
+//! and is handed to [`register`] from `run_app` before the app starts. The
+//! chat picks up everything registered when it is created; plugins added
+//! later are not seen.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// A message on its way out, before it reaches the socket.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutgoingMessage {
+    pub room: String,
+    pub text: String,
+}
+
+/// A message someone else sent, as it arrives.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReceivedMessage {
+    pub id: Option<u64>,
+    pub from: String,
+    pub room: String,
+    pub text: String,
+}
+
+/// An extra button next to the composer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ComposerButton {
+    /// Passed back to [`Plugin::on_button`] when clicked.
+    pub id: &'static str,
+    /// Usually a single emoji.
+    pub label: String,
+    /// Tooltip and accessible name.
+    pub title: String,
+}
+
+/// A `/name args` command typed into the composer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SlashCommand {
+    /// Without the leading slash.
+    pub name: &'static str,
+    pub description: String,
+}
+
+/// The state a command or button runs against.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandContext {
+    pub username: String,
+    pub room: String,
+    /// What's currently in the composer.
+    pub draft: String,
+}
+
+/// What the chat does once a command or button has run.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CommandOutcome {
+    /// Send this as a message to the current room.
+    Send(String),
+    /// Put this in the composer for the user to finish.
+    Draft(String),
+    /// Show this to the user as a notification.
+    Notice(String),
+    /// Nothing further.
+    Done,
+}
+
+pub trait Plugin {
+    /// Identifies the plugin in logs.
+    fn name(&self) -> &str;
+
+    /// Sees every message the user sends before it goes out, and may rewrite
+    /// it. Returning `None` drops the message. Runs after slash commands.
+    fn before_send(&self, message: OutgoingMessage) -> Option<OutgoingMessage> {
+        Some(message)
+    }
+
+    /// Called once for each new message from someone else.
+    fn on_receive(&self, _message: &ReceivedMessage) {}
+
+    fn composer_buttons(&self) -> Vec<ComposerButton> {
+        Vec::new()
+    }
+
+    fn on_button(&self, _id: &str, _context: &CommandContext) -> CommandOutcome {
+        CommandOutcome::Done
+    }
+
+    fn commands(&self) -> Vec<SlashCommand> {
+        Vec::new()
+    }
+
+    /// Runs `/name args`, for one of the names from [`Plugin::commands`].
+    fn run_command(&self, _name: &str, _args: &str, _context: &CommandContext) -> CommandOutcome {
+        CommandOutcome::Done
+    }
+}
+
+thread_local! {
+    static REGISTRY: RefCell<Vec<Rc<dyn Plugin>>> = RefCell::new(Vec::new());
+}
+
+pub fn register(plugin: impl Plugin + 'static) {
+    log::debug!("registered plugin {}", plugin.name());
+    REGISTRY.with(|registry| registry.borrow_mut().push(Rc::new(plugin)));
+}
+
+/// Everything registered so far, in registration order.
+pub(crate) fn registered() -> Vec<Rc<dyn Plugin>> {
+    REGISTRY.with(|registry| registry.borrow().clone())
+}
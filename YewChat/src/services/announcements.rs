@@ -0,0 +1,43 @@
+use gloo_storage::{LocalStorage, Storage};
+use std::collections::HashSet;
+
+const STORAGE_KEY_PREFIX: &str = "yewchat.dismissed_announcements.";
+
+/// Announcements we've put away, kept per user across reloads, so one
+/// meant to stay at the top stops once we've read it.
+#[derive(Default)]
+pub struct DismissedAnnouncements {
+    key: String,
+    ids: HashSet<u64>,
+}
+
+impl DismissedAnnouncements {
+    pub fn load(username: &str) -> Self {
+        let key = format!("{}{}", STORAGE_KEY_PREFIX, username);
+        let ids = LocalStorage::get(&key).unwrap_or_default();
+        Self { key, ids }
+    }
+
+    /// Moves what we've dismissed over to `username`, for when we're renamed.
+    pub fn rename_user(&mut self, username: &str) {
+        LocalStorage::delete(&self.key);
+        self.key = format!("{}{}", STORAGE_KEY_PREFIX, username);
+        self.save();
+    }
+
+    pub fn contains(&self, id: u64) -> bool {
+        self.ids.contains(&id)
+    }
+
+    pub fn dismiss(&mut self, id: u64) {
+        if self.ids.insert(id) {
+            self.save();
+        }
+    }
+
+    fn save(&self) {
+        if let Err(e) = LocalStorage::set(&self.key, &self.ids) {
+            log::error!("failed to save dismissed announcements: {:?}", e);
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Stamped into every export. Bump it whenever the shape below changes, and
+/// have `import` bring the previous version forward.
+pub const ARCHIVE_VERSION: u32 = 2;
+
+/// An exported conversation: the messages we hold, with what happened to
+/// them since they were sent.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Archive {
+    pub version: u32,
+    /// Milliseconds since the epoch.
+    pub exported: f64,
+    pub messages: Vec<ArchivedMessage>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchivedMessage {
+    pub id: u64,
+    pub from: String,
+    pub room: String,
+    /// The text as last edited.
+    pub message: String,
+    #[serde(default)]
+    pub time: Option<f64>,
+    #[serde(default)]
+    pub edited: bool,
+    #[serde(default)]
+    pub deleted: bool,
+    /// Emoji -> how many times it was used.
+    #[serde(default)]
+    pub reactions: BTreeMap<String, usize>,
+    /// Who reacted, and how many times.
+    #[serde(default)]
+    pub reactors: BTreeMap<String, usize>,
+    /// The thread it's a reply in. Since version 2.
+    #[serde(default)]
+    pub parent_id: Option<u64>,
+    /// The message it quotes. Since version 2.
+    #[serde(default)]
+    pub reply_to: Option<u64>,
+}
+
+#[derive(Debug)]
+pub enum ArchiveError {
+    Json(serde_json::Error),
+    /// No version stamp: not one of our exports.
+    Unversioned,
+    /// Written by a newer client than this one.
+    TooNew(u32),
+}
+
+impl fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArchiveError::Json(e) => write!(f, "not a history file: {}", e),
+            ArchiveError::Unversioned => f.write_str("not a history file"),
+            ArchiveError::TooNew(version) => write!(
+                f,
+                "the file is from a newer version of the app (format {}, this one reads up to {})",
+                version, ARCHIVE_VERSION
+            ),
+        }
+    }
+}
+
+impl Archive {
+    pub fn new(messages: Vec<ArchivedMessage>) -> Self {
+        Self { version: ARCHIVE_VERSION, exported: js_sys::Date::now(), messages }
+    }
+
+    pub fn export(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// Reads an export of any version up to ours, bringing older ones
+    /// forward a step at a time by rewriting the JSON before it's parsed.
+    pub fn import(json: &str) -> Result<Self, ArchiveError> {
+        let mut value: Value = serde_json::from_str(json).map_err(ArchiveError::Json)?;
+        let version = match value["version"].as_u64() {
+            Some(version) if version >= 1 => u32::try_from(version).unwrap_or(u32::MAX),
+            _ => return Err(ArchiveError::Unversioned),
+        };
+        if version > ARCHIVE_VERSION {
+            return Err(ArchiveError::TooNew(version));
+        }
+        // 1 -> 2: replies came in. Version 1 predates threads and quotes,
+        // so every message was top-level and quoted nothing.
+        if version < 2 {
+            for message in value["messages"].as_array_mut().into_iter().flatten() {
+                message["parentId"] = Value::Null;
+                message["replyTo"] = Value::Null;
+            }
+        }
+        value["version"] = ARCHIVE_VERSION.into();
+        serde_json::from_value(value).map_err(ArchiveError::Json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn message(extra: Value) -> Value {
+        let mut message = json!({ "id": 2, "from": "ann", "room": "rust", "message": "hi" });
+        message.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        message
+    }
+
+    #[test]
+    fn brings_version_1_forward() {
+        let v1 = json!({ "version": 1, "exported": 0.0, "messages": [message(json!({}))] });
+        let archive = Archive::import(&v1.to_string()).unwrap();
+        assert_eq!(archive.version, ARCHIVE_VERSION);
+        assert_eq!((archive.messages[0].parent_id, archive.messages[0].reply_to), (None, None));
+    }
+
+    #[test]
+    fn keeps_replies_through_a_round_trip() {
+        let v2 = json!({ "version": 2, "exported": 0.0, "messages": [message(json!({ "parentId": 1, "replyTo": 1 }))] });
+        let archive = Archive::import(&v2.to_string()).unwrap();
+        assert_eq!((archive.messages[0].parent_id, archive.messages[0].reply_to), (Some(1), Some(1)));
+        assert_eq!(Archive::import(&archive.export()).unwrap(), archive);
+    }
+
+    #[test]
+    fn refuses_files_it_cant_read() {
+        assert!(matches!(Archive::import(r#"{ "messages": [] }"#), Err(ArchiveError::Unversioned)));
+        assert!(matches!(Archive::import(r#"{ "version": 99, "messages": [] }"#), Err(ArchiveError::TooNew(99))));
+    }
+}
@@ -0,0 +1,74 @@
+use gloo_storage::{LocalStorage, Storage};
+use reqwasm::http::Request;
+use serde::Deserialize;
+use serde_json::json;
+use std::cell::RefCell;
+
+use crate::services::config::Config;
+
+const STORAGE_KEY: &str = "yewchat.auth";
+
+thread_local! {
+    /// Why the server last turned our token down, for the login screen.
+    static FAILURE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+#[derive(Deserialize)]
+struct LoginResponse {
+    token: String,
+}
+
+/// Asks the login endpoint for a token for `username` and keeps it for
+/// `register`. The first sign-in under a name sets its password. The error
+/// is worded for the login screen.
+pub async fn login(username: &str, password: &str) -> Result<String, String> {
+    let url = Config::load().await.login_url();
+    let response = Request::post(&url)
+        .header("Content-Type", "application/json")
+        .body(json!({ "username": username, "password": password }).to_string())
+        .send()
+        .await
+        .map_err(|e| {
+            log::warn!("login request failed: {:?}", e);
+            "Couldn't reach the server. Please try again.".to_string()
+        })?;
+    if !response.ok() {
+        return Err(match response.text().await {
+            Ok(reason) if !reason.is_empty() => reason,
+            _ => format!("The server refused to sign you in ({}).", response.status()),
+        });
+    }
+    let token = response
+        .json::<LoginResponse>()
+        .await
+        .map_err(|e| format!("The server's answer made no sense: {:?}", e))?
+        .token;
+    save_token(username, &token);
+    Ok(token)
+}
+
+/// The token from the last login as `username`, if there was one.
+pub fn token(username: &str) -> Option<String> {
+    LocalStorage::get::<(String, String)>(STORAGE_KEY)
+        .ok()
+        .filter(|(user, _)| user == username)
+        .map(|(_, token)| token)
+}
+
+fn save_token(username: &str, token: &str) {
+    if let Err(e) = LocalStorage::set(STORAGE_KEY, (username, token)) {
+        log::error!("failed to save auth token: {:?}", e);
+    }
+}
+
+/// The server rejected our token: forget it, and remember why so the login
+/// screen can say.
+pub fn fail(reason: String) {
+    LocalStorage::delete(STORAGE_KEY);
+    FAILURE.with(|failure| *failure.borrow_mut() = Some(reason));
+}
+
+/// Why the last sign-in was rejected, once.
+pub fn take_failure() -> Option<String> {
+    FAILURE.with(|failure| failure.borrow_mut().take())
+}
@@ -0,0 +1,65 @@
+/// Parts of the protocol a server may or may not support. The client
+/// hides what the server it's talking to can't do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Feature {
+    Threads,
+    Reactions,
+    Pins,
+    Announcements,
+}
+
+impl Feature {
+    const ALL: [Feature; 4] = [Feature::Threads, Feature::Reactions, Feature::Pins, Feature::Announcements];
+
+    /// As servers list it in `hello`.
+    fn name(&self) -> &str {
+        match self {
+            Feature::Threads => "threads",
+            Feature::Reactions => "reactions",
+            Feature::Pins => "pins",
+            Feature::Announcements => "announcements",
+        }
+    }
+
+    fn label(&self) -> &str {
+        match self {
+            Feature::Threads => "threads",
+            Feature::Reactions => "reactions",
+            Feature::Pins => "pinned messages",
+            Feature::Announcements => "announcements",
+        }
+    }
+}
+
+/// What the server said it supports, as far as we care.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Capabilities {
+    missing: Vec<Feature>,
+}
+
+impl Capabilities {
+    /// Servers from before features were listed in `hello` send none, and
+    /// support everything this client knows about.
+    pub fn negotiate(advertised: Option<&[String]>) -> Self {
+        let missing = match advertised {
+            Some(names) => Feature::ALL.into_iter().filter(|f| !names.iter().any(|n| n == f.name())).collect(),
+            None => Vec::new(),
+        };
+        Self { missing }
+    }
+
+    pub fn has(&self, feature: Feature) -> bool {
+        !self.missing.contains(&feature)
+    }
+
+    /// What's been turned off, for a banner; `None` if nothing has.
+    pub fn describe_missing(&self) -> Option<String> {
+        let labels: Vec<&str> = self.missing.iter().map(Feature::label).collect();
+        let list = match labels.as_slice() {
+            [] => return None,
+            [one] => one.to_string(),
+            [rest @ .., last] => format!("{} and {}", rest.join(", "), last),
+        };
+        Some(format!("This server doesn't support {}, so they're turned off.", list))
+    }
+}
@@ -0,0 +1,202 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use yew_agent::Dispatched;
+
+use crate::services::event_bus::{EventBus, Request};
+use crate::services::traffic::{LoggedFrame, TrafficStats};
+use crate::services::websocket::{ConnectionState, WebsocketService};
+
+/// What `Chat` needs from its connection. Frames from the server don't come
+/// through here but over the `EventBus`, so a stand-in only has to put them
+/// there.
+pub trait ChatTransport {
+    /// Queues a frame for the server. Fails only if it can't even be queued.
+    fn send(&self, frame: String) -> Result<(), String>;
+    /// Sets the frame sent first on every (re)connect.
+    fn set_handshake(&self, frame: String);
+    /// Holds off reconnect attempts until `until` (ms since the epoch).
+    fn hold_reconnects_until(&self, until: f64);
+    /// Closes the connection for good.
+    fn close(&mut self);
+    /// What has gone over the connection, for diagnostics.
+    fn traffic(&self) -> TrafficStats {
+        TrafficStats::default()
+    }
+    /// The latest raw frames, oldest first, for debugging.
+    fn frame_log(&self) -> Vec<LoggedFrame> {
+        vec![]
+    }
+}
+
+impl ChatTransport for WebsocketService {
+    fn send(&self, frame: String) -> Result<(), String> {
+        self.tx.clone().try_send(frame).map_err(|e| format!("{:?}", e))
+    }
+
+    fn set_handshake(&self, frame: String) {
+        WebsocketService::set_handshake(self, frame)
+    }
+
+    fn hold_reconnects_until(&self, until: f64) {
+        WebsocketService::hold_reconnects_until(self, until)
+    }
+
+    fn close(&mut self) {
+        WebsocketService::close(self)
+    }
+
+    fn traffic(&self) -> TrafficStats {
+        WebsocketService::traffic(self)
+    }
+
+    fn frame_log(&self) -> Vec<LoggedFrame> {
+        WebsocketService::frame_log(self)
+    }
+}
+
+/// Provided as context to choose how `Chat` connects; it calls this once
+/// each time it's mounted. Without it, `Chat` opens a `WebsocketService`.
+#[derive(Clone)]
+pub struct TransportContext {
+    connect: Rc<dyn Fn() -> Box<dyn ChatTransport>>,
+    login: bool,
+}
+
+impl TransportContext {
+    pub fn new(connect: impl Fn() -> Box<dyn ChatTransport> + 'static) -> Self {
+        Self { connect: Rc::new(connect), login: true }
+    }
+
+    /// For a transport with no server behind it to sign in to, so the
+    /// login screen lets anyone straight through.
+    pub fn without_login(self) -> Self {
+        Self { login: false, ..self }
+    }
+
+    pub(crate) fn connect(&self) -> Box<dyn ChatTransport> {
+        (self.connect)()
+    }
+
+    pub(crate) fn needs_login(&self) -> bool {
+        self.login
+    }
+}
+
+impl PartialEq for TransportContext {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.connect, &other.connect) && self.login == other.login
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    sent: RefCell<Vec<String>>,
+    handshake: RefCell<Option<String>>,
+    held_until: Cell<f64>,
+    closed: Cell<bool>,
+}
+
+/// A connection that goes nowhere, for tests: it records what `Chat` sends
+/// and can play the server's part. Clones share the same record, so keep
+/// one and hand `context()` to the component.
+#[derive(Clone, Default)]
+pub struct MockTransport {
+    state: Rc<MockState>,
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn context(&self) -> TransportContext {
+        let mock = self.clone();
+        TransportContext::new(move || Box::new(mock.clone()))
+    }
+
+    /// Everything sent so far, oldest first, as JSON frames.
+    pub fn sent(&self) -> Vec<String> {
+        self.state.sent.borrow().clone()
+    }
+
+    /// Like `sent`, but also forgets them.
+    pub fn take_sent(&self) -> Vec<String> {
+        self.state.sent.take()
+    }
+
+    pub fn handshake(&self) -> Option<String> {
+        self.state.handshake.borrow().clone()
+    }
+
+    pub fn held_until(&self) -> f64 {
+        self.state.held_until.get()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.state.closed.get()
+    }
+
+    /// Delivers `frame` as though the server had sent it.
+    pub fn inject(&self, frame: &str) {
+        EventBus::dispatcher().send(Request::EventBusMsg(frame.to_string()));
+    }
+
+    /// Reports a change in the connection, as the real service would.
+    pub fn set_connection(&self, state: ConnectionState) {
+        EventBus::dispatcher().send(Request::Connection(state));
+    }
+}
+
+impl ChatTransport for MockTransport {
+    fn send(&self, frame: String) -> Result<(), String> {
+        if self.state.closed.get() {
+            return Err("closed".to_string());
+        }
+        self.state.sent.borrow_mut().push(frame);
+        Ok(())
+    }
+
+    fn set_handshake(&self, frame: String) {
+        *self.state.handshake.borrow_mut() = Some(frame);
+    }
+
+    fn hold_reconnects_until(&self, until: f64) {
+        self.state.held_until.set(until);
+    }
+
+    fn close(&mut self) {
+        self.state.closed.set(true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_what_is_sent_until_closed() {
+        let mock = MockTransport::new();
+        let mut transport = mock.context().connect();
+        transport.set_handshake("hello".to_string());
+        transport.hold_reconnects_until(42.0);
+        assert_eq!(transport.send("one".to_string()), Ok(()));
+        assert_eq!(transport.send("two".to_string()), Ok(()));
+        assert_eq!(mock.sent(), ["one", "two"]);
+        assert_eq!(mock.take_sent(), ["one", "two"]);
+        assert!(mock.sent().is_empty());
+        assert_eq!(mock.handshake().as_deref(), Some("hello"));
+        assert_eq!(mock.held_until(), 42.0);
+
+        transport.close();
+        assert!(mock.is_closed());
+        assert!(transport.send("three".to_string()).is_err());
+        assert!(mock.sent().is_empty());
+    }
+
+    #[test]
+    fn context_needs_login_unless_told_otherwise() {
+        let mock = MockTransport::new();
+        assert!(mock.context().needs_login());
+        assert!(!mock.context().without_login().needs_login());
+    }
+}
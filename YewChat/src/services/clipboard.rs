@@ -0,0 +1,21 @@
+use js_sys::{Function, Promise, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+
+/// Puts `text` on the clipboard. `navigator.clipboard` is reached through
+/// reflection, since web-sys only exposes it behind its unstable APIs.
+pub async fn write_text(text: &str) -> Result<(), String> {
+    let navigator = web_sys::window().ok_or("no window")?.navigator();
+    let clipboard = Reflect::get(&navigator, &JsValue::from_str("clipboard")).map_err(|e| format!("{:?}", e))?;
+    if clipboard.is_undefined() {
+        return Err("this browser has no clipboard API".to_string());
+    }
+    let write: Function = Reflect::get(&clipboard, &JsValue::from_str("writeText"))
+        .and_then(|f| f.dyn_into())
+        .map_err(|e| format!("{:?}", e))?;
+    let promise: Promise = write
+        .call1(&clipboard, &JsValue::from_str(text))
+        .and_then(|p| p.dyn_into())
+        .map_err(|e| format!("{:?}", e))?;
+    JsFuture::from(promise).await.map(|_| ()).map_err(|e| format!("{:?}", e))
+}
@@ -0,0 +1,142 @@
+use reqwasm::http::Request;
+use serde::Deserialize;
+use std::cell::RefCell;
+use wasm_bindgen::JsValue;
+
+use crate::services::transport::TransportKind;
+
+const CONFIG_URL: &str = "/config.json";
+const WINDOW_CONFIG: &str = "__CHAT_CONFIG__";
+
+// Used for anything the deployment doesn't set.
+const DEFAULT_SERVER_URL: &str = "ws://127.0.0.1:8080";
+const DEFAULT_HEALTH_URL: &str = match option_env!("YEWCHAT_HEALTH_URL") {
+    Some(url) => url,
+    None => "http://127.0.0.1:8080/health",
+};
+const DEFAULT_LOGIN_URL: &str = match option_env!("YEWCHAT_LOGIN_URL") {
+    Some(url) => url,
+    None => "http://127.0.0.1:8080/login",
+};
+
+thread_local! {
+    static LOADED: RefCell<Option<Config>> = const { RefCell::new(None) };
+}
+
+/// Where to find the server, decided when the page loads rather than when
+/// the bundle is built, so one build can serve every environment.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Config {
+    server_url: Option<String>,
+    /// Tried in order, moving on when one stops answering. Takes
+    /// precedence over `server_url`.
+    server_urls: Vec<String>,
+    health_url: Option<String>,
+    /// Where to trade a name for the token sent with `register`.
+    login_url: Option<String>,
+    /// A LibreTranslate-style endpoint. Without one, messages aren't
+    /// offered for translation.
+    translate_url: Option<String>,
+    /// `"websocket"` or `"longpoll"`. Left out, the client tries WebSocket
+    /// first and falls back to long-polling if it can't get through.
+    transport: Option<TransportKind>,
+}
+
+impl Config {
+    /// `window.__CHAT_CONFIG__` if the page sets one, otherwise
+    /// `/config.json`, otherwise the built-in defaults. Remembered for the
+    /// rest of the page's life once found.
+    pub async fn load() -> Self {
+        if let Some(config) = LOADED.with(|loaded| loaded.borrow().clone()) {
+            return config;
+        }
+        let config = match from_window() {
+            Some(config) => config,
+            None => fetch().await.unwrap_or_default(),
+        };
+        LOADED.with(|loaded| *loaded.borrow_mut() = Some(config.clone()));
+        config
+    }
+
+    /// The WebSocket endpoints in order of preference, with `ws`/`wss`
+    /// matched to the page. Never empty.
+    pub fn server_urls(&self) -> Vec<String> {
+        if self.server_urls.is_empty() {
+            vec![websocket_url(self.server_url.as_deref().unwrap_or(DEFAULT_SERVER_URL))]
+        } else {
+            self.server_urls.iter().map(|url| websocket_url(url)).collect()
+        }
+    }
+
+    pub fn transport(&self) -> Option<TransportKind> {
+        self.transport
+    }
+
+    pub fn health_url(&self) -> String {
+        self.health_url.clone().unwrap_or_else(|| DEFAULT_HEALTH_URL.to_string())
+    }
+
+    pub fn login_url(&self) -> String {
+        self.login_url.clone().unwrap_or_else(|| DEFAULT_LOGIN_URL.to_string())
+    }
+
+    pub fn translate_url(&self) -> Option<String> {
+        self.translate_url.clone()
+    }
+}
+
+fn from_window() -> Option<Config> {
+    let window = web_sys::window()?;
+    let value = js_sys::Reflect::get(&window, &JsValue::from_str(WINDOW_CONFIG)).ok()?;
+    if value.is_undefined() || value.is_null() {
+        return None;
+    }
+    let json = js_sys::JSON::stringify(&value).ok()?.as_string()?;
+    match serde_json::from_str(&json) {
+        Ok(config) => Some(config),
+        Err(e) => {
+            log::warn!("ignoring malformed {}: {}", WINDOW_CONFIG, e);
+            None
+        }
+    }
+}
+
+async fn fetch() -> Option<Config> {
+    let response = Request::get(CONFIG_URL).send().await.ok()?;
+    if !response.ok() {
+        return None;
+    }
+    match response.json().await {
+        Ok(config) => Some(config),
+        Err(e) => {
+            log::warn!("ignoring malformed {}: {:?}", CONFIG_URL, e);
+            None
+        }
+    }
+}
+
+/// Accepts a full `ws://` or `wss://` URL, a protocol-relative `//host/path`,
+/// a path on the page's own host, or a bare `host:port`. Plain `ws://` is
+/// upgraded on https pages, where browsers would refuse it anyway.
+fn websocket_url(url: &str) -> String {
+    let location = web_sys::window().map(|window| window.location());
+    let secure = location
+        .as_ref()
+        .and_then(|location| location.protocol().ok())
+        .is_some_and(|protocol| protocol == "https:");
+    let scheme = if secure { "wss:" } else { "ws:" };
+
+    if url.starts_with("wss://") {
+        url.to_string()
+    } else if let Some(rest) = url.strip_prefix("ws://") {
+        format!("{}//{}", scheme, rest)
+    } else if url.starts_with("//") {
+        format!("{}{}", scheme, url)
+    } else if url.starts_with('/') {
+        let host = location.and_then(|location| location.host().ok()).unwrap_or_default();
+        format!("{}//{}{}", scheme, host, url)
+    } else {
+        format!("{}//{}", scheme, url)
+    }
+}
@@ -0,0 +1,340 @@
+use gloo_timers::callback::{Interval, Timeout};
+use serde_json::{json, Value};
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use yew_agent::Dispatched;
+
+use crate::services::chat_transport::{ChatTransport, TransportContext};
+use crate::services::event_bus::{EventBus, Request};
+use crate::services::websocket::ConnectionState;
+
+const ROOM: &str = "general";
+const BOTS: [&str; 4] = ["ada", "grace", "linus", "margaret"];
+const LINES: [&str; 10] = [
+    "Morning, everyone!",
+    "Has anyone tried the new build yet?",
+    "I think the tests are flaky again 🙃",
+    "Lunch in ten?",
+    "That's a great idea.",
+    "Can someone review my PR?",
+    "Rust's borrow checker strikes again.",
+    "Deploying now, fingers crossed 🤞",
+    "Looks good to me!",
+    "Who's up for a coffee? ☕",
+];
+const EMOJI: [&str; 4] = ["👍", "❤️", "😂", "🎉"];
+// How often a bot does something, and how long it "types" first.
+const CHATTER_MS: u32 = 5_000;
+const TYPING_MS: u32 = 1_500;
+// Same as the real server's default.
+const PIN_LIMIT: usize = 5;
+
+fn pick<T: Copy>(items: &[T]) -> T {
+    items[(js_sys::Math::random() * items.len() as f64) as usize % items.len()]
+}
+
+/// Delivers `frame` as though the server had sent it.
+fn deliver(frame: Value) {
+    EventBus::dispatcher().send(Request::EventBusMsg(frame.to_string()));
+}
+
+#[derive(Default)]
+struct DemoState {
+    username: RefCell<String>,
+    next_id: Cell<u64>,
+    /// Every message so far, for the bots to react to.
+    ids: RefCell<Vec<u64>>,
+    /// Pinned message IDs per room, in order.
+    pins: RefCell<HashMap<String, Vec<u64>>>,
+    /// Room topics that have been set.
+    topics: RefCell<HashMap<String, String>>,
+    archived: RefCell<HashSet<String>>,
+    /// Bots we've removed, who stay gone.
+    kicked: RefCell<HashSet<String>>,
+    chatter: RefCell<Option<Interval>>,
+}
+
+impl DemoState {
+    fn message(&self, from: &str, text: &str, room: &str, client_id: Option<&str>) -> Value {
+        let id = self.next_id.get() + 1;
+        self.next_id.set(id);
+        self.ids.borrow_mut().push(id);
+        json!({
+            "id": id,
+            "from": from,
+            "message": text,
+            "room": room,
+            "clientId": client_id,
+            "time": js_sys::Date::now(),
+        })
+    }
+}
+
+/// A server that lives in the page, for trying the app out without one: a
+/// few made-up users chat, type and react on timers, and whatever we send is
+/// stored and echoed back the way the real server would.
+#[derive(Clone, Default)]
+pub struct DemoTransport {
+    state: Rc<DemoState>,
+}
+
+impl DemoTransport {
+    pub fn context() -> TransportContext {
+        TransportContext::new(|| Box::new(DemoTransport::default())).without_login()
+    }
+
+    /// Whether the page was opened with `?demo=1`.
+    pub fn requested() -> bool {
+        web_sys::window()
+            .and_then(|window| window.location().search().ok())
+            .map(|search| search.trim_start_matches('?').split('&').any(|p| p == "demo=1"))
+            .unwrap_or(false)
+    }
+
+    fn welcome(&self) {
+        EventBus::dispatcher().send(Request::Connection(ConnectionState::Connected));
+        // We moderate, so pinning and removing people can be tried out.
+        let hello = json!({ "version": 1, "minClientVersion": 1, "moderator": true, "role": "moderator" });
+        deliver(json!({ "messageType": "hello", "data": hello.to_string() }));
+        self.users();
+    }
+
+    fn users(&self) {
+        let username = self.state.username.borrow().clone();
+        let kicked = self.state.kicked.borrow();
+        let mut names: Vec<&str> = BOTS.iter().copied().filter(|bot| !kicked.contains(*bot)).collect();
+        names.push(&username);
+        let entries: serde_json::Map<String, Value> = names
+            .iter()
+            .map(|name| {
+                let status = if *name == BOTS[1] { "away" } else { "online" };
+                let role = if *name == BOTS[0] { "owner" } else if *name == username { "moderator" } else { "member" };
+                (name.to_string(), json!({ "status": status, "role": role }))
+            })
+            .collect();
+        deliver(json!({
+            "messageType": "users",
+            "dataArray": names,
+            "data": Value::Object(entries).to_string(),
+        }));
+    }
+
+    /// One bot does one thing: starts typing a message, or reacts to one.
+    fn chatter(state: &Rc<DemoState>) {
+        let bot = pick(&BOTS);
+        if state.kicked.borrow().contains(bot) {
+            return;
+        }
+        let last = state.ids.borrow().last().copied();
+        match last {
+            Some(id) if js_sys::Math::random() < 0.3 => {
+                let data = json!({ "id": id, "emoji": pick(&EMOJI), "from": bot });
+                deliver(json!({ "messageType": "reaction", "data": data.to_string() }));
+            }
+            _ => {
+                deliver(json!({ "messageType": "typing", "data": json!({ "from": bot, "room": ROOM }).to_string() }));
+                let state = state.clone();
+                Timeout::new(TYPING_MS, move || {
+                    let text = if js_sys::Math::random() < 0.2 {
+                        format!("@{} what do you think?", state.username.borrow())
+                    } else {
+                        pick(&LINES).to_string()
+                    };
+                    let stored = state.message(bot, &text, ROOM, None);
+                    deliver(json!({ "messageType": "message", "data": stored.to_string() }));
+                })
+                .forget();
+            }
+        }
+    }
+
+    fn history(&self, room: &str, older: Option<u64>, client_id: &Value) {
+        let page: Vec<String> = match older {
+            Some(_) => vec![],
+            None if room == ROOM => [
+                ("ada", "Welcome to the YewChat demo! Nothing here leaves your browser."),
+                ("linus", "Say hi, react to something, or open another room."),
+            ]
+            .iter()
+            .map(|(from, text)| self.state.message(from, text, room, None).to_string())
+            .collect(),
+            None => vec![],
+        };
+        deliver(json!({ "messageType": "history", "room": room, "id": older, "clientId": client_id, "dataArray": page }));
+        if older.is_none() && !room.starts_with('@') {
+            self.room_update(room, None);
+        }
+    }
+
+    /// Everyone's in every room: the bots since a few days ago, and us, who
+    /// count as having made it, since just now.
+    fn members(&self, room: &str) {
+        const DAY_MS: f64 = 24.0 * 60.0 * 60.0 * 1000.0;
+        let now = js_sys::Date::now();
+        let mut members: Vec<Value> = BOTS
+            .iter()
+            .enumerate()
+            .map(|(i, bot)| json!({ "name": bot, "role": "member", "joinedAt": now - (BOTS.len() - i) as f64 * DAY_MS }))
+            .map(|member| Value::String(member.to_string()))
+            .collect();
+        let me = json!({ "name": *self.state.username.borrow(), "role": "creator", "joinedAt": now });
+        members.push(Value::String(me.to_string()));
+        deliver(json!({
+            "messageType": "members",
+            "room": room,
+            "id": 0,
+            "data": members.len().to_string(),
+            "dataArray": members,
+        }));
+    }
+
+    /// Sends `room`'s topic. Every room here counts as ours, so it can
+    /// always be edited.
+    fn room_update(&self, room: &str, by: Option<&str>) {
+        let description = self.state.topics.borrow().get(room).cloned().unwrap_or_default();
+        let data = json!({
+            "description": description,
+            "createdBy": *self.state.username.borrow(),
+            "archived": self.state.archived.borrow().contains(room),
+            "by": by,
+        });
+        deliver(json!({ "messageType": "roomupdate", "room": room, "data": data.to_string() }));
+    }
+
+    /// Applies a `pin`, `unpin` or reordering `pins` frame, then sends
+    /// everyone (that is, us) the room's pins as they now stand.
+    fn pins(&self, room: &str, frame: &Value) {
+        let mut all = self.state.pins.borrow_mut();
+        let pins = all.entry(room.to_string()).or_default();
+        let id = frame["id"].as_u64();
+        match (frame["messageType"].as_str(), id) {
+            (Some("pin"), Some(id)) if !pins.contains(&id) && pins.len() >= PIN_LIMIT => {
+                let error = json!({ "code": "pin_limit", "message": format!("#{} already has {} pins. Unpin one first.", room, PIN_LIMIT) });
+                deliver(json!({ "messageType": "error", "data": error.to_string() }));
+            }
+            (Some("pin"), Some(id)) if !pins.contains(&id) => pins.push(id),
+            (Some("unpin"), Some(id)) => pins.retain(|p| *p != id),
+            (Some("pins"), _) => {
+                let order: Vec<u64> = frame["dataArray"]
+                    .as_array()
+                    .map(|ids| ids.iter().filter_map(|id| id.as_str()?.parse().ok()).collect())
+                    .unwrap_or_default();
+                if order.len() == pins.len() && pins.iter().all(|p| order.contains(p)) {
+                    *pins = order;
+                }
+            }
+            _ => {}
+        }
+        deliver(json!({
+            "messageType": "pins",
+            "room": room,
+            "data": PIN_LIMIT.to_string(),
+            "dataArray": pins.iter().map(u64::to_string).collect::<Vec<_>>(),
+        }));
+    }
+}
+
+impl ChatTransport for DemoTransport {
+    fn send(&self, frame: String) -> Result<(), String> {
+        let frame: Value = serde_json::from_str(&frame).map_err(|e| e.to_string())?;
+        let room = match frame["to"].as_str() {
+            Some(to) => format!("@{}", to),
+            None => frame["room"].as_str().unwrap_or(ROOM).to_string(),
+        };
+        match frame["messageType"].as_str() {
+            Some("message") if self.state.archived.borrow().contains(&room) => {
+                let error = json!({ "code": "room_archived", "message": "This room is archived, so it takes no new messages.", "clientId": frame["clientId"] });
+                deliver(json!({ "messageType": "error", "data": error.to_string() }));
+            }
+            Some("message") => {
+                let username = self.state.username.borrow().clone();
+                let client_id = frame["clientId"].as_str();
+                let text = frame["data"].as_str().unwrap_or_default();
+                let mut stored = self.state.message(&username, text, &room, client_id);
+                for key in ["parentId", "replyTo"] {
+                    if let Some(id) = frame[key].as_u64() {
+                        stored[key] = json!(id);
+                    }
+                }
+                if let Some(client_id) = client_id {
+                    deliver(json!({ "messageType": "ack", "clientId": client_id, "id": stored["id"] }));
+                }
+                deliver(json!({ "messageType": "message", "data": stored.to_string() }));
+            }
+            // We moderate here, so every announcement goes out.
+            Some("announce") => {
+                let request: Value = serde_json::from_str(frame["data"].as_str().unwrap_or_default()).unwrap_or_default();
+                let username = self.state.username.borrow().clone();
+                let mut stored = self.state.message(&username, request["text"].as_str().unwrap_or_default(), &room, None);
+                stored["announcement"] = json!(true);
+                stored["sticky"] = json!(request["sticky"].as_bool().unwrap_or_default());
+                deliver(json!({ "messageType": "announce", "data": stored.to_string() }));
+            }
+            Some("history") => self.history(&room, frame["id"].as_u64(), &frame["clientId"]),
+            Some("pin" | "unpin") if self.state.archived.borrow().contains(&room) => {
+                let error = json!({ "code": "room_archived", "message": "This room is archived, so it takes no new messages." });
+                deliver(json!({ "messageType": "error", "data": error.to_string() }));
+                // Puts back whatever the client showed changing.
+                self.pins(&room, &Value::Null);
+            }
+            Some("pin" | "unpin" | "pins") => self.pins(&room, &frame),
+            // Anyone can have any room here, so every request succeeds.
+            Some("createroom") => {
+                let description = frame["data"].as_str().unwrap_or_default().to_string();
+                self.state.topics.borrow_mut().insert(room.clone(), description);
+                deliver(json!({
+                    "messageType": "createroom",
+                    "room": room,
+                    "clientId": frame["clientId"],
+                    "data": frame["data"],
+                }));
+            }
+            Some("members") => self.members(&room),
+            Some("roomupdate") => {
+                let topic = frame["data"].as_str().unwrap_or_default().trim().to_string();
+                self.state.topics.borrow_mut().insert(room.clone(), topic);
+                let username = self.state.username.borrow().clone();
+                self.room_update(&room, Some(&username));
+            }
+            Some(kind @ ("archive" | "unarchive")) => {
+                if kind == "archive" {
+                    self.state.archived.borrow_mut().insert(room.clone());
+                } else {
+                    self.state.archived.borrow_mut().remove(&room);
+                }
+                let username = self.state.username.borrow().clone();
+                self.room_update(&room, Some(&username));
+            }
+            // A ban looks the same as a kick here: the bot is gone for good.
+            Some("kick" | "ban") => {
+                let request: Value = serde_json::from_str(frame["data"].as_str().unwrap_or_default()).unwrap_or_default();
+                let name = request["name"].as_str().unwrap_or_default();
+                if BOTS[1..].contains(&name) {
+                    self.state.kicked.borrow_mut().insert(name.to_string());
+                    self.users();
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn set_handshake(&self, frame: String) {
+        let frame: Value = serde_json::from_str(&frame).unwrap_or_default();
+        *self.state.username.borrow_mut() = frame["data"].as_str().unwrap_or_default().to_string();
+        // Chat registers while it's still being created; answer once it listens.
+        let demo = self.clone();
+        Timeout::new(0, move || demo.welcome()).forget();
+        if self.state.chatter.borrow().is_none() {
+            let state = self.state.clone();
+            *self.state.chatter.borrow_mut() = Some(Interval::new(CHATTER_MS, move || Self::chatter(&state)));
+        }
+    }
+
+    fn hold_reconnects_until(&self, _until: f64) {}
+
+    fn close(&mut self) {
+        self.state.chatter.take();
+    }
+}
@@ -2,9 +2,24 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use yew_agent::{Agent, AgentLink, Context, HandlerId};
 
+use crate::services::websocket::ConnectionState;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Request {
     EventBusMsg(String),
+    Connection(ConnectionState),
+    /// The server URL now in use, when there is more than one to pick from.
+    Endpoint(String),
+    /// Round-trip time of the latest heartbeat, in milliseconds.
+    Latency(f64),
+}
+
+#[derive(Clone, Debug)]
+pub enum BusEvent {
+    Frame(String),
+    Connection(ConnectionState),
+    Endpoint(String),
+    Latency(f64),
 }
 
 pub struct EventBus {
@@ -16,7 +31,7 @@ impl Agent for EventBus {
     type Reach = Context<Self>;
     type Message = ();
     type Input = Request;
-    type Output = String;
+    type Output = BusEvent;
 
     fn create(link: AgentLink<Self>) -> Self {
         Self {
@@ -28,12 +43,14 @@ impl Agent for EventBus {
     fn update(&mut self, _msg: Self::Message) {}
 
     fn handle_input(&mut self, msg: Self::Input, _id: HandlerId) {
-        match msg {
-            Request::EventBusMsg(s) => {
-                for sub in self.subscribers.iter() {
-                    self.link.respond(*sub, s.clone())
-                }
-            }
+        let event = match msg {
+            Request::EventBusMsg(s) => BusEvent::Frame(s),
+            Request::Connection(state) => BusEvent::Connection(state),
+            Request::Endpoint(url) => BusEvent::Endpoint(url),
+            Request::Latency(ms) => BusEvent::Latency(ms),
+        };
+        for sub in self.subscribers.iter() {
+            self.link.respond(*sub, event.clone())
         }
     }
 
@@ -44,4 +61,4 @@ impl Agent for EventBus {
     fn disconnected(&mut self, id: HandlerId) {
         self.subscribers.remove(&id);
     }
-}
\ No newline at end of file
+}
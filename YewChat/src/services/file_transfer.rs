@@ -0,0 +1,209 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Blob, BlobPropertyBag, File, Url};
+
+/// Files bigger than this aren't offered; everything goes through the chat
+/// server's memory on its way past.
+pub const MAX_FILE_BYTES: usize = 2 * 1024 * 1024;
+/// Bytes per `filechunk`, before base64. Stays well inside the long-poll
+/// transport's frame limit once encoded.
+const CHUNK_BYTES: usize = 16 * 1024;
+/// The longest picture description, in characters.
+pub const MAX_ALT_LENGTH: usize = 500;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for group in bytes.chunks(3) {
+        let n = group.iter().enumerate().fold(0u32, |n, (i, b)| n | ((*b as u32) << (16 - 8 * i)));
+        for i in 0..4 {
+            if i <= group.len() {
+                out.push(ALPHABET[((n >> (18 - 6 * i)) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+fn decode(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim_end_matches('=').as_bytes();
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    for group in text.chunks(4) {
+        if group.len() == 1 {
+            return None;
+        }
+        let mut n = 0u32;
+        for (i, c) in group.iter().enumerate() {
+            let value = ALPHABET.iter().position(|a| a == c)? as u32;
+            n |= value << (18 - 6 * i);
+        }
+        for i in 0..group.len() - 1 {
+            out.push((n >> (16 - 8 * i)) as u8);
+        }
+    }
+    Some(out)
+}
+
+/// What a `filestart` announces. The server fills in `from`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileMeta {
+    pub id: String,
+    pub name: String,
+    pub mime: String,
+    pub size: usize,
+    pub chunks: usize,
+    #[serde(default)]
+    pub from: String,
+    /// A description of a picture, for screen readers. Empty for none.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub alt: String,
+}
+
+impl FileMeta {
+    pub fn new(id: String, name: String, mime: String, size: usize) -> Self {
+        Self { id, name, mime, size, chunks: size.div_ceil(CHUNK_BYTES), from: String::new(), alt: String::new() }
+    }
+
+    pub fn is_image(&self) -> bool {
+        self.mime.starts_with("image/")
+    }
+
+    /// Whether the announced shape is one we'd accept: not too big, and
+    /// the chunk count matching the size.
+    pub fn plausible(&self) -> bool {
+        self.size <= MAX_FILE_BYTES
+            && self.chunks == self.size.div_ceil(CHUNK_BYTES)
+            && self.alt.chars().count() <= MAX_ALT_LENGTH
+    }
+}
+
+/// The contents of `file`, read whole.
+pub async fn read(file: &File) -> Result<Vec<u8>, String> {
+    let buffer = JsFuture::from(file.array_buffer()).await.map_err(|e| format!("{:?}", e))?;
+    Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+}
+
+/// `bytes` cut into base64 chunks, one per `filechunk` frame.
+pub fn split(bytes: &[u8]) -> Vec<String> {
+    bytes.chunks(CHUNK_BYTES).map(encode).collect()
+}
+
+fn object_url(bytes: &[u8], mime: &str) -> Option<String> {
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes));
+    let mut options = BlobPropertyBag::new();
+    options.type_(mime);
+    Blob::new_with_u8_array_sequence_and_options(&parts, &options)
+        .and_then(|blob| Url::create_object_url_with_blob(&blob))
+        .map_err(|e| log::error!("couldn't store a received file: {:?}", e))
+        .ok()
+}
+
+/// A file on its way in, or one we sent. Once complete it has an object URL
+/// to download from, revoked when the transfer is dropped.
+pub struct Transfer {
+    pub meta: FileMeta,
+    parts: Vec<Option<Vec<u8>>>,
+    received: usize,
+    url: Option<String>,
+    failed: bool,
+}
+
+impl Transfer {
+    pub fn incoming(meta: FileMeta) -> Self {
+        let parts = vec![None; meta.chunks];
+        Self { meta, parts, received: 0, url: None, failed: false }
+    }
+
+    /// Our own file: nothing to wait for.
+    pub fn outgoing(meta: FileMeta, bytes: &[u8]) -> Self {
+        let url = object_url(bytes, &meta.mime);
+        Self { received: meta.chunks, meta, parts: vec![], url, failed: false }
+    }
+
+    pub fn add(&mut self, index: usize, data: &str) {
+        match (self.parts.get_mut(index), decode(data)) {
+            (Some(slot @ None), Some(bytes)) => {
+                *slot = Some(bytes);
+                self.received += 1;
+            }
+            (Some(Some(_)), Some(_)) => {}
+            _ => self.failed = true,
+        }
+    }
+
+    /// The sender says that was everything: put the file together, or
+    /// give up if anything is missing or the size is off.
+    pub fn finish(&mut self) {
+        if self.failed || self.url.is_some() {
+            return;
+        }
+        let bytes: Vec<u8> = self.parts.drain(..).flatten().flatten().collect();
+        if self.received != self.meta.chunks || bytes.len() != self.meta.size {
+            self.failed = true;
+            return;
+        }
+        self.url = object_url(&bytes, &self.meta.mime);
+        self.failed = self.url.is_none();
+    }
+
+    /// Chunks in so far, out of how many.
+    pub fn progress(&self) -> (usize, usize) {
+        (self.received, self.meta.chunks)
+    }
+
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    pub fn failed(&self) -> bool {
+        self.failed
+    }
+}
+
+impl Drop for Transfer {
+    fn drop(&mut self) {
+        if let Some(url) = &self.url {
+            let _ = Url::revoke_object_url(url);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_the_rfc_4648_vectors() {
+        let vectors = [("", ""), ("f", "Zg=="), ("fo", "Zm8="), ("foo", "Zm9v"), ("foob", "Zm9vYg=="), ("fooba", "Zm9vYmE="), ("foobar", "Zm9vYmFy")];
+        for (plain, encoded) in vectors {
+            assert_eq!(encode(plain.as_bytes()), encoded);
+            assert_eq!(decode(encoded).as_deref(), Some(plain.as_bytes()));
+        }
+    }
+
+    #[test]
+    fn round_trips_every_byte_and_length() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        for len in 0..bytes.len() {
+            assert_eq!(decode(&encode(&bytes[..len])).as_deref(), Some(&bytes[..len]));
+        }
+    }
+
+    #[test]
+    fn round_trips_whole_chunks() {
+        let bytes: Vec<u8> = (0..CHUNK_BYTES * 2 + 7).map(|i| (i * 31 % 251) as u8).collect();
+        let decoded: Vec<u8> = split(&bytes).iter().flat_map(|chunk| decode(chunk).unwrap()).collect();
+        assert_eq!(decoded, bytes);
+    }
+
+    #[test]
+    fn rejects_what_is_not_base64() {
+        assert_eq!(decode("Zm9v!"), None);
+        assert_eq!(decode("Z"), None);
+        assert_eq!(decode("Zm 9v"), None);
+    }
+}
@@ -0,0 +1,37 @@
+use std::collections::VecDeque;
+
+/// Lets at most `limit` events through in any window of `window_ms`.
+pub struct RateLimiter {
+    limit: usize,
+    window_ms: f64,
+    recent: VecDeque<f64>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: usize, window_ms: f64) -> Self {
+        Self { limit, window_ms, recent: VecDeque::new() }
+    }
+
+    /// Whether another event may go through at `now` (ms since the epoch).
+    /// Only events that go through count against the limit.
+    pub fn allow(&mut self, now: f64) -> bool {
+        while self.recent.front().is_some_and(|at| now - at >= self.window_ms) {
+            self.recent.pop_front();
+        }
+        if self.recent.len() >= self.limit {
+            return false;
+        }
+        self.recent.push_back(now);
+        true
+    }
+
+    /// Milliseconds from `now` until `allow` would let an event through.
+    pub fn wait(&self, now: f64) -> f64 {
+        let in_window = self.recent.iter().filter(|&&at| now - at < self.window_ms).count();
+        if in_window < self.limit {
+            return 0.0;
+        }
+        let oldest = self.recent[self.recent.len() - self.limit];
+        (oldest + self.window_ms - now).max(0.0)
+    }
+}
@@ -0,0 +1,65 @@
+use gloo_timers::callback::Interval;
+use reqwasm::http::Request;
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen_futures::spawn_local;
+use yew::Callback;
+use crate::services::config::Config;
+
+const POLL_INTERVAL_MS: u32 = 30_000;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    #[default]
+    Ok,
+    Degraded,
+    Maintenance,
+}
+
+/// What the server's health endpoint reports.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Health {
+    pub status: HealthStatus,
+    pub message: Option<String>,
+    /// End of the maintenance window, in ms since the epoch, when known.
+    pub until: Option<f64>,
+}
+
+/// Polls the configured health endpoint and
+/// reports whenever the answer changes. An unreachable endpoint is not
+/// reported; the socket's own connection state covers that.
+pub struct HealthMonitor {
+    _poll: Interval,
+}
+
+impl HealthMonitor {
+    pub fn new(on_change: Callback<Health>) -> Self {
+        let last = Rc::new(RefCell::new(Health::default()));
+        let check = move || {
+            let last = last.clone();
+            let on_change = on_change.clone();
+            spawn_local(async move {
+                let url = Config::load().await.health_url();
+                let health = match Request::get(&url).send().await {
+                    Ok(response) => response.json::<Health>().await,
+                    Err(e) => Err(e),
+                };
+                match health {
+                    Ok(health) if health != *last.borrow() => {
+                        *last.borrow_mut() = health.clone();
+                        on_change.emit(health);
+                    }
+                    Ok(_) => {}
+                    Err(e) => log::debug!("health check failed: {:?}", e),
+                }
+            });
+        };
+        check();
+        Self {
+            _poll: Interval::new(POLL_INTERVAL_MS, check),
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use gloo_storage::{SessionStorage, Storage};
+
+const STORAGE_KEY: &str = "yewchat.invite";
+
+/// A link that opens `room` for whoever follows it, signing them in first
+/// if need be.
+pub fn link(room: &str) -> Option<String> {
+    let origin = web_sys::window()?.location().origin().ok()?;
+    Some(format!("{}/invite/{}", origin, String::from(js_sys::encode_uri_component(room))))
+}
+
+/// Holds on to the room an invite link named, through sign-in. `room` is
+/// as it came in the path, still encoded.
+pub fn remember(room: &str) {
+    let room = js_sys::decode_uri_component(room).map(String::from).unwrap_or_else(|_| room.to_string());
+    if let Err(e) = SessionStorage::set(STORAGE_KEY, room) {
+        log::error!("failed to save invite: {:?}", e);
+    }
+}
+
+/// The room we were invited to, once.
+pub fn take() -> Option<String> {
+    let room = SessionStorage::get::<String>(STORAGE_KEY).ok();
+    SessionStorage::delete(STORAGE_KEY);
+    room.filter(|room| !room.trim().is_empty())
+}
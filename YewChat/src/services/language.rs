@@ -0,0 +1,93 @@
+// Short texts rarely settle on a language; below this many stop-word hits a
+// Latin-script message stays untagged.
+const MIN_STOPWORD_HITS: usize = 2;
+
+// The most frequent function words of each language. They are short and
+// common enough to show up even in chat-sized messages.
+const STOPWORDS: &[(&str, &[&str])] = &[
+    ("en", &["the", "and", "is", "are", "you", "to", "of", "it", "that", "this", "what", "with", "have", "for", "not"]),
+    ("id", &["yang", "dan", "ini", "itu", "aku", "kamu", "tidak", "ada", "dengan", "untuk", "apa", "sudah", "saya", "juga", "ke"]),
+    ("es", &["el", "la", "que", "de", "y", "los", "es", "por", "una", "pero", "para", "como", "con", "muy", "qué"]),
+    ("fr", &["le", "la", "les", "et", "est", "je", "tu", "vous", "une", "pas", "que", "pour", "avec", "mais", "c'est"]),
+    ("de", &["der", "die", "das", "und", "ist", "ich", "nicht", "du", "ein", "eine", "mit", "auf", "zu", "es", "wir"]),
+    ("pt", &["o", "a", "que", "não", "é", "um", "uma", "com", "para", "você", "eu", "isso", "mas", "os", "muito"]),
+    ("it", &["il", "che", "e", "non", "di", "un", "una", "sono", "per", "con", "questo", "ma", "io", "gli", "anche"]),
+    ("nl", &["de", "het", "een", "en", "is", "niet", "ik", "je", "dat", "van", "op", "met", "maar", "wat", "zijn"]),
+];
+
+/// The script a character belongs to, named by the language it most likely
+/// means in a chat message.
+fn script_language(c: char) -> Option<&'static str> {
+    match c as u32 {
+        0x3040..=0x30FF => Some("ja"),
+        0xAC00..=0xD7AF | 0x1100..=0x11FF => Some("ko"),
+        0x4E00..=0x9FFF | 0x3400..=0x4DBF => Some("zh"),
+        0x0400..=0x04FF => Some("ru"),
+        0x0600..=0x06FF => Some("ar"),
+        0x0590..=0x05FF => Some("he"),
+        0x0370..=0x03FF => Some("el"),
+        0x0E00..=0x0E7F => Some("th"),
+        0x0900..=0x097F => Some("hi"),
+        _ => None,
+    }
+}
+
+/// Guesses the ISO 639-1 language of `text`. Non-Latin scripts decide by
+/// character counts; Latin text by which language's stop words it uses most.
+/// `None` when the text is too short or too mixed to call.
+pub fn detect(text: &str) -> Option<&'static str> {
+    let mut scripts: Vec<(&'static str, usize)> = Vec::new();
+    let mut letters = 0;
+    for c in text.chars().filter(|c| c.is_alphabetic()) {
+        letters += 1;
+        if let Some(lang) = script_language(c) {
+            match scripts.iter_mut().find(|(l, _)| *l == lang) {
+                Some((_, n)) => *n += 1,
+                None => scripts.push((lang, 1)),
+            }
+        }
+    }
+    if letters == 0 {
+        return None;
+    }
+    // Any kana means Japanese, even though most of the text may be kanji.
+    if scripts.iter().any(|(l, _)| *l == "ja") {
+        return Some("ja");
+    }
+    if let Some(&(lang, n)) = scripts.iter().max_by_key(|(_, n)| *n) {
+        if n * 2 >= letters {
+            return Some(lang);
+        }
+    }
+
+    let lowered = text.to_lowercase();
+    let words: Vec<&str> = lowered
+        .split(|c: char| !c.is_alphanumeric() && c != '\'')
+        .filter(|w| !w.is_empty())
+        .collect();
+    let mut best: Option<(&'static str, usize)> = None;
+    let mut tied = false;
+    for (lang, stopwords) in STOPWORDS {
+        let hits = words.iter().filter(|w| stopwords.contains(w)).count();
+        match best {
+            Some((_, top)) if hits == top => tied = true,
+            Some((_, top)) if hits < top => {}
+            _ => {
+                best = Some((lang, hits));
+                tied = false;
+            }
+        }
+    }
+    match best {
+        Some((lang, hits)) if hits >= MIN_STOPWORD_HITS && !tied => Some(lang),
+        _ => None,
+    }
+}
+
+/// The reader's own language, from the browser, as an ISO 639-1 code.
+pub fn user_language() -> String {
+    web_sys::window()
+        .and_then(|w| w.navigator().language())
+        .and_then(|l| l.split('-').next().map(str::to_lowercase))
+        .unwrap_or_else(|| "en".to_string())
+}
@@ -0,0 +1,56 @@
+use gloo_storage::{LocalStorage, Storage};
+use std::collections::HashMap;
+
+const STORAGE_KEY_PREFIX: &str = "yewchat.last_read.";
+
+/// The newest message we've seen in each room, kept per user across
+/// reloads. Everything after it from someone else is unread.
+#[derive(Default)]
+pub struct LastRead {
+    key: String,
+    rooms: HashMap<String, u64>,
+}
+
+impl LastRead {
+    pub fn load(username: &str) -> Self {
+        let key = format!("{}{}", STORAGE_KEY_PREFIX, username);
+        let rooms = LocalStorage::get(&key).unwrap_or_default();
+        Self { key, rooms }
+    }
+
+    fn save(&self) {
+        if let Err(e) = LocalStorage::set(&self.key, &self.rooms) {
+            log::error!("failed to save read positions: {:?}", e);
+        }
+    }
+
+    /// `None` for a room we've never had a position in.
+    pub fn get(&self, room: &str) -> Option<u64> {
+        self.rooms.get(room).copied()
+    }
+
+    /// Moves the room's position up to `id`, never back. Returns whether it
+    /// moved.
+    pub fn advance(&mut self, room: &str, id: u64) -> bool {
+        if self.get(room).is_some_and(|seen| seen >= id) {
+            return false;
+        }
+        self.rooms.insert(room.to_string(), id);
+        self.save();
+        true
+    }
+
+    /// Moves the positions over to `username`, for when we're renamed.
+    pub fn rename_user(&mut self, username: &str) {
+        LocalStorage::delete(&self.key);
+        self.key = format!("{}{}", STORAGE_KEY_PREFIX, username);
+        self.save();
+    }
+
+    pub fn rename_room(&mut self, from: &str, to: &str) {
+        if let Some(id) = self.rooms.remove(from) {
+            self.rooms.insert(to.to_string(), id);
+            self.save();
+        }
+    }
+}
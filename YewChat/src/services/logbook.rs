@@ -0,0 +1,59 @@
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use wasm_bindgen::JsValue;
+use web_sys::console;
+
+// Log lines kept for problem reports; the oldest go first.
+const LOGBOOK_LIMIT: usize = 300;
+
+thread_local! {
+    static LINES: RefCell<VecDeque<String>> = const { RefCell::new(VecDeque::new()) };
+}
+
+/// Writes to the browser console like any logger, and keeps the latest
+/// lines so a problem report can include them.
+struct Logbook;
+
+impl Log for Logbook {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Debug
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = format!("{} {}: {}", record.level(), record.target(), record.args());
+        let text = JsValue::from_str(&line);
+        match record.level() {
+            Level::Error => console::error_1(&text),
+            Level::Warn => console::warn_1(&text),
+            Level::Info => console::info_1(&text),
+            Level::Debug | Level::Trace => console::debug_1(&text),
+        }
+        let stamped = format!("{} {}", js_sys::Date::new_0().to_iso_string(), line);
+        LINES.with(|lines| {
+            let mut lines = lines.borrow_mut();
+            if lines.len() >= LOGBOOK_LIMIT {
+                lines.pop_front();
+            }
+            lines.push_back(stamped);
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGBOOK: Logbook = Logbook;
+
+pub fn init() {
+    if log::set_logger(&LOGBOOK).is_ok() {
+        log::set_max_level(LevelFilter::Debug);
+    }
+}
+
+/// The latest log lines, oldest first.
+pub fn lines() -> Vec<String> {
+    LINES.with(|lines| lines.borrow().iter().cloned().collect())
+}
@@ -0,0 +1,96 @@
+use futures::future::LocalBoxFuture;
+use futures::{sink, stream, FutureExt};
+use reqwasm::http::{Request, Response};
+use serde::Deserialize;
+use std::collections::VecDeque;
+
+use crate::services::transport::{FrameSink, FrameStream, Transport, TransportError, TransportKind};
+
+/// For networks whose proxies won't pass a WebSocket. Frames to the server
+/// go out one POST each; a GET that the server holds open until it has
+/// something brings back everything sent to us since the previous one.
+pub struct LongPollTransport;
+
+#[derive(Deserialize)]
+struct Opened {
+    id: String,
+}
+
+impl Transport for LongPollTransport {
+    fn kind(&self) -> TransportKind {
+        TransportKind::LongPoll
+    }
+
+    fn connect(&self, url: &str) -> LocalBoxFuture<'static, Result<(FrameSink, FrameStream), TransportError>> {
+        let base = poll_url(url);
+        async move {
+            let response = Request::post(&base).send().await.map_err(|e| TransportError(e.to_string()))?;
+            check(&response)?;
+            let opened: Opened = response.json().await.map_err(|e| TransportError(e.to_string()))?;
+            let session = format!("{}/{}", base, opened.id);
+
+            let sink = sink::unfold(session.clone(), |session, frame: String| async move {
+                let response = Request::post(&session)
+                    .body(frame)
+                    .send()
+                    .await
+                    .map_err(|e| TransportError(e.to_string()))?;
+                check(&response)?;
+                Ok(session)
+            });
+            let stream = stream::unfold((session, VecDeque::new()), |(session, mut pending)| async move {
+                loop {
+                    if let Some(frame) = pending.pop_front() {
+                        return Some((frame, (session, pending)));
+                    }
+                    pending.extend(next_batch(&session).await?);
+                }
+            });
+            Ok((Box::pin(sink) as FrameSink, Box::pin(stream) as FrameStream))
+        }
+        .boxed_local()
+    }
+}
+
+/// The server's polling endpoint, next to its WebSocket one.
+fn poll_url(server_url: &str) -> String {
+    let http = if let Some(rest) = server_url.strip_prefix("wss://") {
+        format!("https://{}", rest)
+    } else if let Some(rest) = server_url.strip_prefix("ws://") {
+        format!("http://{}", rest)
+    } else {
+        server_url.to_string()
+    };
+    format!("{}/poll", http.trim_end_matches('/'))
+}
+
+fn check(response: &Response) -> Result<(), TransportError> {
+    if response.ok() {
+        Ok(())
+    } else {
+        Err(TransportError(format!("{} {}", response.status(), response.status_text())))
+    }
+}
+
+/// Waits for the next batch of frames. `None` once the server has forgotten
+/// us or can't be reached, which ends the connection.
+async fn next_batch(session: &str) -> Option<Vec<String>> {
+    let response = match Request::get(session).send().await {
+        Ok(response) => response,
+        Err(e) => {
+            log::error!("poll: {}", e);
+            return None;
+        }
+    };
+    if let Err(e) = check(&response) {
+        log::warn!("poll: {}", e);
+        return None;
+    }
+    match response.json().await {
+        Ok(batch) => Some(batch),
+        Err(e) => {
+            log::error!("poll: {}", e);
+            None
+        }
+    }
+}
@@ -1,2 +1,40 @@
 pub mod websocket;
-pub mod event_bus;
\ No newline at end of file
+pub mod event_bus;
+pub mod notifications;
+pub mod settings;
+pub mod files;
+pub mod presence;
+pub mod health;
+pub mod recent;
+pub mod language;
+pub mod unsent;
+pub mod config;
+pub mod webauthn;
+pub mod transport;
+pub mod longpoll;
+pub mod flood;
+pub mod chat_transport;
+pub mod transcript;
+pub mod archive;
+pub mod outbox;
+pub mod traffic;
+pub mod onboarding;
+pub mod demo;
+pub mod auth;
+pub mod file_transfer;
+pub mod clipboard;
+pub mod storage;
+pub mod logbook;
+pub mod report;
+pub mod notification_rules;
+pub mod watchlist;
+pub mod starred;
+pub mod last_read;
+pub mod translate;
+pub mod shortcuts;
+pub mod qr;
+pub mod invite;
+pub mod permissions;
+pub mod capabilities;
+pub mod word_filter;
+pub mod announcements;
\ No newline at end of file
@@ -0,0 +1,219 @@
+use serde::{Deserialize, Serialize};
+
+/// Something about an incoming message a rule can look for.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "lowercase")]
+pub enum Condition {
+    /// Posted in this room; `@name` for a direct conversation.
+    Room(String),
+    Sender(String),
+    /// Contains this text, ignoring case.
+    Keyword(String),
+    /// Mentions us by name.
+    Mention,
+}
+
+impl Condition {
+    pub const KINDS: [&'static str; 4] = ["room", "sender", "keyword", "mention"];
+
+    /// An empty condition of the given kind, for the editor.
+    pub fn of_kind(kind: &str) -> Self {
+        match kind {
+            "room" => Condition::Room(String::new()),
+            "sender" => Condition::Sender(String::new()),
+            "keyword" => Condition::Keyword(String::new()),
+            _ => Condition::Mention,
+        }
+    }
+
+    /// What it compares against, if it takes anything.
+    pub fn value(&self) -> Option<&str> {
+        match self {
+            Condition::Room(v) | Condition::Sender(v) | Condition::Keyword(v) => Some(v),
+            Condition::Mention => None,
+        }
+    }
+
+    pub fn set_value(&mut self, value: String) {
+        match self {
+            Condition::Room(v) => *v = value.trim().trim_start_matches('#').to_string(),
+            Condition::Sender(v) => *v = value.trim().trim_start_matches('@').to_string(),
+            Condition::Keyword(v) => *v = value.trim().to_string(),
+            Condition::Mention => {}
+        }
+    }
+
+    fn matches(&self, message: &Message, me: &str) -> bool {
+        match self {
+            Condition::Room(room) => room.is_empty() || message.room == *room,
+            Condition::Sender(sender) => sender.is_empty() || message.from == *sender,
+            Condition::Keyword(keyword) => message.text.to_lowercase().contains(&keyword.to_lowercase()),
+            Condition::Mention => mentions(message.text, me),
+        }
+    }
+}
+
+/// Whether `text` has `@name` standing on its own, so `@bob` isn't found in
+/// `@bobby` or an address like `rob@bob.com`.
+pub fn mentions(text: &str, name: &str) -> bool {
+    let mention = format!("@{}", name);
+    let part_of_name = |c: char| c.is_alphanumeric() || c == '_' || c == '-';
+    text.match_indices(&mention).any(|(at, _)| {
+        let before = text[..at].chars().next_back();
+        let after = text[at + mention.len()..].chars().next();
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(part_of_name)
+    })
+}
+
+/// How to let us know about a message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Sound,
+    Toast,
+    Desktop,
+    /// Count it on the room's badge and nothing else. Never combined with
+    /// the others.
+    Badge,
+}
+
+impl Action {
+    pub const ALL: [Action; 4] = [Action::Sound, Action::Toast, Action::Desktop, Action::Badge];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::Sound => "Sound",
+            Action::Toast => "Toast",
+            Action::Desktop => "Desktop",
+            Action::Badge => "Badge only",
+        }
+    }
+}
+
+/// When every one of `conditions` holds, notify with `actions`. A
+/// condition left empty, or no conditions at all, matches anything.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Rule {
+    pub enabled: bool,
+    pub conditions: Vec<Condition>,
+    pub actions: Vec<Action>,
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            conditions: vec![Condition::Keyword(String::new())],
+            actions: vec![Action::Toast],
+        }
+    }
+}
+
+impl Rule {
+    fn matches(&self, message: &Message, me: &str) -> bool {
+        self.enabled && self.conditions.iter().all(|c| c.matches(message, me))
+    }
+
+    /// Turns `action` on or off, keeping "badge only" on its own.
+    pub fn toggle_action(&mut self, action: Action) {
+        if self.actions.contains(&action) {
+            self.actions.retain(|a| *a != action);
+        } else if action == Action::Badge {
+            self.actions = vec![Action::Badge];
+        } else {
+            self.actions.retain(|a| *a != Action::Badge);
+            self.actions.push(action);
+        }
+    }
+}
+
+/// What the rules look at.
+pub struct Message<'a> {
+    pub room: &'a str,
+    pub from: &'a str,
+    pub text: &'a str,
+}
+
+/// The actions for `message`: those of the first rule it matches, in
+/// order, or `otherwise` if it matches none.
+pub fn decide(rules: &[Rule], message: &Message, me: &str, otherwise: Vec<Action>) -> Vec<Action> {
+    match rules.iter().find(|rule| rule.matches(message, me)) {
+        Some(rule) => rule.actions.clone(),
+        None => otherwise,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message<'a>(room: &'a str, from: &'a str, text: &'a str) -> Message<'a> {
+        Message { room, from, text }
+    }
+
+    fn rule(conditions: Vec<Condition>, actions: Vec<Action>) -> Rule {
+        Rule { enabled: true, conditions, actions }
+    }
+
+    #[test]
+    fn mentions_match_whole_names_only() {
+        assert!(mentions("@bob look", "bob"));
+        assert!(mentions("thanks, @bob!", "bob"));
+        assert!(mentions("(@bob)", "bob"));
+        assert!(!mentions("@bobby look", "bob"));
+        assert!(!mentions("@bob_2 look", "bob"));
+        assert!(!mentions("mail rob@bob.com", "bob"));
+        assert!(mentions("@bobby and @bob", "bob"));
+    }
+
+    #[test]
+    fn conditions_match() {
+        let m = message("general", "alice", "Deploy is DONE, @bob");
+        assert!(Condition::Room("general".into()).matches(&m, "bob"));
+        assert!(!Condition::Room("random".into()).matches(&m, "bob"));
+        assert!(Condition::Sender("alice".into()).matches(&m, "bob"));
+        assert!(Condition::Keyword("done".into()).matches(&m, "bob"));
+        assert!(!Condition::Keyword("failed".into()).matches(&m, "bob"));
+        assert!(Condition::Mention.matches(&m, "bob"));
+        assert!(!Condition::Mention.matches(&m, "bo"));
+        // Left empty, they match anything.
+        assert!(Condition::Room(String::new()).matches(&m, "bob"));
+        assert!(Condition::Keyword(String::new()).matches(&m, "bob"));
+    }
+
+    #[test]
+    fn rules_need_every_condition_and_to_be_enabled() {
+        let m = message("general", "alice", "hello");
+        let both = rule(vec![Condition::Room("general".into()), Condition::Sender("alice".into())], vec![Action::Sound]);
+        assert!(both.matches(&m, "bob"));
+        let one = rule(vec![Condition::Room("general".into()), Condition::Sender("carol".into())], vec![Action::Sound]);
+        assert!(!one.matches(&m, "bob"));
+        assert!(!Rule { enabled: false, ..both }.matches(&m, "bob"));
+        assert!(rule(vec![], vec![]).matches(&m, "bob"));
+    }
+
+    #[test]
+    fn decide_takes_the_first_matching_rule() {
+        let rules = vec![
+            rule(vec![Condition::Sender("carol".into())], vec![Action::Desktop]),
+            rule(vec![Condition::Room("general".into())], vec![Action::Badge]),
+            rule(vec![], vec![Action::Sound]),
+        ];
+        assert_eq!(decide(&rules, &message("general", "carol", "hi"), "bob", vec![]), vec![Action::Desktop]);
+        assert_eq!(decide(&rules, &message("general", "alice", "hi"), "bob", vec![]), vec![Action::Badge]);
+        assert_eq!(decide(&rules, &message("random", "alice", "hi"), "bob", vec![]), vec![Action::Sound]);
+        assert_eq!(decide(&rules[..1], &message("random", "alice", "hi"), "bob", vec![Action::Toast]), vec![Action::Toast]);
+    }
+
+    #[test]
+    fn badge_only_stays_on_its_own() {
+        let mut r = rule(vec![], vec![Action::Sound, Action::Toast]);
+        r.toggle_action(Action::Badge);
+        assert_eq!(r.actions, vec![Action::Badge]);
+        r.toggle_action(Action::Desktop);
+        assert_eq!(r.actions, vec![Action::Desktop]);
+        r.toggle_action(Action::Desktop);
+        assert!(r.actions.is_empty());
+    }
+}
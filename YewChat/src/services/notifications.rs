@@ -0,0 +1,261 @@
+use gloo_timers::callback::Timeout;
+use js_sys::{Array, Function, Intl, Object, Reflect};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{AudioContext, Notification, NotificationOptions, NotificationPermission};
+use yew::Callback;
+
+use crate::services::notification_rules::Action;
+
+// Messages arriving within this window of the first one are reported together.
+const BURST_WINDOW_MS: u32 = 1_500;
+// Never surface more than one notification per this interval, however busy
+// the room is.
+const MIN_INTERVAL_MS: f64 = 5_000.0;
+// During quiet hours the burst waits, checking this often whether they're over.
+const QUIET_RECHECK_MS: u32 = 60_000;
+// The chime: a short sine blip that fades out.
+const CHIME_HZ: f32 = 880.0;
+const CHIME_SECONDS: f64 = 0.25;
+
+thread_local! {
+    // Browsers cap how many of these a page may open, so there's just the one.
+    static AUDIO: RefCell<Option<AudioContext>> = const { RefCell::new(None) };
+}
+
+/// A daily stretch during which nothing pops up. `start` and `end` are
+/// `HH:MM` on the clock of `timezone` (an IANA name such as
+/// `Europe/London`), or of the browser when that's empty. An `end` before
+/// `start` runs past midnight.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuietHours {
+    pub enabled: bool,
+    pub start: String,
+    pub end: String,
+    pub timezone: String,
+}
+
+impl Default for QuietHours {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            start: "22:00".to_string(),
+            end: "07:00".to_string(),
+            timezone: String::new(),
+        }
+    }
+}
+
+fn minutes(clock: &str) -> Option<u32> {
+    let (hours, minutes) = clock.trim().split_once(':')?;
+    let (hours, minutes): (u32, u32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+/// Minutes past midnight at `time` (ms since the epoch) in `timezone`.
+/// Falls back to the browser's clock if the zone isn't one `Intl` knows.
+fn minutes_in(timezone: &str, time: f64) -> u32 {
+    let date = js_sys::Date::new(&time.into());
+    let local = date.get_hours() * 60 + date.get_minutes();
+    if timezone.is_empty() {
+        return local;
+    }
+    let options = Object::new();
+    let _ = Reflect::set(&options, &"timeZone".into(), &timezone.into());
+    let _ = Reflect::set(&options, &"hour".into(), &"2-digit".into());
+    let _ = Reflect::set(&options, &"minute".into(), &"2-digit".into());
+    let _ = Reflect::set(&options, &"hourCycle".into(), &"h23".into());
+    // An unknown zone makes the constructor throw, which `Reflect::construct`
+    // turns into an error rather than a trap.
+    let constructor = Reflect::get(&js_sys::global(), &"Intl".into())
+        .and_then(|intl| Reflect::get(&intl, &"DateTimeFormat".into()))
+        .and_then(|constructor| constructor.dyn_into::<Function>());
+    constructor
+        .and_then(|constructor| Reflect::construct(&constructor, &Array::of2(&"en-GB".into(), &options)))
+        .ok()
+        .map(|format| format.unchecked_into::<Intl::DateTimeFormat>().format())
+        .and_then(|format| format.call1(&JsValue::NULL, &date).ok())
+        .and_then(|clock| clock.as_string())
+        .and_then(|clock| minutes(&clock))
+        .unwrap_or(local)
+}
+
+/// The browser's own time zone, for showing what an empty one means.
+pub fn browser_timezone() -> String {
+    let options = Intl::DateTimeFormat::new(&Array::new(), &Object::new()).resolved_options();
+    Reflect::get(&options, &"timeZone".into()).ok().and_then(|zone| zone.as_string()).unwrap_or_default()
+}
+
+impl QuietHours {
+    /// Whether `time` (ms since the epoch) falls inside the quiet stretch.
+    pub fn active(&self, time: f64) -> bool {
+        let (start, end) = match (self.enabled, minutes(&self.start), minutes(&self.end)) {
+            (true, Some(start), Some(end)) => (start, end),
+            _ => return false,
+        };
+        let now = minutes_in(&self.timezone, time);
+        if start <= end {
+            start <= now && now < end
+        } else {
+            now >= start || now < end
+        }
+    }
+}
+
+pub struct Incoming {
+    pub room: String,
+    pub from: String,
+    pub text: String,
+    /// What the notification rules decided for it.
+    pub actions: Vec<Action>,
+}
+
+/// What a burst comes to once it's been summarised.
+#[derive(Default)]
+pub struct Burst {
+    /// One line per room, for the messages that asked for a toast.
+    pub toasts: Vec<String>,
+    /// Some message in it asked for a sound.
+    pub sound: bool,
+}
+
+/// Collects incoming messages into bursts and reports each burst as a single
+/// summary instead of one toast per message.
+pub struct Notifier {
+    pending: Vec<Incoming>,
+    last_flush: f64,
+    timer: Option<Timeout>,
+    on_flush: Callback<()>,
+}
+
+impl Notifier {
+    /// `on_flush` fires when a burst is ready; the owner should then call
+    /// [`Notifier::flush`].
+    pub fn new(on_flush: Callback<()>) -> Self {
+        Self {
+            pending: Vec::new(),
+            last_flush: 0.0,
+            timer: None,
+            on_flush,
+        }
+    }
+
+    pub fn request_permission() {
+        if Notification::permission() == NotificationPermission::Default {
+            let _ = Notification::request_permission();
+        }
+    }
+
+    pub fn push(&mut self, item: Incoming) {
+        self.pending.push(item);
+        if self.timer.is_none() {
+            let since_last = js_sys::Date::now() - self.last_flush;
+            let wait = (MIN_INTERVAL_MS - since_last).max(BURST_WINDOW_MS as f64) as u32;
+            let on_flush = self.on_flush.clone();
+            self.timer = Some(Timeout::new(wait, move || on_flush.emit(())));
+        }
+    }
+
+    /// Summarises the pending burst, one line per room, and raises a single
+    /// desktop notification for the messages that asked for one when the
+    /// tab is in the background. During `quiet` hours the burst is held,
+    /// and everything that came in meanwhile is summarised once they end.
+    pub fn flush(&mut self, quiet: &QuietHours) -> Burst {
+        self.timer = None;
+        self.last_flush = js_sys::Date::now();
+
+        if quiet.active(self.last_flush) {
+            log::debug!("quiet hours, holding back {} notifications", self.pending.len());
+            let on_flush = self.on_flush.clone();
+            self.timer = Some(Timeout::new(QUIET_RECHECK_MS, move || on_flush.emit(())));
+            return Burst::default();
+        }
+        let pending = std::mem::take(&mut self.pending);
+        let desktop = summarise(&pending, Action::Desktop);
+        if !desktop.is_empty() && is_hidden() {
+            show_desktop(&desktop);
+        }
+        Burst {
+            toasts: summarise(&pending, Action::Toast),
+            sound: pending.iter().any(|item| item.actions.contains(&Action::Sound)),
+        }
+    }
+}
+
+/// One line per room for the messages in `pending` that asked for `action`.
+fn summarise(pending: &[Incoming], action: Action) -> Vec<String> {
+    let mut rooms: Vec<(&str, Vec<&Incoming>)> = Vec::new();
+    for item in pending.iter().filter(|item| item.actions.contains(&action)) {
+        match rooms.iter_mut().find(|(room, _)| *room == item.room) {
+            Some((_, items)) => items.push(item),
+            None => rooms.push((&item.room, vec![item])),
+        }
+    }
+
+    rooms
+        .iter()
+        .map(|(room, items)| match (items.as_slice(), room.strip_prefix('@')) {
+            ([single], Some(_)) => format!("{} to you: {}", single.from, single.text),
+            ([single], None) => format!("{} in #{}: {}", single.from, room, single.text),
+            (_, Some(peer)) => format!("{} new messages from {}", items.len(), peer),
+            (_, None) => format!("{} new messages in #{}", items.len(), room),
+        })
+        .collect()
+}
+
+pub fn is_hidden() -> bool {
+    web_sys::window()
+        .and_then(|w| w.document())
+        .map(|d| d.hidden())
+        .unwrap_or(false)
+}
+
+fn show_desktop(lines: &[String]) {
+    if Notification::permission() != NotificationPermission::Granted {
+        return;
+    }
+    let title = match lines {
+        [single] => single.clone(),
+        _ => format!("New messages in {} rooms", lines.len()),
+    };
+    let mut options = NotificationOptions::new();
+    // Reusing the tag replaces the previous notification instead of stacking.
+    options.tag("yewchat").body(&lines.join("\n"));
+    if let Err(e) = Notification::new_with_options(&title, &options) {
+        log::debug!("notification failed: {:?}", e);
+    }
+}
+
+/// Plays a short chime. Browsers keep audio silent until the page has been
+/// interacted with, which by the time anyone messages us it usually has.
+pub fn play_chime() {
+    AUDIO.with(|audio| {
+        let mut audio = audio.borrow_mut();
+        if audio.is_none() {
+            *audio = AudioContext::new().map_err(|e| log::debug!("no audio: {:?}", e)).ok();
+        }
+        let context = match audio.as_ref() {
+            Some(context) => context,
+            None => return,
+        };
+        let played = (|| -> Result<(), JsValue> {
+            let _ = context.resume()?;
+            let now = context.current_time();
+            let oscillator = context.create_oscillator()?;
+            oscillator.frequency().set_value(CHIME_HZ);
+            let gain = context.create_gain()?;
+            gain.gain().set_value_at_time(0.2, now)?;
+            gain.gain().exponential_ramp_to_value_at_time(0.001, now + CHIME_SECONDS)?;
+            oscillator.connect_with_audio_node(&gain)?;
+            gain.connect_with_audio_node(&context.destination())?;
+            oscillator.start()?;
+            oscillator.stop_with_when(now + CHIME_SECONDS)?;
+            Ok(())
+        })();
+        if let Err(e) = played {
+            log::debug!("chime failed: {:?}", e);
+        }
+    });
+}
@@ -0,0 +1,79 @@
+use gloo_storage::{LocalStorage, Storage};
+use serde::{Deserialize, Serialize};
+
+const STORAGE_KEY: &str = "yewchat.onboarding";
+
+/// Something a new user is shown how to do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Step {
+    SendMessage,
+    AddReaction,
+    ChangeTheme,
+    JoinRoom,
+}
+
+impl Step {
+    pub const ALL: [Step; 4] = [Step::SendMessage, Step::AddReaction, Step::ChangeTheme, Step::JoinRoom];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Step::SendMessage => "Send a message",
+            Step::AddReaction => "React to a message",
+            Step::ChangeTheme => "Pick a theme",
+            Step::JoinRoom => "Join another room",
+        }
+    }
+}
+
+/// How far through the getting-started checklist we are. Kept in
+/// localStorage so it survives reloads and is never shown again once
+/// dismissed.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Onboarding {
+    pub done: Vec<Step>,
+    pub dismissed: bool,
+    /// The finished checklist has had its confetti, so reloads don't
+    /// play it again.
+    pub celebrated: bool,
+}
+
+impl Onboarding {
+    pub fn load() -> Self {
+        LocalStorage::get(STORAGE_KEY).unwrap_or_default()
+    }
+
+    fn save(&self) {
+        if let Err(e) = LocalStorage::set(STORAGE_KEY, self) {
+            log::error!("failed to save onboarding progress: {:?}", e);
+        }
+    }
+
+    pub fn is_done(&self, step: Step) -> bool {
+        self.done.contains(&step)
+    }
+
+    pub fn finished(&self) -> bool {
+        Step::ALL.iter().all(|&step| self.is_done(step))
+    }
+
+    /// Ticks off `step`, unless the checklist has been put away.
+    pub fn complete(&mut self, step: Step) {
+        if self.dismissed || self.is_done(step) {
+            return;
+        }
+        self.done.push(step);
+        self.save();
+    }
+
+    pub fn celebrate(&mut self) {
+        self.celebrated = true;
+        self.save();
+    }
+
+    pub fn dismiss(&mut self) {
+        self.dismissed = true;
+        self.save();
+    }
+}
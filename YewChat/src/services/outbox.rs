@@ -0,0 +1,94 @@
+use std::collections::VecDeque;
+
+// Typing notices older than this say nothing true any more; they're dropped
+// rather than sent late.
+const EPHEMERAL_TTL_MS: f64 = 5_000.0;
+
+/// How urgently a queued frame should go out once we're connected.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Priority {
+    /// Registration, presence and the like: the server needs these before
+    /// anything we send makes sense to it.
+    Control,
+    /// What the user did, kept in the order they did it.
+    User,
+    /// Only worth sending while it's fresh.
+    Ephemeral,
+}
+
+pub(crate) fn message_type(frame: &str) -> Option<String> {
+    serde_json::from_str::<serde_json::Value>(frame).ok()?["messageType"].as_str().map(str::to_string)
+}
+
+fn priority(message_type: Option<&str>) -> Priority {
+    match message_type {
+        Some("register" | "presence" | "hello" | "verify" | "enroll" | "unenroll" | "codec") => Priority::Control,
+        Some("typing" | "ping") => Priority::Ephemeral,
+        _ => Priority::User,
+    }
+}
+
+/// Frames waiting for the connection. Control frames go out first, then the
+/// user's in their original order, then whatever ephemeral frames are still
+/// fresh. Only the latest presence is kept, since it replaces the others.
+pub struct Outbox {
+    limit: usize,
+    control: VecDeque<String>,
+    user: VecDeque<String>,
+    /// With the time each was queued.
+    ephemeral: VecDeque<(f64, String)>,
+}
+
+impl Outbox {
+    pub fn new(limit: usize) -> Self {
+        Self { limit, control: VecDeque::new(), user: VecDeque::new(), ephemeral: VecDeque::new() }
+    }
+
+    fn len(&self) -> usize {
+        self.control.len() + self.user.len() + self.ephemeral.len()
+    }
+
+    /// Queues `frame` behind others of its kind. When full, ephemeral frames
+    /// are dropped first, then the oldest of the user's.
+    pub fn push(&mut self, frame: String, now: f64) {
+        let kind = message_type(&frame);
+        if kind.as_deref() == Some("presence") {
+            self.control.retain(|queued| message_type(queued).as_deref() != Some("presence"));
+        }
+        if self.len() >= self.limit {
+            log::warn!("outbox full, dropping a frame");
+            if self.ephemeral.pop_front().is_none() && self.user.pop_front().is_none() {
+                self.control.pop_front();
+            }
+        }
+        match priority(kind.as_deref()) {
+            Priority::Control => self.control.push_back(frame),
+            Priority::User => self.user.push_back(frame),
+            Priority::Ephemeral => self.ephemeral.push_back((now, frame)),
+        }
+    }
+
+    /// Puts back a frame that `pop` gave us but couldn't be sent, so it goes
+    /// first again among its kind.
+    pub fn push_front(&mut self, frame: String, now: f64) {
+        match priority(message_type(&frame).as_deref()) {
+            Priority::Control => self.control.push_front(frame),
+            Priority::User => self.user.push_front(frame),
+            Priority::Ephemeral => self.ephemeral.push_front((now, frame)),
+        }
+    }
+
+    /// The next frame to send, dropping stale ephemeral ones on the way.
+    pub fn pop(&mut self, now: f64) -> Option<String> {
+        if let Some(frame) = self.control.pop_front().or_else(|| self.user.pop_front()) {
+            return Some(frame);
+        }
+        while let Some((queued, frame)) = self.ephemeral.pop_front() {
+            if now - queued <= EPHEMERAL_TTL_MS {
+                return Some(frame);
+            }
+            log::debug!("dropping stale frame {}", frame);
+        }
+        None
+    }
+}
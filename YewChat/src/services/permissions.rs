@@ -0,0 +1,71 @@
+use serde::Deserialize;
+
+/// Where someone stands on the server, or in a room. Later roles outrank
+/// earlier ones.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    #[default]
+    Member,
+    /// Made the room with `createroom`. Only a room's member list says so.
+    Creator,
+    Moderator,
+    Owner,
+}
+
+impl Role {
+    /// The badge shown by the name; members, being most people, get none.
+    pub fn badge(&self) -> Option<(&str, &str)> {
+        match self {
+            Role::Member => None,
+            Role::Creator => Some(("Creator", "bg-green-100 text-green-800")),
+            Role::Moderator => Some(("Moderator", "bg-blue-100 text-blue-800")),
+            Role::Owner => Some(("Owner", "bg-amber-100 text-amber-800")),
+        }
+    }
+}
+
+/// What we may do, going by our role. The server checks the same rules;
+/// these only keep buttons it would refuse out of sight.
+pub struct Permissions<'a> {
+    me: &'a str,
+    role: Role,
+}
+
+impl<'a> Permissions<'a> {
+    pub fn new(me: &'a str, role: Role) -> Self {
+        Self { me, role }
+    }
+
+    fn moderates(&self) -> bool {
+        self.role >= Role::Moderator
+    }
+
+    /// Changing a room's topic, archiving it and pinning in it, given who
+    /// created it. Rooms that sprang up on their own have no creator.
+    pub fn can_manage_room(&self, created_by: &str) -> bool {
+        self.moderates() || (!created_by.is_empty() && created_by == self.me)
+    }
+
+    /// Pinning is open to anyone in rooms with no creator, and otherwise
+    /// goes with managing the room.
+    pub fn can_pin(&self, created_by: &str) -> bool {
+        created_by.is_empty() || self.can_manage_room(created_by)
+    }
+
+    /// Anyone can delete what they wrote; moderators can delete anything.
+    pub fn can_delete(&self, author: &str) -> bool {
+        author == self.me || self.moderates()
+    }
+
+    /// Announcing to a whole room is for moderators.
+    pub fn can_announce(&self) -> bool {
+        self.moderates()
+    }
+
+    /// Moderators can remove, by a kick or a ban, members, and owners
+    /// moderators too.
+    pub fn can_remove(&self, name: &str, role: Role) -> bool {
+        name != self.me && self.moderates() && self.role > role
+    }
+}
@@ -0,0 +1,88 @@
+use gloo_events::EventListener;
+use gloo_timers::callback::Interval;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::rc::Rc;
+use yew::Callback;
+
+// Without keyboard or pointer activity for this long we report ourselves away.
+const IDLE_AFTER_MS: f64 = 5.0 * 60.0 * 1000.0;
+const IDLE_CHECK_MS: u32 = 30_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PresenceStatus {
+    Online,
+    Away,
+    Offline,
+}
+
+impl PresenceStatus {
+    pub fn label(&self) -> &str {
+        match self {
+            PresenceStatus::Online => "Online",
+            PresenceStatus::Away => "Away",
+            PresenceStatus::Offline => "Offline",
+        }
+    }
+
+    pub fn dot_class(&self) -> &str {
+        match self {
+            PresenceStatus::Online => "bg-green-500",
+            PresenceStatus::Away => "bg-yellow-400",
+            PresenceStatus::Offline => "bg-gray-400",
+        }
+    }
+}
+
+/// Watches tab visibility and user activity and reports our own status
+/// whenever it changes.
+pub struct PresenceTracker {
+    _listeners: Vec<EventListener>,
+    _idle_check: Interval,
+}
+
+impl PresenceTracker {
+    pub fn new(on_change: Callback<PresenceStatus>) -> Self {
+        let window = web_sys::window().expect("no window");
+        let document = window.document().expect("no document");
+        let last_activity = Rc::new(Cell::new(js_sys::Date::now()));
+        let current = Rc::new(Cell::new(PresenceStatus::Online));
+
+        let update = {
+            let document = document.clone();
+            let last_activity = last_activity.clone();
+            Rc::new(move || {
+                let idle = js_sys::Date::now() - last_activity.get() > IDLE_AFTER_MS;
+                let status = if document.hidden() || idle {
+                    PresenceStatus::Away
+                } else {
+                    PresenceStatus::Online
+                };
+                if status != current.get() {
+                    current.set(status);
+                    on_change.emit(status);
+                }
+            })
+        };
+
+        let mut listeners = Vec::new();
+        {
+            let update = update.clone();
+            listeners.push(EventListener::new(&document, "visibilitychange", move |_| update()));
+        }
+        for event in ["keydown", "mousemove", "pointerdown"] {
+            let update = update.clone();
+            let last_activity = last_activity.clone();
+            listeners.push(EventListener::new(&window, event, move |_| {
+                last_activity.set(js_sys::Date::now());
+                update();
+            }));
+        }
+
+        Self {
+            _listeners: listeners,
+            _idle_check: Interval::new(IDLE_CHECK_MS, move || update()),
+        }
+    }
+}
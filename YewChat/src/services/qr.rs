@@ -0,0 +1,408 @@
+//! QR codes (ISO/IEC 18004) in byte mode at error correction level M,
+//! enough for links to be scanned off a screen.
+//!
+//! Cut down from Project Nayuki's QR Code generator library
+//! (https://www.nayuki.io/page/qr-code-generator-library), under its
+//! licence:
+//!
+//! Copyright (c) Project Nayuki. (MIT License)
+//!
+//! Permission is hereby granted, free of charge, to any person obtaining a copy of
+//! this software and associated documentation files (the "Software"), to deal in
+//! the Software without restriction, including without limitation the rights to
+//! use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+//! the Software, and to permit persons to whom the Software is furnished to do so,
+//! subject to the following conditions:
+//! - The above copyright notice and this permission notice shall be included in
+//!   all copies or substantial portions of the Software.
+//! - The Software is provided "as is", without warranty of any kind, express or
+//!   implied, including but not limited to the warranties of merchantability,
+//!   fitness for a particular purpose and noninfringement. In no event shall the
+//!   authors or copyright holders be liable for any claim, damages or other
+//!   liability, whether in an action of contract, tort or otherwise, arising from,
+//!   out of or in connection with the Software or the use or other dealings in the
+//!   Software.
+
+// Indexed by version; level M only.
+const ECC_CODEWORDS_PER_BLOCK: [usize; 41] = [
+    0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26, 30, 22, 22, 24, 24, 28, 28, 26, 26, 26, 26, 28, 28, 28, 28, 28, 28, 28, 28,
+    28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28,
+];
+const ERROR_CORRECTION_BLOCKS: [usize; 41] = [
+    0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5, 5, 8, 9, 9, 10, 10, 11, 13, 14, 16, 17, 17, 18, 20, 21, 23, 25, 26, 28, 29, 31, 33,
+    35, 37, 38, 40, 43, 45, 47, 49,
+];
+// Level M's two bits in the format information.
+const FORMAT_LEVEL_BITS: u32 = 0;
+
+/// A square of modules, dark or light, without the quiet zone around it.
+pub struct QrCode {
+    pub size: usize,
+    modules: Vec<bool>,
+    // Finder, timing and the like, which masking leaves alone.
+    function: Vec<bool>,
+}
+
+impl QrCode {
+    /// The smallest code holding `data`, or `None` if it's too long for any.
+    pub fn encode(data: &[u8]) -> Option<QrCode> {
+        let version = (1..=40).find(|&v| 4 + count_bits(v) + data.len() * 8 <= data_codewords(v) * 8)?;
+        let capacity = data_codewords(version) * 8;
+
+        let mut bits = Bits::default();
+        bits.push(0b0100, 4);
+        bits.push(data.len() as u32, count_bits(version));
+        for &byte in data {
+            bits.push(byte.into(), 8);
+        }
+        let terminator = (capacity - bits.0.len()).min(4);
+        bits.push(0, terminator);
+        let to_byte = (8 - bits.0.len() % 8) % 8;
+        bits.push(0, to_byte);
+        for pad in [0xEC, 0x11].iter().cycle() {
+            if bits.0.len() >= capacity {
+                break;
+            }
+            bits.push(*pad, 8);
+        }
+        let codewords: Vec<u8> = bits.0.chunks(8).map(|byte| byte.iter().fold(0, |acc, &b| acc << 1 | b as u8)).collect();
+
+        let size = version * 4 + 17;
+        let mut code = QrCode { size, modules: vec![false; size * size], function: vec![false; size * size] };
+        code.draw_function_patterns(version);
+        code.draw_codewords(&add_ecc_and_interleave(version, &codewords));
+        let mask = (0..8)
+            .min_by_key(|&mask| {
+                code.apply_mask(mask);
+                code.draw_format_bits(mask);
+                let penalty = code.penalty();
+                code.apply_mask(mask);
+                penalty
+            })
+            .unwrap_or(0);
+        code.apply_mask(mask);
+        code.draw_format_bits(mask);
+        Some(code)
+    }
+
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.size + x]
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y * self.size + x] = dark;
+        self.function[y * self.size + x] = true;
+    }
+
+    fn draw_function_patterns(&mut self, version: usize) {
+        for i in 0..self.size {
+            self.set_function(6, i, i % 2 == 0);
+            self.set_function(i, 6, i % 2 == 0);
+        }
+        let far = self.size - 4;
+        for (x, y) in [(3, 3), (far, 3), (3, far)] {
+            self.draw_finder(x, y);
+        }
+        let positions = alignment_positions(version, self.size);
+        let last = positions.len().saturating_sub(1);
+        for (i, &x) in positions.iter().enumerate() {
+            for (j, &y) in positions.iter().enumerate() {
+                // Those corners are the finders'.
+                if (i, j) != (0, 0) && (i, j) != (0, last) && (i, j) != (last, 0) {
+                    self.draw_alignment(x, y);
+                }
+            }
+        }
+        // Reserved now, written for real once the mask is chosen.
+        self.draw_format_bits(0);
+        self.draw_version(version);
+    }
+
+    fn draw_finder(&mut self, x: usize, y: usize) {
+        for dy in -4i32..=4 {
+            for dx in -4i32..=4 {
+                let (xx, yy) = (x as i32 + dx, y as i32 + dy);
+                if (0..self.size as i32).contains(&xx) && (0..self.size as i32).contains(&yy) {
+                    let ring = dx.abs().max(dy.abs());
+                    self.set_function(xx as usize, yy as usize, ring != 2 && ring != 4);
+                }
+            }
+        }
+    }
+
+    fn draw_alignment(&mut self, x: usize, y: usize) {
+        for dy in -2i32..=2 {
+            for dx in -2i32..=2 {
+                self.set_function((x as i32 + dx) as usize, (y as i32 + dy) as usize, dx.abs().max(dy.abs()) != 1);
+            }
+        }
+    }
+
+    fn draw_format_bits(&mut self, mask: u32) {
+        let data = FORMAT_LEVEL_BITS << 3 | mask;
+        let mut rem = data;
+        for _ in 0..10 {
+            rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+        }
+        let bits = (data << 10 | rem) ^ 0x5412;
+        let bit = |i: usize| (bits >> i) & 1 != 0;
+        for i in 0..6 {
+            self.set_function(8, i, bit(i));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i, 8, bit(i));
+        }
+        let size = self.size;
+        for i in 0..8 {
+            self.set_function(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(8, size - 15 + i, bit(i));
+        }
+        self.set_function(8, size - 8, true);
+    }
+
+    fn draw_version(&mut self, version: usize) {
+        if version < 7 {
+            return;
+        }
+        let mut rem = version as u32;
+        for _ in 0..12 {
+            rem = (rem << 1) ^ ((rem >> 11) * 0x1F25);
+        }
+        let bits = (version as u32) << 12 | rem;
+        for i in 0..18 {
+            let dark = (bits >> i) & 1 != 0;
+            let (a, b) = (self.size - 11 + i % 3, i / 3);
+            self.set_function(a, b, dark);
+            self.set_function(b, a, dark);
+        }
+    }
+
+    /// Lays the codewords out in the zigzag of two-module columns, from the
+    /// bottom right.
+    fn draw_codewords(&mut self, codewords: &[u8]) {
+        let mut i = 0;
+        let mut right = self.size as i32 - 1;
+        while right >= 1 {
+            // The vertical timing pattern is skipped whole.
+            if right == 6 {
+                right = 5;
+            }
+            for vert in 0..self.size {
+                for j in 0..2 {
+                    let x = (right - j) as usize;
+                    let upward = (right + 1) & 2 == 0;
+                    let y = if upward { self.size - 1 - vert } else { vert };
+                    if !self.function[y * self.size + x] && i < codewords.len() * 8 {
+                        self.modules[y * self.size + x] = (codewords[i >> 3] >> (7 - (i & 7))) & 1 != 0;
+                        i += 1;
+                    }
+                }
+            }
+            right -= 2;
+        }
+    }
+
+    /// Flips the data modules the mask picks. Doing it twice undoes it.
+    fn apply_mask(&mut self, mask: u32) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                if invert && !self.function[y * self.size + x] {
+                    self.modules[y * self.size + x] ^= true;
+                }
+            }
+        }
+    }
+
+    /// How hard the code is to scan, by the standard's four rules: long
+    /// runs, 2×2 blocks, finder look-alikes and dark/light imbalance.
+    fn penalty(&self) -> usize {
+        let size = self.size;
+        let lines = (0..size)
+            .map(|y| (0..size).map(|x| self.is_dark(x, y)).collect::<Vec<_>>())
+            .chain((0..size).map(|x| (0..size).map(|y| self.is_dark(x, y)).collect()));
+        let finder_like = [true, false, true, true, true, false, true, false, false, false, false];
+        let finder_like_back: Vec<bool> = finder_like.iter().rev().copied().collect();
+        let mut penalty = 0;
+        for line in lines {
+            let mut run = 1;
+            for i in 1..=size {
+                if i < size && line[i] == line[i - 1] {
+                    run += 1;
+                    continue;
+                }
+                if run >= 5 {
+                    penalty += run - 2;
+                }
+                run = 1;
+            }
+            penalty += line.windows(11).filter(|w| *w == finder_like || *w == finder_like_back.as_slice()).count() * 40;
+        }
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let dark = self.is_dark(x, y);
+                if dark == self.is_dark(x + 1, y) && dark == self.is_dark(x, y + 1) && dark == self.is_dark(x + 1, y + 1) {
+                    penalty += 3;
+                }
+            }
+        }
+        let dark = self.modules.iter().filter(|&&m| m).count();
+        let total = size * size;
+        // Each 5% away from half and half.
+        penalty + (dark * 20).abs_diff(total * 10).div_ceil(total) * 10
+    }
+}
+
+#[derive(Default)]
+struct Bits(Vec<bool>);
+
+impl Bits {
+    fn push(&mut self, value: u32, len: usize) {
+        self.0.extend((0..len).rev().map(|i| (value >> i) & 1 != 0));
+    }
+}
+
+fn count_bits(version: usize) -> usize {
+    if version <= 9 {
+        8
+    } else {
+        16
+    }
+}
+
+/// Modules left for codewords once the function patterns are drawn.
+fn raw_data_modules(version: usize) -> usize {
+    let mut modules = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let alignments = version / 7 + 2;
+        modules -= (25 * alignments - 10) * alignments - 55;
+        if version >= 7 {
+            modules -= 36;
+        }
+    }
+    modules
+}
+
+fn data_codewords(version: usize) -> usize {
+    raw_data_modules(version) / 8 - ECC_CODEWORDS_PER_BLOCK[version] * ERROR_CORRECTION_BLOCKS[version]
+}
+
+fn alignment_positions(version: usize, size: usize) -> Vec<usize> {
+    if version == 1 {
+        return vec![];
+    }
+    let count = version / 7 + 2;
+    let step = if version == 32 { 26 } else { (version * 4 + count * 2 + 1) / (count * 2 - 2) * 2 };
+    let mut positions: Vec<usize> = (0..count - 1).map(|k| size - 7 - k * step).collect();
+    positions.push(6);
+    positions.reverse();
+    positions
+}
+
+/// Splits the data into blocks, adds each one's Reed-Solomon codewords and
+/// interleaves the lot.
+fn add_ecc_and_interleave(version: usize, data: &[u8]) -> Vec<u8> {
+    let blocks = ERROR_CORRECTION_BLOCKS[version];
+    let ecc_len = ECC_CODEWORDS_PER_BLOCK[version];
+    let raw_codewords = raw_data_modules(version) / 8;
+    let short_blocks = blocks - raw_codewords % blocks;
+    let short_len = raw_codewords / blocks;
+    let divisor = reed_solomon_divisor(ecc_len);
+
+    let mut split = Vec::with_capacity(blocks);
+    let mut k = 0;
+    for i in 0..blocks {
+        let len = short_len - ecc_len + usize::from(i >= short_blocks);
+        let mut block = data[k..k + len].to_vec();
+        k += len;
+        let ecc = reed_solomon_remainder(&block, &divisor);
+        // Padding to line the short blocks up; skipped when interleaving.
+        if i < short_blocks {
+            block.push(0);
+        }
+        block.extend(ecc);
+        split.push(block);
+    }
+    let mut result = Vec::with_capacity(raw_codewords);
+    for i in 0..split[0].len() {
+        for (j, block) in split.iter().enumerate() {
+            if i != short_len - ecc_len || j >= short_blocks {
+                result.push(block[i]);
+            }
+        }
+    }
+    result
+}
+
+// Multiplication in GF(2^8) modulo x^8 + x^4 + x^3 + x^2 + 1.
+fn gf_multiply(x: u8, y: u8) -> u8 {
+    let mut z: u32 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11D);
+        z ^= ((y as u32 >> i) & 1) * x as u32;
+    }
+    z as u8
+}
+
+fn reed_solomon_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0; degree];
+    result[degree - 1] = 1;
+    let mut root = 1;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = gf_multiply(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = gf_multiply(root, 0x02);
+    }
+    result
+}
+
+fn reed_solomon_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0; divisor.len()];
+    for &byte in data {
+        let factor = byte ^ result.remove(0);
+        result.push(0);
+        for (x, &y) in result.iter_mut().zip(divisor) {
+            *x ^= gf_multiply(y, factor);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reed_solomon_matches_the_standard() {
+        // "HELLO WORLD" at 1-M, the standard's worked example.
+        let data = [32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17];
+        let ecc = reed_solomon_remainder(&data, &reed_solomon_divisor(ECC_CODEWORDS_PER_BLOCK[1]));
+        assert_eq!(ecc, [196, 35, 39, 119, 235, 215, 231, 226, 93, 23]);
+        assert_eq!(add_ecc_and_interleave(1, &data), [&data[..], &ecc[..]].concat());
+    }
+
+    #[test]
+    fn picks_the_smallest_version() {
+        assert_eq!(QrCode::encode(b"https://example.com").map(|code| code.size), Some(25));
+        assert_eq!(QrCode::encode(&[b'a'; 14]).map(|code| code.size), Some(21));
+        assert!(QrCode::encode(&[b'a'; 3000]).is_none());
+    }
+}
@@ -0,0 +1,61 @@
+use gloo_storage::{LocalStorage, Storage};
+use serde::{Deserialize, Serialize};
+
+const STORAGE_KEY: &str = "yewchat.recent";
+const MAX_ENTRIES: usize = 50;
+// A visit's weight halves every day.
+const HALF_LIFE_MS: f64 = 24.0 * 60.0 * 60.0 * 1000.0;
+
+/// Recently visited rooms and recently messaged users, with the time of the
+/// last visit, so pickers can rank warm entries first.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Recent {
+    pub rooms: Vec<(String, f64)>,
+    pub users: Vec<(String, f64)>,
+}
+
+fn touch(entries: &mut Vec<(String, f64)>, name: &str) {
+    entries.retain(|(n, _)| n != name);
+    entries.insert(0, (name.to_string(), js_sys::Date::now()));
+    entries.truncate(MAX_ENTRIES);
+}
+
+fn weight(entries: &[(String, f64)], name: &str) -> f64 {
+    entries
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, at)| 0.5f64.powf((js_sys::Date::now() - at) / HALF_LIFE_MS))
+        .unwrap_or(0.0)
+}
+
+impl Recent {
+    pub fn load() -> Self {
+        LocalStorage::get(STORAGE_KEY).unwrap_or_default()
+    }
+
+    fn save(&self) {
+        if let Err(e) = LocalStorage::set(STORAGE_KEY, self) {
+            log::error!("failed to save recent entries: {:?}", e);
+        }
+    }
+
+    pub fn visit_room(&mut self, room: &str) {
+        touch(&mut self.rooms, room);
+        self.save();
+    }
+
+    pub fn message_user(&mut self, user: &str) {
+        touch(&mut self.users, user);
+        self.save();
+    }
+
+    /// 1.0 for a room visited just now, decaying towards 0.0.
+    pub fn room_weight(&self, room: &str) -> f64 {
+        weight(&self.rooms, room)
+    }
+
+    pub fn user_weight(&self, user: &str) -> f64 {
+        weight(&self.users, user)
+    }
+}
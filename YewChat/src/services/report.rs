@@ -0,0 +1,169 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::services::settings::Settings;
+use crate::services::traffic::LoggedFrame;
+
+// Never worth sending anyone, redacting or not.
+const SECRET_KEYS: [&str; 2] = ["auth", "token"];
+// What people wrote or sent, and to whom: message text, file names,
+// picture descriptions, room topics and direct message recipients.
+const BODY_KEYS: [&str; 6] = ["message", "alt", "text", "name", "description", "to"];
+// Frames whose top-level `data` is itself what someone wrote or sent.
+const BODY_FRAMES: [&str; 5] = ["message", "edit", "filechunk", "createroom", "roomupdate"];
+
+fn placeholder(text: &str) -> Value {
+    Value::String(placeholder_text(text))
+}
+
+fn placeholder_text(text: &str) -> String {
+    format!("[redacted, {} chars]", text.chars().count())
+}
+
+/// Walks `value`, hiding secrets always and message bodies when `bodies`.
+/// Payloads are often JSON inside a string, so those are opened up too.
+fn scrub(value: &mut Value, bodies: bool) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                match value {
+                    Value::String(s) if SECRET_KEYS.contains(&key.as_str()) => *value = placeholder(s),
+                    Value::String(s) if bodies && BODY_KEYS.contains(&key.as_str()) => *value = placeholder(s),
+                    _ => scrub(value, bodies),
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| scrub(item, bodies)),
+        Value::String(s) => {
+            if let Ok(mut inner @ (Value::Object(_) | Value::Array(_))) = serde_json::from_str::<Value>(s) {
+                scrub(&mut inner, bodies);
+                *s = inner.to_string();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// `frame` with anything sensitive taken out. Frames that aren't JSON are
+/// kept only as their length when redacting.
+pub fn redact(frame: &str, bodies: bool) -> String {
+    let mut value: Value = match serde_json::from_str(frame) {
+        Ok(value) => value,
+        Err(_) if bodies => return placeholder_text(frame),
+        Err(_) => return frame.to_string(),
+    };
+    let kind = value["messageType"].as_str().unwrap_or_default().to_string();
+    if bodies && BODY_FRAMES.contains(&kind.as_str()) {
+        if let Some(Value::String(data)) = value.get("data") {
+            // An incoming `message` carries JSON, scrubbed below; ours is the bare text.
+            if serde_json::from_str::<Value>(data).map_or(true, |v| !v.is_object()) {
+                value["data"] = placeholder(data);
+            }
+        }
+    }
+    scrub(&mut value, bodies);
+    value.to_string()
+}
+
+/// A log line with any JSON in it redacted like a frame, since some lines
+/// quote the frame they're about.
+fn redact_line(line: &str, bodies: bool) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let mut values = serde_json::Deserializer::from_str(&rest[start..]).into_iter::<Value>();
+        match values.next() {
+            Some(Ok(_)) => {
+                let end = start + values.byte_offset();
+                out.push_str(&redact(&rest[start..end], bodies));
+                rest = &rest[end..];
+            }
+            _ => {
+                out.push('{');
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[derive(Serialize)]
+struct ReportFrame {
+    time: f64,
+    outbound: bool,
+    kind: String,
+    frame: String,
+}
+
+/// Everything a problem report holds, as JSON.
+pub struct Report<'a> {
+    pub protocol_version: u32,
+    pub endpoint: Option<&'a str>,
+    pub connection: String,
+    pub settings: &'a Settings,
+    pub frames: &'a [LoggedFrame],
+    pub logs: Vec<String>,
+    pub redact: bool,
+}
+
+impl Report<'_> {
+    pub fn to_json(&self) -> String {
+        let mut settings = serde_json::to_value(self.settings).unwrap_or_default();
+        if self.redact {
+            // Written by the user, like their messages.
+            if let Some(map) = settings.as_object_mut() {
+                map.remove("templates");
+                map.remove("away_message");
+            }
+        }
+        let frames: Vec<ReportFrame> = self
+            .frames
+            .iter()
+            .map(|f| ReportFrame { time: f.time, outbound: f.outbound, kind: f.kind.clone(), frame: redact(&f.frame, self.redact) })
+            .collect();
+        let logs: Vec<String> = self.logs.iter().map(|line| redact_line(line, self.redact)).collect();
+        let user_agent = web_sys::window().and_then(|w| w.navigator().user_agent().ok());
+        serde_json::to_string_pretty(&json!({
+            "app": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+            "protocolVersion": self.protocol_version,
+            "created": js_sys::Date::new_0().to_iso_string().as_string(),
+            "userAgent": user_agent,
+            "endpoint": self.endpoint,
+            "connection": self.connection,
+            "redacted": self.redact,
+            "settings": settings,
+            "frames": frames,
+            "logs": logs,
+        }))
+        .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hides_file_names_topics_and_recipients() {
+        let filestart = r#"{"messageType":"filestart","data":"{\"name\":\"payslip.pdf\",\"size\":10}"}"#;
+        assert!(!redact(filestart, true).contains("payslip"));
+        let topic = r#"{"messageType":"createroom","room":"plans","data":"Surprise party for Sam"}"#;
+        assert!(!redact(topic, true).contains("Sam"));
+        let direct = r#"{"messageType":"message","data":"hi","to":"sam"}"#;
+        assert!(!redact(direct, true).contains("sam"));
+    }
+
+    #[test]
+    fn redacts_frames_quoted_in_log_lines() {
+        let line = r#"WARN chat: dropping frame {"messageType":"message","data":"secret plan","auth":"abc"}: bad data"#;
+        let redacted = redact_line(line, true);
+        assert!(redacted.starts_with("WARN chat: dropping frame {"));
+        assert!(redacted.ends_with("}: bad data"));
+        assert!(!redacted.contains("secret plan"));
+        assert!(!redact_line(line, false).contains("abc"));
+        assert_eq!(redact_line("no frames { here", true), "no frames { here");
+    }
+}
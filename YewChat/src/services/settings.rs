@@ -0,0 +1,423 @@
+use gloo_storage::{LocalStorage, Storage};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::services::language;
+use crate::services::notification_rules::{self, Action, Message, Rule};
+use crate::services::notifications::QuietHours;
+use crate::services::word_filter;
+
+const STORAGE_KEY: &str = "yewchat.settings";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    Light,
+    Dark,
+    Ocean,
+    Forest,
+}
+
+impl Theme {
+    pub fn get_css_classes(&self) -> &str {
+        match self {
+            Theme::Light => "bg-white text-black",
+            Theme::Dark => "bg-gray-800 text-black",
+            Theme::Ocean => "bg-blue-900 text-black",
+            Theme::Forest => "bg-green-900 text-black",
+        }
+    }
+
+    pub fn tokens(&self) -> ThemeTokens {
+        match self {
+            Theme::Light => ThemeTokens {
+                surface: "bg-gray-100",
+                raised: "bg-gray-200 hover:bg-gray-300",
+                border: "border-gray-200",
+                text: "text-gray-900",
+                muted: "text-gray-500",
+            },
+            Theme::Dark => ThemeTokens {
+                surface: "bg-gray-700",
+                raised: "bg-gray-600 hover:bg-gray-500",
+                border: "border-gray-600",
+                text: "text-gray-100",
+                muted: "text-gray-400",
+            },
+            Theme::Ocean => ThemeTokens {
+                surface: "bg-blue-800",
+                raised: "bg-blue-700 hover:bg-blue-600",
+                border: "border-blue-700",
+                text: "text-blue-50",
+                muted: "text-blue-200",
+            },
+            Theme::Forest => ThemeTokens {
+                surface: "bg-green-800",
+                raised: "bg-green-700 hover:bg-green-600",
+                border: "border-green-700",
+                text: "text-green-50",
+                muted: "text-green-200",
+            },
+        }
+    }
+}
+
+/// Tailwind classes for content shown inside messages, by role rather than
+/// colour, so it follows the theme. `Chat` provides them as context.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ThemeTokens {
+    /// What a message sits on.
+    pub surface: &'static str,
+    /// Buttons and placeholders on a surface.
+    pub raised: &'static str,
+    pub border: &'static str,
+    pub text: &'static str,
+    pub muted: &'static str,
+}
+
+impl Default for ThemeTokens {
+    fn default() -> Self {
+        Theme::Dark.tokens()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageFont {
+    System,
+    Serif,
+    Monospace,
+}
+
+impl MessageFont {
+    pub fn get_css_class(&self) -> &str {
+        match self {
+            MessageFont::System => "font-sans",
+            MessageFont::Serif => "font-serif",
+            MessageFont::Monospace => "font-mono",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmojiStyle {
+    Native,
+    Twemoji,
+}
+
+/// What Enter does in the composer.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnterKey {
+    /// Enter sends; Shift+Enter starts a new line.
+    Send,
+    /// Enter starts a new line; Ctrl+Enter (Cmd+Enter on a Mac) sends.
+    CtrlSend,
+    /// Like `CtrlSend` on touch screens, where the send button is always in
+    /// reach and Enter is the only way to a new line; like `Send` elsewhere.
+    Auto,
+}
+
+impl EnterKey {
+    /// Whether Enter, with these modifiers held, sends the message.
+    pub fn sends(&self, ctrl: bool, shift: bool) -> bool {
+        match self {
+            EnterKey::Send => !shift,
+            EnterKey::CtrlSend => ctrl,
+            EnterKey::Auto if touch_screen() => ctrl,
+            EnterKey::Auto => !shift,
+        }
+    }
+}
+
+/// Whether the main pointer is a finger.
+fn touch_screen() -> bool {
+    web_sys::window()
+        .and_then(|window| window.match_media("(pointer: coarse)").ok().flatten())
+        .map(|query| query.matches())
+        .unwrap_or(false)
+}
+
+/// A saved reply. `{user}`, `{me}` and `{room}` in the text are filled in
+/// when it is used.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Template {
+    pub name: String,
+    pub text: String,
+}
+
+impl Template {
+    /// `user` is whoever we're most likely replying to.
+    pub fn expand(&self, user: &str, me: &str, room: &str) -> String {
+        self.text
+            .replace("{user}", user)
+            .replace("{me}", me)
+            .replace("{room}", room)
+    }
+}
+
+/// What one conversation keeps to itself, on top of the privacy toggles
+/// that apply everywhere.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConversationPrivacy {
+    pub hide_read_receipts: bool,
+    pub hide_typing: bool,
+}
+
+/// User preferences, persisted in localStorage. Missing fields fall back to
+/// their defaults so older saved blobs keep loading as settings grow.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub theme: Theme,
+    pub message_font: MessageFont,
+    pub emoji_style: EmojiStyle,
+    pub enter_key: EnterKey,
+    /// Show `@name` in the composer as a chip that's deleted in one go.
+    pub mention_chips: bool,
+    pub show_typing_indicators: bool,
+    pub send_typing_events: bool,
+    /// Typing events in direct conversations as well. Off unless asked
+    /// for, since they tell one person just when we're writing to them.
+    pub send_direct_typing_events: bool,
+    pub send_read_receipts: bool,
+    /// Conversations that keep more to themselves than the toggles above,
+    /// by room name, `@name` for a direct conversation.
+    pub conversation_privacy: HashMap<String, ConversationPrivacy>,
+    pub broadcast_presence: bool,
+    pub message_stats: bool,
+    pub diagnostics: bool,
+    pub low_data: bool,
+    /// Ask for a description before sending a picture.
+    pub ask_alt_text: bool,
+    /// Pictures larger than this, in bytes, only load when clicked. `None`
+    /// for no limit.
+    pub media_auto_load_limit: Option<usize>,
+    /// What messages are translated into, as an ISO 639-1 code. Empty to
+    /// follow the browser.
+    pub display_language: String,
+    /// Names and jargon the translator is never given to translate.
+    pub translation_glossary: Vec<String>,
+    pub toast_notifications: bool,
+    pub desktop_notifications: bool,
+    /// Start replies with an @mention of whoever we're answering.
+    pub mention_on_reply: bool,
+    pub quiet_hours: QuietHours,
+    /// Checked in order for each message; the first that matches decides
+    /// how we're told. Messages none match use the toggles above.
+    pub notification_rules: Vec<Rule>,
+    /// Messages containing any of these are highlighted and counted with
+    /// mentions.
+    pub watch_keywords: Vec<String>,
+    /// Star out `filtered_words` in messages, and ask before sending them.
+    pub word_filter: bool,
+    pub filtered_words: Vec<String>,
+    pub muted_users: Vec<String>,
+    /// Their messages are collapsed, and their typing and presence ignored.
+    pub blocked_users: Vec<String>,
+    /// Joined as soon as we're signed in.
+    pub auto_join_rooms: Vec<String>,
+    /// Where we land after signing in. Empty for the default room.
+    pub default_room: String,
+    /// Rooms that never notify, though their unread counts still grow.
+    /// `@name` for a direct conversation.
+    pub muted_rooms: Vec<String>,
+    /// Links to these domains (and their subdomains) open without asking.
+    pub trusted_domains: Vec<String>,
+    pub quick_reactions: Vec<String>,
+    /// Added by double-clicking or double-tapping a message. Empty for off.
+    pub double_tap_reaction: String,
+    pub templates: Vec<Template>,
+    /// Sent back to whoever mentions us while we're away. Empty for none.
+    pub away_message: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            theme: Theme::Dark,
+            message_font: MessageFont::System,
+            emoji_style: EmojiStyle::Native,
+            enter_key: EnterKey::Auto,
+            mention_chips: false,
+            show_typing_indicators: true,
+            send_typing_events: true,
+            send_direct_typing_events: false,
+            send_read_receipts: true,
+            conversation_privacy: HashMap::new(),
+            broadcast_presence: true,
+            message_stats: false,
+            diagnostics: false,
+            low_data: false,
+            ask_alt_text: true,
+            media_auto_load_limit: Some(2 * 1024 * 1024),
+            display_language: String::new(),
+            translation_glossary: vec![],
+            toast_notifications: true,
+            desktop_notifications: true,
+            mention_on_reply: true,
+            quiet_hours: QuietHours::default(),
+            notification_rules: vec![],
+            watch_keywords: vec![],
+            word_filter: false,
+            filtered_words: vec![],
+            muted_users: vec![],
+            blocked_users: vec![],
+            auto_join_rooms: vec![],
+            default_room: String::new(),
+            muted_rooms: vec![],
+            trusted_domains: vec![],
+            quick_reactions: vec!["👍".into(), "❤️".into(), "😂".into()],
+            double_tap_reaction: "👍".into(),
+            templates: vec![],
+            away_message: String::new(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        LocalStorage::get(STORAGE_KEY).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        if let Err(e) = LocalStorage::set(STORAGE_KEY, self) {
+            log::error!("failed to save settings: {:?}", e);
+        }
+    }
+
+    pub fn export(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn import(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Twemoji images are fetched from a CDN, so low-data mode falls back to
+    /// the platform's own glyphs.
+    pub fn rendered_emoji_style(&self) -> &EmojiStyle {
+        if self.low_data {
+            &EmojiStyle::Native
+        } else {
+            &self.emoji_style
+        }
+    }
+
+    pub fn template(&self, name: &str) -> Option<&Template> {
+        self.templates.iter().find(|t| t.name.eq_ignore_ascii_case(name))
+    }
+
+    /// How to tell us about `message`, by the rules or else the toggles.
+    pub fn notification_actions(&self, message: &Message, me: &str) -> Vec<Action> {
+        let mut otherwise = vec![];
+        if self.toast_notifications {
+            otherwise.push(Action::Toast);
+        }
+        if self.desktop_notifications {
+            otherwise.push(Action::Desktop);
+        }
+        notification_rules::decide(&self.notification_rules, message, me, otherwise)
+    }
+
+    /// The reader's language: the one they picked, or the browser's.
+    pub fn language(&self) -> String {
+        if self.display_language.is_empty() {
+            language::user_language()
+        } else {
+            self.display_language.clone()
+        }
+    }
+
+    /// `text` as it should be shown, filtered words starred out.
+    pub fn masked<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if self.word_filter {
+            word_filter::mask(&self.filtered_words, text)
+        } else {
+            Cow::Borrowed(text)
+        }
+    }
+
+    /// The filtered words in `text`, to warn about before it's sent.
+    pub fn filtered_in(&self, text: &str) -> Vec<&str> {
+        if self.word_filter {
+            word_filter::found(&self.filtered_words, text)
+        } else {
+            vec![]
+        }
+    }
+
+    pub fn is_muted(&self, user: &str) -> bool {
+        self.muted_users.iter().any(|u| u == user)
+    }
+
+    pub fn toggle_muted(&mut self, user: &str) {
+        if self.is_muted(user) {
+            self.muted_users.retain(|u| u != user);
+        } else {
+            self.muted_users.push(user.to_string());
+        }
+    }
+
+    pub fn is_blocked(&self, user: &str) -> bool {
+        self.blocked_users.iter().any(|u| u == user)
+    }
+
+    pub fn toggle_blocked(&mut self, user: &str) {
+        if self.is_blocked(user) {
+            self.blocked_users.retain(|u| u != user);
+        } else {
+            self.blocked_users.push(user.to_string());
+        }
+    }
+
+    /// Every room to join on signing in, the one to land in included.
+    pub fn rooms_to_join(&self) -> impl Iterator<Item = &String> {
+        self.auto_join_rooms.iter().chain((!self.default_room.is_empty()).then_some(&self.default_room))
+    }
+
+    pub fn privacy_in(&self, room: &str) -> ConversationPrivacy {
+        self.conversation_privacy.get(room).copied().unwrap_or_default()
+    }
+
+    /// Sets what `room` keeps to itself; a conversation back to the
+    /// defaults is forgotten.
+    pub fn set_privacy(&mut self, room: &str, privacy: ConversationPrivacy) {
+        if privacy == ConversationPrivacy::default() {
+            self.conversation_privacy.remove(room);
+        } else {
+            self.conversation_privacy.insert(room.to_string(), privacy);
+        }
+    }
+
+    /// Whether we tell others in `room` how far we've read.
+    pub fn sends_read_receipts(&self, room: &str) -> bool {
+        self.send_read_receipts && !self.privacy_in(room).hide_read_receipts
+    }
+
+    /// Whether we tell others in `room` when we're typing.
+    pub fn sends_typing(&self, room: &str) -> bool {
+        self.allows_typing_in(room) && !self.privacy_in(room).hide_typing
+    }
+
+    /// Whether the toggles that apply everywhere leave typing events in
+    /// `room`, `@name` being a direct conversation, up to the room.
+    pub fn allows_typing_in(&self, room: &str) -> bool {
+        self.send_typing_events && (!room.starts_with('@') || self.send_direct_typing_events)
+    }
+
+    pub fn is_room_muted(&self, room: &str) -> bool {
+        self.muted_rooms.iter().any(|r| r == room)
+    }
+
+    pub fn toggle_room_muted(&mut self, room: &str) {
+        if self.is_room_muted(room) {
+            self.muted_rooms.retain(|r| r != room);
+        } else {
+            self.muted_rooms.push(room.to_string());
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use gloo_events::EventListener;
+use wasm_bindgen::JsCast;
+use web_sys::KeyboardEvent;
+use yew::Callback;
+
+/// Keys that work wherever focus is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shortcut {
+    /// Ctrl+K (⌘K): the quick switcher.
+    Palette,
+    /// Alt+Shift+F: the frame log. By key position, since Alt changes the
+    /// character on some layouts.
+    FrameLog,
+}
+
+impl Shortcut {
+    fn of(e: &KeyboardEvent) -> Option<Self> {
+        if (e.ctrl_key() || e.meta_key()) && e.key().eq_ignore_ascii_case("k") {
+            Some(Shortcut::Palette)
+        } else if e.alt_key() && e.shift_key() && e.code() == "KeyF" {
+            Some(Shortcut::FrameLog)
+        } else {
+            None
+        }
+    }
+}
+
+/// Listens on the window for the shortcuts, keeping the browser's own use
+/// of the keys out of the way. Stops when the listener is dropped.
+pub fn listen(on_shortcut: Callback<Shortcut>) -> Option<EventListener> {
+    let window = web_sys::window()?;
+    Some(EventListener::new(&window, "keydown", move |e| {
+        if let Some(shortcut) = e.dyn_ref::<KeyboardEvent>().and_then(Shortcut::of) {
+            e.prevent_default();
+            on_shortcut.emit(shortcut);
+        }
+    }))
+}
@@ -0,0 +1,77 @@
+use gloo_storage::{LocalStorage, Storage};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const STORAGE_KEY: &str = "yewchat.starred";
+
+/// A message as it was when we starred it, so it can still be listed once
+/// it's no longer loaded.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StarredMessage {
+    pub id: u64,
+    pub room: String,
+    pub from: String,
+    pub text: String,
+    pub starred_at: f64,
+}
+
+/// Messages starred on this device, keyed by message ID. Nobody else sees
+/// them.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Starred {
+    messages: BTreeMap<u64, StarredMessage>,
+}
+
+impl Starred {
+    pub fn load() -> Self {
+        LocalStorage::get(STORAGE_KEY).unwrap_or_default()
+    }
+
+    fn save(&self) {
+        if let Err(e) = LocalStorage::set(STORAGE_KEY, self) {
+            log::error!("failed to save starred messages: {:?}", e);
+        }
+    }
+
+    pub fn contains(&self, id: u64) -> bool {
+        self.messages.contains_key(&id)
+    }
+
+    pub fn star(&mut self, message: StarredMessage) {
+        self.messages.insert(message.id, message);
+        self.save();
+    }
+
+    pub fn unstar(&mut self, id: u64) {
+        if self.messages.remove(&id).is_some() {
+            self.save();
+        }
+    }
+
+    /// Follows someone's rename: their name on what they wrote, and the
+    /// room of our direct conversation with them.
+    pub fn rename_user(&mut self, from: &str, to: &str, old_dm: &str, new_dm: &str) {
+        let mut changed = false;
+        for message in self.messages.values_mut() {
+            if message.from == from {
+                message.from = to.to_string();
+                changed = true;
+            }
+            if message.room == old_dm {
+                message.room = new_dm.to_string();
+                changed = true;
+            }
+        }
+        if changed {
+            self.save();
+        }
+    }
+
+    /// Everything starred, most recently starred first.
+    pub fn list(&self) -> Vec<StarredMessage> {
+        let mut list: Vec<StarredMessage> = self.messages.values().cloned().collect();
+        list.sort_by(|a, b| b.starred_at.total_cmp(&a.starred_at));
+        list
+    }
+}
@@ -0,0 +1,64 @@
+use js_sys::{Function, Promise, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+
+/// Storage this full or more earns a warning.
+const WARN_RATIO: f64 = 0.8;
+/// Files held in memory past this much earn one too, whatever the quota.
+pub const MEMORY_WARN_BYTES: usize = 50 * 1024 * 1024;
+/// Pruning a room keeps this many of its newest messages.
+pub const KEEP_WHEN_PRUNING: usize = 50;
+
+/// How much of the browser's storage for this site is in use, from the
+/// Storage API: IndexedDB, caches and the like, by the browser's reckoning.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Estimate {
+    pub usage: f64,
+    pub quota: f64,
+}
+
+impl Estimate {
+    pub fn ratio(&self) -> f64 {
+        if self.quota > 0.0 {
+            self.usage / self.quota
+        } else {
+            0.0
+        }
+    }
+
+    pub fn nearly_full(&self) -> bool {
+        self.ratio() >= WARN_RATIO
+    }
+}
+
+/// What one room is holding on to in this tab.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoomUsage {
+    pub room: String,
+    pub messages: usize,
+    /// Message text, in bytes.
+    pub text_bytes: usize,
+    pub files: usize,
+    pub file_bytes: usize,
+}
+
+impl RoomUsage {
+    pub fn bytes(&self) -> usize {
+        self.text_bytes + self.file_bytes
+    }
+}
+
+/// Asks `navigator.storage.estimate()`, reached through reflection like the
+/// clipboard. `None` where the browser doesn't say.
+pub async fn estimate() -> Option<Estimate> {
+    let navigator = web_sys::window()?.navigator();
+    let storage = Reflect::get(&navigator, &JsValue::from_str("storage")).ok().filter(|s| !s.is_undefined())?;
+    let estimate: Function = Reflect::get(&storage, &JsValue::from_str("estimate")).ok()?.dyn_into().ok()?;
+    let promise: Promise = estimate.call0(&storage).ok()?.dyn_into().ok()?;
+    let result = JsFuture::from(promise)
+        .await
+        .map_err(|e| log::warn!("storage estimate failed: {:?}", e))
+        .ok()?;
+    let number = |key: &str| Reflect::get(&result, &JsValue::from_str(key)).ok().and_then(|v| v.as_f64());
+    Some(Estimate { usage: number("usage")?, quota: number("quota")? })
+}
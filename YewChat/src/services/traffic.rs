@@ -0,0 +1,112 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::services::outbox::message_type;
+
+// Raw frames kept for the debug panel; the oldest go first.
+const FRAME_LOG_LIMIT: usize = 500;
+
+/// Frames and their total size, in characters of JSON.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FrameCount {
+    pub frames: u64,
+    pub bytes: u64,
+}
+
+impl FrameCount {
+    fn add(&mut self, bytes: usize) {
+        self.frames += 1;
+        self.bytes += bytes as u64;
+    }
+
+    pub fn average(&self) -> f64 {
+        if self.frames == 0 {
+            0.0
+        } else {
+            self.bytes as f64 / self.frames as f64
+        }
+    }
+}
+
+/// Traffic one way, in total and by `messageType`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Direction {
+    pub total: FrameCount,
+    pub by_type: BTreeMap<String, FrameCount>,
+}
+
+impl Direction {
+    fn record(&mut self, kind: &str, bytes: usize) {
+        self.total.add(bytes);
+        self.by_type.entry(kind.to_string()).or_default().add(bytes);
+    }
+}
+
+/// What has gone over the connection since the service started, counted
+/// as JSON before any codec, so it shows what a denser encoding could save.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TrafficStats {
+    /// Milliseconds since the epoch.
+    pub since: f64,
+    pub sent: Direction,
+    pub received: Direction,
+}
+
+impl TrafficStats {
+    pub fn new() -> Self {
+        Self { since: js_sys::Date::now(), ..Self::default() }
+    }
+
+    fn record(&mut self, frame: &LoggedFrame) {
+        let direction = if frame.outbound { &mut self.sent } else { &mut self.received };
+        direction.record(&frame.kind, frame.frame.len());
+    }
+}
+
+/// One frame as it went over the wire.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoggedFrame {
+    /// Milliseconds since the epoch.
+    pub time: f64,
+    /// Sent by us, rather than received.
+    pub outbound: bool,
+    /// Its `messageType`, or "?" if it has none.
+    pub kind: String,
+    pub frame: String,
+}
+
+/// Everything the connection sees: running totals, and the latest frames
+/// verbatim for debugging.
+#[derive(Default)]
+pub struct Traffic {
+    stats: TrafficStats,
+    frames: VecDeque<LoggedFrame>,
+}
+
+impl Traffic {
+    pub fn new() -> Self {
+        Self { stats: TrafficStats::new(), frames: VecDeque::new() }
+    }
+
+    pub fn record(&mut self, outbound: bool, frame: &str) {
+        let logged = LoggedFrame {
+            time: js_sys::Date::now(),
+            outbound,
+            kind: message_type(frame).unwrap_or_else(|| "?".to_string()),
+            frame: frame.to_string(),
+        };
+        self.stats.record(&logged);
+        if self.frames.len() >= FRAME_LOG_LIMIT {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(logged);
+    }
+
+    pub fn stats(&self) -> TrafficStats {
+        self.stats.clone()
+    }
+
+    /// The latest frames, oldest first.
+    pub fn frames(&self) -> Vec<LoggedFrame> {
+        self.frames.iter().cloned().collect()
+    }
+}
@@ -0,0 +1,153 @@
+use js_sys::{Array, Function, Object, Promise, Reflect};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use yew::Callback;
+
+/// Appends the conversation to a file on disk as it happens, through the
+/// File System Access API, so a long session doesn't depend on what the
+/// browser is willing to keep. Every batch of lines is written and closed
+/// on its own; a crash loses at most what was still queued.
+pub struct Transcript {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    handle: JsValue,
+    name: String,
+    /// Bytes written so far, where the next batch goes.
+    offset: Cell<f64>,
+    pending: RefCell<Vec<String>>,
+    writing: Cell<bool>,
+    failed: Cell<bool>,
+    on_error: Callback<String>,
+}
+
+/// Whether this browser lets pages write to files the user picks.
+pub fn supported() -> bool {
+    web_sys::window()
+        .map(|window| Reflect::has(&window, &JsValue::from_str("showSaveFilePicker")).unwrap_or(false))
+        .unwrap_or(false)
+}
+
+fn call(target: &JsValue, method: &str, args: &[&JsValue]) -> Result<JsValue, JsValue> {
+    let function: Function = Reflect::get(target, &JsValue::from_str(method))?.dyn_into()?;
+    let args: Array = args.iter().copied().collect();
+    function.apply(target, &args)
+}
+
+async fn call_async(target: &JsValue, method: &str, args: &[&JsValue]) -> Result<JsValue, JsValue> {
+    let promise: Promise = call(target, method, args)?.dyn_into()?;
+    JsFuture::from(promise).await
+}
+
+fn object(entries: &[(&str, JsValue)]) -> Object {
+    let object = Object::new();
+    for (key, value) in entries {
+        let _ = Reflect::set(&object, &JsValue::from_str(key), value);
+    }
+    object
+}
+
+fn name_of(value: &JsValue) -> Option<String> {
+    Reflect::get(value, &JsValue::from_str("name")).ok()?.as_string()
+}
+
+fn describe(error: &JsValue) -> String {
+    error
+        .dyn_ref::<js_sys::Error>()
+        .map(|e| String::from(e.message()))
+        .unwrap_or_else(|| "The file couldn't be written.".to_string())
+}
+
+/// Writes `text` at `offset`. Without `keep`, whatever the file held before
+/// is thrown away.
+async fn write(handle: &JsValue, keep: bool, offset: f64, text: &str) -> Result<(), JsValue> {
+    let options = object(&[("keepExistingData", keep.into())]);
+    let writable = call_async(handle, "createWritable", &[&options]).await?;
+    let chunk = object(&[("type", "write".into()), ("position", offset.into()), ("data", text.into())]);
+    call_async(&writable, "write", &[&chunk]).await?;
+    call_async(&writable, "close", &[]).await?;
+    Ok(())
+}
+
+impl Transcript {
+    /// Asks the user where to save and starts the file. `Ok(None)` if they
+    /// cancelled the picker. `on_error` hears about a later write failing,
+    /// after which nothing more is written.
+    pub async fn start(on_error: Callback<String>) -> Result<Option<Transcript>, String> {
+        let window = web_sys::window().ok_or("No window")?;
+        let date = String::from(js_sys::Date::new_0().to_iso_string());
+        let options = object(&[
+            ("suggestedName", format!("yewchat-transcript-{}.txt", &date[..10]).into()),
+            (
+                "types",
+                Array::of1(&object(&[
+                    ("description", "Text".into()),
+                    ("accept", object(&[("text/plain", Array::of1(&".txt".into()).into())]).into()),
+                ]))
+                .into(),
+            ),
+        ]);
+        let handle = match call_async(&window, "showSaveFilePicker", &[&options]).await {
+            Ok(handle) => handle,
+            Err(e) if name_of(&e).as_deref() == Some("AbortError") => return Ok(None),
+            Err(e) => return Err(describe(&e)),
+        };
+        let name = name_of(&handle).unwrap_or_default();
+        let started = js_sys::Date::new_0().to_locale_string("default", &JsValue::UNDEFINED);
+        let header = format!("YewChat transcript, started {}\n\n", String::from(started));
+        write(&handle, false, 0.0, &header).await.map_err(|e| describe(&e))?;
+
+        Ok(Some(Transcript {
+            inner: Rc::new(Inner {
+                handle,
+                name,
+                offset: Cell::new(header.len() as f64),
+                pending: RefCell::new(Vec::new()),
+                writing: Cell::new(false),
+                failed: Cell::new(false),
+                on_error,
+            }),
+        }))
+    }
+
+    /// The file's name, as the user picked it.
+    pub fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    pub fn append(&self, room: &str, from: &str, text: &str, time: Option<f64>) {
+        let inner = &self.inner;
+        if inner.failed.get() {
+            return;
+        }
+        let time = time.unwrap_or_else(js_sys::Date::now);
+        let time = String::from(js_sys::Date::new(&time.into()).to_locale_time_string("default"));
+        inner.pending.borrow_mut().push(format!("[{}] #{} {}: {}\n", time, room, from, text));
+        if !inner.writing.get() {
+            inner.writing.set(true);
+            spawn_local(flush(inner.clone()));
+        }
+    }
+}
+
+/// Writes queued lines until there are none left; lines that arrive
+/// meanwhile go out in the next batch.
+async fn flush(inner: Rc<Inner>) {
+    loop {
+        let text = inner.pending.take().concat();
+        if text.is_empty() {
+            break;
+        }
+        if let Err(e) = write(&inner.handle, true, inner.offset.get(), &text).await {
+            log::error!("transcript: {:?}", e);
+            inner.failed.set(true);
+            inner.on_error.emit(describe(&e));
+            break;
+        }
+        inner.offset.set(inner.offset.get() + text.len() as f64);
+    }
+    inner.writing.set(false);
+}
@@ -0,0 +1,126 @@
+use reqwasm::http::Request;
+use serde::Deserialize;
+use serde_json::json;
+
+/// A message's translation, once asked for.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Translation {
+    /// Holds the language asked for.
+    Pending(String),
+    Done(String),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Translated {
+    translated_text: String,
+}
+
+/// Stands in for a glossary term on its way through the translator, which
+/// leaves it alone.
+fn placeholder(i: usize) -> String {
+    format!("⟦{}⟧", i)
+}
+
+/// `text` with each whole-word glossary term, ignoring case, swapped for a
+/// placeholder, and the terms as they were written, in placeholder order.
+fn protect(text: &str, glossary: &[String]) -> (String, Vec<String>) {
+    let mut terms: Vec<&str> = glossary.iter().map(|t| t.trim()).filter(|t| !t.is_empty()).collect();
+    // Longest first, so "Rust Belt" is kept whole before "Rust" is.
+    terms.sort_by_key(|t| std::cmp::Reverse(t.len()));
+    let is_word = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+    let mut protected = String::with_capacity(text.len());
+    let mut kept = Vec::new();
+    let mut i = 0;
+    while let Some(c) = text[i..].chars().next() {
+        let term = (!is_word(text[..i].chars().next_back()))
+            .then(|| {
+                terms.iter().find(|t| {
+                    text.get(i..i + t.len()).is_some_and(|s| s.eq_ignore_ascii_case(t))
+                        && !is_word(text[i + t.len()..].chars().next())
+                })
+            })
+            .flatten();
+        match term {
+            Some(term) => {
+                protected.push_str(&placeholder(kept.len()));
+                kept.push(text[i..i + term.len()].to_string());
+                i += term.len();
+            }
+            None => {
+                protected.push(c);
+                i += c.len_utf8();
+            }
+        }
+    }
+    (protected, kept)
+}
+
+fn restore(translated: &str, kept: &[String]) -> String {
+    kept.iter()
+        .enumerate()
+        .fold(translated.to_string(), |text, (i, term)| text.replace(&placeholder(i), term))
+}
+
+/// Asks `url`, a LibreTranslate-style endpoint, for `text` in `target`.
+/// Glossary terms are taken out first and put back afterwards, so they
+/// come through as written. The error is worded for a toast.
+pub async fn translate(url: &str, text: &str, source: Option<&str>, target: &str, glossary: &[String]) -> Result<String, String> {
+    let (protected, kept) = protect(text, glossary);
+    let body = json!({ "q": protected, "source": source.unwrap_or("auto"), "target": target, "format": "text" });
+    let response = Request::post(url)
+        .header("Content-Type", "application/json")
+        .body(body.to_string())
+        .send()
+        .await
+        .map_err(|e| {
+            log::warn!("translation request failed: {:?}", e);
+            "Couldn't reach the translation service.".to_string()
+        })?;
+    if !response.ok() {
+        return Err(format!("The translation service turned that down ({}).", response.status()));
+    }
+    let translated = response
+        .json::<Translated>()
+        .await
+        .map_err(|e| format!("The translation service's answer made no sense: {:?}", e))?;
+    Ok(restore(&translated.translated_text, &kept))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glossary(terms: &[&str]) -> Vec<String> {
+        terms.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn protects_whole_terms_as_written() {
+        let (protected, kept) = protect("I like rust and RUST, not rusty things", &glossary(&["Rust"]));
+        assert_eq!(protected, "I like ⟦0⟧ and ⟦1⟧, not rusty things");
+        assert_eq!(kept, ["rust", "RUST"]);
+    }
+
+    #[test]
+    fn protects_the_longest_term_first() {
+        let (protected, kept) = protect("Rust Belt and Rust", &glossary(&["Rust", "Rust Belt", " "]));
+        assert_eq!(protected, "⟦0⟧ and ⟦1⟧");
+        assert_eq!(kept, ["Rust Belt", "Rust"]);
+    }
+
+    #[test]
+    fn protects_beside_multibyte_text() {
+        let (protected, kept) = protect("café Yew über", &glossary(&["yew"]));
+        assert_eq!(protected, "café ⟦0⟧ über");
+        assert_eq!(kept, ["Yew"]);
+        assert_eq!(protect("éyew", &glossary(&["yew"])).1, Vec::<String>::new());
+    }
+
+    #[test]
+    fn restores_wherever_the_translator_moved_them() {
+        let kept = glossary(&["Rust Belt", "Yew"]);
+        assert_eq!(restore("⟦1⟧ est dans ⟦0⟧", &kept), "Yew est dans Rust Belt");
+        assert_eq!(restore("nothing kept", &[]), "nothing kept");
+    }
+}
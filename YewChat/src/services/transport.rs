@@ -0,0 +1,57 @@
+use futures::future::LocalBoxFuture;
+use futures::{Sink, Stream};
+use serde::Deserialize;
+use std::fmt;
+use std::pin::Pin;
+
+use crate::services::longpoll::LongPollTransport;
+use crate::services::websocket::WebSocketTransport;
+
+/// Outgoing JSON frames. Closing it ends the connection.
+pub type FrameSink = Pin<Box<dyn Sink<String, Error = TransportError>>>;
+/// Incoming JSON frames. Ends when the connection is lost.
+pub type FrameStream = Pin<Box<dyn Stream<Item = String>>>;
+
+#[derive(Debug)]
+pub struct TransportError(pub String);
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A way of exchanging frames with the server. Everything above this deals
+/// in JSON strings; how they travel is the transport's business.
+pub trait Transport {
+    fn kind(&self) -> TransportKind;
+
+    /// Connects to the server at `url` (a `ws://` or `wss://` URL; other
+    /// transports derive their own from it). Resolves once frames can be sent.
+    fn connect(&self, url: &str) -> LocalBoxFuture<'static, Result<(FrameSink, FrameStream), TransportError>>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportKind {
+    WebSocket,
+    /// Plain HTTP requests, for networks whose proxies block WebSockets.
+    LongPoll,
+}
+
+impl TransportKind {
+    pub fn transport(self) -> &'static dyn Transport {
+        match self {
+            TransportKind::WebSocket => &WebSocketTransport,
+            TransportKind::LongPoll => &LongPollTransport,
+        }
+    }
+
+    /// What to try next while looking for a transport that gets through.
+    pub fn other(self) -> Self {
+        match self {
+            TransportKind::WebSocket => TransportKind::LongPoll,
+            TransportKind::LongPoll => TransportKind::WebSocket,
+        }
+    }
+}
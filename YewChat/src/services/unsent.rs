@@ -0,0 +1,49 @@
+use gloo_storage::{SessionStorage, Storage};
+use serde::{Deserialize, Serialize};
+
+const STORAGE_KEY: &str = "yewchat.unsent";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UnsentMessage {
+    pub room: String,
+    pub text: String,
+    /// When it was first sent, in ms since the epoch.
+    pub time: f64,
+    /// Sent again under the same ID, so the server can tell if it already
+    /// has it.
+    #[serde(default)]
+    pub client_id: Option<String>,
+}
+
+/// What would be lost if the tab went away right now: the half-written
+/// message and anything the server hasn't confirmed. Kept in session
+/// storage, so it survives a reload or a reopened tab but not the browser.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Unsent {
+    pub user: String,
+    pub room: String,
+    pub draft: String,
+    pub pending: Vec<UnsentMessage>,
+}
+
+impl Unsent {
+    /// Whatever `user` left behind last time, if anything.
+    pub fn load(user: &str) -> Option<Self> {
+        SessionStorage::get::<Self>(STORAGE_KEY)
+            .ok()
+            .filter(|unsent| unsent.user == user && !unsent.is_empty())
+    }
+
+    pub fn save(&self) {
+        if self.is_empty() {
+            SessionStorage::delete(STORAGE_KEY);
+        } else if let Err(e) = SessionStorage::set(STORAGE_KEY, self) {
+            log::error!("failed to save unsent messages: {:?}", e);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.draft.trim().is_empty() && self.pending.is_empty()
+    }
+}
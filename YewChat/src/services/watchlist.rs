@@ -0,0 +1,37 @@
+use std::collections::HashSet;
+
+/// Whether `text` contains any of `keywords`, ignoring case.
+fn matches(keywords: &[String], text: &str) -> bool {
+    let text = text.to_lowercase();
+    keywords.iter().any(|k| !k.is_empty() && text.contains(&k.to_lowercase()))
+}
+
+/// Watches messages as they're stored for words the user asked to look out
+/// for, and remembers which ones matched so they can be highlighted.
+#[derive(Default)]
+pub struct Watchlist {
+    hits: HashSet<u64>,
+}
+
+impl Watchlist {
+    /// Looks over a stored message. Returns whether it's one to highlight.
+    pub fn observe(&mut self, keywords: &[String], id: u64, text: &str) -> bool {
+        let hit = matches(keywords, text);
+        if hit {
+            self.hits.insert(id);
+        }
+        hit
+    }
+
+    pub fn is_hit(&self, id: u64) -> bool {
+        self.hits.contains(&id)
+    }
+
+    /// Starts again with new `keywords`, over everything already stored.
+    pub fn rescan<'a>(&mut self, keywords: &[String], messages: impl Iterator<Item = (u64, &'a str)>) {
+        self.hits.clear();
+        for (id, text) in messages {
+            self.observe(keywords, id, text);
+        }
+    }
+}
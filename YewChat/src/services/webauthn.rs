@@ -0,0 +1,198 @@
+use gloo_storage::{SessionStorage, Storage};
+use js_sys::{Array, ArrayBuffer, Function, Reflect, Uint8Array};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    AuthenticatorAssertionResponse, AuthenticatorAttachment, AuthenticatorAttestationResponse,
+    AuthenticatorSelectionCriteria, CredentialCreationOptions, CredentialRequestOptions, PublicKeyCredential,
+    PublicKeyCredentialCreationOptions, PublicKeyCredentialDescriptor, PublicKeyCredentialParameters,
+    PublicKeyCredentialRequestOptions, PublicKeyCredentialRpEntity, PublicKeyCredentialType,
+    PublicKeyCredentialUserEntity, UserVerificationRequirement,
+};
+
+const SESSION_KEY: &str = "yewchat.device-session";
+// How long the browser may keep its own prompt open.
+const PROMPT_TIMEOUT_MS: u32 = 60_000;
+
+/// The server's challenge when our name is protected by a registered device.
+/// Binary values travel as byte arrays.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct VerifyRequest {
+    pub challenge: Vec<u8>,
+    pub credentials: Vec<Vec<u8>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Assertion {
+    pub id: Vec<u8>,
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: Vec<u8>,
+    pub authenticator_data: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+/// The server's go-ahead for registering a new device.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnrollRequest {
+    pub challenge: Vec<u8>,
+    pub user_id: Vec<u8>,
+    pub algorithms: Vec<i32>,
+    /// Devices already registered, so the browser doesn't offer them again.
+    #[serde(default)]
+    pub exclude: Vec<Vec<u8>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewDevice {
+    pub id: Vec<u8>,
+    /// SPKI DER, as `getPublicKey()` hands it out.
+    pub public_key: Vec<u8>,
+    pub algorithm: i32,
+    pub name: String,
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: Vec<u8>,
+}
+
+/// A registered device, as the server lists it.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Device {
+    pub id: String,
+    pub name: String,
+    /// Milliseconds since the epoch.
+    pub added: f64,
+}
+
+/// Whether this browser has WebAuthn at all.
+pub fn supported() -> bool {
+    web_sys::window()
+        .map(|window| Reflect::has(&window, &JsValue::from_str("PublicKeyCredential")).unwrap_or(false))
+        .unwrap_or(false)
+}
+
+/// Whether there's a built-in authenticator (Touch ID, Windows Hello, a
+/// phone's screen lock) to register.
+pub async fn platform_authenticator_available() -> bool {
+    if !supported() {
+        return false;
+    }
+    JsFuture::from(PublicKeyCredential::is_user_verifying_platform_authenticator_available())
+        .await
+        .map(|available| available.is_truthy())
+        .unwrap_or(false)
+}
+
+fn bytes(buffer: &ArrayBuffer) -> Vec<u8> {
+    Uint8Array::new(buffer).to_vec()
+}
+
+fn buffer(bytes: &[u8]) -> Uint8Array {
+    Uint8Array::from(bytes)
+}
+
+fn descriptors(ids: &[Vec<u8>]) -> Array {
+    ids.iter()
+        .map(|id| JsValue::from(PublicKeyCredentialDescriptor::new(&buffer(id), PublicKeyCredentialType::PublicKey)))
+        .collect()
+}
+
+/// Turns a rejected browser call into something to show the user.
+fn describe(error: JsValue) -> String {
+    let name = Reflect::get(&error, &JsValue::from_str("name")).ok().and_then(|n| n.as_string());
+    match name.as_deref() {
+        Some("NotAllowedError") => "The request was cancelled or timed out.".to_string(),
+        Some("InvalidStateError") => "This device is already registered.".to_string(),
+        Some("SecurityError") => "Device verification isn't allowed on this page.".to_string(),
+        _ => error
+            .dyn_ref::<js_sys::Error>()
+            .map(|e| String::from(e.message()))
+            .unwrap_or_else(|| "The device couldn't be used.".to_string()),
+    }
+}
+
+/// Optional `AuthenticatorAttestationResponse` methods that this version of
+/// web-sys doesn't bind.
+fn call_optional(target: &JsValue, method: &str) -> Option<JsValue> {
+    let function = Reflect::get(target, &JsValue::from_str(method)).ok()?.dyn_into::<Function>().ok()?;
+    function.call0(target).ok().filter(|v| !v.is_null() && !v.is_undefined())
+}
+
+/// Asks the browser to sign the server's challenge with one of our devices.
+pub async fn verify(request: &VerifyRequest) -> Result<Assertion, String> {
+    let window = web_sys::window().ok_or("No window")?;
+    let mut public_key = PublicKeyCredentialRequestOptions::new(&buffer(&request.challenge));
+    public_key
+        .allow_credentials(&descriptors(&request.credentials))
+        .user_verification(UserVerificationRequirement::Preferred)
+        .timeout(PROMPT_TIMEOUT_MS);
+    let mut options = CredentialRequestOptions::new();
+    options.public_key(&public_key);
+
+    let promise = window.navigator().credentials().get_with_options(&options).map_err(describe)?;
+    let credential: PublicKeyCredential = JsFuture::from(promise).await.map_err(describe)?.unchecked_into();
+    let response: AuthenticatorAssertionResponse = credential.response().unchecked_into();
+    Ok(Assertion {
+        id: bytes(&credential.raw_id()),
+        client_data_json: bytes(&response.client_data_json()),
+        authenticator_data: bytes(&response.authenticator_data()),
+        signature: bytes(&response.signature()),
+    })
+}
+
+/// Registers this device's built-in authenticator for `username`.
+pub async fn enroll(request: &EnrollRequest, username: &str, name: String) -> Result<NewDevice, String> {
+    let window = web_sys::window().ok_or("No window")?;
+    let rp = PublicKeyCredentialRpEntity::new("YewChat");
+    let user = PublicKeyCredentialUserEntity::new(username, username, &buffer(&request.user_id));
+    let algorithms: Array = request
+        .algorithms
+        .iter()
+        .map(|alg| JsValue::from(PublicKeyCredentialParameters::new(*alg, PublicKeyCredentialType::PublicKey)))
+        .collect();
+    let mut selection = AuthenticatorSelectionCriteria::new();
+    selection
+        .authenticator_attachment(AuthenticatorAttachment::Platform)
+        .user_verification(UserVerificationRequirement::Preferred);
+    let mut public_key = PublicKeyCredentialCreationOptions::new(&buffer(&request.challenge), &algorithms, &rp, &user);
+    public_key
+        .authenticator_selection(&selection)
+        .exclude_credentials(&descriptors(&request.exclude))
+        .timeout(PROMPT_TIMEOUT_MS);
+    let mut options = CredentialCreationOptions::new();
+    options.public_key(&public_key);
+
+    let promise = window.navigator().credentials().create_with_options(&options).map_err(describe)?;
+    let credential: PublicKeyCredential = JsFuture::from(promise).await.map_err(describe)?.unchecked_into();
+    let response: AuthenticatorAttestationResponse = credential.response().unchecked_into();
+    let public_key = call_optional(&response, "getPublicKey")
+        .and_then(|key| key.dyn_into::<ArrayBuffer>().ok())
+        .ok_or("This browser can't share the device's public key. Try a newer browser.")?;
+    let algorithm = call_optional(&response, "getPublicKeyAlgorithm")
+        .and_then(|alg| alg.as_f64())
+        .ok_or("This browser can't share the device's key type. Try a newer browser.")?;
+    Ok(NewDevice {
+        id: bytes(&credential.raw_id()),
+        public_key: bytes(&public_key),
+        algorithm: algorithm as i32,
+        name,
+        client_data_json: bytes(&response.client_data_json()),
+    })
+}
+
+/// The token the server hands out after a successful verification, so that
+/// reconnecting doesn't ask for the device again.
+pub fn session_token(username: &str) -> Option<String> {
+    SessionStorage::get::<(String, String)>(SESSION_KEY)
+        .ok()
+        .filter(|(user, _)| user == username)
+        .map(|(_, token)| token)
+}
+
+pub fn save_session_token(username: &str, token: &str) {
+    if let Err(e) = SessionStorage::set(SESSION_KEY, (username, token)) {
+        log::error!("failed to save device session: {:?}", e);
+    }
+}
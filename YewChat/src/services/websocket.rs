@@ -1,52 +1,585 @@
-use futures::{channel::mpsc::Sender, SinkExt, StreamExt};
-use reqwasm::websocket::{futures::WebSocket, Message};
-use yew_agent::Dispatched;
+use futures::{
+    channel::mpsc::{Receiver, Sender},
+    channel::oneshot,
+    future::{self, poll_fn, LocalBoxFuture},
+    select,
+    FutureExt, SinkExt, StreamExt,
+};
+use gloo_timers::future::{IntervalStream, TimeoutFuture};
+use reqwasm::websocket::{futures::WebSocket, Message, State};
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use yew_agent::{Dispatched, Dispatcher};
+use crate::services::config::Config;
 use crate::services::event_bus::{EventBus, Request};
+use crate::services::outbox::Outbox;
+use crate::services::traffic::{LoggedFrame, Traffic, TrafficStats};
+use crate::services::transport::{FrameSink, FrameStream, Transport, TransportError, TransportKind};
 
 use wasm_bindgen_futures::spawn_local;
 
+const PING_FRAME: &str = r#"{"messageType":"ping"}"#;
+const HEARTBEAT_INTERVAL_MS: u32 = 10_000;
+// A connection that hasn't produced a single frame (pongs included) for this
+// long is considered dead, even if the browser still reports it as open.
+const HEARTBEAT_TIMEOUT_MS: f64 = 25_000.0;
+
+const RECONNECT_BASE_MS: u32 = 1_000;
+const RECONNECT_MAX_MS: u32 = 30_000;
+const OFFLINE_AFTER_FAILURES: u32 = 5;
+// With several servers configured, move on to the next after this many
+// failed attempts in a row on the current one.
+const FAILOVER_AFTER_FAILURES: u32 = 3;
+
+// Until one transport has connected, switch to the other after this many
+// failed attempts, in case something between us and the server blocks it.
+const TRANSPORT_PROBE_FAILURES: u32 = 2;
+
+// Frames larger than this are dropped unread. A full history page is well
+// under it.
+const MAX_FRAME_BYTES: usize = 1024 * 1024;
+
+// Frames queued while disconnected; beyond this, some are dropped.
+const OUTBOX_LIMIT: usize = 500;
+
+// Asks the server to switch to MessagePack. Servers that don't know the
+// frame ignore it, and we keep talking JSON.
+#[cfg(feature = "msgpack")]
+const CODEC_REQUEST: &str = r#"{"messageType":"codec","data":"msgpack"}"#;
+
+/// How frames go on the wire. The codec only changes the bytes actually
+/// sent; the frames handed to and from the transport stay JSON.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Codec {
+    Json,
+    #[cfg(feature = "msgpack")]
+    MsgPack,
+}
+
+impl Codec {
+    fn encode(self, frame: String) -> Message {
+        match self {
+            Codec::Json => Message::Text(frame),
+            #[cfg(feature = "msgpack")]
+            Codec::MsgPack => {
+                let packed = serde_json::from_str::<serde_json::Value>(&frame)
+                    .ok()
+                    .and_then(|value| rmp_serde::to_vec_named(&value).ok());
+                match packed {
+                    Some(bytes) => Message::Bytes(bytes),
+                    None => Message::Text(frame),
+                }
+            }
+        }
+    }
+}
+
+/// Turns a binary frame back into JSON. MessagePack is tried first when
+/// built in; anything else is taken as UTF-8 JSON.
+fn decode_bytes(bytes: &[u8]) -> Option<String> {
+    #[cfg(feature = "msgpack")]
+    if let Ok(value @ serde_json::Value::Object(_)) = rmp_serde::from_slice::<serde_json::Value>(bytes) {
+        return Some(value.to_string());
+    }
+    std::str::from_utf8(bytes).ok().map(String::from)
+}
+
+/// A plain WebSocket. Negotiates MessagePack when built with it.
+pub struct WebSocketTransport;
+
+impl Transport for WebSocketTransport {
+    fn kind(&self) -> TransportKind {
+        TransportKind::WebSocket
+    }
+
+    fn connect(&self, url: &str) -> LocalBoxFuture<'static, Result<(FrameSink, FrameStream), TransportError>> {
+        let url = url.to_string();
+        async move {
+            let mut ws = WebSocket::open(&url).map_err(|e| TransportError(format!("{:?}", e)))?;
+
+            // The sink only becomes ready once the socket has left the connecting state.
+            let _ = poll_fn(|cx| ws.poll_ready_unpin(cx)).await;
+            if !matches!(ws.state(), State::Open) {
+                return Err(TransportError("connection refused".into()));
+            }
+
+            #[cfg(feature = "msgpack")]
+            ws.send(Message::Text(CODEC_REQUEST.into()))
+                .await
+                .map_err(|e| TransportError(format!("{:?}", e)))?;
+
+            let (write, read) = ws.split();
+            let codec = Rc::new(Cell::new(Codec::Json));
+
+            let sink = {
+                let codec = codec.clone();
+                write
+                    .sink_map_err(|e| TransportError(format!("{:?}", e)))
+                    .with(move |frame: String| future::ready(Ok(codec.get().encode(frame))))
+            };
+            let stream = read.filter_map(move |msg| {
+                future::ready(match msg {
+                    Ok(Message::Text(data)) => match codec_ack(&data) {
+                        Some(agreed) => {
+                            log::debug!("ws: switching to {:?}", agreed);
+                            codec.set(agreed);
+                            None
+                        }
+                        None => Some(data),
+                    },
+                    Ok(Message::Bytes(b)) => decode_bytes(&b),
+                    Err(e) => {
+                        log::error!("ws: {:?}", e);
+                        None
+                    }
+                })
+            });
+            Ok((Box::pin(sink) as FrameSink, Box::pin(stream) as FrameStream))
+        }
+        .boxed_local()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ConnectionState {
+    Connected,
+    Reconnecting,
+    Offline,
+}
+
 pub struct WebsocketService {
     pub tx: Sender<String>,
+    shared: Rc<Shared>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+/// What the service and its connection task both see.
+#[derive(Default)]
+struct Shared {
+    handshake: RefCell<Option<String>>,
+    hold_until: Cell<f64>,
+    traffic: RefCell<Traffic>,
+}
+
+enum SessionEnd {
+    /// The connection never opened.
+    Failed,
+    /// The connection was open and then went away.
+    Dropped,
+    /// The service was closed or dropped; stop reconnecting.
+    Shutdown,
+}
+
+/// Where reconnecting stands between sessions: how many attempts in a row
+/// have failed, and on which server and transport the next one goes.
+#[derive(Debug)]
+struct Reconnect {
+    endpoints: usize,
+    active: usize,
+    transport: TransportKind,
+    // Without a configured transport, look for one that gets through and
+    // stay with it once it has.
+    probing: bool,
+    failures: u32,
+    endpoint_failures: u32,
+}
+
+/// What to do before the next attempt.
+#[derive(Debug, PartialEq)]
+struct Retry {
+    state: ConnectionState,
+    delay_ms: u32,
+    /// The endpoint failed over to, if it changed.
+    failed_over: Option<usize>,
+}
+
+impl Reconnect {
+    fn new(endpoints: usize, transport: Option<TransportKind>) -> Self {
+        Self {
+            endpoints,
+            active: 0,
+            transport: transport.unwrap_or(TransportKind::WebSocket),
+            probing: transport.is_none(),
+            failures: 0,
+            endpoint_failures: 0,
+        }
+    }
+
+    /// Takes in how the last session ended, `opened` if it got as far as
+    /// connecting, and says how to go on.
+    fn next(&mut self, opened: bool) -> Retry {
+        if opened {
+            self.failures = 0;
+            self.endpoint_failures = 0;
+            self.probing = false;
+        } else {
+            self.failures += 1;
+            self.endpoint_failures += 1;
+        }
+
+        if self.probing && self.failures.is_multiple_of(TRANSPORT_PROBE_FAILURES) {
+            self.transport = self.transport.other();
+            log::warn!("ws: trying {:?} instead", self.transport);
+        }
+
+        let mut failed_over = None;
+        if self.endpoints > 1 && self.endpoint_failures >= FAILOVER_AFTER_FAILURES {
+            self.active = (self.active + 1) % self.endpoints;
+            self.endpoint_failures = 0;
+            failed_over = Some(self.active);
+        }
+
+        let state = if self.failures >= OFFLINE_AFTER_FAILURES {
+            ConnectionState::Offline
+        } else {
+            ConnectionState::Reconnecting
+        };
+        let delay_ms = RECONNECT_BASE_MS.saturating_mul(1 << self.failures.min(5)).min(RECONNECT_MAX_MS);
+        Retry { state, delay_ms, failed_over }
+    }
+}
+
+/// Whether an open connection is still alive, and the ping whose pong
+/// gives the latency. Times are ms since the epoch.
+#[derive(Debug)]
+struct Heartbeat {
+    last_seen: f64,
+    ping_sent: Option<f64>,
+}
+
+impl Heartbeat {
+    fn new(now: f64) -> Self {
+        Self { last_seen: now, ping_sent: None }
+    }
+
+    fn pinged(&mut self, now: f64) {
+        self.ping_sent = Some(now);
+    }
+
+    /// Notes a frame coming in. For the pong to our last ping, the round
+    /// trip; a second pong to the same ping doesn't count again.
+    fn received(&mut self, now: f64, pong: bool) -> Option<f64> {
+        self.last_seen = now;
+        pong.then(|| self.ping_sent.take()).flatten().map(|sent| now - sent)
+    }
+
+    /// Whether it's been quiet too long to trust the connection, even if
+    /// the browser still reports it as open.
+    fn timed_out(&self, now: f64) -> bool {
+        now - self.last_seen > HEARTBEAT_TIMEOUT_MS
+    }
 }
 
 impl WebsocketService {
     pub fn new() -> Self {
-        let ws = WebSocket::open("ws://127.0.0.1:8080").unwrap();
+        let (in_tx, in_rx) = futures::channel::mpsc::channel::<String>(1000);
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let shared = Rc::new(Shared { traffic: RefCell::new(Traffic::new()), ..Shared::default() });
 
-        let (mut write, mut read) = ws.split();
+        spawn_local(run(in_rx, shared.clone(), shutdown_rx));
 
-        let (in_tx, mut in_rx) = futures::channel::mpsc::channel::<String>(1000);
-        let mut event_bus = EventBus::dispatcher();
+        Self { tx: in_tx, shared, shutdown: Some(shutdown_tx) }
+    }
+
+    /// What has gone over the connection so far.
+    pub fn traffic(&self) -> TrafficStats {
+        self.shared.traffic.borrow().stats()
+    }
 
-        spawn_local(async move {
-            while let Some(s) = in_rx.next().await {
-                log::debug!("got event from channel! {}", s);
-                write.send(Message::Text(s)).await.unwrap();
+    /// The latest frames sent and received, oldest first.
+    pub fn frame_log(&self) -> Vec<LoggedFrame> {
+        self.shared.traffic.borrow().frames()
+    }
+
+    /// Sets the frame sent first on every (re)connect, ahead of anything
+    /// waiting in the outbox.
+    pub fn set_handshake(&self, frame: String) {
+        *self.shared.handshake.borrow_mut() = Some(frame);
+    }
+
+    /// Holds off reconnect attempts until `until` (ms since the epoch), e.g.
+    /// while the server is down for maintenance. Pass 0 to lift the hold.
+    pub fn hold_reconnects_until(&self, until: f64) {
+        self.shared.hold_until.set(until);
+    }
+
+    /// Closes the socket for good. Anything still queued is discarded and
+    /// no reconnect is attempted.
+    pub fn close(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+async fn run(
+    mut in_rx: Receiver<String>,
+    shared: Rc<Shared>,
+    shutdown: oneshot::Receiver<()>,
+) {
+    let mut shutdown = shutdown.fuse();
+    let config = Config::load().await;
+    let endpoints = config.server_urls();
+    let mut reconnect = Reconnect::new(endpoints.len(), config.transport());
+    let mut event_bus = EventBus::dispatcher();
+    let mut outbox = Outbox::new(OUTBOX_LIMIT);
+
+    if endpoints.len() > 1 {
+        event_bus.send(Request::Endpoint(endpoints[reconnect.active].clone()));
+    }
+
+    'reconnect: loop {
+        let end = session(
+            reconnect.transport.transport(),
+            &endpoints[reconnect.active],
+            &mut in_rx,
+            &mut outbox,
+            &shared,
+            &mut shutdown,
+            &mut event_bus,
+        )
+        .await;
+        let retry = match end {
+            SessionEnd::Shutdown => break,
+            SessionEnd::Dropped => reconnect.next(true),
+            SessionEnd::Failed => reconnect.next(false),
+        };
+        if let Some(active) = retry.failed_over {
+            log::warn!("ws: failing over to {}", endpoints[active]);
+            event_bus.send(Request::Endpoint(endpoints[active].clone()));
+        }
+        event_bus.send(Request::Connection(retry.state));
+
+        // Keep accepting frames while we wait so nothing is dropped.
+        let mut delay = TimeoutFuture::new(retry.delay_ms).fuse();
+        loop {
+            select! {
+                _ = delay => {
+                    let held = shared.hold_until.get() - js_sys::Date::now();
+                    if held <= 0.0 {
+                        break;
+                    }
+                    delay = TimeoutFuture::new(held.min(RECONNECT_MAX_MS as f64) as u32).fuse();
+                }
+                _ = shutdown => break 'reconnect,
+                frame = in_rx.next() => match frame {
+                    Some(s) => outbox.push(s, js_sys::Date::now()),
+                    None => break 'reconnect,
+                },
             }
-        });
-
-        spawn_local(async move {
-            while let Some(msg) = read.next().await {
-                match msg {
-                    Ok(Message::Text(data)) => {
-                        log::debug!("from websocket: {}", data);
-                        event_bus.send(Request::EventBusMsg(data));
+        }
+    }
+    log::debug!("WebSocket Closed");
+}
+
+async fn session(
+    transport: &dyn Transport,
+    server_url: &str,
+    in_rx: &mut Receiver<String>,
+    outbox: &mut Outbox,
+    shared: &Shared,
+    mut shutdown: &mut futures::future::Fuse<oneshot::Receiver<()>>,
+    event_bus: &mut Dispatcher<EventBus>,
+) -> SessionEnd {
+    let (mut write, read) = match transport.connect(server_url).await {
+        Ok(connection) => connection,
+        Err(e) => {
+            log::error!("{:?}: {}", transport.kind(), e);
+            return SessionEnd::Failed;
+        }
+    };
+    event_bus.send(Request::Connection(ConnectionState::Connected));
+    let mut read = read.fuse();
+
+    let traffic = &shared.traffic;
+    let handshake = shared.handshake.borrow().clone();
+    if let Some(frame) = handshake {
+        if !send(&mut write, traffic, frame).await {
+            return SessionEnd::Dropped;
+        }
+    }
+    if !flush(&mut write, outbox, traffic).await {
+        return SessionEnd::Dropped;
+    }
+
+    // Ping straight away so there's a latency reading before the first beat.
+    let mut alive = Heartbeat::new(js_sys::Date::now());
+    alive.pinged(js_sys::Date::now());
+    if !send(&mut write, traffic, PING_FRAME.into()).await {
+        return SessionEnd::Dropped;
+    }
+
+    let mut heartbeat = IntervalStream::new(HEARTBEAT_INTERVAL_MS).fuse();
+
+    loop {
+        select! {
+            frame = in_rx.next() => match frame {
+                Some(s) => {
+                    outbox.push(s, js_sys::Date::now());
+                    if !flush(&mut write, outbox, traffic).await {
+                        return SessionEnd::Dropped;
                     }
-                    Ok(Message::Bytes(b)) => {
-                        let decoded = std::str::from_utf8(&b);
-                        if let Ok(val) = decoded {
-                            log::debug!("from websocket: {}", val);
-                            event_bus.send(Request::EventBusMsg(val.into()));
-                        }
+                }
+                None => return SessionEnd::Shutdown,
+            },
+            _ = shutdown => {
+                let _ = write.close().await;
+                return SessionEnd::Shutdown;
+            },
+            frame = read.next() => match frame {
+                Some(data) => {
+                    traffic.borrow_mut().record(false, &data);
+                    let pong = has_type(&data, "pong");
+                    if let Some(latency) = alive.received(js_sys::Date::now(), pong) {
+                        event_bus.send(Request::Latency(latency));
                     }
-                    Err(e) => {
-                        log::error!("ws: {:?}", e);
+                    if !pong {
+                        forward(event_bus, data);
                     }
                 }
-            }
-            log::debug!("WebSocket Closed");
-        });
+                None => return SessionEnd::Dropped,
+            },
+            _ = heartbeat.next() => {
+                if alive.timed_out(js_sys::Date::now()) {
+                    log::warn!("ws: heartbeat timed out");
+                    return SessionEnd::Dropped;
+                }
+                alive.pinged(js_sys::Date::now());
+                if !send(&mut write, traffic, PING_FRAME.into()).await {
+                    return SessionEnd::Dropped;
+                }
+            },
+        }
+    }
+}
+
+/// Sends queued frames, most urgent first. A frame that fails to send goes
+/// back to the front of its queue for the next connection.
+async fn flush(write: &mut FrameSink, outbox: &mut Outbox, traffic: &RefCell<Traffic>) -> bool {
+    while let Some(frame) = outbox.pop(js_sys::Date::now()) {
+        if !send(write, traffic, frame.clone()).await {
+            outbox.push_front(frame, js_sys::Date::now());
+            return false;
+        }
+    }
+    true
+}
+
+/// Sends one frame, counting it once it's gone. `false` if the connection
+/// failed, so a frame that's retried later is only counted then.
+async fn send(write: &mut FrameSink, traffic: &RefCell<Traffic>, frame: String) -> bool {
+    match write.send(frame.clone()).await {
+        Ok(()) => {
+            traffic.borrow_mut().record(true, &frame);
+            true
+        }
+        Err(e) => {
+            log::error!("ws: {}", e);
+            false
+        }
+    }
+}
+
+fn forward(event_bus: &mut Dispatcher<EventBus>, data: String) {
+    if data.len() > MAX_FRAME_BYTES {
+        log::warn!("dropping oversized frame ({} bytes)", data.len());
+        return;
+    }
+    event_bus.send(Request::EventBusMsg(data));
+}
+
+fn has_type(data: &str, message_type: &str) -> bool {
+    data.contains(message_type)
+        && serde_json::from_str::<serde_json::Value>(data)
+            .map(|v| v["messageType"] == message_type)
+            .unwrap_or(false)
+}
 
-        Self { tx: in_tx }
+/// The codec the server agreed to, if `data` is its answer to our request.
+fn codec_ack(data: &str) -> Option<Codec> {
+    if !has_type(data, "codec") {
+        return None;
     }
-}
\ No newline at end of file
+    let agreed = serde_json::from_str::<serde_json::Value>(data).ok()?;
+    match agreed["data"].as_str() {
+        #[cfg(feature = "msgpack")]
+        Some("msgpack") => Some(Codec::MsgPack),
+        _ => Some(Codec::Json),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backs_off_doubling_up_to_the_cap_and_goes_offline() {
+        let mut reconnect = Reconnect::new(1, Some(TransportKind::WebSocket));
+        let delays: Vec<u32> = (0..7).map(|_| reconnect.next(false).delay_ms).collect();
+        assert_eq!(delays, [2_000, 4_000, 8_000, 16_000, 30_000, 30_000, 30_000]);
+        assert_eq!(reconnect.failures, 7);
+        assert_eq!(reconnect.next(false).state, ConnectionState::Offline);
+
+        // A connection that opened starts the count again.
+        let retry = reconnect.next(true);
+        assert_eq!((retry.state, retry.delay_ms), (ConnectionState::Reconnecting, RECONNECT_BASE_MS));
+    }
+
+    #[test]
+    fn reports_offline_after_enough_failures() {
+        let mut reconnect = Reconnect::new(1, Some(TransportKind::WebSocket));
+        let states: Vec<ConnectionState> = (0..OFFLINE_AFTER_FAILURES).map(|_| reconnect.next(false).state).collect();
+        assert!(states[..states.len() - 1].iter().all(|s| *s == ConnectionState::Reconnecting));
+        assert_eq!(states.last(), Some(&ConnectionState::Offline));
+    }
+
+    #[test]
+    fn fails_over_round_the_servers() {
+        let mut reconnect = Reconnect::new(2, Some(TransportKind::WebSocket));
+        let moves: Vec<Option<usize>> = (0..6).map(|_| reconnect.next(false).failed_over).collect();
+        assert_eq!(moves, [None, None, Some(1), None, None, Some(0)]);
+
+        let mut alone = Reconnect::new(1, Some(TransportKind::WebSocket));
+        assert!((0..6).all(|_| alone.next(false).failed_over.is_none()));
+    }
+
+    #[test]
+    fn probes_transports_until_one_gets_through() {
+        let mut reconnect = Reconnect::new(1, None);
+        reconnect.next(false);
+        assert_eq!(reconnect.transport, TransportKind::WebSocket);
+        reconnect.next(false);
+        assert_eq!(reconnect.transport, TransportKind::LongPoll);
+        reconnect.next(true);
+        for _ in 0..4 {
+            reconnect.next(false);
+        }
+        assert_eq!(reconnect.transport, TransportKind::LongPoll);
+
+        let mut configured = Reconnect::new(1, Some(TransportKind::WebSocket));
+        for _ in 0..4 {
+            configured.next(false);
+        }
+        assert_eq!(configured.transport, TransportKind::WebSocket);
+    }
+
+    #[test]
+    fn measures_latency_once_per_ping() {
+        let mut heartbeat = Heartbeat::new(1_000.0);
+        assert_eq!(heartbeat.received(1_100.0, true), None);
+        heartbeat.pinged(2_000.0);
+        assert_eq!(heartbeat.received(2_050.0, false), None);
+        assert_eq!(heartbeat.received(2_120.0, true), Some(120.0));
+        assert_eq!(heartbeat.received(2_200.0, true), None);
+    }
+
+    #[test]
+    fn times_out_after_a_quiet_spell() {
+        let mut heartbeat = Heartbeat::new(0.0);
+        assert!(!heartbeat.timed_out(HEARTBEAT_TIMEOUT_MS));
+        assert!(heartbeat.timed_out(HEARTBEAT_TIMEOUT_MS + 1.0));
+        heartbeat.received(20_000.0, false);
+        assert!(!heartbeat.timed_out(HEARTBEAT_TIMEOUT_MS + 1.0));
+    }
+}
@@ -0,0 +1,140 @@
+use std::borrow::Cow;
+
+/// `text` cut into runs of letters and digits and the runs between them,
+/// each flagged with whether it's a word. Words and gaps alternate.
+fn pieces(text: &str) -> Vec<(bool, &str)> {
+    let mut pieces = vec![];
+    let mut start = 0;
+    let mut in_word = None;
+    for (i, c) in text.char_indices() {
+        let word = c.is_alphanumeric();
+        if in_word.is_some_and(|w| w != word) {
+            pieces.push((!word, &text[start..i]));
+            start = i;
+        }
+        in_word = Some(word);
+    }
+    if let Some(word) = in_word {
+        pieces.push((word, &text[start..]));
+    }
+    pieces
+}
+
+/// Each of `words` as the lowercased words it's made of, so a phrase like
+/// "bad word" or "bad-word" is kept as both. Longest first, so a phrase is
+/// matched whole before any word in it is.
+fn entries(words: &[String]) -> Vec<(&str, Vec<String>)> {
+    let mut entries: Vec<(&str, Vec<String>)> = words
+        .iter()
+        .map(|entry| {
+            let words = pieces(entry).into_iter().filter(|(word, _)| *word).map(|(_, w)| w.to_lowercase()).collect();
+            (entry.as_str(), words)
+        })
+        .filter(|(_, words): &(&str, Vec<String>)| !words.is_empty())
+        .collect();
+    entries.sort_by_key(|(_, words)| std::cmp::Reverse(words.len()));
+    entries
+}
+
+/// The entry whose words, ignoring case, start at `pieces[i]`, and how many
+/// pieces they span with the gaps between them.
+fn listed_at<'a>(entries: &[(&'a str, Vec<String>)], pieces: &[(bool, &str)], i: usize) -> Option<(&'a str, usize)> {
+    entries.iter().find_map(|(entry, words)| {
+        let matched = words
+            .iter()
+            .enumerate()
+            .all(|(k, w)| pieces.get(i + 2 * k).is_some_and(|(word, piece)| *word && piece.to_lowercase() == *w));
+        matched.then(|| (*entry, 2 * words.len() - 1))
+    })
+}
+
+/// `text` with every whole word or phrase in `words` starred out, letter
+/// for letter. Borrowed when nothing needed masking.
+pub fn mask<'a>(words: &[String], text: &'a str) -> Cow<'a, str> {
+    let entries = entries(words);
+    if entries.is_empty() {
+        return Cow::Borrowed(text);
+    }
+    let pieces = pieces(text);
+    let mut masked = String::with_capacity(text.len());
+    let mut changed = false;
+    let mut i = 0;
+    while i < pieces.len() {
+        let (word, piece) = pieces[i];
+        match word.then(|| listed_at(&entries, &pieces, i)).flatten() {
+            Some((_, span)) => {
+                for &(word, piece) in &pieces[i..i + span] {
+                    if word {
+                        masked.extend(piece.chars().map(|_| '*'));
+                    } else {
+                        masked.push_str(piece);
+                    }
+                }
+                changed = true;
+                i += span;
+            }
+            None => {
+                masked.push_str(piece);
+                i += 1;
+            }
+        }
+    }
+    if changed {
+        Cow::Owned(masked)
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// Which of `words` appear in `text`, once each, as they're listed.
+pub fn found<'a>(words: &'a [String], text: &str) -> Vec<&'a str> {
+    let entries = entries(words);
+    let pieces = pieces(text);
+    let mut found = vec![];
+    for (i, (word, _)) in pieces.iter().enumerate() {
+        if let Some((listed, _)) = word.then(|| listed_at(&entries, &pieces, i)).flatten() {
+            if !found.contains(&listed) {
+                found.push(listed);
+            }
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn masks_whole_words_in_any_case() {
+        let list = words(&["darn"]);
+        assert_eq!(mask(&list, "Darn it, DARN! darned"), "**** it, ****! darned");
+        assert_eq!(found(&list, "Darn it, DARN!"), ["darn"]);
+        assert!(matches!(mask(&list, "nothing here"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn masks_between_punctuation() {
+        let list = words(&["heck"]);
+        assert_eq!(mask(&list, "(heck)...heck's"), "(****)...****'s");
+        assert!(found(&list, "hecks").is_empty());
+    }
+
+    #[test]
+    fn masks_multibyte_words_letter_for_letter() {
+        let list = words(&["Scheiße", "ÄRGER"]);
+        assert_eq!(mask(&list, "so ein scheiße, ärger!"), "so ein *******, *****!");
+        assert_eq!(mask(&list, "Ärgerlich"), "Ärgerlich");
+    }
+
+    #[test]
+    fn masks_phrases_whole() {
+        let list = words(&["bad word", "bad"]);
+        assert_eq!(mask(&list, "a Bad  Word and a bad-word, bad words"), "a ***  **** and a ***-****, *** words");
+        assert_eq!(found(&list, "bad words, bad word"), ["bad", "bad word"]);
+    }
+}
@@ -8,16 +8,23 @@ module.exports = {
     mode: 'production',
     devServer: {
         port: 8000,
+        // Links straight to /chat or /invite/<room> get the app too.
+        historyApiFallback: true,
     },
     entry: './bootstrap.js',
     output: {
         path: distPath,
         filename: 'yewchat.js',
+        publicPath: '/',
         webassemblyModuleFilename: 'yewchat_bg.wasm',
     },
     plugins: [
         new CopyWebpackPlugin({
-            patterns: [{ from: './static', to: distPath }],
+            patterns: [
+                { from: './static', to: distPath },
+                // The Twemoji set, for the "consistent emoji" setting.
+                { from: './node_modules/@twemoji/svg', to: path.join(distPath, 'twemoji'), globOptions: { ignore: ['**/package.json', '**/*.md'] } },
+            ],
         }),
         new WasmPackPlugin({
             crateDirectory: '.',